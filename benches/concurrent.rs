@@ -0,0 +1,100 @@
+//! Compares the throughput of `ConcurrentRateLog` and `SharedRateLog` as the number of
+//! logging threads grows.
+
+use std::hint::black_box;
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rate_log::{ConcurrentRateLog, Limit, RateLog, SharedRateLog, Sink};
+
+/// The thread counts to measure.
+const THREADS: [usize; 5] = [1, 2, 4, 8, 16];
+
+/// The distinct messages each thread cycles through.
+const MESSAGES: [&str; 8] = [
+    "connection reset",
+    "upstream timeout",
+    "cache miss",
+    "retrying request",
+    "queue full",
+    "slow consumer",
+    "disk nearly full",
+    "heartbeat missed",
+];
+
+/// A sink discarding every line, so only the rate limiting is measured.
+struct NullSink;
+
+impl Sink for NullSink {
+    fn emit(&mut self, record: &str) {
+        black_box(record);
+    }
+}
+
+/// Runs `iters` calls of `log` spread over `threads` threads started at once, and
+/// returns the time until all of them are done.
+fn run(threads: usize, iters: u64, log: impl Fn(&str) + Send + Sync + 'static) -> Duration {
+    let log = Arc::new(log);
+    let barrier = Arc::new(Barrier::new(threads + 1));
+    let per_thread = iters.div_ceil(threads as u64);
+    let handles: Vec<_> = (0..threads)
+        .map(|thread| {
+            let log = Arc::clone(&log);
+            let barrier = Arc::clone(&barrier);
+            thread::spawn(move || {
+                barrier.wait();
+                for i in 0..per_thread {
+                    log(MESSAGES[(thread + i as usize) % MESSAGES.len()]);
+                }
+            })
+        })
+        .collect();
+
+    barrier.wait();
+    let start = Instant::now();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    start.elapsed()
+}
+
+fn scalability(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scalability");
+    group.throughput(Throughput::Elements(1));
+
+    for threads in THREADS {
+        group.bench_with_input(
+            BenchmarkId::new("ConcurrentRateLog", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iters| {
+                    let logger = ConcurrentRateLog::with_sink(Limit::Rate(1000), NullSink).unwrap();
+                    run(threads, iters, move |msg| {
+                        black_box(logger.log(msg));
+                    })
+                });
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("SharedRateLog", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iters| {
+                    let logger = SharedRateLog::from_rate_log(RateLog::with_sink(
+                        Limit::Rate(1000),
+                        NullSink,
+                    ));
+                    run(threads, iters, move |msg| {
+                        black_box(logger.log(msg));
+                    })
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, scalability);
+criterion_main!(benches);
@@ -0,0 +1,72 @@
+//! Measures the overhead of a suppressed repeat, the hot path of a limiter sitting in
+//! a tight loop.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use rate_log::{Limit, RateLog, Sink};
+
+/// A sink discarding every line, so only the rate limiting is measured.
+struct NullSink;
+
+impl Sink for NullSink {
+    fn emit(&mut self, record: &str) {
+        black_box(record);
+    }
+}
+
+/// A count limit high enough that warnings barely show up in the measurement.
+const RATE: u32 = 1_000_000;
+
+/// A message of typical length, differing from [`OTHER`] only in its last byte.
+const MESSAGE: &str = "dropped packet from 10.0.0.1: checksum mismatch on queue 1";
+
+/// The message alternated with [`MESSAGE`].
+const OTHER: &str = "dropped packet from 10.0.0.1: checksum mismatch on queue 2";
+
+fn suppressed(c: &mut Criterion) {
+    let mut group = c.benchmark_group("suppressed");
+    group.throughput(Throughput::Elements(1));
+
+    group.bench_function("log/Rate", |b| {
+        let mut logger = RateLog::with_sink(Limit::Rate(RATE), NullSink);
+        b.iter(|| black_box(logger.log(black_box(MESSAGE))));
+    });
+    group.bench_function("log/Rate/lazy_clock", |b| {
+        let mut logger = RateLog::with_sink(Limit::Rate(RATE), NullSink).with_lazy_clock(true);
+        b.iter(|| black_box(logger.log(black_box(MESSAGE))));
+    });
+    group.bench_function("log_keyed/Rate", |b| {
+        let mut logger = RateLog::with_sink(Limit::Rate(RATE), NullSink);
+        b.iter(|| black_box(logger.log_keyed(black_box("checksum"), black_box(MESSAGE))));
+    });
+    group.bench_function("check/Rate", |b| {
+        let mut logger = RateLog::with_sink(Limit::Rate(RATE), NullSink);
+        b.iter(|| black_box(logger.check(black_box(MESSAGE))));
+    });
+    group.bench_function("log/Any", |b| {
+        let mut logger = RateLog::with_sink(
+            Limit::Any(RATE, std::time::Duration::from_secs(3600)),
+            NullSink,
+        );
+        b.iter(|| black_box(logger.log(black_box(MESSAGE))));
+    });
+    group.finish();
+}
+
+fn switching(c: &mut Criterion) {
+    let mut group = c.benchmark_group("switching");
+    group.throughput(Throughput::Elements(2));
+
+    group.bench_function("log/Rate", |b| {
+        let mut logger = RateLog::with_sink(Limit::Rate(RATE), NullSink);
+        b.iter(|| {
+            black_box(logger.log(black_box(MESSAGE)));
+            black_box(logger.log(black_box(OTHER)));
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, suppressed, switching);
+criterion_main!(benches);
@@ -0,0 +1,84 @@
+//! Tightening per-message limits while the overall output is too high.
+
+use core::time::Duration;
+
+use crate::clock::Instant;
+
+/// The largest factor limits are scaled by; scaled values saturate, see
+/// [`Limit::scaled`](crate::Limit::scaled).
+const MAX_FACTOR: f64 = 1000.0;
+
+/// Scales limits so the lines emitted per interval across all messages approach a
+/// target.
+///
+/// Lines are counted in fixed windows of `per` starting with the first line. When a
+/// window closes, the factor is multiplied by the ratio of the lines it saw to the
+/// target, so a window with twice the target doubles the factor and a window with half
+/// of it halves the factor again. The factor never drops below 1, so limits are only
+/// ever tightened, and a window without any lines restores the configured limits.
+#[derive(Debug)]
+pub(crate) struct AdaptiveLimit {
+    /// The target number of lines per window.
+    target: u32,
+    /// The length of a window.
+    per: Duration,
+    /// The start of the current window.
+    window_start: Option<Instant>,
+    /// The lines emitted in the current window.
+    lines: u32,
+    /// The factor limits are currently scaled by.
+    factor: f64,
+}
+
+impl AdaptiveLimit {
+    pub(crate) fn new(target: u32, per: Duration) -> Self {
+        AdaptiveLimit {
+            target: target.max(1),
+            per,
+            window_start: None,
+            lines: 0,
+            factor: 1.0,
+        }
+    }
+
+    /// Records one line emitted at `now`.
+    pub(crate) fn record(&mut self, now: Instant) {
+        self.roll(now);
+        self.lines = self.lines.saturating_add(1);
+    }
+
+    /// Returns the factor limits are scaled by at `now`.
+    pub(crate) fn factor(&mut self, now: Instant) -> f64 {
+        self.roll(now);
+        self.factor
+    }
+
+    /// Restores the configured limits and starts counting lines anew.
+    pub(crate) fn reset(&mut self) {
+        self.window_start = None;
+        self.lines = 0;
+        self.factor = 1.0;
+    }
+
+    /// Closes the current window once `per` has passed, adjusting the factor.
+    fn roll(&mut self, now: Instant) {
+        let Some(start) = self.window_start else {
+            self.window_start = Some(now);
+            return;
+        };
+        let elapsed = now.duration_since(start);
+        if elapsed < self.per {
+            return;
+        }
+
+        self.factor = if elapsed >= self.per.saturating_mul(2) {
+            // At least one whole window passed without any lines
+            1.0
+        } else {
+            let ratio = f64::from(self.lines) / f64::from(self.target);
+            (self.factor * ratio).clamp(1.0, MAX_FACTOR)
+        };
+        self.window_start = Some(now);
+        self.lines = 0;
+    }
+}
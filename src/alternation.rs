@@ -0,0 +1,118 @@
+//! Detection of rapid alternation between a small set of messages.
+
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::clock::Instant;
+
+use crate::DurationStyle;
+
+/// Tracks recently replaced messages to recognize A, B, A, B... alternation.
+///
+/// In consecutive mode every message change resets the counters and emits the new
+/// message, so two alternating messages would be printed on every call. While an
+/// alternation batch is active, returning to a recently seen message is counted
+/// instead, and a single summary is emitted once the alternation stops.
+#[derive(Debug)]
+pub(crate) struct AlternationTracker {
+    /// The maximum number of recently replaced messages remembered.
+    set_size: usize,
+
+    /// How recently a message must have been seen for a return to it to count as
+    /// alternation.
+    within: Duration,
+
+    /// Recently replaced messages with the time they were replaced, most recent first.
+    recent: VecDeque<(String, Instant)>,
+
+    /// The alternation currently being batched, if any.
+    batch: Option<Batch>,
+}
+
+/// An ongoing alternation between several messages.
+#[derive(Debug)]
+struct Batch {
+    /// The distinct messages taking part in the alternation.
+    keys: Vec<String>,
+    /// The number of message changes batched so far.
+    switches: u32,
+    /// The time of the first batched change.
+    started: Instant,
+    /// The time of the latest batched change.
+    last: Instant,
+}
+
+impl AlternationTracker {
+    pub(crate) fn new(set_size: usize, within: Duration) -> Self {
+        AlternationTracker {
+            set_size,
+            within,
+            recent: VecDeque::new(),
+            batch: None,
+        }
+    }
+
+    /// Records a change from message `from` to message `to`.
+    ///
+    /// Returns `true` if the change is part of a rapid alternation and should be
+    /// batched instead of emitted. Otherwise the caller should first emit the summary
+    /// of any finished batch, obtained through [`AlternationTracker::finish`].
+    pub(crate) fn on_switch(&mut self, from: &str, to: &str, now: Instant) -> bool {
+        let rapid = self
+            .recent
+            .iter()
+            .any(|(key, seen)| key == to && now.duration_since(*seen) < self.within);
+
+        if !from.is_empty() {
+            self.remember(from, now);
+        }
+
+        if !rapid {
+            return false;
+        }
+
+        let batch = self.batch.get_or_insert_with(|| Batch {
+            keys: vec![from.to_string()],
+            switches: 0,
+            started: now,
+            last: now,
+        });
+        if !batch.keys.iter().any(|key| key == to) {
+            batch.keys.push(to.to_string());
+        }
+        batch.switches += 1;
+        batch.last = now;
+
+        true
+    }
+
+    /// Ends the current batch, returning its summary line if any changes were batched.
+    pub(crate) fn finish(&mut self, style: &DurationStyle) -> Option<String> {
+        let batch = self.batch.take()?;
+        Some(format!(
+            "{} messages alternated {} times in the past {}",
+            batch.keys.len(),
+            batch.switches,
+            style.format(batch.last.duration_since(batch.started))
+        ))
+    }
+
+    /// Forgets the recently replaced messages and any batch in progress.
+    pub(crate) fn clear(&mut self) {
+        self.recent.clear();
+        self.batch = None;
+    }
+
+    /// Moves `key` to the front of the recently replaced messages.
+    fn remember(&mut self, key: &str, now: Instant) {
+        if let Some(pos) = self.recent.iter().position(|(recent, _)| recent == key) {
+            self.recent.remove(pos);
+        }
+        self.recent.push_front((key.to_string(), now));
+        self.recent.truncate(self.set_size);
+    }
+}
@@ -0,0 +1,230 @@
+//! Non-blocking emission for async services.
+
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::task::JoinHandle;
+
+use crate::{Limit, LineKind, LogOutcome, RateLog, Sink, StdoutSink};
+
+/// A request forwarded to the writer task.
+enum Command {
+    /// Write a line, of the given kind if known.
+    Line(String, Option<LineKind>),
+    /// Flush the sink.
+    Flush,
+}
+
+/// A sink handing lines over to a background task that writes them to another sink.
+///
+/// Emitting only pushes the line into an unbounded channel, so it never blocks and
+/// never awaits; the writer task drains the channel and performs the actual, possibly
+/// blocking, writes on tokio's blocking thread pool. The channel grows while the writer
+/// falls behind, which the rate limiting in front of it is meant to keep in check.
+pub struct AsyncSink {
+    /// Sends lines to the writer task; `None` once closed.
+    sender: Option<UnboundedSender<Command>>,
+
+    /// The writer task; `None` once closed.
+    writer: Option<JoinHandle<()>>,
+
+    /// Whether the wrapped sink writes to a terminal.
+    is_terminal: bool,
+}
+
+impl AsyncSink {
+    /// Starts a writer task forwarding every emitted line to `sink`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn spawn<S: Sink + Send + 'static>(mut sink: S) -> Self {
+        let is_terminal = sink.is_terminal();
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let writer = tokio::task::spawn_blocking(move || {
+            while let Some(command) = receiver.blocking_recv() {
+                match command {
+                    Command::Line(line, None) => sink.emit(&line),
+                    Command::Line(line, Some(kind)) => sink.emit_line(&line, kind),
+                    Command::Flush => sink.flush(),
+                }
+            }
+            sink.flush();
+        });
+        AsyncSink {
+            sender: Some(sender),
+            writer: Some(writer),
+            is_terminal,
+        }
+    }
+
+    /// Forwards a command to the writer task, if it is still running.
+    fn send(&self, command: Command) {
+        if let Some(sender) = &self.sender {
+            // The writer only stops early if the wrapped sink panicked
+            let _ = sender.send(command);
+        }
+    }
+
+    /// Closes the channel and waits until the writer task has written every line.
+    async fn close(&mut self) {
+        self.sender = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.await;
+        }
+    }
+}
+
+impl Sink for AsyncSink {
+    fn emit(&mut self, record: &str) {
+        self.send(Command::Line(record.to_string(), None));
+    }
+
+    fn emit_line(&mut self, record: &str, kind: LineKind) {
+        self.send(Command::Line(record.to_string(), Some(kind)));
+    }
+
+    fn flush(&mut self) {
+        self.send(Command::Flush);
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.is_terminal
+    }
+}
+
+/// A rate limiter for async code whose logging calls never block.
+///
+/// Rate limiting decisions are taken inline, as with [`RateLog`], but emitted lines are
+/// handed over to a background task through an [`AsyncSink`] instead of being written
+/// by the caller. High-throughput services thus never wait for stdout, a lock or a slow
+/// writer on the logging path.
+///
+/// Call [`AsyncRateLog::shutdown`] before exiting to emit pending summaries and wait
+/// until every line has been written.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{AsyncRateLog, Limit};
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let mut logger = AsyncRateLog::new(Limit::Rate(10));
+///
+/// for _ in 0..100 {
+///     logger.log("upstream unavailable");  // Returns immediately
+/// }
+/// logger.shutdown().await;
+/// # }
+/// ```
+pub struct AsyncRateLog {
+    /// The limiter taking decisions inline and writing to the channel.
+    rate_log: RateLog<AsyncSink>,
+}
+
+impl AsyncRateLog {
+    /// Creates a limiter writing to stdout from a background task.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn new(limit: Limit) -> Self {
+        AsyncRateLog::with_sink(limit, StdoutSink)
+    }
+
+    /// Creates a limiter writing to `sink` from a background task.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn with_sink<S: Sink + Send + 'static>(limit: Limit, sink: S) -> Self {
+        AsyncRateLog::from_rate_log(RateLog::with_sink(limit, AsyncSink::spawn(sink)))
+    }
+
+    /// Wraps a limiter configured with an [`AsyncSink`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{AsyncRateLog, AsyncSink, Limit, RateLog, StderrSink};
+    /// use std::time::Duration;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let rate_log = RateLog::with_sink(Limit::Rate(10), AsyncSink::spawn(StderrSink))
+    ///     .with_cooldown(Duration::from_secs(30));
+    /// let mut logger = AsyncRateLog::from_rate_log(rate_log);
+    /// # logger.shutdown().await;
+    /// # }
+    /// ```
+    pub fn from_rate_log(rate_log: RateLog<AsyncSink>) -> Self {
+        AsyncRateLog { rate_log }
+    }
+
+    /// Logs a message without blocking.
+    ///
+    /// See [`RateLog::log`] for the rate limiting behavior.
+    pub fn log(&mut self, msg: &str) -> LogOutcome {
+        self.rate_log.log(msg)
+    }
+
+    /// Logs a message rate limited under `key` without blocking.
+    ///
+    /// See [`RateLog::log_keyed`] for the rate limiting behavior.
+    pub fn log_keyed(&mut self, key: &str, msg: &str) -> LogOutcome {
+        self.rate_log.log_keyed(key, msg)
+    }
+
+    /// Returns the wrapped limiter, e.g. to inspect its statistics.
+    pub fn rate_log(&self) -> &RateLog<AsyncSink> {
+        &self.rate_log
+    }
+
+    /// Returns a mutable reference to the wrapped limiter.
+    pub fn rate_log_mut(&mut self) -> &mut RateLog<AsyncSink> {
+        &mut self.rate_log
+    }
+
+    /// Emits pending summaries, then waits until the background task has written
+    /// every line and flushed the sink.
+    pub async fn shutdown(mut self) {
+        self.rate_log.flush();
+        self.rate_log.sink_mut().close().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// A sink recording lines where the test can still see them after shutdown.
+    #[derive(Clone, Default)]
+    struct SharedSink(Arc<Mutex<Vec<String>>>);
+
+    impl Sink for SharedSink {
+        fn emit(&mut self, record: &str) {
+            self.0.lock().unwrap().push(record.to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_emission() {
+        let sink = SharedSink::default();
+        let mut logger = AsyncRateLog::with_sink(Limit::Rate(2), sink.clone());
+
+        logger.log("message1");
+        logger.log("message1");
+        logger.log("message1");
+        logger.log("message2");
+        logger.shutdown().await;
+
+        assert_eq!(
+            *sink.0.lock().unwrap(),
+            [
+                "message1",
+                "Message: \"message1\" repeat for 2 times in the past 0ms",
+                "message2"
+            ]
+        );
+    }
+}
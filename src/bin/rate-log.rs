@@ -0,0 +1,211 @@
+//! Rate limits lines piped to stdin, e.g. `journalctl -f | rate-log --rate 20`.
+
+use std::borrow::Cow;
+use std::env;
+use std::io::{self, BufRead};
+use std::process::ExitCode;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use rate_log::{Error, RateLogBuilder, WriterSink};
+use regex::Regex;
+
+const USAGE: &str = "\
+Usage: rate-log [OPTIONS]
+
+Copies lines from stdin to stdout, replacing repeats of a line with summaries.
+
+Options:
+  --rate N              Report repeats after N of them (default 10)
+  --duration D          Report repeats after D, e.g. 500ms, 10s, 5m or 1h; with
+                        --rate, after whichever is reached first
+  --window N:D          Emit at most N copies of a line per D
+  --limit LIMIT         Any limit in the RATE_LOG syntax, e.g. gcra:1s:5
+  --group-regex REGEX   Treat lines as repeats if they only differ in text
+                        matching REGEX, e.g. '[0-9]+'
+  -h, --help            Print this help
+  -V, --version         Print the version
+
+Further options are read from the RATE_LOG environment variable, e.g.
+RATE_LOG=cooldown=30s,rate=true. Pending repeats are reported once no new line
+arrived for a second, and at the end of the input.";
+
+/// How long to wait for a new line before reporting pending repeats.
+const IDLE_FLUSH: Duration = Duration::from_secs(1);
+
+/// The command line options.
+#[derive(Debug)]
+struct Args {
+    /// The limit in the `RATE_LOG` syntax, if one was given.
+    limit: Option<String>,
+    /// Lines with the same text after replacing its matches are repeats.
+    group: Option<Regex>,
+}
+
+impl Args {
+    /// Parses the command line arguments, without the program name.
+    ///
+    /// Returns `Ok(None)` if the help or version was requested.
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Args>, String> {
+        let (mut rate, mut duration, mut window, mut limit, mut group) =
+            (None, None, None, None, None);
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let slot = match name {
+                "-h" | "--help" => {
+                    println!("{USAGE}");
+                    return Ok(None);
+                }
+                "-V" | "--version" => {
+                    println!("rate-log {}", env!("CARGO_PKG_VERSION"));
+                    return Ok(None);
+                }
+                "--rate" => &mut rate,
+                "--duration" => &mut duration,
+                "--window" => &mut window,
+                "--limit" => &mut limit,
+                "--group-regex" => &mut group,
+                _ => return Err(format!("unknown argument \"{arg}\"")),
+            };
+            let value = inline
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{name} requires a value"))?;
+            *slot = Some(value);
+        }
+
+        let limit = match (rate, duration, window, limit) {
+            (None, None, None, limit) => limit,
+            (Some(rate), None, None, None) => Some(format!("rate:{rate}")),
+            (None, Some(duration), None, None) => Some(format!("duration:{duration}")),
+            (Some(rate), Some(duration), None, None) => Some(format!("any:{rate}:{duration}")),
+            (None, None, Some(window), None) => Some(format!("window:{window}")),
+            _ => return Err("--window and --limit cannot be combined with other limits".into()),
+        };
+        let group = group
+            .map(|pattern| Regex::new(&pattern))
+            .transpose()
+            .map_err(|err| format!("invalid --group-regex: {err}"))?;
+        Ok(Some(Args { limit, group }))
+    }
+
+    /// Returns the options in the `RATE_LOG` syntax, applied after `env`.
+    fn spec(&self, env: &str) -> String {
+        match &self.limit {
+            Some(limit) => format!("{env},limit={limit}"),
+            None => env.to_string(),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => return ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("rate-log: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    let env = env::var("RATE_LOG").unwrap_or_default();
+    let builder = match RateLogBuilder::from_spec(&args.spec(&env)) {
+        Ok(builder) => builder,
+        Err(err) => {
+            eprintln!("rate-log: {err}");
+            return ExitCode::from(2);
+        }
+    };
+    let mut rate_log = builder.sink(WriterSink::new(io::stdout())).build();
+
+    // Read on a separate thread, so pending repeats are reported while stdin is quiet
+    let (sender, lines) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().split(b'\n') {
+            let Ok(mut line) = line else { break };
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            let line = String::from_utf8_lossy(&line).into_owned();
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    loop {
+        match lines.recv_timeout(IDLE_FLUSH) {
+            Ok(line) => {
+                let key = match &args.group {
+                    Some(group) => group.replace_all(&line, "<*>"),
+                    None => Cow::Borrowed(line.as_str()),
+                };
+                if key == line {
+                    rate_log.log(&line);
+                } else {
+                    rate_log.log_keyed(&key, &line);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => rate_log.flush(),
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        if let Some(err) = rate_log.sink_mut().take_error() {
+            return exit_on(err);
+        }
+    }
+
+    rate_log.flush();
+    match rate_log.sink_mut().take_error() {
+        Some(err) => exit_on(err),
+        None => ExitCode::SUCCESS,
+    }
+}
+
+/// Returns the exit code for a failure to write to stdout.
+///
+/// A closed pipe, e.g. `rate-log | head`, is a normal way to stop.
+fn exit_on(err: Error) -> ExitCode {
+    match err {
+        Error::Io(err) if err.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        err => {
+            eprintln!("rate-log: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|arg| arg.to_string())).map(Option::unwrap)
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse(&[]).unwrap().limit, None);
+        assert_eq!(parse(&["--rate", "20"]).unwrap().spec(""), ",limit=rate:20");
+        assert_eq!(
+            parse(&["--rate=20", "--duration", "10s"])
+                .unwrap()
+                .spec("cooldown=30s"),
+            "cooldown=30s,limit=any:20:10s"
+        );
+        assert_eq!(
+            parse(&["--window", "5:1m"]).unwrap().limit.as_deref(),
+            Some("window:5:1m")
+        );
+
+        let group = parse(&["--group-regex", "[0-9]+"]).unwrap().group.unwrap();
+        assert_eq!(group.replace_all("pid 42 exited", "<*>"), "pid <*> exited");
+
+        assert!(parse(&["--window", "5:1m", "--rate", "20"]).is_err());
+        assert!(parse(&["--rate"]).is_err());
+        assert!(parse(&["--group-regex", "("]).is_err());
+        assert!(parse(&["--verbose"]).is_err());
+    }
+}
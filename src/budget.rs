@@ -0,0 +1,81 @@
+//! A global output budget layered on top of per-message limits.
+
+use core::time::Duration;
+
+use crate::clock::Instant;
+
+/// Caps the number of lines emitted per interval across all messages.
+///
+/// Per-message limits cannot catch a flood of unique messages, since every one of them
+/// is a first occurrence. The budget counts every line in fixed windows of `per`
+/// starting with the first line; lines beyond `max` are dropped and counted.
+#[derive(Debug)]
+pub(crate) struct GlobalBudget {
+    /// The maximum number of lines per window.
+    max: u32,
+    /// The length of a window.
+    per: Duration,
+    /// The start of the current window.
+    window_start: Option<Instant>,
+    /// The lines emitted in the current window.
+    used: u32,
+    /// The lines dropped since the last notice.
+    suppressed: u64,
+}
+
+/// Whether a line may be emitted under the budget.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Admission {
+    /// Emit the line.
+    Emit,
+    /// Emit a notice about the given number of dropped lines, then the line.
+    EmitAfterSuppressed(u64),
+    /// Drop the line.
+    Suppress,
+}
+
+impl GlobalBudget {
+    pub(crate) fn new(max: u32, per: Duration) -> Self {
+        GlobalBudget {
+            max,
+            per,
+            window_start: None,
+            used: 0,
+            suppressed: 0,
+        }
+    }
+
+    /// Takes budget for one line emitted at `now`.
+    pub(crate) fn admit(&mut self, now: Instant) -> Admission {
+        let expired = match self.window_start {
+            Some(start) => now.duration_since(start) >= self.per,
+            None => true,
+        };
+        if expired {
+            self.window_start = Some(now);
+            self.used = 0;
+        }
+
+        if self.used >= self.max {
+            self.suppressed += 1;
+            return Admission::Suppress;
+        }
+        self.used += 1;
+        match self.take_suppressed() {
+            Some(suppressed) => Admission::EmitAfterSuppressed(suppressed),
+            None => Admission::Emit,
+        }
+    }
+
+    /// Restores the full budget and forgets the lines dropped so far.
+    pub(crate) fn reset(&mut self) {
+        self.window_start = None;
+        self.used = 0;
+        self.suppressed = 0;
+    }
+
+    /// Returns and clears the number of lines dropped since the last notice, if any.
+    pub(crate) fn take_suppressed(&mut self) -> Option<u64> {
+        Some(core::mem::take(&mut self.suppressed)).filter(|&suppressed| suppressed > 0)
+    }
+}
@@ -0,0 +1,172 @@
+//! Buffering of emitted records for high-throughput sinks.
+
+use std::time::Duration;
+
+use crate::clock::Instant;
+use crate::{Sink, TextPolicy};
+
+/// A sink wrapper that collects records and hands them to the inner sink in batches.
+///
+/// For high-throughput sinks (files, sockets) writing every record on its own turns
+/// thousands of emissions per second into thousands of tiny write syscalls. `Buffered`
+/// holds records back and passes them to [`Sink::emit_batch`] once any of these
+/// thresholds is reached:
+///
+/// - **records**: the number of buffered records (default 64)
+/// - **bytes**: the total size of the buffered records (default 64 KiB)
+/// - **delay**: the age of the oldest buffered record (disabled by default)
+///
+/// The delay is checked whenever a record is emitted; there is no background thread,
+/// so a quiet buffer is only written on the next record, on [`Sink::flush`] (called by
+/// [`RateLog::flush`](crate::RateLog::flush)) or when the `Buffered` sink is dropped.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{Buffered, CaptureSink, Limit, RateLog};
+/// use std::time::Duration;
+///
+/// let sink = Buffered::new(CaptureSink::new())
+///     .max_records(2)
+///     .max_delay(Duration::from_secs(1));
+/// let mut logger = RateLog::with_sink(Limit::Rate(10), sink);
+///
+/// logger.log("first");
+/// assert!(logger.sink().inner().lines().is_empty());
+///
+/// logger.log("second");
+/// assert_eq!(logger.sink().inner().lines(), ["first", "second"]);
+/// ```
+pub struct Buffered<S: Sink> {
+    inner: S,
+    buffer: Vec<String>,
+    buffered_bytes: usize,
+    oldest: Option<Instant>,
+    max_records: usize,
+    max_bytes: usize,
+    max_delay: Option<Duration>,
+}
+
+impl<S: Sink> Buffered<S> {
+    /// Wraps `inner` with the default thresholds.
+    pub fn new(inner: S) -> Self {
+        Buffered {
+            inner,
+            buffer: Vec::new(),
+            buffered_bytes: 0,
+            oldest: None,
+            max_records: 64,
+            max_bytes: 64 * 1024,
+            max_delay: None,
+        }
+    }
+
+    /// Writes the buffer once it holds `max_records` records.
+    pub fn max_records(mut self, max_records: usize) -> Self {
+        self.max_records = max_records;
+        self
+    }
+
+    /// Writes the buffer once its records add up to `max_bytes` bytes.
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Writes the buffer once its oldest record is `max_delay` old.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = Some(max_delay);
+        self
+    }
+
+    /// Returns a reference to the wrapped sink.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped sink.
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Returns the number of records currently held back.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Hands all buffered records to the inner sink.
+    fn write_buffer(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        self.inner.emit_batch(&self.buffer);
+        self.buffer.clear();
+        self.buffered_bytes = 0;
+        self.oldest = None;
+    }
+}
+
+impl<S: Sink> Sink for Buffered<S> {
+    fn emit(&mut self, record: &str) {
+        let now = Instant::now();
+        let oldest = *self.oldest.get_or_insert(now);
+
+        self.buffered_bytes += record.len();
+        self.buffer.push(record.to_string());
+
+        let expired = self
+            .max_delay
+            .is_some_and(|max_delay| now.duration_since(oldest) >= max_delay);
+        if self.buffer.len() >= self.max_records || self.buffered_bytes >= self.max_bytes || expired
+        {
+            self.write_buffer();
+        }
+    }
+
+    fn flush(&mut self) {
+        self.write_buffer();
+        self.inner.flush();
+    }
+
+    fn text_policy(&self) -> TextPolicy {
+        self.inner.text_policy()
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.inner.is_terminal()
+    }
+}
+
+impl<S: Sink> Drop for Buffered<S> {
+    fn drop(&mut self) {
+        self.write_buffer();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CaptureSink;
+    use std::thread;
+
+    #[test]
+    fn test_buffered_thresholds() {
+        let mut sink = Buffered::new(CaptureSink::new()).max_bytes(10);
+        sink.emit("12345");
+        assert_eq!(sink.buffered(), 1);
+        sink.emit("67890");
+        assert_eq!(sink.buffered(), 0);
+        assert_eq!(sink.inner().lines(), ["12345", "67890"]);
+
+        let mut sink = Buffered::new(CaptureSink::new()).max_delay(Duration::from_millis(20));
+        sink.emit("first");
+        thread::sleep(Duration::from_millis(20));
+        sink.emit("second");
+        assert_eq!(sink.inner().lines(), ["first", "second"]);
+
+        sink.emit("third");
+        sink.flush();
+        assert_eq!(sink.inner().lines(), ["first", "second", "third"]);
+    }
+}
@@ -0,0 +1,665 @@
+//! Step-by-step configuration of a [`RateLog`].
+
+use std::time::Duration;
+
+use crate::clock::{Clock, MonotonicClock};
+use crate::exemplar::ExemplarSource;
+use crate::warning::Formatter;
+use crate::{
+    CountMode, DedupWindow, DurationMode, DurationStyle, Limit, LimitCallback, Limiter, Monotonic,
+    Normalizer, OutputFormat, RateLog, Severity, Sink, StdoutSink, Warning,
+};
+
+/// A builder for configuring a [`RateLog`] with several options at once.
+///
+/// Created with [`RateLog::builder`]. Every option has a default, so only the
+/// settings that differ from it need to be specified:
+///
+/// - **limit**: `Limit::Rate(10)`
+/// - **sink**: [`StdoutSink`]
+/// - **history**: disabled
+/// - **announce**: disabled
+/// - **grouping**: disabled, every message is its own key
+/// - **quiet keys**: none
+/// - **emit first**: enabled, first occurrences are emitted
+/// - **alternation batching**: disabled
+/// - **dedup window**: none, only consecutive repeats are deduplicated
+/// - **auto report**: disabled
+/// - **severity weights**: 1 for every severity
+/// - **exemplars**: the current `tracing` span with the `tracing` feature, otherwise none
+/// - **report last message**: disabled
+/// - **rate**: disabled
+/// - **duration style**: [`DurationStyle::Compact`], e.g. `1h59m`
+/// - **interval percentiles**: disabled
+/// - **message on limit**: disabled
+/// - **formatter**: the built-in `Message: "..." repeat for N times ...` wording
+/// - **limiter**: none, the limit decides
+/// - **grace period**: disabled
+/// - **idle TTL**: disabled
+/// - **settle timeout**: disabled
+/// - **cooldown**: disabled
+/// - **summary interval**: disabled
+/// - **escalation**: disabled
+/// - **decay**: disabled
+/// - **count mode**: [`CountMode::RepeatsOnly`]
+/// - **duration mode**: [`DurationMode::SumOfGaps`]
+/// - **clock**: [`SystemClock`](crate::SystemClock)
+/// - **clock read interval**: 1, the clock is read on every call
+/// - **lazy clock**: disabled
+/// - **format**: [`OutputFormat::Text`]
+/// - **limit exceeded callback**: none
+/// - **warnings**: enabled
+/// - **enabled**: enabled, messages are rate limited
+/// - **warning coalescing**: disabled
+/// - **max tracked messages**: unbounded
+/// - **hashed keys**: disabled, keys are remembered by their text
+/// - **target limits**: none, every target uses the limit
+/// - **global budget**: unlimited
+/// - **adaptive limit**: disabled, limits are never scaled
+/// - **color**: [`ColorChoice::Auto`](crate::ColorChoice::Auto) with the `color` feature
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{RateLog, Limit, StderrSink};
+/// use std::time::Duration;
+///
+/// let mut logger = RateLog::builder()
+///     .limit(Limit::Duration(Duration::from_secs(5)))
+///     .sink(StderrSink)
+///     .history(32)
+///     .build();
+///
+/// logger.log("Connection lost");  // Prints to stderr: "Connection lost"
+/// ```
+pub struct RateLogBuilder<S = StdoutSink> {
+    options: Options,
+    sink: S,
+}
+
+/// The sink independent settings collected by a [`RateLogBuilder`].
+struct Options {
+    limit: Limit,
+    history_capacity: usize,
+    announce: bool,
+    normalizer: Option<Normalizer>,
+    quiet_keys: Vec<String>,
+    emit_first: bool,
+    alternation: Option<(usize, Duration)>,
+    dedup_window: Option<DedupWindow>,
+    auto_report: Option<Duration>,
+    severity_weights: Vec<(Severity, f64)>,
+    exemplars: Option<ExemplarSource>,
+    report_last_message: bool,
+    show_rate: bool,
+    duration_style: DurationStyle,
+    interval_percentiles: bool,
+    message_on_limit: bool,
+    formatter: Option<Formatter>,
+    limiter: Option<Box<dyn Limiter>>,
+    grace_period: Option<Duration>,
+    idle_ttl: Option<Duration>,
+    settle_timeout: Option<Duration>,
+    cooldown: Option<Duration>,
+    summary_interval: Option<Duration>,
+    escalation: Option<u32>,
+    decay: Option<Duration>,
+    count_mode: CountMode,
+    duration_mode: DurationMode,
+    clock: Option<Box<dyn Clock + Send>>,
+    clock_read_interval: u32,
+    lazy_clock: bool,
+    format: OutputFormat,
+    limit_callback: Option<LimitCallback>,
+    warnings: bool,
+    enabled: bool,
+    warning_coalescing: Option<Duration>,
+    max_tracked_messages: Option<usize>,
+    hashed_keys: bool,
+    target_limits: Vec<(String, Limit)>,
+    global_budget: Option<(u32, Duration)>,
+    adaptive_limit: Option<(u32, Duration)>,
+    #[cfg(feature = "color")]
+    color: crate::ColorChoice,
+}
+
+impl RateLogBuilder {
+    /// Creates a builder with all options set to their defaults.
+    pub fn new() -> Self {
+        RateLogBuilder {
+            options: Options {
+                limit: Limit::Rate(10),
+                history_capacity: 0,
+                announce: false,
+                normalizer: None,
+                quiet_keys: Vec::new(),
+                emit_first: true,
+                alternation: None,
+                dedup_window: None,
+                auto_report: None,
+                severity_weights: Vec::new(),
+                exemplars: None,
+                report_last_message: false,
+                show_rate: false,
+                duration_style: DurationStyle::Compact,
+                interval_percentiles: false,
+                message_on_limit: false,
+                formatter: None,
+                limiter: None,
+                grace_period: None,
+                idle_ttl: None,
+                settle_timeout: None,
+                cooldown: None,
+                summary_interval: None,
+                escalation: None,
+                decay: None,
+                count_mode: CountMode::default(),
+                duration_mode: DurationMode::default(),
+                clock: None,
+                clock_read_interval: 1,
+                lazy_clock: false,
+                format: OutputFormat::Text,
+                limit_callback: None,
+                warnings: true,
+                enabled: true,
+                warning_coalescing: None,
+                max_tracked_messages: None,
+                hashed_keys: false,
+                target_limits: Vec::new(),
+                global_budget: None,
+                adaptive_limit: None,
+                #[cfg(feature = "color")]
+                color: crate::ColorChoice::Auto,
+            },
+            sink: StdoutSink,
+        }
+    }
+}
+
+impl Default for RateLogBuilder {
+    fn default() -> Self {
+        RateLogBuilder::new()
+    }
+}
+
+impl<S: Sink> RateLogBuilder<S> {
+    /// Sets the rate limiting threshold to enforce.
+    pub fn limit(mut self, limit: Limit) -> Self {
+        self.options.limit = limit;
+        self
+    }
+
+    /// Sets the destination for emitted messages and warnings.
+    pub fn sink<T: Sink>(self, sink: T) -> RateLogBuilder<T> {
+        RateLogBuilder {
+            options: self.options,
+            sink,
+        }
+    }
+
+    /// Keeps a bounded history of the last `capacity` emitted lines.
+    ///
+    /// See [`RateLog::with_history`] for details.
+    pub fn history(mut self, capacity: usize) -> Self {
+        self.options.history_capacity = capacity;
+        self
+    }
+
+    /// Emits a one-time description of the active policy when the logger is built.
+    ///
+    /// See [`RateLog::announce`] for details.
+    pub fn announce(mut self, announce: bool) -> Self {
+        self.options.announce = announce;
+        self
+    }
+
+    /// Groups messages that differ only in variable details, such as numbers or UUIDs.
+    ///
+    /// See [`RateLog::group_by`] for details.
+    pub fn group_by(mut self, normalizer: Normalizer) -> Self {
+        self.options.normalizer = Some(normalizer);
+        self
+    }
+
+    /// Suppresses even the first occurrence of `key`, emitting only summaries.
+    ///
+    /// May be called several times to configure multiple keys.
+    /// See [`RateLog::with_quiet_key`] for details.
+    pub fn quiet_key(mut self, key: impl Into<String>) -> Self {
+        self.options.quiet_keys.push(key.into());
+        self
+    }
+
+    /// Enables or disables emitting the first occurrence of every message.
+    ///
+    /// See [`RateLog::with_emit_first`] for details.
+    pub fn emit_first(mut self, enabled: bool) -> Self {
+        self.options.emit_first = enabled;
+        self
+    }
+
+    /// Batches rapid alternation between up to `set_size` messages seen within `within`.
+    ///
+    /// See [`RateLog::with_alternation_batching`] for details.
+    pub fn alternation_batching(mut self, set_size: usize, within: Duration) -> Self {
+        self.options.alternation = Some((set_size, within));
+        self
+    }
+
+    /// Treats a message seen again within `window` as a repeat.
+    ///
+    /// See [`RateLog::with_dedup_window`] for details.
+    pub fn dedup_window(mut self, window: DedupWindow) -> Self {
+        self.options.dedup_window = Some(window);
+        self
+    }
+
+    /// Reports suppressed occurrences automatically every `interval`.
+    ///
+    /// See [`RateLog::with_auto_report`] for details.
+    pub fn auto_report(mut self, interval: Duration) -> Self {
+        self.options.auto_report = Some(interval);
+        self
+    }
+
+    /// Sets the weight of `severity` used to rank top offenders.
+    ///
+    /// See [`RateLog::with_severity_weight`] for details.
+    pub fn severity_weight(mut self, severity: Severity, weight: f64) -> Self {
+        self.options.severity_weights.push((severity, weight));
+        self
+    }
+
+    /// Attaches exemplars captured by `source` to warnings and summaries.
+    ///
+    /// See [`RateLog::with_exemplars`] for details.
+    pub fn exemplars(mut self, source: impl FnMut() -> Option<String> + Send + 'static) -> Self {
+        self.options.exemplars = Some(Box::new(source));
+        self
+    }
+
+    /// Reports the text of the latest suppressed repeat in summaries of keyed messages.
+    ///
+    /// See [`RateLog::with_report_last_message`] for details.
+    pub fn report_last_message(mut self, enabled: bool) -> Self {
+        self.options.report_last_message = enabled;
+        self
+    }
+
+    /// Includes the rate of the covered repeats in warnings and summaries.
+    ///
+    /// See [`RateLog::with_rate`] for details.
+    pub fn rate(mut self, enabled: bool) -> Self {
+        self.options.show_rate = enabled;
+        self
+    }
+
+    /// Sets how durations are written in warnings, summaries and reports.
+    ///
+    /// See [`RateLog::with_duration_style`] for details.
+    pub fn duration_style(mut self, style: DurationStyle) -> Self {
+        self.options.duration_style = style;
+        self
+    }
+
+    /// Includes percentiles of the time between repeats in warnings and summaries.
+    ///
+    /// See [`RateLog::with_interval_percentiles`] for details.
+    pub fn interval_percentiles(mut self, enabled: bool) -> Self {
+        self.options.interval_percentiles = enabled;
+        self
+    }
+
+    /// Emits the message itself again whenever its limit is exceeded.
+    ///
+    /// See [`RateLog::with_message_on_limit`] for details.
+    pub fn message_on_limit(mut self, enabled: bool) -> Self {
+        self.options.message_on_limit = enabled;
+        self
+    }
+
+    /// Replaces the built-in wording of rate limit warnings and summaries.
+    ///
+    /// See [`RateLog::with_formatter`] for details.
+    pub fn formatter(
+        mut self,
+        formatter: impl Fn(&Warning<'_>) -> String + Send + 'static,
+    ) -> Self {
+        self.options.formatter = Some(Box::new(formatter));
+        self
+    }
+
+    /// Replaces the built-in rate limiting algorithm with a custom [`Limiter`].
+    ///
+    /// See [`RateLog::with_limiter`] for details.
+    pub fn limiter(mut self, limiter: impl Limiter + 'static) -> Self {
+        self.options.limiter = Some(Box::new(limiter));
+        self
+    }
+
+    /// Emits every occurrence of a newly seen key verbatim during `grace_period`.
+    ///
+    /// See [`RateLog::with_grace_period`] for details.
+    pub fn grace_period(mut self, grace_period: Duration) -> Self {
+        self.options.grace_period = Some(grace_period);
+        self
+    }
+
+    /// Treats the tracked message as new again once it has not been seen for `ttl`.
+    ///
+    /// See [`RateLog::with_idle_ttl`] for details.
+    pub fn idle_ttl(mut self, ttl: Duration) -> Self {
+        self.options.idle_ttl = Some(ttl);
+        self
+    }
+
+    /// Summarizes the pending repeats of a message once it has been quiet for `timeout`.
+    ///
+    /// See [`RateLog::with_settle_timeout`] for details.
+    pub fn settle_timeout(mut self, timeout: Duration) -> Self {
+        self.options.settle_timeout = Some(timeout);
+        self
+    }
+
+    /// Holds back further rate limit warnings for `cooldown` after one was emitted.
+    ///
+    /// See [`RateLog::with_cooldown`] for details.
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.options.cooldown = Some(cooldown);
+        self
+    }
+
+    /// Emits warnings and summaries for the same key at most once per `interval`.
+    ///
+    /// See [`RateLog::with_summary_interval`] for details.
+    pub fn summary_interval(mut self, interval: Duration) -> Self {
+        self.options.summary_interval = Some(interval);
+        self
+    }
+
+    /// Selects whether the first occurrence of a message counts towards count based
+    /// limits.
+    ///
+    /// See [`CountMode`] for details.
+    pub fn count_mode(mut self, mode: CountMode) -> Self {
+        self.options.count_mode = mode;
+        self
+    }
+
+    /// Selects how the duration of pending repeats is measured.
+    ///
+    /// See [`DurationMode`] for details.
+    pub fn duration_mode(mut self, mode: DurationMode) -> Self {
+        self.options.duration_mode = mode;
+        self
+    }
+
+    /// Forgets one pending repeat for every `per` the message goes quiet.
+    ///
+    /// See [`RateLog::with_decay`] for details.
+    pub fn decay(mut self, per: Duration) -> Self {
+        self.options.decay = Some(per);
+        self
+    }
+
+    /// Escalates a message once it has repeated `threshold` times without interruption.
+    ///
+    /// See [`RateLog::with_escalation`] for details.
+    pub fn escalation(mut self, threshold: u32) -> Self {
+        self.options.escalation = Some(threshold);
+        self
+    }
+
+    /// Reads the current time from `clock`, e.g. a [`MockClock`](crate::MockClock) in tests.
+    ///
+    /// See [`RateLog::with_clock`] for details.
+    pub fn clock(mut self, clock: impl Clock + Send + 'static) -> Self {
+        self.options.clock = Some(Box::new(clock));
+        self
+    }
+
+    /// Reads the current time from a [`Monotonic`] source, such as a hardware timer.
+    ///
+    /// See [`RateLog::with_monotonic`] for details.
+    pub fn monotonic(mut self, source: impl Monotonic + Send + 'static) -> Self {
+        self.options.clock = Some(Box::new(MonotonicClock::new(source)));
+        self
+    }
+
+    /// Reads the clock only on every `every`th logging call.
+    ///
+    /// See [`RateLog::with_clock_read_interval`] for details.
+    pub fn clock_read_interval(mut self, every: u32) -> Self {
+        self.options.clock_read_interval = every;
+        self
+    }
+
+    /// Sets whether repeats that a [`Limit::Rate`] suppresses anyway skip reading the
+    /// clock. Disabled by default.
+    ///
+    /// See [`RateLog::with_lazy_clock`] for details.
+    pub fn lazy_clock(mut self, enabled: bool) -> Self {
+        self.options.lazy_clock = enabled;
+        self
+    }
+
+    /// Selects how emitted lines are rendered.
+    ///
+    /// See [`RateLog::with_output_format`] for details.
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.options.format = format;
+        self
+    }
+
+    /// Calls `callback` whenever the rate limit is exceeded.
+    ///
+    /// See [`RateLog::on_limit_exceeded`] for details.
+    pub fn on_limit_exceeded(
+        mut self,
+        callback: impl FnMut(&str, u32, Duration) + Send + 'static,
+    ) -> Self {
+        self.options.limit_callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Enables or disables writing rate limit warnings and summaries to the sink.
+    ///
+    /// See [`RateLog::with_warnings`] for details.
+    pub fn warnings(mut self, enabled: bool) -> Self {
+        self.options.warnings = enabled;
+        self
+    }
+
+    /// Enables or disables rate limiting altogether.
+    ///
+    /// See [`RateLog::with_enabled`] for details.
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.options.enabled = enabled;
+        self
+    }
+
+    /// Coalesces the warnings and summaries raised within each `tick` into one block.
+    ///
+    /// See [`RateLog::with_warning_coalescing`] for details.
+    pub fn warning_coalescing(mut self, tick: Duration) -> Self {
+        self.options.warning_coalescing = Some(tick);
+        self
+    }
+
+    /// Bounds the number of keys remembered for statistics and reports to `max`.
+    ///
+    /// See [`RateLog::with_max_tracked_messages`] for details.
+    pub fn max_tracked_messages(mut self, max: usize) -> Self {
+        self.options.max_tracked_messages = Some(max);
+        self
+    }
+
+    /// Remembers keys for statistics and reports by a 64-bit hash instead of their text.
+    ///
+    /// See [`RateLog::with_hashed_keys`] for details.
+    pub fn hashed_keys(mut self, enabled: bool) -> Self {
+        self.options.hashed_keys = enabled;
+        self
+    }
+
+    /// Applies `limit` to the messages of targets matching `pattern`.
+    ///
+    /// See [`RateLog::with_target_limit`] for details.
+    pub fn target_limit(mut self, pattern: impl Into<String>, limit: Limit) -> Self {
+        self.options.target_limits.push((pattern.into(), limit));
+        self
+    }
+
+    /// Caps the output at `max` lines per `per` across all messages.
+    ///
+    /// See [`RateLog::with_global_budget`] for details.
+    pub fn global_budget(mut self, max: u32, per: Duration) -> Self {
+        self.options.global_budget = Some((max, per));
+        self
+    }
+
+    /// Tightens per-message limits while more than `target` lines per `per` are written.
+    ///
+    /// See [`RateLog::with_adaptive_limit`] for details.
+    pub fn adaptive_limit(mut self, target: u32, per: Duration) -> Self {
+        self.options.adaptive_limit = Some((target, per));
+        self
+    }
+
+    /// Selects whether warnings and notices are colored.
+    ///
+    /// See [`RateLog::with_color`] for details.
+    #[cfg(feature = "color")]
+    pub fn color(mut self, choice: crate::ColorChoice) -> Self {
+        self.options.color = choice;
+        self
+    }
+
+    /// Creates the configured [`RateLog`].
+    pub fn build(self) -> RateLog<S> {
+        let options = self.options;
+        let mut rate_log = RateLog::with_sink(options.limit, self.sink)
+            .with_history(options.history_capacity)
+            .with_clock_read_interval(options.clock_read_interval)
+            .with_lazy_clock(options.lazy_clock)
+            .with_output_format(options.format)
+            .with_warnings(options.warnings)
+            .with_emit_first(options.emit_first)
+            .with_enabled(options.enabled)
+            .with_hashed_keys(options.hashed_keys)
+            .with_report_last_message(options.report_last_message)
+            .with_rate(options.show_rate)
+            .with_duration_style(options.duration_style)
+            .with_interval_percentiles(options.interval_percentiles)
+            .with_message_on_limit(options.message_on_limit)
+            .with_count_mode(options.count_mode)
+            .with_duration_mode(options.duration_mode);
+        #[cfg(feature = "color")]
+        {
+            rate_log = rate_log.with_color(options.color);
+        }
+        if let Some(normalizer) = options.normalizer {
+            rate_log = rate_log.group_by(normalizer);
+        }
+        for key in options.quiet_keys {
+            rate_log = rate_log.with_quiet_key(key);
+        }
+        for (pattern, limit) in options.target_limits {
+            rate_log = rate_log.with_target_limit(pattern, limit);
+        }
+        if let Some((set_size, within)) = options.alternation {
+            rate_log = rate_log.with_alternation_batching(set_size, within);
+        }
+        if let Some(window) = options.dedup_window {
+            rate_log = rate_log.with_dedup_window(window);
+        }
+        if let Some(interval) = options.auto_report {
+            rate_log = rate_log.with_auto_report(interval);
+        }
+        for (severity, weight) in options.severity_weights {
+            rate_log = rate_log.with_severity_weight(severity, weight);
+        }
+        if let Some(source) = options.exemplars {
+            rate_log = rate_log.with_exemplars(source);
+        }
+        if let Some(formatter) = options.formatter {
+            rate_log = rate_log.with_formatter(formatter);
+        }
+        if let Some(limiter) = options.limiter {
+            rate_log = rate_log.with_limiter(limiter);
+        }
+        if let Some(grace_period) = options.grace_period {
+            rate_log = rate_log.with_grace_period(grace_period);
+        }
+        if let Some((max, per)) = options.global_budget {
+            rate_log = rate_log.with_global_budget(max, per);
+        }
+        if let Some((target, per)) = options.adaptive_limit {
+            rate_log = rate_log.with_adaptive_limit(target, per);
+        }
+        if let Some(max) = options.max_tracked_messages {
+            rate_log = rate_log.with_max_tracked_messages(max);
+        }
+        if let Some(tick) = options.warning_coalescing {
+            rate_log = rate_log.with_warning_coalescing(tick);
+        }
+        if let Some(ttl) = options.idle_ttl {
+            rate_log = rate_log.with_idle_ttl(ttl);
+        }
+        if let Some(timeout) = options.settle_timeout {
+            rate_log = rate_log.with_settle_timeout(timeout);
+        }
+        if let Some(cooldown) = options.cooldown {
+            rate_log = rate_log.with_cooldown(cooldown);
+        }
+        if let Some(interval) = options.summary_interval {
+            rate_log = rate_log.with_summary_interval(interval);
+        }
+        if let Some(per) = options.decay {
+            rate_log = rate_log.with_decay(per);
+        }
+        if let Some(threshold) = options.escalation {
+            rate_log = rate_log.with_escalation(threshold);
+        }
+        if let Some(callback) = options.limit_callback {
+            rate_log = rate_log.on_limit_exceeded(callback);
+        }
+        if let Some(clock) = options.clock {
+            rate_log = rate_log.with_clock(clock);
+        }
+        if options.announce {
+            rate_log.announce();
+        }
+        rate_log
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CaptureSink;
+
+    #[test]
+    fn test_builder() {
+        let mut rate_log = RateLog::builder()
+            .history(1)
+            .sink(CaptureSink::new())
+            .limit(Limit::Rate(1))
+            .build();
+
+        rate_log.log("message1");
+        rate_log.log("message1");
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" repeat for 1 times in the past 0ms"
+            ]
+        );
+        assert_eq!(
+            rate_log.recent().collect::<Vec<_>>(),
+            ["Message: \"message1\" repeat for 1 times in the past 0ms"]
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Rate limiting `anyhow` and `eyre` errors by the root cause of their chain.
+
+use std::error::Error;
+
+use crate::{Detail, LogOutcome, RateLog, Severity, Sink};
+
+impl<S: Sink> RateLog<S> {
+    /// Logs an [`anyhow::Error`] with its whole context chain, rate limited by its root
+    /// cause.
+    ///
+    /// A retried operation usually fails with the same root cause wrapped in slightly
+    /// different contexts, e.g. `fetching user 42: connection refused` and `fetching
+    /// user 43: connection refused`, so rate limiting by the text would treat every
+    /// attempt as a new message. Errors are keyed by the text of their root cause
+    /// instead, and emitted as the full chain joined by `: ` like `{:#}` formats it.
+    /// Summaries report the key and the chain of the latest repeat. Errors are logged
+    /// with [`Severity::Error`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use anyhow::{anyhow, Context};
+    /// use rate_log::{Limit, RateLog};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(10));
+    ///
+    /// for user in [42, 43] {
+    ///     let err = Err::<(), _>(anyhow!("connection refused"))
+    ///         .with_context(|| format!("fetching user {user}"))
+    ///         .unwrap_err();
+    ///     // Prints: "fetching user 42: connection refused", the second is a repeat
+    ///     logger.log_anyhow(&err);
+    /// }
+    /// ```
+    #[cfg(feature = "anyhow")]
+    pub fn log_anyhow(&mut self, err: &anyhow::Error) -> LogOutcome {
+        self.log_error_chain(err.as_ref())
+    }
+
+    /// Logs an [`eyre::Report`] with its whole context chain, rate limited by its root
+    /// cause.
+    ///
+    /// See [`RateLog::log_anyhow`] for details.
+    #[cfg(feature = "eyre")]
+    pub fn log_eyre(&mut self, report: &eyre::Report) -> LogOutcome {
+        self.log_error_chain(report.as_ref())
+    }
+
+    /// Logs `err` and its sources joined by `: `, keyed by the innermost source.
+    fn log_error_chain(&mut self, err: &(dyn Error + 'static)) -> LogOutcome {
+        let mut chain = err.to_string();
+        let mut root = err;
+        while let Some(source) = root.source() {
+            chain.push_str(": ");
+            chain.push_str(&source.to_string());
+            root = source;
+        }
+        let key = root.to_string();
+        self.track(&key, Detail::Text(&chain), Severity::Error, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CaptureSink, Limit, MockClock, RateLog};
+
+    #[cfg(feature = "anyhow")]
+    #[test]
+    fn test_log_anyhow() {
+        use anyhow::{anyhow, Context};
+
+        let mut rate_log =
+            RateLog::with_sink(Limit::Rate(2), CaptureSink::new()).with_clock(MockClock::new());
+
+        for user in [42, 43, 44] {
+            let err = Err::<(), _>(anyhow!("connection refused"))
+                .context("opening socket")
+                .with_context(|| format!("fetching user {user}"))
+                .unwrap_err();
+            rate_log.log_anyhow(&err);
+        }
+        rate_log.log_anyhow(&anyhow!("disk full"));
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "fetching user 42: opening socket: connection refused",
+                "Key: \"connection refused\" repeat for 2 times in the past 0ms, last message: \"fetching user 44: opening socket: connection refused\"",
+                "disk full",
+            ]
+        );
+    }
+
+    #[cfg(feature = "eyre")]
+    #[test]
+    fn test_log_eyre() {
+        use eyre::{eyre, WrapErr};
+
+        let mut rate_log =
+            RateLog::with_sink(Limit::Rate(1), CaptureSink::new()).with_clock(MockClock::new());
+
+        for attempt in 1..=2 {
+            let report = Err::<(), _>(eyre!("timed out"))
+                .wrap_err_with(|| format!("attempt {attempt}"))
+                .unwrap_err();
+            rate_log.log_eyre(&report);
+        }
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "attempt 1: timed out",
+                "Key: \"timed out\" repeat for 1 times in the past 0ms, last message: \"attempt 2: timed out\"",
+            ]
+        );
+    }
+}
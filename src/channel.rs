@@ -0,0 +1,135 @@
+//! Delivering output to in-process consumers over a channel.
+
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use crate::{Event, LineKind, Severity, Sink};
+
+/// A sink sending every emitted line as a typed [`Event`] over a
+/// [`std::sync::mpsc`] channel instead of writing text.
+///
+/// Lines reaching the sink without an event, e.g. through a text transforming wrapper
+/// such as [`Decorated`](crate::Decorated), arrive as [`Event::Emitted`] for messages
+/// and as an [`Event::Notice`] of kind `text` otherwise.
+///
+/// Lets applications route messages and summaries to a GUI, a TUI or a custom
+/// transport on another thread. Sending never blocks. Once the receiver is dropped,
+/// events are discarded and [`ChannelSink::is_disconnected`] returns `true`.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{ChannelSink, Event, Limit, RateLog, Severity};
+///
+/// let (sink, events) = ChannelSink::channel();
+/// let mut logger = RateLog::with_sink(Limit::Rate(5), sink);
+///
+/// logger.log("Connection lost");
+/// assert_eq!(
+///     events.recv().unwrap(),
+///     Event::Emitted { msg: "Connection lost".into(), severity: Severity::Info }
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChannelSink {
+    sender: Sender<Event<'static>>,
+    disconnected: bool,
+}
+
+impl ChannelSink {
+    /// Creates a sink sending events to `sender`.
+    pub fn new(sender: Sender<Event<'static>>) -> Self {
+        ChannelSink {
+            sender,
+            disconnected: false,
+        }
+    }
+
+    /// Creates a sink together with the receiving end of its channel.
+    pub fn channel() -> (Self, Receiver<Event<'static>>) {
+        let (sender, receiver) = mpsc::channel();
+        (ChannelSink::new(sender), receiver)
+    }
+
+    /// Returns `true` if the receiver was dropped, so events are discarded.
+    pub fn is_disconnected(&self) -> bool {
+        self.disconnected
+    }
+}
+
+impl Sink for ChannelSink {
+    fn emit(&mut self, record: &str) {
+        self.emit_line(record, LineKind::Message(Severity::default()));
+    }
+
+    fn emit_line(&mut self, record: &str, kind: LineKind) {
+        self.emit_event(&Event::from_line(record, kind), record);
+    }
+
+    fn emit_event(&mut self, event: &Event<'_>, _line: &str) {
+        if self.disconnected {
+            return;
+        }
+        if self.sender.send(event.clone().into_owned()).is_err() {
+            self.disconnected = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Decorated, Limit, MockClock, RateLog, Trigger, WarningKind};
+    use std::time::Duration;
+
+    #[test]
+    fn test_channel_sink() {
+        let (sink, events) = ChannelSink::channel();
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(Limit::Rate(1), sink).with_clock(clock.clone());
+
+        rate_log.log_with_severity(Severity::Error, "message1");
+        clock.advance(Duration::from_millis(5));
+        rate_log.log("message1");
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            [
+                Event::Emitted {
+                    msg: "message1".into(),
+                    severity: Severity::Error
+                },
+                Event::Summary {
+                    key: "message1".into(),
+                    count: 1,
+                    duration: Duration::from_millis(5),
+                    kind: WarningKind::LimitExceeded(Trigger::Count)
+                },
+            ]
+        );
+
+        drop(events);
+        rate_log.log("message2");
+        assert!(rate_log.sink().is_disconnected());
+    }
+
+    #[test]
+    fn test_channel_sink_lines() {
+        let (sink, events) = ChannelSink::channel();
+        let mut rate_log = RateLog::with_sink(Limit::Rate(1), Decorated::new(sink).prefix("> "));
+
+        rate_log.log("message1");
+        rate_log.log("message1");
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            [
+                Event::Emitted {
+                    msg: "> message1".into(),
+                    severity: Severity::Info
+                },
+                Event::Notice {
+                    kind: "text",
+                    text: "> Message: \"message1\" repeat for 1 times in the past 0ms".into()
+                },
+            ]
+        );
+    }
+}
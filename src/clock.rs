@@ -0,0 +1,345 @@
+//! Reading the current time for rate limiting.
+
+use alloc::boxed::Box;
+#[cfg(not(feature = "std"))]
+use core::ops::{Add, AddAssign, Sub};
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "std")]
+use std::sync::Arc;
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+pub use std::time::Instant;
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+pub(crate) use std::time::{SystemTime, UNIX_EPOCH};
+// `std::time` panics on `wasm32-unknown-unknown`; `web_time` reads `performance.now()`
+// and `Date.now()` there, and re-exports `std::time` on every other target
+#[cfg(feature = "wasm")]
+pub use web_time::Instant;
+#[cfg(feature = "wasm")]
+pub(crate) use web_time::{SystemTime, UNIX_EPOCH};
+
+/// A point in time, measured on the time line of a [`Monotonic`] source.
+///
+/// Replaces `std::time::Instant` when building without the `std` feature. It holds the
+/// time elapsed since the source's fixed starting point and mirrors the subset of the
+/// `std` API used for rate limiting.
+#[cfg(not(feature = "std"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(Duration);
+
+#[cfg(not(feature = "std"))]
+impl Instant {
+    /// Returns the instant `elapsed` after the starting point of the time line.
+    pub const fn from_elapsed(elapsed: Duration) -> Self {
+        Instant(elapsed)
+    }
+
+    /// Returns the time elapsed from `earlier` to this instant, or zero if `earlier`
+    /// is later.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        self.0.saturating_sub(earlier.0)
+    }
+
+    /// Returns the time elapsed from `earlier` to this instant, or `None` if `earlier`
+    /// is later.
+    pub fn checked_duration_since(&self, earlier: Instant) -> Option<Duration> {
+        self.0.checked_sub(earlier.0)
+    }
+
+    /// Returns the time elapsed from `earlier` to this instant, or zero if `earlier`
+    /// is later.
+    pub fn saturating_duration_since(&self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+
+    /// Returns the instant `duration` later, or `None` on overflow.
+    pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+        self.0.checked_add(duration).map(Instant)
+    }
+
+    /// Returns the instant `duration` earlier, or `None` before the starting point.
+    pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+        self.0.checked_sub(duration).map(Instant)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        Instant(self.0 + duration)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, duration: Duration) {
+        self.0 += duration;
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, duration: Duration) -> Instant {
+        Instant(self.0 - duration)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+/// A monotonic time source, such as a hardware timer.
+///
+/// Returns the time elapsed since a fixed starting point, e.g. boot; only differences
+/// between readings matter, so the starting point is arbitrary. This is how targets
+/// without the `std` feature supply time, with [`RateLog::with_monotonic`]. Without a
+/// source time stands still there, so only count based limits apply.
+///
+/// [`RateLog::with_monotonic`]: crate::RateLog::with_monotonic
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{Limit, Monotonic, RateLog};
+/// use std::time::Duration;
+///
+/// /// A free-running microsecond counter, e.g. a Cortex-M timer peripheral.
+/// struct Timer;
+///
+/// impl Timer {
+///     fn ticks(&self) -> u64 {
+///         // Read the hardware register here
+///         0
+///     }
+/// }
+///
+/// impl Monotonic for Timer {
+///     fn elapsed(&self) -> Duration {
+///         Duration::from_micros(self.ticks())
+///     }
+/// }
+///
+/// let logger = RateLog::new(Limit::Duration(Duration::from_secs(5))).with_monotonic(Timer);
+/// ```
+pub trait Monotonic {
+    /// Returns the time elapsed since the starting point of the source.
+    fn elapsed(&self) -> Duration;
+}
+
+/// Adapts a [`Monotonic`] source to a [`Clock`].
+pub(crate) struct MonotonicClock<M> {
+    /// The time source.
+    source: M,
+    /// The reading of the source when the clock was created.
+    start: Duration,
+    /// The instant `start` corresponds to.
+    origin: Instant,
+}
+
+impl<M: Monotonic> MonotonicClock<M> {
+    pub(crate) fn new(source: M) -> Self {
+        let start = source.elapsed();
+        #[cfg(feature = "std")]
+        let origin = Instant::now();
+        #[cfg(not(feature = "std"))]
+        let origin = Instant::from_elapsed(start);
+        MonotonicClock {
+            source,
+            start,
+            origin,
+        }
+    }
+}
+
+impl<M: Monotonic> Clock for MonotonicClock<M> {
+    fn now(&self) -> Instant {
+        self.origin + self.source.elapsed().saturating_sub(self.start)
+    }
+}
+
+/// The clock used without the `std` feature until a [`Monotonic`] source is installed,
+/// standing still at the starting point.
+#[cfg(not(feature = "std"))]
+struct StoppedClock;
+
+#[cfg(not(feature = "std"))]
+impl Clock for StoppedClock {
+    fn now(&self) -> Instant {
+        Instant::from_elapsed(Duration::ZERO)
+    }
+}
+
+/// A source of the current time.
+///
+/// [`RateLog`](crate::RateLog) reads the time through this trait so that tests can swap
+/// the real clock for a [`MockClock`] and check duration based limits deterministically,
+/// without sleeping. The default is [`SystemClock`].
+pub trait Clock {
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+}
+
+impl<C: Clock + ?Sized> Clock for Box<C> {
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+}
+
+/// The real clock, reading [`Instant::now`].
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A manually advanced clock for deterministic tests.
+///
+/// Time stands still until [`MockClock::advance`] is called. Clones share the same
+/// time, so one clone can be handed to a [`RateLog`](crate::RateLog) while the test
+/// keeps another to advance it.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{CaptureSink, Limit, MockClock, RateLog};
+/// use std::time::Duration;
+///
+/// let clock = MockClock::new();
+/// let mut logger = RateLog::builder()
+///     .limit(Limit::Duration(Duration::from_secs(5)))
+///     .sink(CaptureSink::new())
+///     .clock(clock.clone())
+///     .build();
+///
+/// logger.log("Disk full");
+/// clock.advance(Duration::from_secs(5));
+/// logger.log("Disk full");
+///
+/// assert_eq!(
+///     logger.sink().lines(),
+///     ["Disk full", "Message: \"Disk full\" repeat for 1 times in the past 5s"]
+/// );
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    /// The time the clock was created at.
+    start: Instant,
+    /// Nanoseconds advanced since `start`, shared between clones.
+    elapsed: Arc<AtomicU64>,
+}
+
+#[cfg(feature = "std")]
+impl MockClock {
+    /// Creates a clock standing at the current time.
+    pub fn new() -> Self {
+        MockClock {
+            start: Instant::now(),
+            elapsed: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Moves the clock, and all its clones, forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.elapsed.fetch_add(nanos, Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for MockClock {
+    fn default() -> Self {
+        MockClock::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + Duration::from_nanos(self.elapsed.load(Ordering::Relaxed))
+    }
+}
+
+/// Reads a [`Clock`] only every `every` calls, reusing the last reading in between.
+///
+/// Trades duration precision for lower per-call overhead in tight loops, where the
+/// cost of reading the clock itself is measurable.
+pub(crate) struct CoarseClock {
+    /// The underlying clock.
+    source: Box<dyn Clock + Send>,
+    /// Read the clock on every `every`th call.
+    every: u32,
+    /// Calls since the clock was last read.
+    calls: u32,
+    /// The last reading.
+    cached: Option<Instant>,
+}
+
+impl CoarseClock {
+    pub(crate) fn new() -> Self {
+        #[cfg(feature = "std")]
+        let source: Box<dyn Clock + Send> = Box::new(SystemClock);
+        #[cfg(not(feature = "std"))]
+        let source: Box<dyn Clock + Send> = Box::new(StoppedClock);
+        CoarseClock {
+            source,
+            every: 1,
+            calls: 0,
+            cached: None,
+        }
+    }
+
+    /// Replaces the underlying clock.
+    pub(crate) fn set_source(&mut self, source: impl Clock + Send + 'static) {
+        self.source = Box::new(source);
+        self.cached = None;
+    }
+
+    /// Reads the underlying clock only on every `every`th call.
+    pub(crate) fn set_every(&mut self, every: u32) {
+        self.every = every.max(1);
+        self.calls = 0;
+    }
+
+    /// Returns the last reading without counting a call, reading the clock if there is none.
+    pub(crate) fn last(&mut self) -> Instant {
+        match self.cached {
+            Some(cached) => cached,
+            None => self.now(),
+        }
+    }
+
+    /// Returns the last reading without updating it, reading the clock if there is none.
+    pub(crate) fn peek(&self) -> Instant {
+        self.cached.unwrap_or_else(|| self.source.now())
+    }
+
+    /// Returns the current time, or the cached reading if the clock is not due yet.
+    pub(crate) fn now(&mut self) -> Instant {
+        self.calls += 1;
+        match self.cached {
+            Some(cached) if self.calls < self.every => cached,
+            _ => {
+                let now = self.source.now();
+                self.calls = 0;
+                self.cached = Some(now);
+                now
+            }
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Coalescing of rate limit warnings raised by many keys within a short interval.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::clock::Instant;
+use crate::Event;
+
+/// The number of keys listed by name in a grouped warning.
+const LISTED_KEYS: usize = 5;
+
+/// Collects warnings raised within one tick so they can be emitted as a single block.
+///
+/// When an outage makes every subsystem trip its limit at once, emitting one warning
+/// per key would itself flood the output. Warnings are therefore held back until the
+/// tick that started with the first of them has passed.
+#[derive(Debug)]
+pub(crate) struct WarningCoalescer {
+    /// How long warnings are collected before they are emitted.
+    tick: Duration,
+
+    /// The time the first pending warning was raised.
+    started: Option<Instant>,
+
+    /// Pending warnings as the rendered line and its summary event, oldest first.
+    pending: Vec<(String, Event<'static>)>,
+}
+
+/// The output of a finished tick.
+pub(crate) enum Coalesced {
+    /// A single warning, emitted as rendered.
+    Single(String, Event<'static>),
+    /// A block summarizing the warnings of several keys.
+    Group(String),
+}
+
+impl WarningCoalescer {
+    pub(crate) fn new(tick: Duration) -> Self {
+        WarningCoalescer {
+            tick,
+            started: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Holds back the warning `line`, whose [`Event::Summary`] is `event`.
+    pub(crate) fn push(&mut self, line: String, event: Event<'static>, now: Instant) {
+        self.started.get_or_insert(now);
+        self.pending.push((line, event));
+    }
+
+    /// Returns `true` if the current tick has passed and pending warnings should be emitted.
+    pub(crate) fn due(&self, now: Instant) -> bool {
+        self.started
+            .is_some_and(|started| now.duration_since(started) >= self.tick)
+    }
+
+    /// Ends the current tick, returning the output for its pending warnings, if any.
+    pub(crate) fn finish(&mut self) -> Option<Coalesced> {
+        self.started = None;
+        let mut pending = core::mem::take(&mut self.pending);
+        if pending.len() <= 1 {
+            return pending
+                .pop()
+                .map(|(line, event)| Coalesced::Single(line, event));
+        }
+
+        // Merge repeated warnings of the same key, keeping first-seen order for ties
+        let mut keys: Vec<(String, u64)> = Vec::new();
+        for (_, event) in pending {
+            let Event::Summary { key, count, .. } = event else {
+                continue;
+            };
+            match keys.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, total)) => *total += u64::from(count),
+                None => keys.push((key.into_owned(), u64::from(count))),
+            }
+        }
+        keys.sort_by_key(|(_, total)| core::cmp::Reverse(*total));
+
+        let listed = keys
+            .iter()
+            .take(LISTED_KEYS)
+            .map(|(key, total)| format!("\"{key}\" ({total} repeats)"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let noun = if keys.len() == 1 { "key" } else { "keys" };
+        let mut block = format!("{} {} exceeded limits: {}", keys.len(), noun, listed);
+        if keys.len() > LISTED_KEYS {
+            block.push_str(&format!(" and {} more", keys.len() - LISTED_KEYS));
+        }
+        Some(Coalesced::Group(block))
+    }
+}
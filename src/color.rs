@@ -0,0 +1,72 @@
+//! ANSI coloring of warnings and notices for terminal output.
+
+use std::env;
+
+/// Whether emitted warnings and notices are colored.
+///
+/// Available with the `color` feature. Logged messages are always written plain; rate
+/// limit warnings, summaries and notices are yellow and escalations red, so they stand
+/// out in dense terminal output. Colors are only applied to [`OutputFormat::Text`]
+/// output.
+///
+/// [`OutputFormat::Text`]: crate::OutputFormat::Text
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{ColorChoice, Limit, RateLog};
+///
+/// // E.g. for a `--color=always` command line flag
+/// let logger = RateLog::new(Limit::Rate(10)).with_color(ColorChoice::Always);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    /// Colors output if the sink writes to a terminal, see [`Sink::is_terminal`].
+    ///
+    /// The `NO_COLOR` and `FORCE_COLOR` environment variables, when set to a non-empty
+    /// value, turn colors off or on regardless of the terminal.
+    ///
+    /// [`Sink::is_terminal`]: crate::Sink::is_terminal
+    #[default]
+    Auto,
+
+    /// Always colors output, e.g. to force colors through a pager.
+    Always,
+
+    /// Never colors output.
+    Never,
+}
+
+impl ColorChoice {
+    /// Returns `true` if output to a sink that is a terminal or not should be colored.
+    pub(crate) fn enabled(self, is_terminal: bool) -> bool {
+        let set = |name| env::var_os(name).is_some_and(|value| !value.is_empty());
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto if set("NO_COLOR") => false,
+            ColorChoice::Auto if set("FORCE_COLOR") => true,
+            ColorChoice::Auto => is_terminal,
+        }
+    }
+}
+
+/// The color of a line that is not a logged message.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Style {
+    /// Rate limit warnings, summaries and notices.
+    Warning,
+    /// Escalated messages.
+    Escalation,
+}
+
+impl Style {
+    /// Wraps `line` in the ANSI escape codes of this style.
+    pub(crate) fn paint(self, line: String) -> String {
+        let code = match self {
+            Style::Warning => "33",
+            Style::Escalation => "31",
+        };
+        format!("\x1b[{code}m{line}\x1b[0m")
+    }
+}
@@ -0,0 +1,597 @@
+//! Rate limiting for many threads logging at once, with sharded read-write locks
+//! instead of one global lock.
+
+use std::borrow::Cow;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::clock::Instant;
+use crate::{
+    Clock, DurationStyle, Error, Event, Limit, LogOutcome, Result, Severity, Sink, StdoutSink,
+    SystemClock, Trigger, WarningKind,
+};
+
+/// The counters of one tracked message, updated without locking.
+struct Entry {
+    /// Repeats since the message was first seen or last reported.
+    repeats: AtomicU64,
+    /// When the current run of repeats started, in nanoseconds since `origin`.
+    since: AtomicU64,
+    /// When the message was last logged, in nanoseconds since `origin`; only kept up
+    /// to date when the number of tracked messages is bounded.
+    last_seen: AtomicU64,
+}
+
+/// A shard of the tracked messages, read locked by repeats and write locked to add
+/// a message.
+type Shard = RwLock<HashMap<String, Entry>>;
+
+/// A rate limiter shared by many threads, built for very high logging rates.
+///
+/// Where [`SharedRateLog`](crate::SharedRateLog) serializes every call through one
+/// mutex, `ConcurrentRateLog` spreads messages over shards, each a map behind its own
+/// [`RwLock`], and counts repeats with per-message atomics. A repeated message only
+/// takes the read lock of its shard and increments a counter, so threads repeating
+/// messages at the same time do not wait for each other. Adding a new message takes
+/// the write lock of its shard, blocking the repeats of the other messages in that
+/// shard meanwhile; the sink is locked only for lines actually written.
+///
+/// Unlike [`RateLog`](crate::RateLog), which tracks runs of consecutive repeats,
+/// every distinct message is tracked on its own, since calls from different threads
+/// interleave arbitrarily. The first occurrence of a message is emitted, and a rate
+/// limit warning is emitted whenever the repeats since the last one exceed the
+/// limit. Supported limits are [`Limit::Rate`], [`Limit::Duration`] and
+/// [`Limit::Any`], which can be decided with atomics alone. Distinct messages are
+/// remembered until dropped; bound them with
+/// [`ConcurrentRateLog::with_max_tracked_messages`] when messages are
+/// high-cardinality.
+///
+/// The map is not lock-free. Like `dashmap`, which shards read-write locks the same
+/// way, it relies on repeats far outnumbering new messages: the read lock of a shard
+/// is one atomic increment, and it only waits while a new message is being added to
+/// that shard. A lock-free map would spare that wait at the cost of an external
+/// dependency and of epoch based reclamation for removed messages, which this crate
+/// does not take on.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{ConcurrentRateLog, Limit};
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let logger = Arc::new(ConcurrentRateLog::new(Limit::Rate(1000))?);
+///
+/// let handles: Vec<_> = (0..8)
+///     .map(|_| {
+///         let logger = Arc::clone(&logger);
+///         thread::spawn(move || {
+///             for _ in 0..10_000 {
+///                 logger.log("upstream unavailable");
+///             }
+///         })
+///     })
+///     .collect();
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+/// // Prints the message once and a warning for every 1000 repeats
+/// # Ok::<(), rate_log::Error>(())
+/// ```
+pub struct ConcurrentRateLog<S: Sink = StdoutSink> {
+    /// The repeat count that triggers a warning, if any.
+    max_repeats: Option<u64>,
+    /// The duration of repeats that triggers a warning, in nanoseconds, if any.
+    max_nanos: Option<u64>,
+    /// The tracked messages, spread over shards by hash.
+    shards: Box<[Shard]>,
+    /// Selects the shard of a message.
+    hasher: RandomState,
+    /// The source of the current time.
+    clock: Box<dyn Clock + Send + Sync>,
+    /// The reference point of the timestamps stored in entries.
+    origin: Instant,
+    /// The number of messages tracked across all shards.
+    tracked: AtomicUsize,
+    /// The number of messages tracked before the least recently seen is evicted.
+    max_tracked: Option<usize>,
+    /// How durations are written in warnings and summaries, behind a lock since a
+    /// custom style need not be `Sync`.
+    duration_style: Mutex<DurationStyle>,
+    /// The destination of emitted lines.
+    sink: Mutex<S>,
+}
+
+impl ConcurrentRateLog {
+    /// Creates a concurrent limiter writing to stdout.
+    ///
+    /// Supports the same limits as [`ConcurrentRateLog::with_sink`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] for any limit other than [`Limit::Rate`],
+    /// [`Limit::Duration`] and [`Limit::Any`].
+    pub fn new(limit: Limit) -> Result<Self> {
+        ConcurrentRateLog::with_sink(limit, StdoutSink)
+    }
+}
+
+impl<S: Sink> ConcurrentRateLog<S> {
+    /// Creates a concurrent limiter writing to `sink`.
+    ///
+    /// Uses four shards per available CPU; see [`ConcurrentRateLog::with_shards`].
+    ///
+    /// Only [`Limit::Rate`], [`Limit::Duration`] and [`Limit::Any`] are supported: they
+    /// are decided with a repeat counter and a start time per message, which threads
+    /// update with atomics. The other limits keep per-message state, such as the
+    /// timestamps of a window or a token count, that would need a lock per message;
+    /// use a [`SharedRateLog`](crate::SharedRateLog) for them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] for any other limit, e.g. [`Limit::Window`] or
+    /// [`Limit::TokenBucket`].
+    pub fn with_sink(limit: Limit, sink: S) -> Result<Self> {
+        let (max_repeats, max_duration) = match limit {
+            Limit::Rate(count) => (Some(count), None),
+            Limit::Duration(duration) => (None, Some(duration)),
+            Limit::Any(count, duration) => (Some(count), Some(duration)),
+            limit => {
+                return Err(Error::Config(format!(
+                    "{limit:?} is not supported by ConcurrentRateLog"
+                )))
+            }
+        };
+        let cpus = thread::available_parallelism().map_or(1, |cpus| cpus.get());
+        let clock = SystemClock;
+        Ok(ConcurrentRateLog {
+            max_repeats: max_repeats.map(|count| u64::from(count.max(1))),
+            max_nanos: max_duration.map(nanos),
+            shards: new_shards(cpus * 4),
+            hasher: RandomState::new(),
+            origin: clock.now(),
+            clock: Box::new(clock),
+            tracked: AtomicUsize::new(0),
+            max_tracked: None,
+            duration_style: Mutex::new(DurationStyle::default()),
+            sink: Mutex::new(sink),
+        })
+    }
+
+    /// Spreads the tracked messages over `shards` independently locked maps.
+    ///
+    /// More shards make it less likely that two threads logging a new message at the
+    /// same time wait for each other; repeats never wait unless a new message is being
+    /// added to the same shard.
+    pub fn with_shards(mut self, shards: usize) -> Self {
+        self.shards = new_shards(shards);
+        self
+    }
+
+    /// Reads the current time from `clock`, e.g. a [`MockClock`](crate::MockClock) in tests.
+    pub fn with_clock(mut self, clock: impl Clock + Send + Sync + 'static) -> Self {
+        self.origin = clock.now();
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Bounds the number of distinct messages tracked to `max`.
+    ///
+    /// Like [`RateLog::with_max_tracked_messages`](crate::RateLog::with_max_tracked_messages),
+    /// adding a new message once `max` are tracked evicts the least recently seen one,
+    /// and evicting a message with unreported repeats emits a summary such as
+    /// `"evicted entry \"Disk full\" with 12 suppressed repeats"`. An evicted message
+    /// logged again counts as a first occurrence. Finding the least recently seen
+    /// message scans every shard, so only new messages pay for the bound; threads adding
+    /// messages at the same time may briefly exceed it. A `max` of zero is treated as
+    /// one.
+    pub fn with_max_tracked_messages(mut self, max: usize) -> Self {
+        self.max_tracked = Some(max.max(1));
+        self
+    }
+
+    /// Sets how durations are written in warnings and summaries, see
+    /// [`RateLog::with_duration_style`](crate::RateLog::with_duration_style).
+    pub fn with_duration_style(mut self, style: DurationStyle) -> Self {
+        self.duration_style = Mutex::new(style);
+        self
+    }
+
+    /// Logs a message, rate limited per distinct message.
+    ///
+    /// Returns [`LogOutcome::Emitted`] for the first occurrence,
+    /// [`LogOutcome::LimitReported`] for the call that emitted a rate limit warning and
+    /// [`LogOutcome::Suppressed`] otherwise.
+    pub fn log(&self, msg: &str) -> LogOutcome {
+        let shard = &self.shards[self.hasher.hash_one(msg) as usize % self.shards.len()];
+        {
+            let entries = shard.read().unwrap_or_else(PoisonError::into_inner);
+            if let Some(entry) = entries.get(msg) {
+                return self.repeat(msg, entry);
+            }
+        }
+
+        if let Some(max) = self.max_tracked {
+            while self.tracked.load(Ordering::Acquire) >= max && self.evict_least_recent() {}
+        }
+        let mut entries = shard.write().unwrap_or_else(PoisonError::into_inner);
+        if let Some(entry) = entries.get(msg) {
+            // Another thread added the message in the meantime
+            return self.repeat(msg, entry);
+        }
+        let now = self.elapsed();
+        entries.insert(
+            msg.to_string(),
+            Entry {
+                repeats: AtomicU64::new(0),
+                since: AtomicU64::new(now),
+                last_seen: AtomicU64::new(now),
+            },
+        );
+        self.tracked.fetch_add(1, Ordering::AcqRel);
+        drop(entries);
+        let event = Event::Emitted {
+            msg: Cow::Borrowed(msg),
+            severity: Severity::default(),
+        };
+        self.write(msg, &event);
+        LogOutcome::Emitted
+    }
+
+    /// Emits a summary for every message with repeats not yet reported, then flushes
+    /// the sink.
+    pub fn flush(&self) {
+        let now = self.elapsed();
+        for shard in self.shards.iter() {
+            let entries = shard.read().unwrap_or_else(PoisonError::into_inner);
+            for (msg, entry) in entries.iter() {
+                let repeats = entry.repeats.swap(0, Ordering::AcqRel);
+                if repeats == 0 {
+                    continue;
+                }
+                let since = entry.since.swap(now, Ordering::AcqRel);
+                let duration = Duration::from_nanos(now.saturating_sub(since));
+                let line = format!(
+                    "Message: \"{msg}\" suppressed {repeats} repeats over {}",
+                    self.format_duration(duration)
+                );
+                let event = Event::Summary {
+                    key: Cow::Borrowed(msg),
+                    count: u32::try_from(repeats).unwrap_or(u32::MAX),
+                    duration,
+                    kind: WarningKind::Summary,
+                };
+                self.write(&line, &event);
+            }
+        }
+        self.sink().flush();
+    }
+
+    /// Returns the number of distinct messages tracked.
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.read().unwrap_or_else(PoisonError::into_inner).len())
+            .sum()
+    }
+
+    /// Returns `true` if no message has been logged yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Locks the sink for direct access, e.g. to inspect a
+    /// [`CaptureSink`](crate::CaptureSink) in tests.
+    pub fn sink(&self) -> MutexGuard<'_, S> {
+        self.sink.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Counts a repeat of `msg` and emits a warning if it exceeds the limit.
+    fn repeat(&self, msg: &str, entry: &Entry) -> LogOutcome {
+        let repeats = entry.repeats.fetch_add(1, Ordering::AcqRel) + 1;
+        // Exactly one thread sees the count reach the threshold
+        let count_reached = self.max_repeats == Some(repeats);
+
+        let now = self.elapsed();
+        if self.max_tracked.is_some() {
+            entry.last_seen.fetch_max(now, Ordering::Relaxed);
+        }
+        let since = entry.since.load(Ordering::Acquire);
+        let duration_reached = self
+            .max_nanos
+            .is_some_and(|max| now > since && now - since >= max);
+
+        let trigger = match (count_reached, duration_reached) {
+            (false, false) => return LogOutcome::Suppressed,
+            (true, false) => {
+                entry.since.store(now, Ordering::Release);
+                Trigger::Count
+            }
+            (count_reached, true) => {
+                // Only the thread that moves the start of the run reports it
+                if entry
+                    .since
+                    .compare_exchange(since, now, Ordering::AcqRel, Ordering::Acquire)
+                    .is_err()
+                {
+                    return LogOutcome::Suppressed;
+                }
+                if count_reached {
+                    Trigger::CountAndDuration
+                } else {
+                    Trigger::Duration
+                }
+            }
+        };
+
+        // Repeats counted by other threads since the threshold are reported as well
+        let count = entry.repeats.swap(0, Ordering::AcqRel);
+        if count == 0 {
+            return LogOutcome::Suppressed;
+        }
+        let duration = Duration::from_nanos(now.saturating_sub(since));
+        let line = format!(
+            "Message: \"{msg}\" repeat for {count} times in the past {}",
+            self.format_duration(duration)
+        );
+        let count = u32::try_from(count).unwrap_or(u32::MAX);
+        let event = Event::Summary {
+            key: Cow::Borrowed(msg),
+            count,
+            duration,
+            kind: WarningKind::LimitExceeded(trigger),
+        };
+        self.write(&line, &event);
+        LogOutcome::LimitReported {
+            count,
+            duration,
+            trigger,
+        }
+    }
+
+    /// Forgets the least recently seen message, summarizing its unreported repeats.
+    ///
+    /// Returns `false` if no message is tracked.
+    fn evict_least_recent(&self) -> bool {
+        let mut oldest: Option<(usize, String, u64)> = None;
+        for (index, shard) in self.shards.iter().enumerate() {
+            let entries = shard.read().unwrap_or_else(PoisonError::into_inner);
+            for (msg, entry) in entries.iter() {
+                let last_seen = entry.last_seen.load(Ordering::Relaxed);
+                if oldest
+                    .as_ref()
+                    .map_or(true, |(_, _, oldest)| last_seen < *oldest)
+                {
+                    oldest = Some((index, msg.clone(), last_seen));
+                }
+            }
+        }
+        let Some((index, msg, _)) = oldest else {
+            return false;
+        };
+
+        let mut entries = self.shards[index]
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        // Another thread may have evicted the message in the meantime
+        let Some(entry) = entries.remove(&msg) else {
+            return true;
+        };
+        self.tracked.fetch_sub(1, Ordering::AcqRel);
+        drop(entries);
+
+        let suppressed = entry.repeats.into_inner();
+        if suppressed > 0 {
+            let line = format!("evicted entry \"{msg}\" with {suppressed} suppressed repeats");
+            let event = Event::Evicted {
+                key: Cow::Borrowed(&msg),
+                suppressed,
+            };
+            self.write(&line, &event);
+        }
+        true
+    }
+
+    /// Renders `duration` in the configured style.
+    fn format_duration(&self, duration: Duration) -> String {
+        self.duration_style
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .format(duration)
+    }
+
+    /// Returns the nanoseconds elapsed since `origin`.
+    fn elapsed(&self) -> u64 {
+        nanos(self.clock.now().duration_since(self.origin))
+    }
+
+    /// Writes a line and the event it renders to the sink.
+    fn write(&self, line: &str, event: &Event<'_>) {
+        self.sink().emit_event(event, line);
+    }
+}
+
+impl<S: Sink> Drop for ConcurrentRateLog<S> {
+    /// Flushes pending repeats so the information that spam occurred is never lost.
+    fn drop(&mut self) {
+        if !thread::panicking() {
+            self.flush();
+        }
+    }
+}
+
+/// Creates `count` empty shards, at least one.
+fn new_shards(count: usize) -> Box<[Shard]> {
+    (0..count.max(1)).map(|_| Shard::default()).collect()
+}
+
+/// Converts a duration to nanoseconds, saturating at `u64::MAX`.
+fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CaptureSink, MockClock};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_concurrent_limits() {
+        let clock = MockClock::new();
+        let limit = Limit::Any(3, Duration::from_secs(1));
+        let logger = ConcurrentRateLog::with_sink(limit, CaptureSink::new())
+            .unwrap()
+            .with_clock(clock.clone());
+
+        for _ in 0..5 {
+            logger.log("message1");
+        }
+        logger.log("message2");
+        assert_eq!(logger.log("message2"), LogOutcome::Suppressed);
+        clock.advance(Duration::from_secs(1));
+        assert!(matches!(
+            logger.log("message2"),
+            LogOutcome::LimitReported {
+                count: 2,
+                trigger: Trigger::Duration,
+                ..
+            }
+        ));
+        logger.flush();
+
+        assert_eq!(
+            logger.sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" repeat for 3 times in the past 0ms",
+                "message2",
+                "Message: \"message2\" repeat for 2 times in the past 1s",
+                "Message: \"message1\" suppressed 1 repeats over 1s"
+            ]
+        );
+        assert!(ConcurrentRateLog::new(Limit::Exponential { base: 2 }).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_limits() {
+        let unsupported = [
+            Limit::All(3, Duration::from_secs(1)),
+            Limit::Window {
+                max: 3,
+                per: Duration::from_secs(1),
+            },
+            Limit::TokenBucket {
+                capacity: 3,
+                refill_per_sec: 1.0,
+            },
+            Limit::Sample(3),
+            Limit::Never,
+        ];
+        for limit in unsupported {
+            let err = ConcurrentRateLog::with_sink(limit.clone(), CaptureSink::new()).err();
+            assert!(
+                matches!(&err, Some(Error::Config(msg)) if msg.ends_with("not supported by ConcurrentRateLog")),
+                "{limit:?}"
+            );
+        }
+
+        // A count of 0 warns on every repeat, like 1
+        let logger = ConcurrentRateLog::with_sink(Limit::Rate(0), CaptureSink::new()).unwrap();
+        logger.log("message1");
+        assert!(matches!(
+            logger.log("message1"),
+            LogOutcome::LimitReported { count: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn test_max_tracked_messages() {
+        let clock = MockClock::new();
+        let logger = ConcurrentRateLog::with_sink(Limit::Rate(10), CaptureSink::new())
+            .unwrap()
+            .with_clock(clock.clone())
+            .with_max_tracked_messages(2)
+            .with_duration_style(DurationStyle::Exact);
+
+        logger.log("message1");
+        clock.advance(Duration::from_millis(1500));
+        logger.log("message2");
+        logger.log("message2");
+        clock.advance(Duration::from_secs(1));
+        logger.log("message1");
+        clock.advance(Duration::from_millis(500));
+        logger.log("message3");
+        assert_eq!(logger.len(), 2);
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(logger.log("message2"), LogOutcome::Emitted);
+        assert_eq!(logger.len(), 2);
+        clock.advance(Duration::from_secs(2));
+        logger.log("message3");
+        logger.flush();
+
+        assert_eq!(
+            logger.sink().lines(),
+            [
+                "message1",
+                "message2",
+                "evicted entry \"message2\" with 1 suppressed repeats",
+                "message3",
+                "evicted entry \"message1\" with 1 suppressed repeats",
+                "message2",
+                "Message: \"message3\" suppressed 1 repeats over 2.5s"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_concurrent_counts() {
+        let logger = Arc::new(
+            ConcurrentRateLog::with_sink(Limit::Rate(100), CaptureSink::new())
+                .unwrap()
+                .with_shards(2),
+        );
+
+        let handles: Vec<_> = (0..8)
+            .map(|thread| {
+                let logger = Arc::clone(&logger);
+                thread::spawn(move || {
+                    for i in 0..1000 {
+                        logger.log(&format!("message{}", (thread + i) % 4));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        logger.flush();
+
+        // Every repeat is reported exactly once, either in a warning or the summary
+        let lines = logger.sink().lines().to_vec();
+        let reported: u64 = lines
+            .iter()
+            .filter_map(|line| {
+                let count = line
+                    .split(" repeat for ")
+                    .nth(1)
+                    .or_else(|| line.split(" suppressed ").nth(1))?;
+                count.split(' ').next()?.parse::<u64>().ok()
+            })
+            .sum();
+        assert_eq!(logger.len(), 4);
+        assert_eq!(
+            lines
+                .iter()
+                .filter(|line| line.starts_with("message"))
+                .count(),
+            4
+        );
+        assert_eq!(reported, 8000 - 4);
+    }
+}
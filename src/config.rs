@@ -0,0 +1,391 @@
+//! Configuration of limiters from strings, such as environment variables.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::{Error, Limit, OutputFormat, RateLog, RateLogBuilder, Sink};
+
+/// The environment variable read by [`RateLogBuilder::from_env`].
+const ENV_VAR: &str = "RATE_LOG";
+
+/// An error in a configuration string, see [`RateLogBuilder::from_spec`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// An option is not of the form `name=value`.
+    Malformed(String),
+
+    /// An option name is not recognized.
+    UnknownOption(String),
+
+    /// The value of an option cannot be parsed or is not supported.
+    InvalidValue {
+        /// The name of the option.
+        option: String,
+        /// The rejected value.
+        value: String,
+    },
+
+    /// The environment variable is set but not valid Unicode.
+    NotUnicode,
+
+    /// A configuration file is not valid TOML or YAML, or has unknown fields.
+    Parse(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Malformed(option) => {
+                write!(f, "expected `name=value`, found \"{option}\"")
+            }
+            ConfigError::UnknownOption(name) => write!(f, "unknown option \"{name}\""),
+            ConfigError::InvalidValue { option, value } => {
+                write!(f, "invalid value \"{value}\" for option \"{option}\"")
+            }
+            ConfigError::NotUnicode => write!(f, "{ENV_VAR} is not valid Unicode"),
+            ConfigError::Parse(msg) => write!(f, "malformed configuration file: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<ConfigError> for Error {
+    fn from(err: ConfigError) -> Self {
+        Error::Config(err.to_string())
+    }
+}
+
+impl RateLogBuilder {
+    /// Creates a builder configured by the `RATE_LOG` environment variable.
+    ///
+    /// Lets deployments tune throttling without a rebuild. The variable holds the
+    /// options described in [`RateLogBuilder::from_spec`]; if it is not set, every
+    /// option keeps its default. Options set on the returned builder override the
+    /// environment.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ConfigError`] if the variable is not valid Unicode or contains an
+    /// invalid option.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::RateLogBuilder;
+    ///
+    /// // RATE_LOG=limit=rate:100,window=10s,format=text
+    /// let mut logger = RateLogBuilder::from_env()?.build();
+    /// logger.log("Connection lost");
+    /// # Ok::<(), rate_log::ConfigError>(())
+    /// ```
+    pub fn from_env() -> Result<Self, ConfigError> {
+        match std::env::var(ENV_VAR) {
+            Ok(spec) => RateLogBuilder::from_spec(&spec),
+            Err(std::env::VarError::NotPresent) => Ok(RateLogBuilder::new()),
+            Err(std::env::VarError::NotUnicode(_)) => Err(ConfigError::NotUnicode),
+        }
+    }
+
+    /// Creates a builder configured by a comma-separated list of `name=value` options.
+    ///
+    /// Durations are written as an integer with a unit of `ms`, `s`, `m` or `h`, and
+    /// switches as `true` or `false`. The supported options are:
+    ///
+    /// - **`limit`**: `rate:N`, `duration:D`, `any:N:D`, `all:N:D`, `window:N:D`,
+    ///   `bytes:N:D`, `token_bucket:CAPACITY:PER_SEC`, `gcra:D:BURST`, `exponential:BASE`,
+    ///   `sample:N`, `burst:N:D`, `never` or `always`
+    /// - **`window`**: a duration turning `limit=rate:N` into at most N messages per
+    ///   window, i.e. [`Limit::Window`]
+    /// - **`format`**: `text`, or `json` with the `serde` feature
+    /// - **`cooldown`**, **`grace_period`**, **`idle_ttl`**, **`decay`**,
+    ///   **`coalesce`**: durations, see the builder methods of the same name
+    ///   (`coalesce` is [`RateLogBuilder::warning_coalescing`])
+    /// - **`escalation`**, **`history`**, **`max_tracked`**: counts
+    /// - **`budget`**: `N:D`, at most N lines per D across all messages
+    /// - **`announce`**, **`warnings`**, **`rate`**, **`percentiles`**,
+    ///   **`hashed_keys`**: switches
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ConfigError`] for the first invalid option.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::RateLogBuilder;
+    ///
+    /// let mut logger = RateLogBuilder::from_spec("limit=rate:100,window=10s,cooldown=30s")?
+    ///     .history(16)
+    ///     .build();
+    /// # Ok::<(), rate_log::ConfigError>(())
+    /// ```
+    pub fn from_spec(spec: &str) -> Result<Self, ConfigError> {
+        RateLogBuilder::new().apply_spec(spec)
+    }
+}
+
+impl<S: Sink> RateLogBuilder<S> {
+    /// Applies the options of `spec` on top of the current settings.
+    pub(crate) fn apply_spec(mut self, spec: &str) -> Result<Self, ConfigError> {
+        let mut limit = None;
+        let mut window = None;
+
+        for option in spec.split(',').map(str::trim).filter(|o| !o.is_empty()) {
+            let (name, value) = option
+                .split_once('=')
+                .map(|(name, value)| (name.trim(), value.trim()))
+                .ok_or_else(|| ConfigError::Malformed(option.to_string()))?;
+            let invalid = || ConfigError::InvalidValue {
+                option: name.to_string(),
+                value: value.to_string(),
+            };
+
+            self = match name {
+                "limit" => {
+                    limit = Some(parse_limit(value).ok_or_else(invalid)?);
+                    self
+                }
+                "window" => {
+                    window = Some((parse_duration(value).ok_or_else(invalid)?, value));
+                    self
+                }
+                "format" => self.format(parse_format(value).ok_or_else(invalid)?),
+                "cooldown" => self.cooldown(parse_duration(value).ok_or_else(invalid)?),
+                "grace_period" => self.grace_period(parse_duration(value).ok_or_else(invalid)?),
+                "idle_ttl" => self.idle_ttl(parse_duration(value).ok_or_else(invalid)?),
+                "decay" => self.decay(parse_duration(value).ok_or_else(invalid)?),
+                "coalesce" => self.warning_coalescing(parse_duration(value).ok_or_else(invalid)?),
+                "escalation" => self.escalation(value.parse().map_err(|_| invalid())?),
+                "history" => self.history(value.parse().map_err(|_| invalid())?),
+                "max_tracked" => self.max_tracked_messages(value.parse().map_err(|_| invalid())?),
+                "budget" => {
+                    let (max, per) = value.split_once(':').ok_or_else(invalid)?;
+                    let max = max.parse().map_err(|_| invalid())?;
+                    self.global_budget(max, parse_duration(per).ok_or_else(invalid)?)
+                }
+                "announce" => self.announce(parse_bool(value).ok_or_else(invalid)?),
+                "warnings" => self.warnings(parse_bool(value).ok_or_else(invalid)?),
+                "rate" => self.rate(parse_bool(value).ok_or_else(invalid)?),
+                "percentiles" => self.interval_percentiles(parse_bool(value).ok_or_else(invalid)?),
+                "hashed_keys" => self.hashed_keys(parse_bool(value).ok_or_else(invalid)?),
+                _ => return Err(ConfigError::UnknownOption(name.to_string())),
+            };
+        }
+
+        let limit = match (limit, window) {
+            (limit, None) => limit,
+            (Some(Limit::Rate(max)), Some((per, _))) => Some(Limit::Window { max, per }),
+            // Only a count limit can be spread over a window
+            (_, Some((_, value))) => {
+                return Err(ConfigError::InvalidValue {
+                    option: "window".to_string(),
+                    value: value.to_string(),
+                })
+            }
+        };
+        if let Some(limit) = limit {
+            self = self.limit(limit);
+        }
+        Ok(self)
+    }
+}
+
+impl RateLog {
+    /// Creates a limiter writing to stdout, configured by the `RATE_LOG` environment
+    /// variable.
+    ///
+    /// Shorthand for `RateLogBuilder::from_env()?.build()`; see
+    /// [`RateLogBuilder::from_env`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ConfigError`] if the variable contains an invalid option.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Ok(RateLogBuilder::from_env()?.build())
+    }
+}
+
+/// Parses a limit such as `rate:100` or `any:100:30s`.
+pub(crate) fn parse_limit(value: &str) -> Option<Limit> {
+    let mut parts = value.split(':');
+    let kind = parts.next()?;
+    let args: Vec<&str> = parts.collect();
+    let limit = match (kind, args.as_slice()) {
+        ("rate", [count]) => Limit::Rate(count.parse().ok()?),
+        ("duration", [duration]) => Limit::Duration(parse_duration(duration)?),
+        ("any", [count, duration]) => Limit::Any(count.parse().ok()?, parse_duration(duration)?),
+        ("all", [count, duration]) => Limit::All(count.parse().ok()?, parse_duration(duration)?),
+        ("window", [max, per]) => Limit::Window {
+            max: max.parse().ok()?,
+            per: parse_duration(per)?,
+        },
+        ("bytes", [max, per]) => Limit::Bytes {
+            max: max.parse().ok()?,
+            per: parse_duration(per)?,
+        },
+        ("token_bucket", [capacity, refill_per_sec]) => Limit::TokenBucket {
+            capacity: capacity
+                .parse()
+                .ok()
+                .filter(|&capacity: &u32| capacity > 0)?,
+            // NaN, infinite and negative rates would refill always or never
+            refill_per_sec: refill_per_sec
+                .parse()
+                .ok()
+                .filter(|rate: &f64| rate.is_finite() && *rate >= 0.0)?,
+        },
+        ("gcra", [period, burst]) => Limit::Gcra {
+            period: parse_duration(period)?,
+            burst: burst.parse().ok()?,
+        },
+        ("exponential", [base]) => Limit::Exponential {
+            base: base.parse().ok()?,
+        },
+        ("sample", [rate]) => Limit::Sample(rate.parse().ok()?),
+        ("burst", [burst, interval]) => Limit::BurstThenSteady {
+            burst: burst.parse().ok()?,
+            interval: parse_duration(interval)?,
+        },
+        ("never", []) => Limit::Never,
+        ("always", []) => Limit::Always,
+        _ => return None,
+    };
+    Some(limit)
+}
+
+/// Parses a duration such as `500ms`, `10s`, `5m` or `1h`.
+pub(crate) fn parse_duration(value: &str) -> Option<Duration> {
+    let split = value.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount.parse().ok()?;
+    let duration = match unit {
+        "ms" => Duration::from_millis(amount),
+        "s" => Duration::from_secs(amount),
+        "m" => Duration::from_secs(amount.checked_mul(60)?),
+        "h" => Duration::from_secs(amount.checked_mul(3600)?),
+        _ => return None,
+    };
+    Some(duration)
+}
+
+/// Parses an output format name.
+pub(crate) fn parse_format(value: &str) -> Option<OutputFormat> {
+    match value {
+        "text" => Some(OutputFormat::Text),
+        #[cfg(feature = "serde")]
+        "json" => Some(OutputFormat::Json),
+        _ => None,
+    }
+}
+
+/// Parses a switch.
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" | "on" | "yes" | "1" => Some(true),
+        "false" | "off" | "no" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CaptureSink, LogOutcome};
+
+    #[test]
+    fn test_from_spec() {
+        let mut rate_log = RateLogBuilder::from_spec(" limit=rate:2, window=10s,rate=true,, ")
+            .unwrap()
+            .sink(CaptureSink::new())
+            .build();
+        let outcomes: Vec<_> = (0..3).map(|_| rate_log.log("message1")).collect();
+        assert_eq!(
+            outcomes,
+            [
+                LogOutcome::Emitted,
+                LogOutcome::Emitted,
+                LogOutcome::Suppressed
+            ]
+        );
+
+        assert_eq!(
+            parse_limit("any:100:30s"),
+            Some(Limit::Any(100, Duration::from_secs(30)))
+        );
+        assert_eq!(
+            parse_limit("gcra:100ms:5"),
+            Some(Limit::Gcra {
+                period: Duration::from_millis(100),
+                burst: 5
+            })
+        );
+        assert_eq!(
+            parse_limit("bytes:4096:1m"),
+            Some(Limit::Bytes {
+                max: 4096,
+                per: Duration::from_secs(60)
+            })
+        );
+        assert_eq!(parse_duration("2m"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_duration("10"), None);
+
+        // The environment is shared by the whole test binary, so only this test uses it
+        std::env::set_var(ENV_VAR, "limit=never");
+        let mut rate_log = RateLog::from_env().unwrap();
+        assert_eq!(rate_log.log("message1"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message1"), LogOutcome::Emitted);
+        std::env::remove_var(ENV_VAR);
+    }
+
+    #[test]
+    fn test_config_errors() {
+        let err = |spec| RateLogBuilder::from_spec(spec).err().unwrap();
+
+        assert_eq!(err("limit"), ConfigError::Malformed("limit".to_string()));
+        assert_eq!(
+            err("speed=5"),
+            ConfigError::UnknownOption("speed".to_string())
+        );
+        assert_eq!(
+            err("limit=rate:many"),
+            ConfigError::InvalidValue {
+                option: "limit".to_string(),
+                value: "rate:many".to_string()
+            }
+        );
+        assert!(matches!(
+            err("limit=sample:5,window=10s"),
+            ConfigError::InvalidValue { option, .. } if option == "window"
+        ));
+        assert_eq!(
+            Error::from(err("cooldown=soon")).to_string(),
+            "invalid configuration: invalid value \"soon\" for option \"cooldown\""
+        );
+    }
+
+    #[test]
+    fn test_degenerate_token_buckets() {
+        assert_eq!(
+            parse_limit("token_bucket:10:0.5"),
+            Some(Limit::TokenBucket {
+                capacity: 10,
+                refill_per_sec: 0.5
+            })
+        );
+        // A bucket that never refills is valid, one that holds nothing is not
+        assert!(parse_limit("token_bucket:10:0").is_some());
+        for spec in ["0:1", "10:NaN", "10:inf", "10:-1"] {
+            assert_eq!(parse_limit(&format!("token_bucket:{spec}")), None, "{spec}");
+        }
+        assert!(matches!(
+            Error::from(
+                RateLogBuilder::from_spec("limit=token_bucket:10:NaN")
+                    .err()
+                    .unwrap()
+            ),
+            Error::Config(_)
+        ));
+    }
+}
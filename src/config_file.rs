@@ -0,0 +1,447 @@
+//! Limiter policies loaded from TOML or YAML files.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::Deserialize;
+
+use crate::config::{parse_format, parse_limit};
+use crate::{
+    ConfigError, FileSink, Limit, Normalizer, OutputFormat, RateLogBuilder, Registry, Result,
+    Rotation, Sink, StderrSink, StdoutSink,
+};
+
+/// The sink of every limiter built from a [`Config`].
+pub type ConfigSink = Box<dyn Sink + Send>;
+
+/// Limiter policies for a fleet of services, loaded from a file.
+///
+/// A configuration holds a default policy and per-key overrides, each describing the
+/// limit, grouping rule, sink, output format and further options of a limiter.
+/// [`Config::into_registry`] turns it into a [`Registry`] handing out one limiter per
+/// key, so log policy can be managed centrally instead of with builder code.
+///
+/// Every field is optional. Key policies inherit each field they leave out from
+/// `default`, and `default` falls back to the defaults of [`RateLogBuilder`]:
+///
+/// ```toml
+/// [default]
+/// limit = "rate:100"              # see the `limit` option of RateLogBuilder::from_spec
+/// group_by = "numbers"            # numbers, uuids or all
+/// sink = "stderr"                 # stdout, stderr or file:PATH
+/// format = "text"                 # text or json
+/// options = "cooldown=30s"        # further RateLogBuilder::from_spec options
+///
+/// [keys.network]
+/// limit = "window:5:10s"
+/// sink = "file:/var/log/network.log"
+/// ```
+///
+/// The same structure can be written in YAML and loaded with [`Config::from_yaml`].
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{Config, Limit};
+///
+/// let config = Config::from_toml_str(
+///     r#"
+///     [default]
+///     limit = "rate:100"
+///
+///     [keys.network]
+///     limit = "rate:5"
+///     group_by = "numbers"
+///     "#,
+/// )?;
+/// assert_eq!(config.limit("network"), Limit::Rate(5));
+///
+/// let registry = config.into_registry()?;
+/// registry.get("network").log("connection 1 reset");
+/// registry.get("network").log("connection 2 reset");  // Suppressed, same group
+/// # Ok::<(), rate_log::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// The policy of keys without one of their own.
+    default: Policy,
+
+    /// The policies configured per key.
+    keys: BTreeMap<String, Policy>,
+}
+
+/// The validated settings of one limiter.
+#[derive(Debug, Clone)]
+struct Policy {
+    /// The limit, taken from the options or the builder default if not set.
+    limit: Limit,
+    group_by: Option<Normalizer>,
+    sink: Option<SinkSpec>,
+    format: Option<OutputFormat>,
+    /// Options in the syntax of [`RateLogBuilder::from_spec`].
+    options: Option<String>,
+}
+
+/// Where the lines of a limiter go.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum SinkSpec {
+    Stdout,
+    Stderr,
+    File(PathBuf),
+}
+
+/// A policy as written in the file, before validation.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawPolicy {
+    limit: Option<String>,
+    group_by: Option<String>,
+    sink: Option<String>,
+    format: Option<String>,
+    options: Option<String>,
+}
+
+/// A configuration file as written, before validation.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConfig {
+    #[serde(default)]
+    default: RawPolicy,
+    #[serde(default)]
+    keys: BTreeMap<String, RawPolicy>,
+}
+
+impl Config {
+    /// Loads a configuration from the TOML file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`](crate::Error::Io) if the file cannot be read, and
+    /// [`Error::Config`](crate::Error::Config) if it is not a valid configuration.
+    pub fn from_toml(path: impl AsRef<Path>) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(Config::from_toml_str(&text)?)
+    }
+
+    /// Parses a configuration from TOML text.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConfigError::Parse`] if the text is not valid TOML or has unknown
+    /// fields, and another [`ConfigError`] if a value is invalid.
+    pub fn from_toml_str(text: &str) -> std::result::Result<Self, ConfigError> {
+        let raw = toml::from_str(text).map_err(|err| ConfigError::Parse(err.to_string()))?;
+        Config::validate(raw)
+    }
+
+    /// Loads a configuration from the YAML file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`](crate::Error::Io) if the file cannot be read, and
+    /// [`Error::Config`](crate::Error::Config) if it is not a valid configuration.
+    pub fn from_yaml(path: impl AsRef<Path>) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(Config::from_yaml_str(&text)?)
+    }
+
+    /// Parses a configuration from YAML text.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConfigError::Parse`] if the text is not valid YAML or has unknown
+    /// fields, and another [`ConfigError`] if a value is invalid.
+    pub fn from_yaml_str(text: &str) -> std::result::Result<Self, ConfigError> {
+        // An empty document is an empty configuration, as in TOML
+        if text.trim().is_empty() {
+            return Config::validate(RawConfig::default());
+        }
+        let raw = serde_yaml::from_str(text).map_err(|err| ConfigError::Parse(err.to_string()))?;
+        Config::validate(raw)
+    }
+
+    /// Returns the limit of `key`, or the default limit if it has none.
+    pub fn limit(&self, key: &str) -> Limit {
+        self.policy(key).limit.clone()
+    }
+
+    /// Returns the keys with a policy of their own, in alphabetical order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.keys.keys().map(String::as_str)
+    }
+
+    /// Builds a registry handing out one limiter per key, configured by its policy.
+    ///
+    /// Every file sink is opened once, without rotation, and shared by all limiters
+    /// writing to it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`](crate::Error::Io) if a file sink cannot be opened.
+    pub fn into_registry(self) -> Result<Registry<ConfigSink>> {
+        let mut files = HashMap::new();
+        for spec in self.policies().filter_map(|policy| policy.sink.as_ref()) {
+            if let SinkSpec::File(path) = spec {
+                if !files.contains_key(path) {
+                    let sink = FileSink::create(path, Rotation::Never)?;
+                    files.insert(path.clone(), SharedFile(Arc::new(Mutex::new(sink))));
+                }
+            }
+        }
+
+        let (default, limits) = self.limits();
+        let registry = Registry::with_factory(default, move |key, limit| {
+            let policy = self.policy(key);
+            let sink: ConfigSink = match &policy.sink {
+                None | Some(SinkSpec::Stdout) => Box::new(StdoutSink),
+                Some(SinkSpec::Stderr) => Box::new(StderrSink),
+                Some(SinkSpec::File(path)) => Box::new(files[path].clone()),
+            };
+            let mut builder = RateLogBuilder::new().sink(sink);
+            if let Some(options) = &policy.options {
+                builder = builder
+                    .apply_spec(options)
+                    .expect("options are validated when loaded");
+            }
+            builder = builder.limit(limit);
+            if let Some(normalizer) = policy.group_by {
+                builder = builder.group_by(normalizer);
+            }
+            if let Some(format) = policy.format {
+                builder = builder.format(format);
+            }
+            builder.build()
+        });
+        Ok(registry.with_limits(limits))
+    }
+
+    /// Loads a configuration from `path`, as YAML if it ends in `.yaml` or `.yml` and
+    /// as TOML otherwise.
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml" | "yml") => Config::from_yaml(path),
+            _ => Config::from_toml(path),
+        }
+    }
+
+    /// Returns the default limit and the limit of every key with a policy.
+    pub(crate) fn limits(&self) -> (Limit, Vec<(String, Limit)>) {
+        let limits = self
+            .keys
+            .iter()
+            .map(|(key, policy)| (key.clone(), policy.limit.clone()))
+            .collect();
+        (self.default.limit.clone(), limits)
+    }
+
+    /// Returns the policy of `key`, or the default policy if it has none.
+    fn policy(&self, key: &str) -> &Policy {
+        self.keys.get(key).unwrap_or(&self.default)
+    }
+
+    /// Returns the default policy followed by the policy of every key.
+    fn policies(&self) -> impl Iterator<Item = &Policy> {
+        std::iter::once(&self.default).chain(self.keys.values())
+    }
+
+    /// Validates a configuration and resolves the key policies against the default.
+    fn validate(raw: RawConfig) -> std::result::Result<Self, ConfigError> {
+        let default = Policy::validate("default", raw.default.clone())?;
+        let keys = raw
+            .keys
+            .into_iter()
+            .map(|(key, policy)| {
+                let policy = RawPolicy {
+                    limit: policy.limit.or_else(|| raw.default.limit.clone()),
+                    group_by: policy.group_by.or_else(|| raw.default.group_by.clone()),
+                    sink: policy.sink.or_else(|| raw.default.sink.clone()),
+                    format: policy.format.or_else(|| raw.default.format.clone()),
+                    options: policy.options.or_else(|| raw.default.options.clone()),
+                };
+                Ok((
+                    key.clone(),
+                    Policy::validate(&format!("keys.{key}"), policy)?,
+                ))
+            })
+            .collect::<std::result::Result<_, ConfigError>>()?;
+        Ok(Config { default, keys })
+    }
+}
+
+impl Policy {
+    /// Validates the fields of a policy, naming them after `section` in errors.
+    fn validate(section: &str, raw: RawPolicy) -> std::result::Result<Self, ConfigError> {
+        fn field<T>(
+            section: &str,
+            name: &str,
+            value: Option<String>,
+            parse: impl FnOnce(&str) -> Option<T>,
+        ) -> std::result::Result<Option<T>, ConfigError> {
+            value
+                .map(|value| {
+                    parse(&value).ok_or_else(|| ConfigError::InvalidValue {
+                        option: format!("{section}.{name}"),
+                        value,
+                    })
+                })
+                .transpose()
+        }
+
+        let options =
+            RateLogBuilder::new().apply_spec(raw.options.as_deref().unwrap_or_default())?;
+        let limit = match field(section, "limit", raw.limit, parse_limit)? {
+            Some(limit) => limit,
+            None => options.build().limit().clone(),
+        };
+        Ok(Policy {
+            limit,
+            group_by: field(section, "group_by", raw.group_by, parse_normalizer)?,
+            sink: field(section, "sink", raw.sink, parse_sink)?,
+            format: field(section, "format", raw.format, parse_format)?,
+            options: raw.options,
+        })
+    }
+}
+
+/// Parses a grouping rule.
+fn parse_normalizer(value: &str) -> Option<Normalizer> {
+    match value {
+        "numbers" => Some(Normalizer::StripNumbers),
+        "uuids" => Some(Normalizer::StripUuids),
+        "all" => Some(Normalizer::StripAll),
+        _ => None,
+    }
+}
+
+/// Parses a sink such as `stderr` or `file:/var/log/app.log`.
+fn parse_sink(value: &str) -> Option<SinkSpec> {
+    match value {
+        "stdout" => Some(SinkSpec::Stdout),
+        "stderr" => Some(SinkSpec::Stderr),
+        _ => match value.strip_prefix("file:") {
+            Some(path) if !path.is_empty() => Some(SinkSpec::File(PathBuf::from(path))),
+            _ => None,
+        },
+    }
+}
+
+/// A file sink shared by every limiter writing to the same file.
+#[derive(Debug, Clone)]
+struct SharedFile(Arc<Mutex<FileSink>>);
+
+impl Sink for SharedFile {
+    fn emit(&mut self, record: &str) {
+        // A panic while writing leaves the file usable
+        let mut sink = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        sink.emit(record);
+    }
+
+    fn flush(&mut self) {
+        let mut sink = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        sink.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn test_config_registry() {
+        let dir = std::env::temp_dir().join(format!("rate-log-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("network.log");
+        let path = dir.join("rate-log.toml");
+        std::fs::write(
+            &path,
+            format!(
+                r#"
+                [default]
+                limit = "rate:100"
+                sink = "file:{}"
+
+                [keys.network]
+                limit = "rate:1"
+                group_by = "numbers"
+                options = "warnings=off"
+                "#,
+                log.display()
+            ),
+        )
+        .unwrap();
+
+        let config = Config::from_toml(&path).unwrap();
+        assert_eq!(config.limit("network"), Limit::Rate(1));
+        assert_eq!(config.limit("storage"), Limit::Rate(100));
+        assert_eq!(config.keys().collect::<Vec<_>>(), ["network"]);
+
+        let registry = config.into_registry().unwrap();
+        assert_eq!(*registry.get("network").lock().limit(), Limit::Rate(1));
+        for i in 0..3 {
+            registry
+                .get("network")
+                .log(&format!("connection {i} reset"));
+        }
+        registry.get("storage").log("disk full");
+        registry.get("storage").log("disk slow");
+        drop(registry);
+
+        let lines = std::fs::read_to_string(&log).unwrap();
+        assert_eq!(
+            lines.lines().collect::<Vec<_>>(),
+            ["connection 0 reset", "disk full", "disk slow"]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_config_formats() {
+        let toml = Config::from_toml_str(
+            r#"
+            [default]
+            limit = "window:5:10s"
+
+            [keys.network]
+            sink = "stderr"
+            "#,
+        )
+        .unwrap();
+        let yaml = Config::from_yaml_str(
+            r#"
+            default:
+              limit: "window:5:10s"
+            keys:
+              network:
+                sink: stderr
+            "#,
+        )
+        .unwrap();
+        for config in [toml, yaml] {
+            assert_eq!(config.keys().collect::<Vec<_>>(), ["network"]);
+            assert_eq!(config.limit("network"), config.limit("other"));
+        }
+        assert_eq!(Config::from_yaml_str("").unwrap().keys().count(), 0);
+
+        assert_eq!(
+            Config::from_toml_str("[keys.network]\nlimit = \"rate:many\"").unwrap_err(),
+            ConfigError::InvalidValue {
+                option: "keys.network.limit".to_string(),
+                value: "rate:many".to_string()
+            }
+        );
+        assert!(matches!(
+            Config::from_toml_str("[default]\nspeed = 5").unwrap_err(),
+            ConfigError::Parse(_)
+        ));
+        assert_eq!(
+            Config::from_toml_str("[default]\noptions = \"speed=5\"").unwrap_err(),
+            ConfigError::UnknownOption("speed".to_string())
+        );
+        assert!(matches!(
+            Config::from_toml("/nonexistent/rate-log.toml"),
+            Err(Error::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,43 @@
+//! Output to the JavaScript console for browser and Node targets.
+
+use web_sys::console;
+
+use crate::{LineKind, Severity, Sink};
+
+/// A sink writing every line to the JavaScript console.
+///
+/// Available with the `wasm` feature, for `wasm32-unknown-unknown` builds running in a
+/// browser or under Node. Logged messages go to the console method matching their
+/// severity (`console.debug`, `console.info`, `console.warn` or `console.error`), and
+/// rate limit warnings and summaries to `console.warn`, so developer tools can filter
+/// them. Lines written without a kind use `console.log`.
+///
+/// Calling into the console on any other target panics, so only install this sink in
+/// WebAssembly builds.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use rate_log::{ConsoleSink, Limit, RateLog};
+///
+/// let mut logger = RateLog::with_sink(Limit::Rate(10), ConsoleSink);
+/// logger.log("WebSocket reconnecting");  // Logs to the browser console
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ConsoleSink;
+
+impl Sink for ConsoleSink {
+    fn emit(&mut self, record: &str) {
+        console::log_1(&record.into());
+    }
+
+    fn emit_line(&mut self, record: &str, kind: LineKind) {
+        let record = record.into();
+        match kind {
+            LineKind::Message(Severity::Trace | Severity::Debug) => console::debug_1(&record),
+            LineKind::Message(Severity::Info) => console::info_1(&record),
+            LineKind::Message(Severity::Warn) | LineKind::Summary => console::warn_1(&record),
+            LineKind::Message(Severity::Error) => console::error_1(&record),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Deduplication of messages recurring after other messages in between.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::clock::Instant;
+use crate::limit::State;
+use crate::{Limit, Location};
+
+/// How far back a message is remembered for deduplication, see
+/// [`RateLog::with_dedup_window`](crate::RateLog::with_dedup_window).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupWindow {
+    /// A message is a repeat if at most this many other messages were logged since
+    /// it was last seen, e.g. `Messages(1)` deduplicates A, B, A, B...
+    Messages(usize),
+
+    /// A message is a repeat if it was last seen less than this long ago, however
+    /// many other messages were logged in between.
+    Time(Duration),
+}
+
+/// The tracking state of a message replaced by another one.
+#[derive(Debug)]
+pub(crate) struct Tracked {
+    pub(crate) key: String,
+    pub(crate) keyed: bool,
+    pub(crate) key_limit: Option<Limit>,
+    pub(crate) state: State,
+    pub(crate) exemplar: Option<String>,
+    pub(crate) location: Option<Location>,
+    pub(crate) last_message: Option<String>,
+}
+
+impl Tracked {
+    /// Returns the state of a logger that has not tracked any message yet.
+    pub(crate) fn new() -> Self {
+        Tracked {
+            key: String::new(),
+            keyed: false,
+            key_limit: None,
+            state: State::new(),
+            exemplar: None,
+            location: None,
+            last_message: None,
+        }
+    }
+}
+
+/// Keeps the state of recently replaced messages, so that returning to one of them
+/// continues its count instead of starting over.
+#[derive(Debug)]
+pub(crate) struct DedupTracker {
+    window: DedupWindow,
+
+    /// Replaced messages with the time they were last seen, most recent first.
+    parked: VecDeque<(Tracked, Instant)>,
+}
+
+impl DedupTracker {
+    pub(crate) fn new(window: DedupWindow) -> Self {
+        DedupTracker {
+            window,
+            parked: VecDeque::new(),
+        }
+    }
+
+    /// Remembers a replaced message last seen at `seen`.
+    ///
+    /// Returns the messages that no longer fit into the window.
+    pub(crate) fn park(&mut self, tracked: Tracked, seen: Instant) -> Vec<Tracked> {
+        self.parked.push_front((tracked, seen));
+        match self.window {
+            DedupWindow::Messages(max) if self.parked.len() > max => self
+                .parked
+                .drain(max..)
+                .map(|(tracked, _)| tracked)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Takes the remembered state of `key`, if it is still within the window.
+    pub(crate) fn take(&mut self, key: &str) -> Option<Tracked> {
+        let index = self
+            .parked
+            .iter()
+            .position(|(tracked, _)| tracked.key == key)?;
+        self.parked.remove(index).map(|(tracked, _)| tracked)
+    }
+
+    /// Removes the messages last seen too long before `now` to be within the window.
+    pub(crate) fn expire(&mut self, now: Instant) -> Vec<Tracked> {
+        let DedupWindow::Time(window) = self.window else {
+            return Vec::new();
+        };
+        let Some(first) = self
+            .parked
+            .iter()
+            .position(|(_, seen)| now.duration_since(*seen) >= window)
+        else {
+            return Vec::new();
+        };
+        self.parked
+            .drain(first..)
+            .map(|(tracked, _)| tracked)
+            .collect()
+    }
+
+    /// Forgets every remembered message.
+    pub(crate) fn clear(&mut self) {
+        self.parked.clear();
+    }
+
+    /// Returns mutable access to every remembered message.
+    pub(crate) fn parked_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut Tracked> {
+        self.parked.iter_mut().map(|(tracked, _)| tracked)
+    }
+}
@@ -0,0 +1,120 @@
+//! Configurable rendering of durations in warnings and summaries.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use core::fmt;
+use core::time::Duration;
+
+use crate::format_duration;
+
+/// How durations are written in warnings and summaries, such as `in the past 1h59m`.
+///
+/// See [`RateLog::with_duration_style`](crate::RateLog::with_duration_style).
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::DurationStyle;
+/// use std::time::Duration;
+///
+/// let duration = Duration::from_millis(7_150_500);
+/// assert_eq!(DurationStyle::Compact.format(duration), "1h59m");
+/// assert_eq!(DurationStyle::Exact.format(duration), "7150.5s");
+/// assert_eq!(DurationStyle::Decimal.format(duration), "1.98h");
+///
+/// let minutes = DurationStyle::custom(|d| format!("{} min", d.as_secs() / 60));
+/// assert_eq!(minutes.format(duration), "119 min");
+/// ```
+#[derive(Default)]
+pub enum DurationStyle {
+    /// At most two whole units, e.g. `1h59m`, `3m25s`, `45s` or `500ms`; smaller
+    /// units are truncated.
+    #[default]
+    Compact,
+
+    /// Total seconds with a millisecond fraction if any, e.g. `7150.5s` or `0.25s`.
+    /// Nothing above milliseconds is lost, and durations are easy to compare.
+    Exact,
+
+    /// The largest unit with up to two decimals, e.g. `1.99h`, `1.5m` or `1.5s`;
+    /// durations below a second as `500ms`.
+    Decimal,
+
+    /// A user supplied function, e.g. to match the format of surrounding log lines.
+    Custom(Box<dyn Fn(Duration) -> String + Send>),
+}
+
+impl DurationStyle {
+    /// Creates a style rendering durations with `format`.
+    pub fn custom(format: impl Fn(Duration) -> String + Send + 'static) -> Self {
+        DurationStyle::Custom(Box::new(format))
+    }
+
+    /// Renders `duration` in this style.
+    pub fn format(&self, duration: Duration) -> String {
+        match self {
+            DurationStyle::Compact => format_duration(duration),
+            DurationStyle::Exact => {
+                let millis = duration.subsec_millis();
+                if millis == 0 {
+                    format!("{}s", duration.as_secs())
+                } else {
+                    let fraction = format!("{millis:03}");
+                    format!("{}.{}s", duration.as_secs(), fraction.trim_end_matches('0'))
+                }
+            }
+            DurationStyle::Decimal => {
+                let millis = duration.as_millis();
+                let (unit_millis, unit) = match duration.as_secs() {
+                    3600.. => (3_600_000, "h"),
+                    60.. => (60_000, "m"),
+                    1.. => (1000, "s"),
+                    0 => return format!("{millis}ms"),
+                };
+                // Rounds down like the other styles, so 119m59s is not shown as 2h
+                let hundredths = millis * 100 / unit_millis;
+                let fraction = format!("{:02}", hundredths % 100);
+                let fraction = fraction.trim_end_matches('0');
+                if fraction.is_empty() {
+                    format!("{}{unit}", hundredths / 100)
+                } else {
+                    format!("{}.{fraction}{unit}", hundredths / 100)
+                }
+            }
+            DurationStyle::Custom(format) => format(duration),
+        }
+    }
+}
+
+impl fmt::Debug for DurationStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DurationStyle::Compact => f.write_str("Compact"),
+            DurationStyle::Exact => f.write_str("Exact"),
+            DurationStyle::Decimal => f.write_str("Decimal"),
+            DurationStyle::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duration_styles() {
+        let cases = [
+            (Duration::from_millis(250), "250ms", "0.25s", "250ms"),
+            (Duration::from_millis(1500), "1s", "1.5s", "1.5s"),
+            (Duration::from_secs(90), "1m30s", "90s", "1.5m"),
+            (Duration::from_secs(3725), "1h2m", "3725s", "1.03h"),
+            (Duration::from_secs(7199), "1h59m", "7199s", "1.99h"),
+        ];
+        for (duration, compact, exact, decimal) in cases {
+            assert_eq!(DurationStyle::Compact.format(duration), compact);
+            assert_eq!(DurationStyle::Exact.format(duration), exact);
+            assert_eq!(DurationStyle::Decimal.format(duration), decimal);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! The error type of fallible operations.
+
+use alloc::string::String;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+/// An error returned by a fallible `rate-log` operation.
+///
+/// Failures are reported to the caller instead of panicking or printing to stderr,
+/// so embedding applications can handle them programmatically.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading or writing a sink, file or other I/O resource failed.
+    #[cfg(feature = "std")]
+    Io(io::Error),
+
+    /// A configuration could not be parsed or is invalid.
+    Config(String),
+
+    /// Saving or restoring persisted limiter state failed.
+    Persistence(String),
+
+    /// An external logging or metrics backend reported a failure.
+    Backend(String),
+
+    /// The global limiter was already initialized, see
+    /// [`init_global`](crate::init_global).
+    AlreadyInitialized,
+}
+
+/// A specialized `Result` type for `rate-log` operations.
+pub type Result<T> = core::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            Error::Io(err) => write!(f, "I/O error: {err}"),
+            Error::Config(msg) => write!(f, "invalid configuration: {msg}"),
+            Error::Persistence(msg) => write!(f, "persistence error: {msg}"),
+            Error::Backend(msg) => write!(f, "backend error: {msg}"),
+            Error::AlreadyInitialized => write!(f, "global rate limiter already initialized"),
+        }
+    }
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
@@ -0,0 +1,145 @@
+//! Typed values describing emitted output.
+
+use alloc::borrow::Cow;
+use core::time::Duration;
+
+use crate::{LineKind, Severity, WarningKind};
+
+/// One piece of output of a [`RateLog`](crate::RateLog), as a typed value instead of
+/// a line of text.
+///
+/// Every line is handed to [`Sink::emit_event`](crate::Sink::emit_event) together
+/// with the event it renders. Text sinks write the line, as they always did;
+/// structured consumers such as [`ChannelSink`](crate::ChannelSink) use the event
+/// instead of parsing the text.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{Event, Limit, RateLog, Sink};
+///
+/// /// Counts suppressed repeats instead of printing anything.
+/// #[derive(Default)]
+/// struct Counter(u32);
+///
+/// impl Sink for Counter {
+///     fn emit(&mut self, _record: &str) {}
+///
+///     fn emit_event(&mut self, event: &Event<'_>, _line: &str) {
+///         if let Event::Summary { count, .. } = event {
+///             self.0 += count;
+///         }
+///     }
+/// }
+///
+/// let mut logger = RateLog::with_sink(Limit::Rate(2), Counter::default());
+/// for _ in 0..3 {
+///     logger.log("disk full");
+/// }
+/// assert_eq!(logger.sink().0, 2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event<'a> {
+    /// A logged message was emitted.
+    Emitted {
+        /// The message, without annotations such as `(seen 4 times)`.
+        msg: Cow<'a, str>,
+        /// The severity the message was logged with.
+        severity: Severity,
+    },
+
+    /// Repeats of a key were reported, because the limit was exceeded or repeats
+    /// still pending were summarized.
+    Summary {
+        /// The rate limiting key, i.e. the message text for plain `log()` calls.
+        key: Cow<'a, str>,
+        /// The number of repeats covered.
+        count: u32,
+        /// The time accumulated between the covered repeats.
+        duration: Duration,
+        /// Why the repeats were reported.
+        kind: WarningKind,
+    },
+
+    /// A message reached the escalation threshold and was emitted again.
+    Escalated {
+        /// The escalated message.
+        msg: Cow<'a, str>,
+    },
+
+    /// A key was evicted from the statistics while it had suppressed repeats.
+    Evicted {
+        /// The evicted key.
+        key: Cow<'a, str>,
+        /// The number of repeats suppressed for the key.
+        suppressed: u64,
+    },
+
+    /// Any other line written by the rate limiter itself.
+    Notice {
+        /// What the notice reports, e.g. `announce`, `report`, `coalesced` or
+        /// `global_limit`; `text` for a line a sink received without an event.
+        kind: &'static str,
+        /// The text of the notice.
+        text: Cow<'a, str>,
+    },
+}
+
+impl Event<'_> {
+    /// Returns what the line rendered for this event reports.
+    pub fn line_kind(&self) -> LineKind {
+        match self {
+            Event::Emitted { severity, .. } => LineKind::Message(*severity),
+            _ => LineKind::Summary,
+        }
+    }
+
+    /// Returns the event for a line a sink received without one, e.g. through a text
+    /// transforming wrapper: [`Event::Emitted`] for messages and an [`Event::Notice`]
+    /// of kind `text` otherwise.
+    pub(crate) fn from_line(line: &str, kind: LineKind) -> Event<'_> {
+        match kind {
+            LineKind::Message(severity) => Event::Emitted {
+                msg: Cow::Borrowed(line),
+                severity,
+            },
+            LineKind::Summary => Event::Notice {
+                kind: "text",
+                text: Cow::Borrowed(line),
+            },
+        }
+    }
+
+    /// Returns a copy of the event that owns all its text.
+    pub fn into_owned(self) -> Event<'static> {
+        match self {
+            Event::Emitted { msg, severity } => Event::Emitted {
+                msg: Cow::Owned(msg.into_owned()),
+                severity,
+            },
+            Event::Summary {
+                key,
+                count,
+                duration,
+                kind,
+            } => Event::Summary {
+                key: Cow::Owned(key.into_owned()),
+                count,
+                duration,
+                kind,
+            },
+            Event::Escalated { msg } => Event::Escalated {
+                msg: Cow::Owned(msg.into_owned()),
+            },
+            Event::Evicted { key, suppressed } => Event::Evicted {
+                key: Cow::Owned(key.into_owned()),
+                suppressed,
+            },
+            Event::Notice { kind, text } => Event::Notice {
+                kind,
+                text: Cow::Owned(text.into_owned()),
+            },
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! Exemplars linking rate limit warnings to one concrete occurrence.
+//!
+//! An exemplar is a Prometheus-style label set such as `trace_id="4bf92f3577b34da6"`
+//! captured while a repeat is suppressed. It is attached to the next warning or
+//! summary for that message, as in `... repeat for 10 times in the past 2s # {trace_id="4bf92f3577b34da6"}`,
+//! so the summary can be navigated back to a traced request exhibiting the problem.
+
+use alloc::boxed::Box;
+#[cfg(feature = "tracing")]
+use alloc::format;
+use alloc::string::String;
+
+/// A function returning the exemplar labels for the current occurrence, if any.
+pub(crate) type ExemplarSource = Box<dyn FnMut() -> Option<String> + Send>;
+
+/// Returns the id of the current [`tracing`] span as exemplar labels, e.g. `span_id="42"`.
+///
+/// Returns `None` outside of any span or when no subscriber is installed. This is the
+/// default exemplar source when the `tracing` feature is enabled; see
+/// [`RateLog::with_exemplars`](crate::RateLog::with_exemplars).
+#[cfg(feature = "tracing")]
+pub fn tracing_span_exemplar() -> Option<String> {
+    let id = tracing::Span::current().id()?;
+    Some(format!("span_id=\"{}\"", id.into_u64()))
+}
+
+/// Returns the default exemplar source, which depends on the enabled features.
+pub(crate) fn default_source() -> Option<ExemplarSource> {
+    #[cfg(feature = "tracing")]
+    return Some(Box::new(tracing_span_exemplar));
+
+    #[cfg(not(feature = "tracing"))]
+    None
+}
@@ -0,0 +1,160 @@
+//! Writing every line to several sinks at once.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::{Event, LineKind, Severity, Sink};
+
+/// Which lines a sink attached to a [`Fanout`] receives.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SinkFilter {
+    /// Every line.
+    #[default]
+    All,
+
+    /// Only logged messages, no warnings, summaries or notices.
+    Messages,
+
+    /// Only rate limit warnings, summaries and notices, no logged messages.
+    Summaries,
+}
+
+impl SinkFilter {
+    /// Returns `true` if a line of `kind` passes the filter.
+    pub fn accepts(&self, kind: LineKind) -> bool {
+        match self {
+            SinkFilter::All => true,
+            SinkFilter::Messages => matches!(kind, LineKind::Message(_)),
+            SinkFilter::Summaries => kind == LineKind::Summary,
+        }
+    }
+}
+
+/// A sink writing every line to several sinks, each with its own [`SinkFilter`].
+///
+/// Lets one limiter feed e.g. the terminal, a file and a metrics pipeline at once,
+/// with only the suppression summaries going to an alerting channel. Sinks receive
+/// lines in the order they were added, together with their [`Event`].
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{ChannelSink, Event, Fanout, Limit, RateLog, SinkFilter, StderrSink};
+///
+/// let (alerts, summaries) = ChannelSink::channel();
+/// let sink = Fanout::new()
+///     .sink(StderrSink)
+///     .sink_filtered(alerts, SinkFilter::Summaries);
+/// let mut logger = RateLog::with_sink(Limit::Rate(1), sink);
+///
+/// logger.log("Disk full");  // Written to stderr only
+/// logger.log("Disk full");  // The warning goes to stderr and the channel
+/// assert!(matches!(summaries.try_recv(), Ok(Event::Summary { count: 1, .. })));
+/// ```
+#[derive(Default)]
+pub struct Fanout {
+    sinks: Vec<(Box<dyn Sink + Send>, SinkFilter)>,
+}
+
+impl Fanout {
+    /// Creates a fanout without any sinks, discarding every line until sinks are added.
+    pub fn new() -> Self {
+        Fanout::default()
+    }
+
+    /// Adds a sink receiving every line.
+    pub fn sink(self, sink: impl Sink + Send + 'static) -> Self {
+        self.sink_filtered(sink, SinkFilter::All)
+    }
+
+    /// Adds a sink receiving only the lines passing `filter`.
+    pub fn sink_filtered(mut self, sink: impl Sink + Send + 'static, filter: SinkFilter) -> Self {
+        self.sinks.push((Box::new(sink), filter));
+        self
+    }
+
+    /// Returns the number of attached sinks.
+    pub fn len(&self) -> usize {
+        self.sinks.len()
+    }
+
+    /// Returns `true` if no sinks are attached.
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+}
+
+impl Sink for Fanout {
+    fn emit(&mut self, record: &str) {
+        self.emit_line(record, LineKind::Message(Severity::default()));
+    }
+
+    fn emit_line(&mut self, record: &str, kind: LineKind) {
+        self.emit_event(&Event::from_line(record, kind), record);
+    }
+
+    fn emit_event(&mut self, event: &Event<'_>, line: &str) {
+        let kind = event.line_kind();
+        for (sink, filter) in &mut self.sinks {
+            if filter.accepts(kind) {
+                sink.emit_event(event, line);
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        for (sink, _) in &mut self.sinks {
+            sink.flush();
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{ChannelSink, Limit, MockClock, RateLog};
+
+    #[test]
+    fn test_fanout() {
+        let (all, all_events) = ChannelSink::channel();
+        let (messages, message_events) = ChannelSink::channel();
+        let (summaries, summary_events) = ChannelSink::channel();
+        let sink = Fanout::new()
+            .sink(all)
+            .sink_filtered(messages, SinkFilter::Messages)
+            .sink_filtered(summaries, SinkFilter::Summaries);
+        assert_eq!(sink.len(), 3);
+        let mut rate_log = RateLog::with_sink(Limit::Rate(1), sink).with_clock(MockClock::new());
+
+        rate_log.log_with_severity(Severity::Error, "message1");
+        rate_log.log("message1");
+        rate_log.log("message2");
+
+        let emitted = |msg: &str, severity| Event::Emitted {
+            msg: msg.to_string().into(),
+            severity,
+        };
+        let summary = Event::Summary {
+            key: "message1".into(),
+            count: 1,
+            duration: core::time::Duration::ZERO,
+            kind: crate::WarningKind::LimitExceeded(crate::Trigger::Count),
+        };
+        assert_eq!(
+            all_events.try_iter().collect::<Vec<_>>(),
+            [
+                emitted("message1", Severity::Error),
+                summary.clone(),
+                emitted("message2", Severity::Info),
+            ]
+        );
+        assert_eq!(
+            message_events.try_iter().collect::<Vec<_>>(),
+            [
+                emitted("message1", Severity::Error),
+                emitted("message2", Severity::Info),
+            ]
+        );
+        assert_eq!(summary_events.try_iter().collect::<Vec<_>>(), [summary]);
+    }
+}
@@ -0,0 +1,247 @@
+//! Appending to log files with built-in rotation.
+
+use crate::clock::{SystemTime, UNIX_EPOCH};
+use crate::{Error, Result, Sink};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// The number of seconds in a day.
+const SECS_PER_DAY: u64 = 86_400;
+
+/// When a [`FileSink`] starts a new file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    /// Never rotates; the file grows without bound.
+    Never,
+
+    /// Rotates before a line would grow the file beyond the given number of bytes.
+    ///
+    /// A single line longer than the limit is still written, to a file of its own.
+    Size(u64),
+
+    /// Rotates on the first line written on a new day (UTC).
+    Daily,
+}
+
+/// A sink appending every line to a file, rotating it by size or day.
+///
+/// On rotation the current file is renamed to `<path>.1`, earlier rotated files shift
+/// to `<path>.2`, `<path>.3` and so on, and a fresh file is created at `path`. Only
+/// the newest [`FileSink::max_files`] rotated files are kept; older ones are deleted.
+/// This makes the sink usable on devices without any logging daemon or `logrotate`.
+///
+/// Lines are written unbuffered, so nothing is lost if the process dies. Wrap the sink
+/// in [`Buffered`](crate::Buffered) to trade that for fewer writes. As with
+/// [`WriterSink`](crate::WriterSink), I/O errors never take the application down: the
+/// first one is kept for [`FileSink::take_error`] and later lines are still attempted.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use rate_log::{FileSink, Limit, RateLog, Rotation};
+///
+/// // Keeps app.log plus at most 3 rotated files of 1 MiB each
+/// let sink = FileSink::create("/var/log/app.log", Rotation::Size(1024 * 1024))?.max_files(3);
+/// let mut logger = RateLog::with_sink(Limit::Rate(5), sink);
+///
+/// logger.log("Connection lost");
+/// # Ok::<(), rate_log::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct FileSink {
+    path: PathBuf,
+    file: File,
+    rotation: Rotation,
+    max_files: usize,
+    /// The size of the current file in bytes.
+    size: u64,
+    /// The day (since the Unix epoch) the current file was last written.
+    day: u64,
+    error: Option<io::Error>,
+}
+
+impl FileSink {
+    /// Opens the file at `path` for appending, creating it if it does not exist.
+    ///
+    /// An existing file counts towards the size limit, and is rotated on the first
+    /// line with [`Rotation::Daily`] if it was last modified on an earlier day.
+    /// Keeps 5 rotated files unless changed with [`FileSink::max_files`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if the file cannot be opened.
+    pub fn create(path: impl AsRef<Path>, rotation: Rotation) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = open(&path)?;
+        let metadata = file.metadata()?;
+        let day = metadata.modified().map_or_else(|_| today(), day_of);
+        Ok(FileSink {
+            path,
+            file,
+            rotation,
+            max_files: 5,
+            size: metadata.len(),
+            day,
+            error: None,
+        })
+    }
+
+    /// Keeps at most `max_files` rotated files besides the current one.
+    ///
+    /// With 0, the current file is truncated on rotation.
+    pub fn max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    /// Returns the path of the current file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns and clears the first I/O error since the last call, if any.
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take().map(Error::Io)
+    }
+
+    /// Returns the path of the rotated file with the given index.
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        PathBuf::from(path)
+    }
+
+    /// Returns `true` if the current file must be rotated before writing `len` bytes.
+    fn needs_rotation(&self, len: u64, today: u64) -> bool {
+        match self.rotation {
+            Rotation::Never => false,
+            Rotation::Size(max) => self.size > 0 && self.size + len > max,
+            Rotation::Daily => self.size > 0 && today != self.day,
+        }
+    }
+
+    /// Shifts the rotated files, moves the current file to `<path>.1` and reopens `path`.
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.max_files > 0 {
+            match fs::remove_file(self.rotated_path(self.max_files)) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+            for index in (1..self.max_files).rev() {
+                match fs::rename(self.rotated_path(index), self.rotated_path(index + 1)) {
+                    Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                    _ => {}
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+            self.file = open(&self.path)?;
+        } else {
+            self.file.set_len(0)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+
+    /// Keeps the first error of an I/O operation.
+    fn record(&mut self, result: io::Result<()>) {
+        if let Err(err) = result {
+            self.error.get_or_insert(err);
+        }
+    }
+}
+
+impl Sink for FileSink {
+    fn emit(&mut self, record: &str) {
+        let len = record.len() as u64 + 1;
+        let today = today();
+        if self.needs_rotation(len, today) {
+            // Keep appending to the current file if rotation fails
+            let result = self.rotate();
+            self.record(result);
+        }
+        self.day = today;
+
+        let result = writeln!(self.file, "{record}");
+        if result.is_ok() {
+            self.size += len;
+        }
+        self.record(result);
+    }
+
+    fn flush(&mut self) {
+        let result = self.file.flush();
+        self.record(result);
+    }
+}
+
+/// Opens `path` for appending, creating it if it does not exist.
+fn open(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Returns the day since the Unix epoch (UTC) of `time`.
+fn day_of(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() / SECS_PER_DAY)
+}
+
+/// Returns the current day since the Unix epoch (UTC).
+fn today() -> u64 {
+    day_of(SystemTime::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a fresh directory for the files of a test.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rate-log-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_size_rotation() {
+        let dir = test_dir("size-rotation");
+        let path = dir.join("app.log");
+        let mut sink = FileSink::create(&path, Rotation::Size(20))
+            .unwrap()
+            .max_files(2);
+
+        for line in [
+            "line1", "line2", "line3", "line4", "line5", "line6", "line7",
+        ] {
+            sink.emit(line);
+        }
+
+        let read = |path: PathBuf| fs::read_to_string(path).unwrap();
+        assert_eq!(read(path.clone()), "line7\n");
+        assert_eq!(read(sink.rotated_path(1)), "line4\nline5\nline6\n");
+        assert_eq!(read(sink.rotated_path(2)), "line1\nline2\nline3\n");
+        assert!(!sink.rotated_path(3).exists());
+        assert!(sink.take_error().is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_daily_rotation() {
+        let dir = test_dir("daily-rotation");
+        let path = dir.join("app.log");
+        let mut sink = FileSink::create(&path, Rotation::Daily).unwrap();
+
+        sink.emit("line1");
+        sink.emit("line2");
+        sink.day -= 1;
+        sink.emit("line3");
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "line3\n");
+        assert_eq!(
+            fs::read_to_string(sink.rotated_path(1)).unwrap(),
+            "line1\nline2\n"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
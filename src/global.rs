@@ -0,0 +1,45 @@
+//! A process-wide rate limiter for small binaries.
+
+use std::sync::OnceLock;
+
+use crate::{Error, Limit, Result, SharedRateLog};
+
+/// The process-wide limiter used by [`glog!`](crate::glog!).
+static GLOBAL: OnceLock<SharedRateLog> = OnceLock::new();
+
+/// Installs the process-wide rate limiter used by [`glog!`](crate::glog!).
+///
+/// # Errors
+///
+/// Returns [`Error::AlreadyInitialized`], leaving the existing limiter in place, if the
+/// global limiter was already initialized, either by an earlier call or implicitly by
+/// logging through it.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{glog, init_global, Limit};
+///
+/// fn main() -> Result<(), rate_log::Error> {
+///     init_global(Limit::Rate(10))?;
+///     poll();
+///     Ok(())
+/// }
+///
+/// fn poll() {
+///     glog!("queue {} is empty", "jobs");  // Prints: "queue jobs is empty (at src/main.rs:12 in app)"
+/// }
+/// ```
+pub fn init_global(limit: Limit) -> Result<()> {
+    GLOBAL
+        .set(SharedRateLog::new(limit))
+        .map_err(|_| Error::AlreadyInitialized)
+}
+
+/// Returns the process-wide rate limiter.
+///
+/// If [`init_global`] was not called, a limiter with the default `Limit::Rate(10)` is
+/// created on first use.
+pub fn global() -> &'static SharedRateLog {
+    GLOBAL.get_or_init(|| SharedRateLog::new(Limit::Rate(10)))
+}
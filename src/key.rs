@@ -0,0 +1,270 @@
+//! Statically known log keys with declarative per-key limits.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+use crate::Limit;
+
+/// A log key from a fixed, compile-time checked set, typically an enum.
+///
+/// Using an enum instead of strings catches typos at compile time and avoids
+/// formatting keys at runtime. Each key may declare its own [`Limit`], overriding the
+/// limit of the [`RateLog`](crate::RateLog) it is logged through. Implement the trait
+/// by hand or declare the enum with [`log_keys!`](crate::log_keys!).
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{Limit, LogKey, RateLog};
+///
+/// #[derive(Clone, Copy)]
+/// enum SensorKey {
+///     Overheat,
+///     Disconnected,
+/// }
+///
+/// impl LogKey for SensorKey {
+///     fn key(&self) -> &'static str {
+///         match self {
+///             SensorKey::Overheat => "Overheat",
+///             SensorKey::Disconnected => "Disconnected",
+///         }
+///     }
+///
+///     fn limit(&self) -> Option<Limit> {
+///         match self {
+///             SensorKey::Overheat => Some(Limit::Rate(100)),
+///             SensorKey::Disconnected => None,
+///         }
+///     }
+/// }
+///
+/// let mut logger = RateLog::new(Limit::Rate(5));
+/// logger.log_key(SensorKey::Overheat, "sensor 3 at 91°C");  // Prints: "sensor 3 at 91°C"
+/// ```
+pub trait LogKey {
+    /// Returns the key used for rate limiting and reported in warnings.
+    fn key(&self) -> &'static str;
+
+    /// Returns the limit for this key, or `None` to use the limiter's own limit.
+    fn limit(&self) -> Option<Limit> {
+        None
+    }
+}
+
+/// A value deriving its own rate limiting key, e.g. an error enum keyed by variant.
+///
+/// Logged with [`RateLog::log_item`](crate::RateLog::log_item), which emits the
+/// [`Display`](core::fmt::Display) text of the value but rate limits by its key.
+/// Keying by variant instead of by formatted text keeps throttling robust against
+/// details such as paths, addresses or ids in the message. Strings are their own key.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{Limit, RateKey, RateLog};
+/// use std::borrow::Cow;
+/// use std::fmt;
+///
+/// enum FetchError {
+///     Timeout { url: String },
+///     Status(u16),
+/// }
+///
+/// impl fmt::Display for FetchError {
+///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+///         match self {
+///             FetchError::Timeout { url } => write!(f, "timeout fetching {url}"),
+///             FetchError::Status(status) => write!(f, "unexpected status {status}"),
+///         }
+///     }
+/// }
+///
+/// impl RateKey for FetchError {
+///     fn rate_key(&self) -> Cow<'_, str> {
+///         match self {
+///             FetchError::Timeout { .. } => Cow::Borrowed("FetchError::Timeout"),
+///             FetchError::Status(status) => Cow::Owned(format!("FetchError::Status({status})")),
+///         }
+///     }
+/// }
+///
+/// let mut logger = RateLog::new(Limit::Rate(5));
+/// logger.log_item(&FetchError::Timeout { url: "https://a.example".into() });
+/// // Prints: "timeout fetching https://a.example"
+/// logger.log_item(&FetchError::Timeout { url: "https://b.example".into() });
+/// // Silent: a repeat of FetchError::Timeout
+/// ```
+pub trait RateKey {
+    /// Returns the key used for rate limiting and reported in warnings.
+    fn rate_key(&self) -> Cow<'_, str>;
+}
+
+impl RateKey for str {
+    fn rate_key(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl RateKey for String {
+    fn rate_key(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl<T: RateKey + ?Sized> RateKey for &T {
+    fn rate_key(&self) -> Cow<'_, str> {
+        (**self).rate_key()
+    }
+}
+
+/// Declares an enum of log keys implementing [`LogKey`](crate::LogKey).
+///
+/// Every variant becomes a key named after the variant. A variant may declare its own
+/// limit with a `#[limit(...)]` attribute; variants without one use the limit of the
+/// [`RateLog`](crate::RateLog) they are logged through. Other attributes on the enum,
+/// such as derives, are kept.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{log_keys, Limit, RateLog};
+/// use std::time::Duration;
+///
+/// log_keys! {
+///     #[derive(Debug, Clone, Copy, PartialEq)]
+///     pub enum SensorKey {
+///         #[limit(Limit::Rate(100))]
+///         Overheat,
+///         #[limit(Limit::Duration(Duration::from_secs(60)))]
+///         Disconnected,
+///         Calibrating,
+///     }
+/// }
+///
+/// let mut logger = RateLog::new(Limit::Rate(5));
+/// logger.log_key(SensorKey::Disconnected, "sensor 3 disconnected");
+/// // Prints: "sensor 3 disconnected"
+/// ```
+#[macro_export]
+macro_rules! log_keys {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[limit($limit:expr)])?
+                $variant:ident
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($variant),*
+        }
+
+        impl $crate::LogKey for $name {
+            fn key(&self) -> &'static str {
+                match self {
+                    $($name::$variant => ::core::stringify!($variant)),*
+                }
+            }
+
+            fn limit(&self) -> ::core::option::Option<$crate::Limit> {
+                match self {
+                    $($name::$variant => $crate::__log_keys_limit!($($limit)?)),*
+                }
+            }
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_keys_limit {
+    () => {
+        ::core::option::Option::None
+    };
+    ($limit:expr) => {
+        ::core::option::Option::Some($limit)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CaptureSink, LogOutcome, RateLog};
+
+    log_keys! {
+        #[derive(Debug, Clone, Copy)]
+        enum TestKey {
+            #[limit(Limit::Rate(1))]
+            Strict,
+            Lenient,
+        }
+    }
+
+    #[test]
+    fn test_log_keys() {
+        assert_eq!(TestKey::Strict.key(), "Strict");
+        assert_eq!(TestKey::Lenient.limit(), None);
+
+        let mut rate_log = RateLog::with_sink(Limit::Rate(3), CaptureSink::new());
+
+        rate_log.log_key(TestKey::Strict, "message1");
+        assert!(matches!(
+            rate_log.log_key(TestKey::Strict, "message1"),
+            LogOutcome::LimitReported { count: 1, .. }
+        ));
+
+        rate_log.log_key(TestKey::Lenient, "message2");
+        assert_eq!(
+            rate_log.log_key(TestKey::Lenient, "message2"),
+            LogOutcome::Suppressed
+        );
+        assert_eq!(
+            rate_log.log_key(TestKey::Lenient, "message2"),
+            LogOutcome::Suppressed
+        );
+    }
+
+    #[cfg(feature = "std")]
+    struct Timeout(u32);
+
+    #[cfg(feature = "std")]
+    impl core::fmt::Display for Timeout {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write!(f, "timeout after {}ms", self.0)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl RateKey for Timeout {
+        fn rate_key(&self) -> Cow<'_, str> {
+            Cow::Borrowed("Timeout")
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_log_item() {
+        let mut rate_log = RateLog::with_sink(Limit::Rate(2), CaptureSink::new())
+            .with_clock(crate::MockClock::new());
+
+        for ms in [100, 200, 300] {
+            rate_log.log_item(&Timeout(ms));
+        }
+        rate_log.log_item("message1");
+        rate_log.log_item(&String::from("message1"));
+        rate_log.flush();
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "timeout after 100ms",
+                "Key: \"Timeout\" repeat for 2 times in the past 0ms, last message: \"timeout after 300ms\"",
+                "message1",
+                "Message: \"message1\" suppressed 1 repeats over 0ms",
+            ]
+        );
+    }
+}
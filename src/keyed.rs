@@ -0,0 +1,225 @@
+//! Rate limiting by arbitrary key values instead of strings.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use core::hash::{Hash, Hasher};
+
+use crate::sink::DefaultSink;
+#[cfg(feature = "std")]
+use crate::Limit;
+use crate::{Detail, LogOutcome, Map, RateLog, Set, Severity, Sink};
+
+/// A rate limiter keyed by any hashable value, such as an error enum, an integer code
+/// or a `(module, line)` tuple.
+///
+/// Keys are stored and compared as they are, so nothing is formatted on the hot path
+/// and keys are told apart even if they look alike. Each distinct key is rendered with
+/// its [`Debug`] representation once, when first logged, and that rendering is what
+/// rate limit warnings, summaries and [`RateLog::stats`] report; a key rendered like an
+/// earlier one is reported with a suffix, e.g. `Timeout #2`. Every distinct key is
+/// remembered, so keys should come from a bounded set.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{KeyedRateLog, Limit};
+///
+/// #[derive(Debug, PartialEq, Eq, Hash)]
+/// enum NetError {
+///     Timeout,
+///     Refused(u16),
+/// }
+///
+/// let mut logger = KeyedRateLog::new(Limit::Rate(2));
+///
+/// logger.log(NetError::Refused(443), "connection refused");  // Prints: "connection refused"
+/// logger.log(NetError::Timeout, "request timed out");        // Prints: "request timed out"
+/// logger.log(NetError::Timeout, "request timed out");        // Silent (1st repetition)
+/// logger.log(NetError::Timeout, "request timed out");
+/// // Prints: "Key: \"Timeout\" repeat for 2 times in the past 0ms, last message: \"request timed out\""
+/// ```
+pub struct KeyedRateLog<K, S: Sink = DefaultSink> {
+    /// The limiter tracking the key labels and writing to the sink.
+    rate_log: RateLog<S>,
+
+    /// Every key logged so far, with the label it is tracked under by `rate_log`.
+    keys: Vec<(K, String)>,
+
+    /// The positions in `keys` of the keys with each hash.
+    positions: Map<u64, Vec<usize>>,
+
+    /// The labels in `keys`.
+    labels: Set<String>,
+
+    /// The position in `keys` of the key of the last message that was logged.
+    current: Option<usize>,
+}
+
+#[cfg(feature = "std")]
+impl<K: Hash + Eq + Debug> KeyedRateLog<K> {
+    /// Creates a keyed limiter writing to stdout.
+    pub fn new(limit: Limit) -> Self {
+        KeyedRateLog::from_rate_log(RateLog::new(limit))
+    }
+}
+
+impl<K: Hash + Eq + Debug, S: Sink> KeyedRateLog<K, S> {
+    /// Wraps an already configured [`RateLog`].
+    pub fn from_rate_log(rate_log: RateLog<S>) -> Self {
+        KeyedRateLog {
+            rate_log,
+            keys: Vec::new(),
+            positions: Map::default(),
+            labels: Set::default(),
+            current: None,
+        }
+    }
+
+    /// Logs `msg` rate limited under `key`.
+    ///
+    /// See [`RateLog::log_keyed`] for the rate limiting behavior.
+    pub fn log(&mut self, key: K, msg: &str) -> LogOutcome {
+        let current = match self.current {
+            Some(current) if self.keys[current].0 == key => current,
+            _ => self.position(key),
+        };
+        self.current = Some(current);
+        self.rate_log.track(
+            &self.keys[current].1,
+            Detail::Text(msg),
+            Severity::default(),
+            None,
+        )
+    }
+
+    /// Returns the wrapped limiter, e.g. to inspect its statistics or sink.
+    pub fn rate_log(&self) -> &RateLog<S> {
+        &self.rate_log
+    }
+
+    /// Returns a mutable reference to the wrapped limiter.
+    pub fn rate_log_mut(&mut self) -> &mut RateLog<S> {
+        &mut self.rate_log
+    }
+
+    /// Returns the position of `key` in `keys`, adding it with a label of its own on
+    /// first use.
+    fn position(&mut self, key: K) -> usize {
+        let mut hasher = KeyHasher::default();
+        key.hash(&mut hasher);
+        let positions = self.positions.entry(hasher.finish()).or_default();
+        if let Some(&position) = positions
+            .iter()
+            .find(|&&position| self.keys[position].0 == key)
+        {
+            return position;
+        }
+
+        let rendering = format!("{key:?}");
+        let mut label = rendering.clone();
+        let mut alike = 1;
+        while self.labels.contains(&label) {
+            alike += 1;
+            label = format!("{rendering} #{alike}");
+        }
+        self.labels.insert(label.clone());
+        positions.push(self.keys.len());
+        self.keys.push((key, label));
+        self.keys.len() - 1
+    }
+}
+
+/// Hashes keys with 64-bit FNV-1a, which needs no random seed and so works without
+/// `std`.
+struct KeyHasher(u64);
+
+impl Default for KeyHasher {
+    fn default() -> Self {
+        KeyHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for KeyHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CaptureSink, Limit};
+
+    #[test]
+    fn test_tuple_keys() {
+        let rate_log = RateLog::with_sink(Limit::Rate(1), CaptureSink::new());
+        let mut logger = KeyedRateLog::from_rate_log(rate_log);
+
+        logger.log(("net", 42), "message1");
+        logger.log(("net", 42), "message2");
+        logger.log(("net", 43), "message3");
+
+        assert_eq!(
+            logger.rate_log().sink().lines(),
+            [
+                "message1",
+                "Key: \"(\"net\", 42)\" repeat for 1 times in the past 0ms, last message: \"message2\"",
+                "message3"
+            ]
+        );
+        assert_eq!(logger.rate_log().stats().messages().len(), 2);
+    }
+
+    #[test]
+    fn test_keys_alike() {
+        use core::fmt;
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        static RENDERED: AtomicUsize = AtomicUsize::new(0);
+
+        /// An error code whose `Debug` output leaves out the code.
+        #[derive(PartialEq, Eq, Hash)]
+        struct Code(u16);
+
+        impl Debug for Code {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                RENDERED.fetch_add(1, Ordering::Relaxed);
+                f.write_str("Code")
+            }
+        }
+
+        let rate_log = RateLog::with_sink(Limit::Rate(1), CaptureSink::new());
+        let mut logger = KeyedRateLog::from_rate_log(rate_log);
+
+        // Keys that look alike are still different keys
+        logger.log(Code(1), "message1");
+        logger.log(Code(2), "message2");
+        logger.log(Code(2), "message3");
+        for _ in 0..3 {
+            logger.log(Code(1), "message4");
+            logger.log(Code(2), "message5");
+        }
+
+        assert_eq!(
+            logger.rate_log().sink().lines()[..3],
+            [
+                "message1",
+                "message2",
+                "Key: \"Code #2\" repeat for 1 times in the past 0ms, last message: \"message3\""
+            ]
+        );
+        let stats = logger.rate_log().stats();
+        assert_eq!(stats.get("Code").unwrap().occurrences, 4);
+        assert_eq!(stats.get("Code #2").unwrap().occurrences, 5);
+        // Each key is rendered once, not on every change
+        assert_eq!(RENDERED.load(Ordering::Relaxed), 2);
+    }
+}
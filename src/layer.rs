@@ -0,0 +1,253 @@
+//! A `tracing-subscriber` layer writing events through a limiter.
+
+use std::fmt::{self, Write};
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::{Severity, SharedRateLog, Sink, StdoutSink};
+
+/// Which fields of an event and its spans make up its throttle key.
+///
+/// By default events are throttled by their message alone, so the same message from
+/// two tenants counts as one. Adding a field such as `tenant_id` gives every value of
+/// it its own count, so one noisy tenant does not suppress identical messages from
+/// other tenants. A field is looked up on the event first, then on its spans from the
+/// innermost outwards; events without it are keyed by their message.
+///
+/// The participating fields are appended to the logged message, e.g.
+/// `connection reset tenant_id=acme`, so warnings and summaries tell the keys apart.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::KeyPolicy;
+///
+/// let policy = KeyPolicy::message().field("tenant_id").field("region");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyPolicy {
+    fields: Vec<&'static str>,
+}
+
+impl KeyPolicy {
+    /// Keys events by their message alone.
+    pub fn message() -> Self {
+        KeyPolicy::default()
+    }
+
+    /// Adds the event or span field `name` to the key.
+    pub fn field(mut self, name: &'static str) -> Self {
+        self.fields.push(name);
+        self
+    }
+
+    /// Returns the fields participating in the key, in order.
+    pub fn fields(&self) -> &[&'static str] {
+        &self.fields
+    }
+}
+
+/// A [`Layer`] writing the message of every event through a [`SharedRateLog`].
+///
+/// Events are throttled by the key their [`KeyPolicy`] composes from the message and
+/// selected fields, with the event level as [`Severity`]. Lines go to the sink of the
+/// limiter, which can be shared with code logging through it directly.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{KeyPolicy, Limit, RateLimitLayer, SharedRateLog};
+/// use tracing_subscriber::prelude::*;
+///
+/// let layer = RateLimitLayer::new(SharedRateLog::new(Limit::Rate(10)))
+///     .with_key_policy(KeyPolicy::message().field("tenant_id"));
+/// let subscriber = tracing_subscriber::registry().with(layer);
+///
+/// tracing::subscriber::with_default(subscriber, || {
+///     let span = tracing::info_span!("request", tenant_id = "acme");
+///     let _guard = span.enter();
+///     tracing::warn!("connection reset");  // Prints: "connection reset tenant_id=acme"
+/// });
+/// ```
+pub struct RateLimitLayer<S: Sink = StdoutSink> {
+    logger: SharedRateLog<S>,
+    policy: KeyPolicy,
+}
+
+/// The values of the key fields recorded for a span, stored in its extensions.
+struct SpanFields(Vec<(&'static str, String)>);
+
+/// Collects the message and the key fields of an event or span.
+struct FieldVisitor<'p> {
+    policy: &'p KeyPolicy,
+    message: Option<String>,
+    fields: Vec<(&'static str, String)>,
+}
+
+impl<S: Sink> RateLimitLayer<S> {
+    /// Creates a layer logging through `logger`, keying events by their message.
+    pub fn new(logger: SharedRateLog<S>) -> Self {
+        RateLimitLayer {
+            logger,
+            policy: KeyPolicy::message(),
+        }
+    }
+
+    /// Sets which fields make up the throttle key of an event.
+    pub fn with_key_policy(mut self, policy: KeyPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Returns the limiter the layer logs through.
+    pub fn logger(&self) -> &SharedRateLog<S> {
+        &self.logger
+    }
+}
+
+impl<'p> FieldVisitor<'p> {
+    fn new(policy: &'p KeyPolicy) -> Self {
+        FieldVisitor {
+            policy,
+            message: None,
+            fields: Vec::new(),
+        }
+    }
+}
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record_debug(field, &format_args!("{value}"));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = Some(format!("{value:?}"));
+        } else if let Some(name) = self
+            .policy
+            .fields
+            .iter()
+            .find(|name| **name == field.name())
+        {
+            self.fields.retain(|(recorded, _)| recorded != name);
+            self.fields.push((name, format!("{value:?}")));
+        }
+    }
+}
+
+impl<S, Sub> Layer<Sub> for RateLimitLayer<S>
+where
+    S: Sink + Send + 'static,
+    Sub: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, Sub>) {
+        if self.policy.fields.is_empty() {
+            return;
+        }
+        let mut visitor = FieldVisitor::new(&self.policy);
+        attrs.record(&mut visitor);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields(visitor.fields));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, Sub>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        let Some(SpanFields(fields)) = extensions.get_mut::<SpanFields>() else {
+            return;
+        };
+        let mut visitor = FieldVisitor::new(&self.policy);
+        visitor.fields = std::mem::take(fields);
+        values.record(&mut visitor);
+        *fields = visitor.fields;
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, Sub>) {
+        let mut visitor = FieldVisitor::new(&self.policy);
+        event.record(&mut visitor);
+        let Some(mut msg) = visitor.message else {
+            return;
+        };
+
+        let mut fields = visitor.fields;
+        if fields.len() < self.policy.fields.len() {
+            for span in ctx.event_scope(event).into_iter().flatten() {
+                if let Some(SpanFields(recorded)) = span.extensions().get::<SpanFields>() {
+                    for (name, value) in recorded {
+                        if !fields.iter().any(|(found, _)| found == name) {
+                            fields.push((name, value.clone()));
+                        }
+                    }
+                }
+            }
+        }
+        for name in &self.policy.fields {
+            if let Some((_, value)) = fields.iter().find(|(found, _)| found == name) {
+                let _ = write!(msg, " {name}={value}");
+            }
+        }
+
+        self.logger
+            .lock()
+            .log_with_severity(severity(*event.metadata().level()), &msg);
+    }
+}
+
+/// Returns the severity of a `tracing` level.
+fn severity(level: Level) -> Severity {
+    match level {
+        Level::ERROR => Severity::Error,
+        Level::WARN => Severity::Warn,
+        Level::INFO => Severity::Info,
+        Level::DEBUG => Severity::Debug,
+        Level::TRACE => Severity::Trace,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CaptureSink, Limit, RateLog};
+    use tracing_subscriber::prelude::*;
+
+    #[test]
+    fn test_key_policy() {
+        let logger =
+            SharedRateLog::from_rate_log(RateLog::with_sink(Limit::Rate(100), CaptureSink::new()));
+        let layer = RateLimitLayer::new(logger.clone())
+            .with_key_policy(KeyPolicy::message().field("tenant_id"));
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            for tenant in ["acme", "acme", "globex"] {
+                let span = tracing::info_span!("request", tenant_id = tenant);
+                let _guard = span.enter();
+                tracing::warn!("connection reset");
+            }
+            let span = tracing::info_span!("request", tenant_id = tracing::field::Empty);
+            span.record("tenant_id", "initech");
+            span.in_scope(|| tracing::warn!("connection reset"));
+            tracing::warn!(tenant_id = "umbrella", "connection reset");
+            tracing::warn!("connection reset");
+        });
+
+        assert_eq!(
+            logger.lock().sink().lines(),
+            [
+                "connection reset tenant_id=acme",
+                "Message: \"connection reset tenant_id=acme\" suppressed 1 repeats over 0ms",
+                "connection reset tenant_id=globex",
+                "connection reset tenant_id=initech",
+                "connection reset tenant_id=umbrella",
+                "connection reset",
+            ]
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! Rate limiting with a different limit per severity.
+
+use crate::sink::DefaultSink;
+use crate::{Detail, Limit, LogOutcome, Map, RateLog, Severity, Sink};
+
+/// A rate limiter that applies a different limit to each severity.
+///
+/// Every call names the severity of its message. Severities configured with
+/// [`LeveledRateLog::with_level_limit`] are rate limited with their own limit, severities
+/// marked with [`LeveledRateLog::with_unlimited_level`] are never suppressed, and all
+/// others use the limit of the wrapped [`RateLog`].
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{LeveledRateLog, Limit, Severity};
+///
+/// let mut logger = LeveledRateLog::new(Limit::Rate(10))
+///     .with_unlimited_level(Severity::Error)
+///     .with_level_limit(Severity::Warn, Limit::Rate(100));
+///
+/// logger.log(Severity::Error, "Disk full");   // Prints: "Disk full"
+/// logger.log(Severity::Error, "Disk full");   // Prints: "Disk full" (errors are never suppressed)
+/// logger.log(Severity::Debug, "Cache miss");  // Prints: "Cache miss", then Rate(10) applies
+/// ```
+pub struct LeveledRateLog<S: Sink = DefaultSink> {
+    /// The limiter tracking messages and writing to the sink.
+    rate_log: RateLog<S>,
+
+    /// The limits of configured severities; `None` means never suppressed.
+    limits: Map<Severity, Option<Limit>>,
+}
+
+#[cfg(feature = "std")]
+impl LeveledRateLog {
+    /// Creates a leveled limiter writing to stdout, using `limit` for every severity
+    /// until others are configured.
+    pub fn new(limit: Limit) -> Self {
+        LeveledRateLog::from_rate_log(RateLog::new(limit))
+    }
+}
+
+impl<S: Sink> LeveledRateLog<S> {
+    /// Wraps an already configured [`RateLog`], whose limit applies to every severity
+    /// until others are configured.
+    pub fn from_rate_log(rate_log: RateLog<S>) -> Self {
+        LeveledRateLog {
+            rate_log,
+            limits: Map::new(),
+        }
+    }
+
+    /// Rate limits messages of `level` with `limit`.
+    pub fn with_level_limit(mut self, level: Severity, limit: Limit) -> Self {
+        self.limits.insert(level, Some(limit));
+        self
+    }
+
+    /// Sets the limits of several severities at once.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{LeveledRateLog, Limit, Severity};
+    /// use std::collections::HashMap;
+    ///
+    /// let limits = HashMap::from([
+    ///     (Severity::Warn, Limit::Rate(100)),
+    ///     (Severity::Debug, Limit::Rate(10)),
+    /// ]);
+    /// let logger = LeveledRateLog::new(Limit::Rate(50)).with_level_limits(limits);
+    /// ```
+    pub fn with_level_limits(
+        mut self,
+        limits: impl IntoIterator<Item = (Severity, Limit)>,
+    ) -> Self {
+        for (level, limit) in limits {
+            self.limits.insert(level, Some(limit));
+        }
+        self
+    }
+
+    /// Never suppresses messages of `level`; every occurrence is emitted verbatim.
+    ///
+    /// Unlimited messages are not tracked, so they neither appear in
+    /// [`RateLog::stats`] nor interrupt the rate limiting of other messages.
+    pub fn with_unlimited_level(mut self, level: Severity) -> Self {
+        self.limits.insert(level, None);
+        self
+    }
+
+    /// Logs `msg` with the limit configured for `level`.
+    ///
+    /// See [`RateLog::log`] for the rate limiting behavior.
+    pub fn log(&mut self, level: Severity, msg: &str) -> LogOutcome {
+        match self.limits.get(&level) {
+            Some(None) => self.rate_log.pass_through(msg, level),
+            Some(Some(limit)) => self.rate_log.track_message(msg, level, Some(limit.clone())),
+            None => self.rate_log.track_message(msg, level, None),
+        }
+    }
+
+    /// Logs `msg` rate limited under `key` with the limit configured for `level`.
+    ///
+    /// See [`RateLog::log_keyed`] for the rate limiting behavior.
+    pub fn log_keyed(&mut self, level: Severity, key: &str, msg: &str) -> LogOutcome {
+        match self.limits.get(&level) {
+            Some(None) => self.rate_log.pass_through(msg, level),
+            Some(Some(limit)) => {
+                self.rate_log
+                    .track(key, Detail::Text(msg), level, Some(limit.clone()))
+            }
+            None => self.rate_log.track(key, Detail::Text(msg), level, None),
+        }
+    }
+
+    /// Returns the wrapped limiter, e.g. to inspect its statistics or sink.
+    pub fn rate_log(&self) -> &RateLog<S> {
+        &self.rate_log
+    }
+
+    /// Returns a mutable reference to the wrapped limiter.
+    pub fn rate_log_mut(&mut self) -> &mut RateLog<S> {
+        &mut self.rate_log
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CaptureSink;
+
+    #[test]
+    fn test_level_limits() {
+        let rate_log = RateLog::with_sink(Limit::Rate(1), CaptureSink::new());
+        let mut logger = LeveledRateLog::from_rate_log(rate_log)
+            .with_unlimited_level(Severity::Error)
+            .with_level_limit(Severity::Warn, Limit::Rate(3));
+
+        for _ in 0..3 {
+            logger.log(Severity::Warn, "message1");
+        }
+        logger.log(Severity::Error, "message2");
+        logger.log(Severity::Error, "message2");
+        for _ in 0..2 {
+            logger.log(Severity::Debug, "message3");
+        }
+
+        assert_eq!(
+            logger.rate_log().sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" suppressed 2 repeats over 0ms",
+                "message2",
+                "message2",
+                "message3",
+                "Message: \"message3\" repeat for 1 times in the past 0ms"
+            ]
+        );
+    }
+}
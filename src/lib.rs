@@ -32,8 +32,10 @@
 //! }
 //! // After 5 repetitions, it will output: "Message: \"This is a new message\" repeat for 5 times in the past 10ms"
 //!
-//! // Different message gets printed immediately and resets counter
-//! rate_log.log("Different message");  // Prints: "Different message"
+//! // Different message reports the 2 pending repeats, then gets printed immediately
+//! rate_log.log("Different message");
+//! // Prints: "Message: \"This is a new message\" suppressed 2 repeats over 4ms"
+//! // Prints: "Different message"
 //! ```
 //!
 //! ## Rate Limiting Types
@@ -72,6 +74,129 @@
 //! logger.log("Periodic event");      // 1100ms total - triggers: "Message: \"Periodic event\" repeat for 2 times in the past 1s"
 //! ```
 //!
+//! ### Combined Limiting (`Limit::Any` / `Limit::All`)
+//!
+//! Combines a count and a duration threshold. `Any` triggers on whichever is reached
+//! first, `All` requires both:
+//!
+//! ```rust
+//! use rate_log::{RateLog, Limit};
+//! use std::time::Duration;
+//!
+//! // Warn after 100 repeats or 30 seconds, whichever happens first
+//! let mut logger = RateLog::new(Limit::Any(100, Duration::from_secs(30)));
+//!
+//! // Warn only once there were 100 repeats spanning at least 30 seconds
+//! let mut strict = RateLog::new(Limit::All(100, Duration::from_secs(30)));
+//! ```
+//!
+//! Warnings of combined limits state which component was reached, e.g.
+//! `"Message: \"text\" repeat for 100 times in the past 2s (count limit reached)"`, and the
+//! [`Trigger`] is also available in the [`LogOutcome`] returned by [`RateLog::log`].
+//!
+//! ### Window Limiting (`Limit::Window`)
+//!
+//! Allows at most `max` identical messages per rolling window and suppresses the rest,
+//! emitting one summary per window:
+//!
+//! ```rust
+//! use rate_log::{RateLog, Limit};
+//! use std::time::Duration;
+//!
+//! let mut logger = RateLog::new(Limit::Window { max: 5, per: Duration::from_secs(1) });
+//!
+//! for _ in 0..100 {
+//!     logger.log("Packet dropped");  // Printed 5 times, the other 95 repeats are suppressed
+//! }
+//! // Once the window has room again, the next call prints:
+//! // "Message: \"Packet dropped\" repeat for 96 times in the past 1s"
+//! ```
+//!
+//! ### Byte Budget Limiting (`Limit::Bytes`)
+//!
+//! Like window limiting, but bounds the bytes emitted per rolling window instead of
+//! the number of messages. Summaries report how many bytes were dropped:
+//!
+//! ```rust
+//! use rate_log::{RateLog, Limit};
+//! use std::time::Duration;
+//!
+//! // At most 1 KiB of the same message per second
+//! let mut logger = RateLog::new(Limit::Bytes { max: 1024, per: Duration::from_secs(1) });
+//! ```
+//!
+//! ### GCRA Pacing (`Limit::Gcra`)
+//!
+//! Paces messages to one per `period` with bursts of up to `burst`, keeping a single
+//! timestamp per message:
+//!
+//! ```rust
+//! use rate_log::{RateLog, Limit};
+//! use std::time::Duration;
+//!
+//! // Bursts of up to 5 messages, then one message every 200ms
+//! let mut logger = RateLog::new(Limit::Gcra { period: Duration::from_millis(200), burst: 5 });
+//! ```
+//!
+//! ### Token Bucket Limiting (`Limit::TokenBucket`)
+//!
+//! Allows bursts up to `capacity` messages, then emits messages at a steady refill rate
+//! and periodically reports the suppressed repeats:
+//!
+//! ```rust
+//! use rate_log::{RateLog, Limit};
+//!
+//! // Bursts of up to 10 messages, then one message every 2 seconds
+//! let mut logger = RateLog::new(Limit::TokenBucket { capacity: 10, refill_per_sec: 0.5 });
+//! ```
+//!
+//! ### Exponential Backoff (`Limit::Exponential`)
+//!
+//! Emits a repeated message on its 1st, 2nd, 4th, 8th, ... occurrence (for `base: 2`),
+//! including the number of occurrences so far:
+//!
+//! ```rust
+//! use rate_log::{RateLog, Limit};
+//!
+//! let mut logger = RateLog::new(Limit::Exponential { base: 2 });
+//!
+//! for _ in 0..4 {
+//!     logger.log("Retrying");
+//! }
+//! // Prints: "Retrying", "Retrying (seen 2 times)", "Retrying (seen 4 times)"
+//! ```
+//!
+//! ### Sampling (`Limit::Sample`)
+//!
+//! Emits 1 out of every N identical messages, annotated with the sampling rate:
+//!
+//! ```rust
+//! use rate_log::{RateLog, Limit};
+//!
+//! let mut logger = RateLog::new(Limit::Sample(3));
+//!
+//! for _ in 0..4 {
+//!     logger.log("Polling");
+//! }
+//! // Prints: "Polling", "Polling (sampled 1/3)"
+//! ```
+//!
+//! ### Burst Then Steady (`Limit::BurstThenSteady`)
+//!
+//! Lets the first `burst` occurrences through, then at most one per `interval`,
+//! annotated with the number of occurrences suppressed in between:
+//!
+//! ```rust
+//! use rate_log::{RateLog, Limit};
+//! use std::time::Duration;
+//!
+//! let mut logger = RateLog::new(Limit::BurstThenSteady {
+//!     burst: 5,
+//!     interval: Duration::from_secs(10),
+//! });
+//! // Prints the first 5 occurrences, then e.g. "Timeout (+120 more)" every 10 seconds
+//! ```
+//!
 //! ## Behavior
 //!
 //! - **New message printing**: Every new/different message is immediately printed to stdout
@@ -80,6 +205,77 @@
 //! - **Smart duration formatting**: Automatically displays duration in appropriate units (ms, s, m, h) with whole numbers
 //! - **Comprehensive warnings**: Rate limit violations show both count and duration: "Message: \"text\" repeat for X times in the past Yms"
 //! - **Counter reset**: Switching to a different message resets all counters and prints the new message
+//! - **Summary flush**: Repeats still pending when the message changes or the logger is dropped are
+//!   reported as "Message: \"text\" suppressed N repeats over X"
+//!
+//! ## Macros
+//!
+//! [`rate_log!`], [`rate_warn!`] and [`rate_error!`] accept `format!`-style arguments and
+//! keep a rate limiter per call site, so no `RateLog` has to be passed around:
+//!
+//! ```rust
+//! use rate_log::{rate_warn, Limit};
+//!
+//! let host = "10.0.0.1";
+//! rate_warn!(Limit::Rate(10), "retrying {}", host);  // Prints: "[WARN] retrying 10.0.0.1"
+//! ```
+//!
+//! [`rate_log_here!`] logs through a given limiter, keyed by the file and line of the
+//! call site instead of the message text:
+//!
+//! ```rust
+//! use rate_log::{rate_log_here, Limit, RateLog};
+//!
+//! let mut logger = RateLog::new(Limit::Rate(10));
+//! for id in 0..3 {
+//!     rate_log_here!(logger, "request {} failed", id);  // Prints only "request 0 failed"
+//! }
+//! ```
+//!
+//! [`glog!`] logs through a single process-wide limiter installed with [`init_global`]:
+//!
+//! ```rust
+//! use rate_log::{glog, init_global, Limit};
+//!
+//! init_global(Limit::Rate(10)).unwrap();
+//! glog!("cache miss for {}", "user:42");  // Prints: "cache miss for user:42"
+//! ```
+//!
+//! ## Feature Flags
+//!
+//! - **`std`** (default): The standard library. Without it the crate only needs `alloc`:
+//!   time comes from a [`Monotonic`] source and output goes to any [`Sink`]; stdout,
+//!   file and thread based types as well as all other features are unavailable
+//! - **`timestamp`**: Wall-clock timestamps ([`TimestampFormat`], [`Timestamped`]) implemented
+//!   purely with `std::time::SystemTime`, without any date/time dependency
+//! - **`chrono`**: Local time and custom format strings for timestamps, implies `timestamp`
+//! - **`serde`**: JSON lines output ([`OutputFormat`]`::Json`) following the versioned `EventRecord` schema,
+//!   and serializable [`Snapshot`]s of the tracking state
+//! - **`tracing`**: Attaches the id of the current `tracing` span to warnings and top
+//!   offenders as an exemplar (see [`RateLog::with_exemplars`])
+//! - **`tracing-layer`**: `RateLimitLayer`, a `tracing-subscriber` layer writing events
+//!   through a limiter, keyed by their message plus span fields chosen with `KeyPolicy`;
+//!   implies `tracing`
+//! - **`std-thread`**: A background `Reporter` thread emitting [`RateLog::report_suppressed`] lines
+//! - **`tokio`**: `AsyncRateLog`, whose logging calls never block
+//! - **`log`**: `RateLimited`, a `log::Log` throttling records before passing them to
+//!   an existing backend such as `env_logger`, and `LogSink`, writing lines as `log`
+//!   records
+//! - **`config`**: `Config`, limiter policies per key loaded from TOML or YAML files and
+//!   turned into a [`Registry`]
+//! - **`watch`**: `ConfigWatcher`, reloading the limits of a registry when its
+//!   configuration file changes, without resetting accumulated counts; implies `config`
+//! - **`metrics`**: Emitted and suppressed counters exported through the `metrics` facade
+//! - **`color`**: Yellow warnings and red escalations on terminals (`ColorChoice`)
+//! - **`syslog`**: `SyslogSink`, forwarding lines to the local syslog daemon (Unix only)
+//! - **`wasm`**: Browser and Node support: timing through `performance.now()`, where
+//!   `std::time` panics, and a `ConsoleSink` writing to the JavaScript console
+//! - **`cli`**: The `rate-log` binary, rate limiting lines piped to its stdin, e.g.
+//!   `journalctl -f | rate-log --rate 20`
+//! - **`anyhow`**, **`eyre`**: `RateLog::log_anyhow` and `RateLog::log_eyre`, logging an
+//!   error with its context chain, rate limited by its root cause
+//! - **`futures`**: `RateLimitExt::rate_limit_logs`, a `Stream` adapter yielding the
+//!   lines a limiter emits for the items of a stream
 //!
 //! ## Use Cases
 //!
@@ -89,7 +285,218 @@
 //! - **Network logging**: Manage connection retry message frequency
 //! - **System monitoring**: Control repeated system state notifications
 
-use std::time::{Duration, Instant};
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::io::Write;
+
+mod adaptive;
+mod alternation;
+#[cfg(feature = "tokio")]
+mod asynchronous;
+mod budget;
+#[cfg(feature = "std")]
+mod buffered;
+#[cfg(feature = "std")]
+mod builder;
+#[cfg(any(feature = "anyhow", feature = "eyre"))]
+mod chain;
+#[cfg(feature = "std")]
+mod channel;
+mod clock;
+mod coalesce;
+#[cfg(feature = "color")]
+mod color;
+#[cfg(feature = "std")]
+mod concurrent;
+#[cfg(feature = "std")]
+mod config;
+#[cfg(feature = "config")]
+mod config_file;
+#[cfg(feature = "wasm")]
+mod console;
+mod dedup;
+mod duration;
+mod error;
+mod event;
+mod exemplar;
+mod fanout;
+#[cfg(feature = "std")]
+mod file;
+#[cfg(feature = "std")]
+mod global;
+mod key;
+mod keyed;
+#[cfg(feature = "tracing-layer")]
+mod layer;
+mod leveled;
+mod limit;
+mod limiter;
+#[cfg(feature = "std")]
+mod local;
+mod location;
+#[cfg(feature = "log")]
+mod logger;
+mod macros;
+mod mux;
+mod normalize;
+mod output;
+#[cfg(feature = "std")]
+mod panic;
+#[cfg(feature = "std")]
+mod pool;
+#[cfg(feature = "std")]
+mod registry;
+#[cfg(feature = "std-thread")]
+mod reporter;
+mod result;
+mod severity;
+#[cfg(feature = "std")]
+mod shared;
+#[cfg(feature = "std")]
+mod simulate;
+mod sink;
+mod snapshot;
+mod stats;
+#[cfg(feature = "futures")]
+mod stream;
+#[cfg(all(feature = "syslog", unix))]
+mod syslog;
+#[cfg(feature = "metrics")]
+mod telemetry;
+mod text;
+#[cfg(feature = "timestamp")]
+mod timestamp;
+mod warning;
+#[cfg(feature = "watch")]
+mod watch;
+
+#[doc(hidden)]
+pub use alloc::format as __format;
+#[cfg(feature = "tokio")]
+pub use asynchronous::{AsyncRateLog, AsyncSink};
+#[cfg(feature = "std")]
+pub use buffered::Buffered;
+#[cfg(feature = "std")]
+pub use builder::RateLogBuilder;
+#[cfg(feature = "std")]
+pub use channel::ChannelSink;
+#[cfg(feature = "std")]
+pub use concurrent::ConcurrentRateLog;
+#[cfg(feature = "std")]
+pub use config::ConfigError;
+#[cfg(feature = "config")]
+pub use config_file::{Config, ConfigSink};
+pub use error::{Error, Result};
+pub use event::Event;
+#[cfg(feature = "tracing")]
+pub use exemplar::tracing_span_exemplar;
+pub use fanout::{Fanout, SinkFilter};
+#[cfg(feature = "std")]
+pub use file::{FileSink, Rotation};
+#[cfg(feature = "std")]
+pub use global::{global, init_global};
+pub use key::{LogKey, RateKey};
+pub use keyed::KeyedRateLog;
+#[cfg(feature = "tracing-layer")]
+pub use layer::{KeyPolicy, RateLimitLayer};
+pub use leveled::LeveledRateLog;
+pub use limit::{CountMode, DurationMode, Limit, Trigger};
+pub use limiter::{Decision, Limiter};
+pub use location::Location;
+#[cfg(feature = "log")]
+pub use logger::{LogSink, RateLimited};
+
+use adaptive::AdaptiveLimit;
+use alternation::AlternationTracker;
+use budget::{Admission, GlobalBudget};
+#[cfg(not(feature = "std"))]
+pub use clock::Instant;
+#[cfg(feature = "std")]
+use clock::Instant;
+pub use clock::{Clock, Monotonic};
+use clock::{CoarseClock, MonotonicClock};
+#[cfg(feature = "std")]
+pub use clock::{MockClock, SystemClock};
+use coalesce::{Coalesced, WarningCoalescer};
+#[cfg(feature = "color")]
+pub use color::ColorChoice;
+#[cfg(feature = "color")]
+use color::Style;
+#[cfg(feature = "wasm")]
+pub use console::ConsoleSink;
+pub use dedup::DedupWindow;
+use dedup::{DedupTracker, Tracked};
+pub use duration::DurationStyle;
+use exemplar::ExemplarSource;
+use limit::State;
+#[cfg(feature = "std")]
+pub use local::ThreadRateLog;
+#[doc(hidden)]
+#[cfg(feature = "std")]
+pub use local::ThreadSlot as __ThreadSlot;
+pub use mux::Mux;
+pub use normalize::Normalizer;
+use output::Annotation;
+#[cfg(feature = "serde")]
+pub use output::EventRecord;
+pub use output::OutputFormat;
+#[cfg(feature = "std")]
+pub use panic::{install_panic_hook, install_panic_hook_with};
+#[cfg(feature = "std")]
+pub use pool::{PooledRateLog, RateLogPool};
+#[cfg(feature = "std")]
+pub use registry::Registry;
+#[cfg(feature = "std-thread")]
+pub use reporter::Reporter;
+pub use result::RateLogResultExt;
+pub use severity::Severity;
+#[cfg(feature = "std")]
+pub use shared::SharedRateLog;
+use sink::DefaultSink;
+pub use sink::{CaptureSink, Decorated, LineKind, Sink};
+#[cfg(feature = "std")]
+pub use sink::{StderrSink, StdoutSink, WriterSink};
+pub use snapshot::{Snapshot, SnapshotEntry};
+use stats::{Entries, Entry, Held};
+pub use stats::{IntervalHistogram, MessageStats, Offender, Stats, Summary};
+#[cfg(feature = "futures")]
+pub use stream::{RateLimitExt, RateLimitedLogs};
+#[cfg(all(feature = "syslog", unix))]
+pub use syslog::{Facility, SyslogLevel, SyslogSink};
+pub use text::{Sanitized, TextPolicy};
+#[cfg(feature = "timestamp")]
+pub use timestamp::{TimestampFormat, Timestamped};
+use warning::Formatter;
+pub use warning::{Warning, WarningKind};
+#[cfg(feature = "watch")]
+pub use watch::ConfigWatcher;
+
+/// The map of tracked keys; ordered without `std`, where there is no random source to
+/// seed a hasher.
+#[cfg(feature = "std")]
+type Map<K, V> = std::collections::HashMap<K, V>;
+#[cfg(not(feature = "std"))]
+type Map<K, V> = alloc::collections::BTreeMap<K, V>;
+
+/// The set counterpart of [`Map`].
+#[cfg(feature = "std")]
+type Set<T> = std::collections::HashSet<T>;
+#[cfg(not(feature = "std"))]
+type Set<T> = alloc::collections::BTreeSet<T>;
+
+/// A callback invoked with the key, repeat count and duration of an exceeded limit.
+type LimitCallback = Box<dyn FnMut(&str, u32, Duration) + Send>;
 
 /// Formats a duration into a human-readable string with at least two parts when possible.
 /// Shows hours and minutes for >= 1 hour, minutes and seconds for >= 1 minute,
@@ -111,82 +518,238 @@ fn format_duration(duration: Duration) -> String {
     }
 }
 
-/// Defines the type and threshold for rate limiting.
+/// Returns `true` if the [`Display`](fmt::Display) text of `item` is `expected`,
+/// comparing piece by piece instead of rendering it.
+fn displays_as<T: fmt::Display + ?Sized>(item: &T, expected: &str) -> bool {
+    struct Matcher<'e>(&'e str);
+
+    impl fmt::Write for Matcher<'_> {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.0 = self.0.strip_prefix(s).ok_or(fmt::Error)?;
+            Ok(())
+        }
+    }
+
+    let mut rest = Matcher(expected);
+    fmt::Write::write_fmt(&mut rest, format_args!("{item}")).is_ok() && rest.0.is_empty()
+}
+
+/// Renders `file:line` without allocating when it fits [`LocationKey::CAPACITY`].
+///
+/// Used by [`RateLog::log_here`], which runs for every repeat of its call site.
+struct LocationKey {
+    buf: [u8; LocationKey::CAPACITY],
+    len: usize,
+    spilled: Option<String>,
+}
+
+impl LocationKey {
+    const CAPACITY: usize = 192;
+
+    fn new(file: &str, line: u32) -> Self {
+        let mut key = Self {
+            buf: [0; Self::CAPACITY],
+            len: 0,
+            spilled: None,
+        };
+        if fmt::Write::write_fmt(&mut key, format_args!("{file}:{line}")).is_err() {
+            key.spilled = Some(format!("{file}:{line}"));
+        }
+        key
+    }
+
+    fn as_str(&self) -> &str {
+        match &self.spilled {
+            Some(key) => key,
+            // Only whole `&str`s are copied in, so the prefix is valid UTF-8
+            None => core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default(),
+        }
+    }
+}
+
+impl fmt::Write for LocationKey {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Returns a hash of `key` that takes the same time whatever its length, mixing the
+/// length with the first and last 8 bytes.
+///
+/// Keys with different hashes differ, so most other keys are told apart from the
+/// tracked one without comparing them in full.
+fn key_hash(key: &str) -> u64 {
+    let bytes = key.as_bytes();
+    // Whole words are read at once, only keys shorter than a word byte by byte
+    let word = |chunk: &[u8]| match <[u8; 8]>::try_from(chunk) {
+        Ok(word) => u64::from_le_bytes(word),
+        Err(_) => chunk
+            .iter()
+            .fold(0_u64, |word, &byte| word << 8 | u64::from(byte)),
+    };
+    let head = word(&bytes[..bytes.len().min(8)]);
+    let tail = word(&bytes[bytes.len().saturating_sub(8)..]);
+    (head ^ tail.rotate_left(32) ^ bytes.len() as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+}
+
+/// Returns the label identifying `key` in statistics when keys are hashed, e.g.
+/// `#a430d84680aabd0b`.
+///
+/// Uses 64-bit FNV-1a, which is stable across runs so snapshots stay valid.
+fn hashed_key(key: &str) -> EntryKey<'static> {
+    let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    let mut label = [b'#'; 17];
+    for (i, digit) in label[1..].iter_mut().enumerate() {
+        *digit = b"0123456789abcdef"[(hash >> (60 - 4 * i)) as usize & 0xf];
+    }
+    EntryKey::Hashed(label)
+}
+
+/// The key statistics are remembered under, see [`RateLog::with_hashed_keys`].
+///
+/// Hashed keys are built on the stack, so looking up statistics never allocates.
+#[derive(Clone, Copy)]
+enum EntryKey<'k> {
+    /// The key itself.
+    Plain(&'k str),
+    /// `#` followed by the hash of the key in 16 hex digits.
+    Hashed([u8; 17]),
+}
+
+impl EntryKey<'_> {
+    fn into_owned(self) -> String {
+        String::from(self.as_ref())
+    }
+}
+
+impl AsRef<str> for EntryKey<'_> {
+    fn as_ref(&self) -> &str {
+        match self {
+            EntryKey::Plain(key) => key,
+            EntryKey::Hashed(label) => core::str::from_utf8(label).unwrap_or_default(),
+        }
+    }
+}
+
+/// Returns `true` if `target` matches `pattern`: the target itself, or with a `::*`
+/// suffix the target and every target below it.
+fn target_matches(pattern: &str, target: &str) -> bool {
+    match pattern.strip_suffix("::*") {
+        Some(parent) => {
+            target == parent
+                || target
+                    .strip_prefix(parent)
+                    .is_some_and(|rest| rest.starts_with("::"))
+        }
+        None => target == pattern,
+    }
+}
+
+/// Formats a rate per second, rounded to whole messages from 10/s upwards.
+fn format_rate(rate: f64) -> String {
+    if rate >= 10.0 {
+        format!("{rate:.0}/s")
+    } else {
+        format!("{rate:.1}/s")
+    }
+}
+
+/// Formats a count with thousands separators, e.g. `4,231`.
+fn format_count(count: u64) -> String {
+    let digits = count.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    // The leading group takes the 1 to 3 digits left over by the groups of three
+    let (head, tail) = digits.split_at((digits.len() - 1) % 3 + 1);
+    out.push_str(head);
+    for start in (0..tail.len()).step_by(3) {
+        out.push(',');
+        out.push_str(&tail[start..start + 3]);
+    }
+    out
+}
+
+/// The decision taken by [`RateLog::log`] for a single call.
 ///
-/// `Limit` specifies how rate limiting should be applied - either by counting
-/// message occurrences or by measuring time duration between repeated messages.
+/// Lets calling code decide whether to forward a message to its own logging
+/// pipeline instead of relying on the sink output alone.
 ///
 /// # Examples
 ///
 /// ```rust
-/// use rate_log::Limit;
-/// use std::time::Duration;
+/// use rate_log::{RateLog, Limit, LogOutcome};
 ///
-/// // Allow up to 10 repeated messages before triggering rate limit
-/// let count_limit = Limit::Rate(10);
+/// let mut logger = RateLog::new(Limit::Rate(1));
 ///
-/// // Allow up to 5 seconds of accumulated time between repeated messages
-/// let time_limit = Limit::Duration(Duration::from_secs(5));
+/// assert_eq!(logger.log("Disk full"), LogOutcome::Emitted);
+/// assert!(matches!(
+///     logger.log("Disk full"),
+///     LogOutcome::LimitReported { count: 1, .. }
+/// ));
 /// ```
-#[derive(Debug, PartialEq, PartialOrd)]
-pub enum Limit {
-    /// Count-based rate limiting.
-    ///
-    /// Triggers when the same message is repeated more than the specified number of times.
-    /// The counter resets when a different message is logged.
-    ///
-    /// # Example
-    /// ```rust
-    /// use rate_log::{RateLog, Limit};
-    ///
-    /// let mut logger = RateLog::new(Limit::Rate(3));
-    /// // Will trigger rate limit warning after 4th identical message
-    /// ```
-    Rate(u32),
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogOutcome {
+    /// The message was emitted, either because it was new or different from the
+    /// previous one or because the limit still allowed it (see [`Limit::Window`],
+    /// [`Limit::TokenBucket`], [`Limit::Exponential`] and [`Limit::Sample`]).
+    Emitted,
 
-    /// Duration-based rate limiting.
-    ///
-    /// Triggers when the accumulated time between consecutive identical messages
-    /// exceeds the specified duration. Time is measured between actual calls,
-    /// providing real-world timing behavior.
-    ///
-    /// # Example
-    /// ```rust
-    /// use rate_log::{RateLog, Limit};
-    /// use std::time::Duration;
-    ///
-    /// let mut logger = RateLog::new(Limit::Duration(Duration::from_millis(500)));
-    /// // Will trigger if total elapsed time between identical messages > 500ms
-    /// ```
-    Duration(Duration),
+    /// The message repeated the previous one and was counted silently.
+    Suppressed,
+
+    /// The message repeated the previous one and exceeded the limit, so a rate limit
+    /// warning was emitted. Carries the repeat count and accumulated duration reported
+    /// in the warning.
+    LimitReported {
+        /// The number of repeats reported in the warning.
+        count: u32,
+        /// The accumulated duration reported in the warning.
+        duration: Duration,
+        /// The limit component that caused the warning.
+        trigger: Trigger,
+    },
+
+    /// The message was discarded without being tracked because it was not clean
+    /// UTF-8 text and the sink's [`TextPolicy`] is [`TextPolicy::Drop`].
+    Dropped,
 }
 
-#[derive(Debug)]
-struct State {
-    count: u32,
-    duration: Duration,
-    last_timestamp: Option<Instant>,
+/// The message text of a logging call, when it differs from the rate limiting key.
+enum Detail<'a> {
+    /// The key is the message itself.
+    Key,
+    /// The message text.
+    Text(&'a str),
+    /// Builds the message text, only called when the message is emitted.
+    Lazy(&'a mut dyn FnMut() -> String),
+    /// Only evaluates the limit; the message itself is never emitted.
+    Check,
 }
 
-impl State {
-    fn new() -> Self {
-        State {
-            count: 0,
-            duration: Duration::from_secs(0),
-            last_timestamp: None,
-        }
+impl<'a> Detail<'a> {
+    /// Returns `true` if the message was logged under a separate key.
+    fn is_keyed(&self) -> bool {
+        !matches!(self, Detail::Key)
     }
 
-    fn reset(&mut self) {
-        self.count = 0;
-        self.duration = Duration::from_secs(0);
-        self.last_timestamp = None;
+    /// Returns `true` if the call only evaluates the limit, see [`RateLog::check`].
+    fn is_check(&self) -> bool {
+        matches!(self, Detail::Check)
     }
 
-    fn exceeds_limit(&self, limit: &Limit) -> bool {
-        match limit {
-            Limit::Rate(limit_count) => self.count >= *limit_count,
-            Limit::Duration(limit_duration) => self.duration >= *limit_duration,
+    /// Returns the message text, building it if necessary.
+    fn resolve(&mut self) -> Option<Cow<'a, str>> {
+        match self {
+            Detail::Key | Detail::Check => None,
+            Detail::Text(text) => Some(Cow::Borrowed(*text)),
+            Detail::Lazy(build) => Some(Cow::Owned(build())),
         }
     }
 }
@@ -196,30 +759,200 @@ impl State {
 /// `RateLog` monitors how frequently the same message is logged and can enforce
 /// limits based on either count (number of occurrences) or time duration.
 /// It will output the message first time and then until the limits are exceeded.
-pub struct RateLog {
+///
+/// All output is written to a [`Sink`]; by default this is [`StdoutSink`].
+pub struct RateLog<S: Sink = DefaultSink> {
     /// The maximum allowed limit for rate limiting.
     /// This defines the threshold that triggers rate limit exceeded warnings.
     /// For `Rate(n)`: maximum number of repeated messages allowed
     /// For `Duration(d)`: maximum time duration allowed for repeated messages
     limit: Limit,
 
+    /// The limit passed with the latest call for the tracked key, overriding `limit`.
+    /// Only set by calls such as [`RateLog::log_key`] and [`RateLog::log_with_limit`].
+    key_limit: Option<Limit>,
+
+    /// The custom algorithm replacing `limit`, if any.
+    limiter: Option<Box<dyn Limiter>>,
+
     /// The current tracking state containing count, duration, and timestamp.
     /// Always tracks both message count and elapsed duration regardless of limit type,
     /// enabling comprehensive rate limit reporting.
     current: State,
 
-    /// The last message that was logged.
+    /// The key of the last message that was logged.
+    /// For plain [`RateLog::log`] calls this is the message text itself.
     /// Used to detect when a different message is being logged, which resets
-    /// the rate limiting counters. Only identical messages contribute to rate limiting.
-    message: String,
-
-    /// Test-only field that captures output messages for verification in unit tests.
-    /// This field is only present when compiled with test configuration and allows
-    /// tests to verify the exact output without relying on stdout capture.
-    #[cfg(test)]
-    output: String,
+    /// the rate limiting counters. Only identical keys contribute to rate limiting.
+    key: String,
+
+    /// The [`key_hash`] of `key`, compared before the key itself.
+    key_hash: u64,
+
+    /// Whether `key` was logged through [`RateLog::log_keyed`].
+    /// Selects between "Message" and "Key" wording in summaries.
+    keyed: bool,
+
+    /// Derives the key of messages logged without one, if grouping is enabled.
+    normalizer: Option<Normalizer>,
+
+    /// Keys whose first occurrence is not echoed.
+    /// Messages with these keys only ever show up in rate limit warnings and summaries.
+    quiet_keys: Set<String>,
+
+    /// Whether first occurrences are echoed at all; if not, every key is quiet.
+    emit_first: bool,
+
+    /// Detects rapid alternation between a few messages, when enabled.
+    alternation: Option<AlternationTracker>,
+
+    /// Keeps the state of recently replaced messages, when a dedup window is set.
+    dedup: Option<DedupTracker>,
+
+    /// Bookkeeping for every key seen so far, kept across message changes.
+    /// Feeds the top-offenders report.
+    entries: Entries,
+
+    /// How many times a poisoned lock around this limiter was recovered, see
+    /// [`SharedRateLog`].
+    poison_recoveries: u64,
+
+    /// Hands out the label values of the suppression counters.
+    #[cfg(feature = "metrics")]
+    labels: telemetry::Labels,
+
+    /// The maximum number of keys kept in `entries`, if bounded.
+    max_tracked: Option<usize>,
+
+    /// Whether `entries` is keyed by a hash of each key instead of its text.
+    hashed_keys: bool,
+
+    /// Target patterns and their limits, see [`RateLog::log_for_target`].
+    target_limits: Vec<(String, Limit)>,
+
+    /// The time of the last [`RateLog::report_suppressed`] call.
+    last_report: Option<Instant>,
+
+    /// How often suppressed occurrences are reported automatically, if at all.
+    auto_report: Option<Duration>,
+
+    /// Weights applied to suppressed counts when ranking top offenders.
+    /// Severities without an explicit weight count with weight 1.
+    severity_weights: Map<Severity, f64>,
+
+    /// Captures exemplar labels for repeats of a message, when enabled.
+    exemplar_source: Option<ExemplarSource>,
+
+    /// The exemplar of the latest repeat not yet covered by a warning or summary.
+    exemplar: Option<String>,
+
+    /// The location of the latest occurrence of the tracked message, if it had one.
+    location: Option<Location>,
+
+    /// Whether summaries of keyed messages report the text of the latest repeat.
+    report_last_message: bool,
+
+    /// Whether warnings and summaries include the rate of the covered repeats.
+    show_rate: bool,
+
+    /// How durations are written in warnings and summaries.
+    duration_style: DurationStyle,
+
+    /// Whether warnings and summaries include percentiles of the repeat intervals.
+    interval_percentiles: bool,
+
+    /// Whether the message itself is emitted again whenever its limit is exceeded.
+    message_on_limit: bool,
+
+    /// The text of the latest suppressed repeat not yet covered by a warning or summary.
+    /// Only kept when `report_last_message` is enabled.
+    last_message: Option<String>,
+
+    /// Renders warnings and summaries, replacing the built-in wording when set.
+    formatter: Option<Formatter>,
+
+    /// How long after its first occurrence a key is emitted verbatim, if enabled.
+    grace_period: Option<Duration>,
+
+    /// How long the tracked message may stay idle before it is treated as new again.
+    idle_ttl: Option<Duration>,
+
+    /// How long a message with pending repeats may stay quiet before they are summarized.
+    settle_timeout: Option<Duration>,
+
+    /// How long after a rate limit warning further warnings are held back, if enabled.
+    cooldown: Option<Duration>,
+
+    /// The minimum time between warnings and summaries for the same key, if enabled.
+    summary_interval: Option<Duration>,
+
+    /// The number of repeats after which a message is escalated, if enabled.
+    escalation: Option<u32>,
+
+    /// The quiet time after which one pending repeat is forgotten, if enabled.
+    decay: Option<Duration>,
+
+    /// Whether the first occurrence counts towards count based limits.
+    count_mode: CountMode,
+
+    /// How the duration of pending repeats is measured.
+    duration_mode: DurationMode,
+
+    /// The source of the current time, possibly read only every few calls.
+    clock: CoarseClock,
+
+    /// Whether repeats a count limit is sure to suppress reuse the last clock reading.
+    lazy_clock: bool,
+
+    /// How emitted lines are rendered.
+    output_format: OutputFormat,
+
+    /// Called with the key, repeat count and duration whenever the limit is exceeded.
+    limit_callback: Option<LimitCallback>,
+
+    /// Whether rate limit warnings and summaries are written to the sink.
+    warnings: bool,
+
+    /// Whether messages are rate limited at all, instead of written straight through.
+    enabled: bool,
+
+    /// Holds back warnings and summaries to emit them as grouped blocks, when enabled.
+    coalescer: Option<WarningCoalescer>,
+
+    /// Caps the number of lines emitted across all messages, if enabled.
+    budget: Option<GlobalBudget>,
+
+    /// Scales per-message limits to the overall output, if enabled.
+    adaptive: Option<AdaptiveLimit>,
+
+    /// The number of lines discarded since [`RateLog::pause`], if output is paused.
+    paused: Option<u64>,
+
+    /// Set while a [`RateLog::log_critical`] call bypasses suppression.
+    critical: bool,
+
+    /// The location passed to the ongoing [`RateLog::log_at`] call.
+    call_location: Option<Location>,
+
+    /// Whether warnings and notices are colored.
+    #[cfg(feature = "color")]
+    color: bool,
+
+    /// The most recently emitted lines, oldest first.
+    /// Holds at most `history_capacity` entries; older lines are discarded as new ones
+    /// are emitted. Empty when history is disabled.
+    history: VecDeque<String>,
+
+    /// The maximum number of emitted lines kept in `history`.
+    /// A capacity of zero disables history tracking entirely.
+    history_capacity: usize,
+
+    /// The destination for every emitted line.
+    /// Receives both first occurrences of a message and rate limit warnings.
+    sink: S,
 }
 
+#[cfg(feature = "std")]
 impl RateLog {
     /// Creates a new `RateLog` instance with the specified limit.
     ///
@@ -243,196 +976,5753 @@ impl RateLog {
     /// let time_limiter = RateLog::new(Limit::Duration(Duration::from_secs(2)));
     /// ```
     pub fn new(limit: Limit) -> Self {
-        let current = State::new();
-
-        RateLog {
-            limit,
-            current,
-            message: String::new(),
-            #[cfg(test)]
-            output: String::new(),
-        }
+        RateLog::with_sink(limit, StdoutSink)
     }
 
-    /// Logs a message with rate limiting applied.
+    /// Creates a `RateLog` printing every message to stdout without rate limiting.
     ///
-    /// This method immediately prints any new or different message to stdout, then tracks
-    /// repeated messages and enforces the configured rate limit. Repeated messages are
-    /// counted silently until the limit is exceeded.
+    /// A drop-in for builds that keep the call sites but want none of the bookkeeping
+    /// cost, see [`RateLog::with_enabled`].
     ///
-    /// # Output Behavior
+    /// # Examples
     ///
-    /// - **New/different message**: Immediately printed to stdout and resets all counters
-    /// - **Repeated message**: Counted silently (no immediate output)
-    /// - **Limit exceeded**: Prints rate limit warning to stdout
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
     ///
-    /// # Rate Limiting Behavior
+    /// let mut logger = if cfg!(debug_assertions) {
+    ///     RateLog::new(Limit::Rate(5))
+    /// } else {
+    ///     RateLog::noop()
+    /// };
+    /// logger.log("Disk full");
+    /// ```
+    pub fn noop() -> Self {
+        RateLog::new(Limit::Never).with_enabled(false)
+    }
+
+    /// Returns a [`RateLogBuilder`] for configuring a `RateLog` with several options.
     ///
-    /// - **Count-based**: Increments counter for each repeated message
-    /// - **Duration-based**: Accumulates elapsed time between repeated messages
-    /// - **Message change**: Resets all tracking state and prints the new message
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit, StderrSink};
+    ///
+    /// let logger = RateLog::builder()
+    ///     .limit(Limit::Rate(5))
+    ///     .sink(StderrSink)
+    ///     .build();
+    /// ```
+    pub fn builder() -> RateLogBuilder {
+        RateLogBuilder::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> RateLog<WriterSink<W>> {
+    /// Creates a new `RateLog` instance that writes its output to `writer`.
+    ///
+    /// Any [`std::io::Write`] target works: stderr, files, `Vec<u8>` or sockets. This is
+    /// a shorthand for [`RateLog::with_sink`] with a [`WriterSink`].
     ///
     /// # Arguments
     ///
-    /// * `msg` - The message to log and track for rate limiting
+    /// * `limit` - The rate limiting threshold to enforce
+    /// * `writer` - The destination for emitted messages and warnings
     ///
     /// # Examples
     ///
-    /// ```rust
+    /// ```rust,no_run
     /// use rate_log::{RateLog, Limit};
+    /// use std::fs::File;
     ///
-    /// let mut logger = RateLog::new(Limit::Rate(2));
-    ///
-    /// logger.log("Starting up");          // Prints: "Starting up"
-    /// logger.log("Error occurred");       // Prints: "Error occurred" (different message)
-    /// logger.log("Error occurred");       // Silent (1st repetition)
-    /// logger.log("Error occurred");       // Silent (2nd repetition)
-    /// logger.log("Error occurred");       // Prints: "Message: \"Error occurred\" repeat for 2 times in the past 15ms"
-    /// logger.log("Shutting down");        // Prints: "Shutting down" (different message)
+    /// let file = File::create("/var/log/app.log").unwrap();
+    /// let mut logger = RateLog::with_writer(Limit::Rate(5), file);
+    /// logger.log("Connection lost");  // Appends "Connection lost" to the file
     /// ```
-    pub fn log(&mut self, msg: &str) {
-        let now = Instant::now();
+    pub fn with_writer(limit: Limit, writer: W) -> Self {
+        RateLog::with_sink(limit, WriterSink::new(writer))
+    }
+}
 
-        if self.message != msg {
-            self.message = msg.to_string();
-            self.current.reset();
+impl<S: Sink> RateLog<S> {
+    /// Creates a new `RateLog` instance that writes its output to `sink`.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - The rate limiting threshold to enforce
+    /// * `sink` - The destination for emitted messages and warnings
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit, StderrSink};
+    ///
+    /// // Keep stdout free for data, report to stderr instead
+    /// let mut logger = RateLog::with_sink(Limit::Rate(5), StderrSink);
+    /// logger.log("Connection lost");  // Prints to stderr: "Connection lost"
+    /// ```
+    pub fn with_sink(limit: Limit, sink: S) -> Self {
+        let current = State::new();
+        #[cfg(feature = "color")]
+        let color = ColorChoice::Auto.enabled(sink.is_terminal());
+
+        RateLog {
+            limit,
+            key_limit: None,
+            limiter: None,
+            current,
+            key: String::new(),
+            key_hash: key_hash(""),
+            keyed: false,
+            normalizer: None,
+            quiet_keys: Set::new(),
+            emit_first: true,
+            alternation: None,
+            dedup: None,
+            entries: Entries::new(),
+            poison_recoveries: 0,
+            #[cfg(feature = "metrics")]
+            labels: telemetry::Labels::default(),
+            last_report: None,
+            auto_report: None,
+            max_tracked: None,
+            hashed_keys: false,
+            target_limits: Vec::new(),
+            severity_weights: Map::new(),
+            exemplar_source: exemplar::default_source(),
+            exemplar: None,
+            location: None,
+            report_last_message: false,
+            show_rate: false,
+            duration_style: DurationStyle::Compact,
+            interval_percentiles: false,
+            message_on_limit: false,
+            last_message: None,
+            formatter: None,
+            grace_period: None,
+            idle_ttl: None,
+            settle_timeout: None,
+            cooldown: None,
+            summary_interval: None,
+            escalation: None,
+            decay: None,
+            count_mode: CountMode::default(),
+            duration_mode: DurationMode::default(),
+            clock: CoarseClock::new(),
+            lazy_clock: false,
+            output_format: OutputFormat::Text,
+            limit_callback: None,
+            warnings: true,
+            enabled: true,
+            coalescer: None,
+            budget: None,
+            adaptive: None,
+            paused: None,
+            critical: false,
+            call_location: None,
+            #[cfg(feature = "color")]
+            color,
+            history: VecDeque::new(),
+            history_capacity: 0,
+            sink,
+        }
+    }
+
+    /// Emits a one-time line describing the active rate limiting policy.
+    ///
+    /// Lets log readers know that suppression is in effect and how it is configured.
+    /// The announcement is written to the sink like any other line but does not affect
+    /// rate limiting state. Use [`RateLogBuilder::announce`] to emit it automatically
+    /// when the logger is built.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(10));
+    /// logger.announce();  // Prints: "rate-log: rate limiting active, warn after 10 repeats"
+    /// ```
+    pub fn announce(&mut self) {
+        let line = format!("rate-log: rate limiting active, {}", self.limit);
+        self.emit_notice("announce", line);
+    }
+
+    /// Returns the limit currently enforced for messages without a key specific limit.
+    pub fn limit(&self) -> &Limit {
+        &self.limit
+    }
+
+    /// Replaces the enforced limit at runtime, e.g. from a SIGHUP handler or an admin
+    /// endpoint.
+    ///
+    /// Accumulated state is kept: the tracked message, its pending repeats and all
+    /// statistics survive the change, so the next warning reports the repeats counted
+    /// under both limits. Budgets specific to a limit type, such as the tokens of
+    /// [`Limit::TokenBucket`], restart as if the tracked message had just been seen.
+    /// Keys with their own limit (see [`RateLog::log_key`]) keep using it. Use
+    /// [`SharedRateLog::set_limit`] to change the limit of a logger shared between
+    /// threads.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(100));
+    ///
+    /// logger.log("Timeout");  // Prints: "Timeout"
+    /// logger.log("Timeout");  // Silent (1st repetition)
+    /// logger.set_limit(Limit::Rate(2));
+    /// logger.log("Timeout");  // Prints: "Message: \"Timeout\" repeat for 2 times in the past 0ms"
+    /// ```
+    pub fn set_limit(&mut self, limit: Limit) {
+        self.limit = limit;
+        if self.key_limit.is_none() && !self.key.is_empty() {
+            let now = self.clock.now();
+            self.current.on_first(&self.limit, now, self.key.len());
+        }
+    }
+
+    /// Pauses all output until [`RateLog::resume`] is called, e.g. for quiet hours.
+    ///
+    /// While paused, messages are still tracked and rate limited and statistics keep
+    /// being collected, but nothing reaches the sink: neither messages nor warnings nor
+    /// summaries. [`LogOutcome`]s are reported as if the output had been written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(10));
+    ///
+    /// logger.pause();
+    /// logger.log("Disk full");  // Silent while paused
+    /// logger.log("Fan failure");  // Silent while paused
+    /// logger.resume();          // Prints: "rate-log: resumed, 2 lines discarded while paused"
+    /// ```
+    pub fn pause(&mut self) {
+        self.paused.get_or_insert(0);
+    }
+
+    /// Resumes output paused by [`RateLog::pause`].
+    ///
+    /// If any lines were discarded while paused, a notice reporting how many is emitted.
+    pub fn resume(&mut self) {
+        if let Some(discarded) = self.paused.take().filter(|&discarded| discarded > 0) {
+            let notice = format!("rate-log: resumed, {discarded} lines discarded while paused");
+            self.emit_notice("resumed", notice);
+        }
+    }
+
+    /// Returns `true` while output is paused, see [`RateLog::pause`].
+    pub fn is_paused(&self) -> bool {
+        self.paused.is_some()
+    }
+
+    /// Returns a reference to the sink receiving this logger's output.
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    /// Returns a mutable reference to the sink receiving this logger's output.
+    pub fn sink_mut(&mut self) -> &mut S {
+        &mut self.sink
+    }
+
+    /// Enables a bounded in-memory history of the last `capacity` emitted lines.
+    ///
+    /// Both immediately printed messages and rate limit warnings are recorded, so the
+    /// history mirrors exactly what was written to the sink. Once `capacity` lines are held,
+    /// each new line evicts the oldest one. A capacity of zero disables the history.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(5)).with_history(2);
+    ///
+    /// logger.log("first");
+    /// logger.log("second");
+    /// logger.log("third");
+    ///
+    /// assert_eq!(logger.recent().collect::<Vec<_>>(), vec!["second", "third"]);
+    /// ```
+    pub fn with_history(mut self, capacity: usize) -> Self {
+        self.history_capacity = capacity;
+        while self.history.len() > capacity {
+            self.history.pop_front();
+        }
+        self
+    }
+
+    /// Suppresses even the first occurrence of messages with the given key.
+    ///
+    /// Meant for known-noise messages that carry no value individually but whose
+    /// frequency still matters: they are counted like any other message, but only
+    /// rate limit warnings and summaries are emitted for them. The key is the message
+    /// text for [`RateLog::log`] and the explicit key for [`RateLog::log_keyed`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(100)).with_quiet_key("health probe ok");
+    ///
+    /// logger.log("health probe ok");  // Silent
+    /// logger.log("health probe ok");  // Silent (1st repetition)
+    /// ```
+    pub fn with_quiet_key(mut self, key: impl Into<String>) -> Self {
+        self.quiet_keys.insert(key.into());
+        self
+    }
+
+    /// Enables or disables emitting the first occurrence of every message.
+    ///
+    /// Enabled by default. When disabled, every key is treated like a
+    /// [quiet key](RateLog::with_quiet_key): nothing is echoed per message, and only
+    /// rate limit warnings and summaries show up, e.g. for known-noisy probes whose
+    /// periodic aggregate is all that matters. Critical messages are still emitted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{CaptureSink, Limit, RateLog};
+    ///
+    /// let mut logger =
+    ///     RateLog::with_sink(Limit::Rate(100), CaptureSink::new()).with_emit_first(false);
+    ///
+    /// logger.log("probe ok");  // Silent
+    /// logger.log("probe ok");  // Silent (1st repetition)
+    /// logger.flush();
+    /// assert_eq!(logger.sink().lines(), ["Message: \"probe ok\" suppressed 1 repeats over 0ms"]);
+    /// ```
+    pub fn with_emit_first(mut self, enabled: bool) -> Self {
+        self.emit_first = enabled;
+        self
+    }
+
+    /// Batches rapid alternation between a small set of messages into one summary.
+    ///
+    /// Only identical consecutive messages count as repeats, so messages alternating
+    /// rapidly (A, B, A, B, ...) would normally reset the counters and be emitted on
+    /// every call. With batching enabled, the last `set_size` replaced messages are
+    /// remembered; changing back to one of them within `within` of its last use is
+    /// counted silently instead. Once a message outside that set shows up, or on
+    /// [`RateLog::flush`], a single summary is emitted:
+    /// `"2 messages alternated 500 times in the past 3s"`.
+    ///
+    /// Repeats pending when a batched change happens become part of the batch.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    /// use std::time::Duration;
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(10))
+    ///     .with_alternation_batching(4, Duration::from_secs(1));
+    ///
+    /// logger.log("link up");    // Prints: "link up"
+    /// logger.log("link down");  // Prints: "link down"
+    /// logger.log("link up");    // Silent, alternation detected
+    /// logger.log("link down");  // Silent
+    /// logger.log("shutdown");
+    /// // Prints: "2 messages alternated 2 times in the past 0ms"
+    /// // Prints: "shutdown"
+    /// ```
+    pub fn with_alternation_batching(mut self, set_size: usize, within: Duration) -> Self {
+        self.alternation = Some(AlternationTracker::new(set_size, within));
+        self
+    }
+
+    /// Treats a message seen again within `window` as a repeat, even if other messages
+    /// were logged in between.
+    ///
+    /// By default only consecutive repeats are deduplicated: every message change
+    /// resets the counters and emits the new message, so an A, B, A, B... alternation
+    /// prints everything. With a dedup window, the counters of a replaced message are
+    /// kept while it is within the window, and returning to it continues counting
+    /// where it left off, with the limit applied to all its occurrences.
+    ///
+    /// Repeats pending when a message is replaced are reported once it leaves the
+    /// window or on [`RateLog::flush`], whichever comes first. A [`DedupWindow::Time`]
+    /// window is checked whenever a message is logged. Alternation batching (see
+    /// [`RateLog::with_alternation_batching`]) has no effect with a dedup window, and
+    /// [`RateLog::snapshot`] only covers the message logged last.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{DedupWindow, Limit, RateLog};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(2)).with_dedup_window(DedupWindow::Messages(1));
+    ///
+    /// logger.log("link up");    // Prints: "link up"
+    /// logger.log("link down");  // Prints: "link down"
+    /// logger.log("link up");    // Silent (1st repetition)
+    /// logger.log("link down");  // Silent (1st repetition)
+    /// logger.log("link up");    // Prints: "Message: \"link up\" repeat for 2 times in the past 0ms"
+    /// ```
+    pub fn with_dedup_window(mut self, window: DedupWindow) -> Self {
+        self.dedup = Some(DedupTracker::new(window));
+        self
+    }
+
+    /// Calls [`RateLog::report_suppressed`] automatically once `interval` has passed
+    /// since the previous report.
+    ///
+    /// The interval is checked whenever a message is logged, so no thread is needed;
+    /// while nothing is logged, nothing is reported. The first period starts with the
+    /// first logged message. Use a `Reporter` (feature `std-thread`) to report on a
+    /// fixed schedule instead.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    /// use std::time::Duration;
+    ///
+    /// let logger = RateLog::new(Limit::Rate(1000)).with_auto_report(Duration::from_secs(60));
+    /// ```
+    pub fn with_auto_report(mut self, interval: Duration) -> Self {
+        self.auto_report = Some(interval);
+        self
+    }
+
+    /// Sets the weight of `severity` used to rank top offenders.
+    ///
+    /// A key's score in [`RateLog::top_offenders`] is its number of suppressed
+    /// occurrences multiplied by the weight of its severity, so e.g. with a weight
+    /// of 10 for errors, one suppressed error counts as much as ten suppressed
+    /// debug lines. Severities without an explicit weight have weight 1.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit, Severity};
+    ///
+    /// let logger = RateLog::new(Limit::Rate(10))
+    ///     .with_severity_weight(Severity::Error, 10.0)
+    ///     .with_severity_weight(Severity::Debug, 0.5);
+    /// ```
+    pub fn with_severity_weight(mut self, severity: Severity, weight: f64) -> Self {
+        self.severity_weights.insert(severity, weight);
+        self
+    }
+
+    /// Attaches exemplars captured by `source` to rate limit warnings and summaries.
+    ///
+    /// `source` is called for every repeat of a message and returns a Prometheus-style
+    /// label set identifying the current occurrence, typically the id of the active
+    /// trace, e.g. `trace_id="4bf92f3577b34da6"`. The latest exemplar is appended to the
+    /// next warning or summary as ` # {labels}` and kept in [`Offender::exemplar`], so
+    /// a suppression summary can be navigated back to one concrete traced request
+    /// exhibiting the problem.
+    ///
+    /// With the `tracing` feature the id of the current `tracing` span is captured by
+    /// default (see `tracing_span_exemplar`).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(2))
+    ///     .with_exemplars(|| Some("trace_id=\"4bf92f\"".to_string()));
+    ///
+    /// logger.log("Timeout");  // Prints: "Timeout"
+    /// logger.log("Timeout");  // Silent (1st repetition)
+    /// logger.log("Timeout");
+    /// // Prints: "Message: \"Timeout\" repeat for 2 times in the past 0ms # {trace_id=\"4bf92f\"}"
+    /// ```
+    pub fn with_exemplars(
+        mut self,
+        source: impl FnMut() -> Option<String> + Send + 'static,
+    ) -> Self {
+        self.exemplar_source = Some(Box::new(source));
+        self
+    }
+
+    /// Replaces the built-in rate limiting algorithm with a custom [`Limiter`].
+    ///
+    /// The limiter decides about every occurrence of a message instead of the
+    /// [`Limit`] of this `RateLog`, e.g. to enforce business-specific quotas. Limits
+    /// passed with individual calls, such as [`RateLog::log_with_limit`] or a
+    /// [`LogKey`] declaring its own limit, still take precedence. Critical messages
+    /// and repeats within the grace period are emitted without asking the limiter.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{Decision, Limit, RateLog, Trigger};
+    /// use std::time::Instant;
+    ///
+    /// // Suppress all repeats, reporting them every 1000th time
+    /// let mut seen = 0;
+    /// let mut logger = RateLog::new(Limit::Rate(10)).with_limiter(move |_now: Instant, _key: &str| {
+    ///     seen += 1;
+    ///     match seen {
+    ///         1 => Decision::Emit,
+    ///         seen if seen % 1000 == 0 => Decision::Report(Trigger::Count),
+    ///         _ => Decision::Suppress,
+    ///     }
+    /// });
+    ///
+    /// logger.log("Disk full");  // Prints: "Disk full"
+    /// logger.log("Disk full");  // Silent
+    /// ```
+    pub fn with_limiter(mut self, limiter: impl Limiter + 'static) -> Self {
+        self.limiter = Some(Box::new(limiter));
+        self
+    }
+
+    /// Replaces the built-in wording of rate limit warnings and summaries.
+    ///
+    /// `formatter` receives a [`Warning`] describing what happened and returns the line
+    /// to emit, e.g. a machine-parseable format for a log aggregator. It is used for
+    /// both [`WarningKind::LimitExceeded`] warnings and [`WarningKind::Summary`] lines;
+    /// emitted messages themselves are not affected.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit, Warning};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(2)).with_formatter(|warning: &Warning| {
+    ///     format!(
+    ///         "RATELIMIT key={:?} count={} duration_ms={}",
+    ///         warning.key,
+    ///         warning.count,
+    ///         warning.duration.as_millis()
+    ///     )
+    /// });
+    ///
+    /// logger.log("Timeout");  // Prints: "Timeout"
+    /// logger.log("Timeout");  // Silent (1st repetition)
+    /// logger.log("Timeout");  // Prints: "RATELIMIT key=\"Timeout\" count=2 duration_ms=0"
+    /// ```
+    pub fn with_formatter(
+        mut self,
+        formatter: impl Fn(&Warning<'_>) -> String + Send + 'static,
+    ) -> Self {
+        self.formatter = Some(Box::new(formatter));
+        self
+    }
+
+    /// Emits every occurrence of a newly seen key verbatim during `grace_period`.
+    ///
+    /// Rate limiting only kicks in once `grace_period` has passed since the first
+    /// occurrence of a key, so the onset and early shape of a new problem are fully
+    /// captured before throttling starts. The grace period applies once per key:
+    /// a key that comes back after other messages is not granted a new one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    /// use std::time::Duration;
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(100))
+    ///     .with_grace_period(Duration::from_secs(10));
+    ///
+    /// logger.log("Connection lost");  // Prints: "Connection lost"
+    /// logger.log("Connection lost");  // Prints: "Connection lost" (within the grace period)
+    /// ```
+    pub fn with_grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = Some(grace_period);
+        self
+    }
+
+    /// Treats the tracked message as new again once it has not been seen for `ttl`.
+    ///
+    /// Without a TTL, a message repeated twice an hour apart still counts as a burst of
+    /// repeats. With it, a repeat arriving after `ttl` of silence first summarizes any
+    /// pending repeats, then clears the counters and is emitted like a first occurrence.
+    /// The idle time is not added to the accumulated duration.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit, MockClock};
+    /// use std::time::Duration;
+    ///
+    /// let clock = MockClock::new();
+    /// let mut logger = RateLog::new(Limit::Rate(10))
+    ///     .with_clock(clock.clone())
+    ///     .with_idle_ttl(Duration::from_secs(600));
+    ///
+    /// logger.log("Backup started");   // Prints: "Backup started"
+    /// clock.advance(Duration::from_secs(3600));
+    /// logger.log("Backup started");   // Prints: "Backup started" (idle for an hour)
+    /// ```
+    pub fn with_idle_ttl(mut self, ttl: Duration) -> Self {
+        self.idle_ttl = Some(ttl);
+        self
+    }
+
+    /// Summarizes the pending repeats of a message once it has been quiet for `timeout`.
+    ///
+    /// A message repeated 4 times under `Limit::Rate(5)` that then stops never reaches
+    /// its limit, so its suppressed repeats would only be reported when another message
+    /// is logged or on [`RateLog::flush`]. With a settle timeout, they are summarized as
+    /// `"Message: \"text\" suppressed N repeats over X"` once no repeat arrived for
+    /// `timeout`. The timeout is checked whenever a message is logged and by
+    /// [`RateLog::settle`], which a `Reporter` (feature `std-thread`) calls on every
+    /// tick. Unlike [`RateLog::with_idle_ttl`], the message stays tracked, so later
+    /// repeats are still rate limited.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit, MockClock};
+    /// use std::time::Duration;
+    ///
+    /// let clock = MockClock::new();
+    /// let mut logger = RateLog::new(Limit::Rate(5))
+    ///     .with_clock(clock.clone())
+    ///     .with_settle_timeout(Duration::from_secs(10));
+    ///
+    /// logger.log("Disk full");  // Prints: "Disk full"
+    /// logger.log("Disk full");  // Silent (1st repetition)
+    /// clock.advance(Duration::from_secs(10));
+    /// logger.settle();          // Prints: "Message: \"Disk full\" suppressed 1 repeats over 0ms"
+    /// ```
+    pub fn with_settle_timeout(mut self, timeout: Duration) -> Self {
+        self.settle_timeout = Some(timeout);
+        self
+    }
+
+    /// Groups messages that differ only in variable details, such as numbers or UUIDs.
+    ///
+    /// Messages logged without a key are rate limited under their normalized form, so
+    /// "request 123 timed out" and "request 456 timed out" count as repeats of each
+    /// other. Emitted lines keep the original text; warnings report the normalized form
+    /// as the key together with the last message seen. Keys passed explicitly, e.g. to
+    /// [`RateLog::log_keyed`], are not normalized.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit, Normalizer};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(2)).group_by(Normalizer::StripNumbers);
+    ///
+    /// logger.log("request 123 timed out");  // Prints: "request 123 timed out"
+    /// logger.log("request 456 timed out");  // Silent (1st repetition)
+    /// logger.log("request 789 timed out");
+    /// // Prints: "Key: \"request <num> timed out\" repeat for 2 times in the past 0ms, last message: \"request 789 timed out\""
+    /// ```
+    pub fn group_by(mut self, normalizer: Normalizer) -> Self {
+        self.normalizer = Some(normalizer);
+        self
+    }
+
+    /// Reports the text of the latest suppressed repeat in summaries of keyed messages.
+    ///
+    /// With [`RateLog::log_keyed`] the details change between repeats. Rate limit
+    /// warnings already show the message that triggered them, but summaries emitted
+    /// when the key changes or on [`RateLog::flush`] only name the key, hiding the final
+    /// variant. When enabled, the text of every suppressed keyed repeat is kept (so
+    /// messages logged with [`RateLog::log_with`] are built even when suppressed) and
+    /// summaries end with `, last message: "..."`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(10)).with_report_last_message(true);
+    ///
+    /// logger.log_keyed("connect", "failed to connect to 10.0.0.1");  // Prints the message
+    /// logger.log_keyed("connect", "failed to connect to 10.0.0.2");  // Silent
+    /// logger.flush();
+    /// // Prints: "Key: \"connect\" suppressed 1 repeats over 0ms, last message: \"failed to connect to 10.0.0.2\""
+    /// ```
+    pub fn with_report_last_message(mut self, enabled: bool) -> Self {
+        self.report_last_message = enabled;
+        self
+    }
+
+    /// Includes the rate of the covered repeats in warnings and summaries.
+    ///
+    /// Operators triaging an incident care more about how fast a message repeats than
+    /// about the raw count. When enabled, plain text warnings and summaries state the
+    /// rate after the duration, e.g. `"repeat for 500 times in the past 2s (250/s)"`.
+    /// The rate is left out when no time passed between the repeats. JSON records
+    /// already carry `repeats` and `window_ms`, and custom formatters can use
+    /// [`Warning::rate`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    /// use std::time::Duration;
+    ///
+    /// let logger = RateLog::new(Limit::Duration(Duration::from_secs(2))).with_rate(true);
+    /// // A message repeated every 4ms prints:
+    /// // "Message: \"Disk full\" repeat for 500 times in the past 2s (250/s)"
+    /// ```
+    pub fn with_rate(mut self, enabled: bool) -> Self {
+        self.show_rate = enabled;
+        self
+    }
+
+    /// Sets how durations are written in warnings, summaries and reports.
+    ///
+    /// The default [`DurationStyle::Compact`] keeps at most two whole units, e.g. `1h59m`,
+    /// which hides sub-second detail in short bursts. [`DurationStyle::Exact`] writes
+    /// seconds with a millisecond fraction, [`DurationStyle::Decimal`] the largest unit
+    /// with two decimals, and [`DurationStyle::custom`] any format. Event fields such as
+    /// `Event::Summary::duration` and the JSON `window_ms` are unaffected.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{DurationStyle, Limit, RateLog};
+    ///
+    /// let logger = RateLog::new(Limit::Rate(10)).with_duration_style(DurationStyle::Exact);
+    /// // A burst of 10 repeats over 1.5 seconds prints:
+    /// // "Message: \"Disk full\" repeat for 10 times in the past 1.5s"
+    /// ```
+    pub fn with_duration_style(mut self, style: DurationStyle) -> Self {
+        self.duration_style = style;
+        self
+    }
+
+    /// Includes the median and 99th percentile of the time between repeats in warnings
+    /// and summaries.
+    ///
+    /// The intervals come from the per-message [`IntervalHistogram`] also exposed by
+    /// [`RateLog::stats`], and hint whether repeats come from a tight loop or from
+    /// periodic retries. When enabled, plain text warnings and summaries add e.g.
+    /// `", p50 2ms, p99 40ms between repeats"` once at least one interval was recorded.
+    /// Percentiles are reported as the upper bound of their histogram bucket.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let logger = RateLog::new(Limit::Rate(100)).with_interval_percentiles(true);
+    /// // A message retried every 3 seconds prints:
+    /// // "Message: \"Retrying\" repeat for 100 times in the past 5m0s, p50 3s, p99 3s between repeats"
+    /// ```
+    pub fn with_interval_percentiles(mut self, enabled: bool) -> Self {
+        self.interval_percentiles = enabled;
+        self
+    }
+
+    /// Emits the message itself on its own line whenever its limit is exceeded, in
+    /// addition to the rate limit warning.
+    ///
+    /// Alert rules downstream often match on the raw message text, which the warning
+    /// alone only quotes. With this option enabled, every warning is preceded by the
+    /// message as it was logged, so such matchers keep firing while the message is
+    /// rate limited.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(2)).with_message_on_limit(true);
+    ///
+    /// logger.log("Disk full");  // Prints: "Disk full"
+    /// logger.log("Disk full");  // Silent (1st repetition)
+    /// logger.log("Disk full");
+    /// // Prints: "Disk full"
+    /// // Prints: "Message: \"Disk full\" repeat for 2 times in the past 0ms"
+    /// ```
+    pub fn with_message_on_limit(mut self, enabled: bool) -> Self {
+        self.message_on_limit = enabled;
+        self
+    }
+
+    /// Holds back further rate limit warnings for `cooldown` after one was emitted.
+    ///
+    /// Without a cooldown every exceeded limit produces another warning line, which can
+    /// itself become spam. During the cooldown, repeats that would trigger a warning are
+    /// only counted; the first warning after it ends reports all of them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    /// use std::time::Duration;
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(2)).with_cooldown(Duration::from_secs(60));
+    ///
+    /// for _ in 0..100 {
+    ///     logger.log("Timeout");
+    /// }
+    /// // Prints "Timeout" and a single "repeat for 2 times" warning, the other
+    /// // 97 repeats are reported once the cooldown has passed
+    /// ```
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = Some(cooldown);
+        self
+    }
+
+    /// Emits warnings and summaries for the same key at most once per `interval`,
+    /// accumulating the repeats they would report in between.
+    ///
+    /// A message repeating millions of times with a small [`Limit::Rate`] produces a
+    /// flood of summary lines of its own, especially while it alternates with other
+    /// messages so that every change emits a summary. With a summary interval, the
+    /// time of the latest warning or summary is remembered per key across message
+    /// changes. Until `interval` has passed, warnings are held back like with
+    /// [`RateLog::with_cooldown`] and summaries are not emitted; the repeats they
+    /// cover are added to the next warning or summary for the key instead.
+    /// [`RateLog::flush`] emits everything still held back.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    /// use std::time::Duration;
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(1)).with_summary_interval(Duration::from_secs(60));
+    ///
+    /// for _ in 0..100 {
+    ///     logger.log("Timeout");
+    /// }
+    /// // Prints "Timeout" and a single "repeat for 1 times" warning, the other 98
+    /// // repeats are counted until a minute has passed
+    /// logger.flush();
+    /// // Prints: "Message: \"Timeout\" suppressed 98 repeats over 0ms"
+    /// ```
+    pub fn with_summary_interval(mut self, interval: Duration) -> Self {
+        self.summary_interval = Some(interval);
+        self
+    }
+
+    /// Selects whether warnings and notices are colored, see [`ColorChoice`].
+    ///
+    /// Without a call, [`ColorChoice::Auto`] applies.
+    #[cfg(feature = "color")]
+    pub fn with_color(mut self, choice: ColorChoice) -> Self {
+        self.color = choice.enabled(self.sink.is_terminal());
+        self
+    }
+
+    /// Selects whether the first occurrence of a message counts towards count based
+    /// limits, see [`CountMode`].
+    pub fn with_count_mode(mut self, mode: CountMode) -> Self {
+        self.count_mode = mode;
+        self
+    }
+
+    /// Selects how the duration reported in warnings and checked by duration based
+    /// limits is measured, see [`DurationMode`].
+    pub fn with_duration_mode(mut self, mode: DurationMode) -> Self {
+        self.duration_mode = mode;
+        self
+    }
+
+    /// Forgets one pending repeat for every `per` the message goes quiet.
+    ///
+    /// Without decay the repeat count only resets when the message changes or its limit
+    /// is exceeded, so a message repeating once an hour eventually triggers the same
+    /// warning as a burst. With decay, the count leaks away like a leaky bucket while the
+    /// message is quiet: only repetition faster than once per `per` accumulates towards
+    /// the limit. Forgotten repeats are no longer reported in warnings and summaries but
+    /// still count in [`RateLog::stats`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    /// use std::time::Duration;
+    ///
+    /// // A repeat every few seconds never reaches the limit, a burst still does
+    /// let mut logger = RateLog::new(Limit::Rate(10)).with_decay(Duration::from_secs(1));
+    /// ```
+    pub fn with_decay(mut self, per: Duration) -> Self {
+        self.decay = Some(per);
+        self
+    }
+
+    /// Escalates a message once it has repeated `threshold` times without interruption.
+    ///
+    /// Sustained repetition is itself a signal: an error that keeps recurring deserves
+    /// promotion rather than just suppression. When the repeats since the message was
+    /// first logged reach `threshold`, typically a multiple of the limit, the message is
+    /// emitted once more with an `ESCALATED: ` prefix (kind `escalated` in JSON output)
+    /// and the [`RateLog::on_limit_exceeded`] callback is invoked with `threshold` and
+    /// the time since the first occurrence. Escalation happens at most once until a
+    /// different message is logged.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(10)).with_escalation(100);
+    ///
+    /// for _ in 0..=100 {
+    ///     logger.log("Disk full");
+    /// }
+    /// // Prints "Disk full", ten warnings and "ESCALATED: Disk full"
+    /// ```
+    pub fn with_escalation(mut self, threshold: u32) -> Self {
+        self.escalation = Some(threshold);
+        self
+    }
+
+    /// Reads the current time from `clock` instead of the system clock.
+    ///
+    /// Meant for tests: with a [`MockClock`], duration based limits can be checked
+    /// deterministically and fast instead of sleeping.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{CaptureSink, Limit, LogOutcome, MockClock, RateLog};
+    /// use std::time::Duration;
+    ///
+    /// let clock = MockClock::new();
+    /// let mut logger = RateLog::with_sink(Limit::Duration(Duration::from_secs(60)), CaptureSink::new())
+    ///     .with_clock(clock.clone());
+    ///
+    /// logger.log("Disk full");
+    /// clock.advance(Duration::from_secs(30));
+    /// assert_eq!(logger.log("Disk full"), LogOutcome::Suppressed);
+    /// clock.advance(Duration::from_secs(30));
+    /// assert!(matches!(logger.log("Disk full"), LogOutcome::LimitReported { .. }));
+    /// ```
+    pub fn with_clock(mut self, clock: impl Clock + Send + 'static) -> Self {
+        self.clock.set_source(clock);
+        self
+    }
+
+    /// Reads the current time from a [`Monotonic`] source, such as a hardware timer.
+    ///
+    /// This is the way to supply time without the `std` feature, where there is no
+    /// system clock and time otherwise stands still; see [`Monotonic`] for an example.
+    pub fn with_monotonic(mut self, source: impl Monotonic + Send + 'static) -> Self {
+        self.clock.set_source(MonotonicClock::new(source));
+        self
+    }
+
+    /// Reads the clock only on every `every`th logging call.
+    ///
+    /// In between, the last reading is reused, trading duration precision for lower
+    /// per-call overhead in tight loops where `Instant::now()` itself is measurable.
+    /// Durations, windows and token refills only advance when the clock is read, so
+    /// they are reported late by up to `every - 1` calls. The default of 1 reads the
+    /// clock on every call; 0 behaves like 1.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// // Hot loop: one clock read per 64 calls is precise enough
+    /// let mut logger = RateLog::new(Limit::Rate(1000)).with_clock_read_interval(64);
+    ///
+    /// for _ in 0..10_000 {
+    ///     logger.log("Queue full");
+    /// }
+    /// ```
+    pub fn with_clock_read_interval(mut self, every: u32) -> Self {
+        self.clock.set_every(every);
+        self
+    }
+
+    /// Sets whether repeats that a [`Limit::Rate`] suppresses anyway skip reading the
+    /// clock. Disabled by default.
+    ///
+    /// Whether a count limit suppresses a repeat does not depend on the time, so the
+    /// last reading is reused for them and the clock is only read for first
+    /// occurrences, the first repeat after each warning, warnings and other calls
+    /// whose outcome the time may change. Warnings still report the exact duration of
+    /// the repeats they cover, but summaries written on a message change or
+    /// [`RateLog::flush`] only cover the time up to the last reading, and the
+    /// statistics of suppressed repeats carry the time of that reading. The clock is
+    /// read on every call for other limits and while features depending on the time
+    /// of repeats are enabled, such as rates, interval percentiles, an idle TTL, a
+    /// settle timeout, decay or adaptive limits.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// // Packet processing loop: reads the clock about once per 10000 drops
+    /// let mut logger = RateLog::new(Limit::Rate(10_000)).with_lazy_clock(true);
+    ///
+    /// for _ in 0..100_000 {
+    ///     logger.log("dropped packet: checksum mismatch");
+    /// }
+    /// ```
+    pub fn with_lazy_clock(mut self, enabled: bool) -> Self {
+        self.lazy_clock = enabled;
+        self
+    }
+
+    /// Selects how emitted lines are rendered, see [`OutputFormat`].
+    ///
+    /// With [`OutputFormat::Json`] (requires the `serde` feature) every line is a JSON
+    /// object such as `{"v":1,"msg":"Disk full","repeats":5,"window_ms":103,"kind":"rate_limited"}`,
+    /// ready to be ingested by log pipelines without regex parsing. A custom formatter
+    /// set with [`RateLog::with_formatter`] still takes precedence for warnings.
+    pub fn with_output_format(mut self, output_format: OutputFormat) -> Self {
+        self.output_format = output_format;
+        self
+    }
+
+    /// Calls `callback` whenever the rate limit is exceeded.
+    ///
+    /// The callback receives the key (the message text for [`RateLog::log`]), the number
+    /// of repeats and the accumulated duration reported by the warning, so applications
+    /// can react programmatically: bump a metric, open a circuit breaker, page someone.
+    /// It is called in addition to emitting the warning; use
+    /// [`RateLog::with_warnings`] to only call the callback.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    /// use std::sync::atomic::{AtomicU32, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let exceeded = Arc::new(AtomicU32::new(0));
+    /// let counter = Arc::clone(&exceeded);
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(2)).on_limit_exceeded(move |_, _, _| {
+    ///     counter.fetch_add(1, Ordering::Relaxed);
+    /// });
+    ///
+    /// for _ in 0..3 {
+    ///     logger.log("Timeout");
+    /// }
+    /// assert_eq!(exceeded.load(Ordering::Relaxed), 1);
+    /// ```
+    pub fn on_limit_exceeded(
+        mut self,
+        callback: impl FnMut(&str, u32, Duration) + Send + 'static,
+    ) -> Self {
+        self.limit_callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Enables or disables writing rate limit warnings and summaries to the sink.
+    ///
+    /// Warnings are enabled by default. Disabling them keeps the emitted output limited
+    /// to the messages themselves, e.g. when limit violations are handled through
+    /// [`RateLog::on_limit_exceeded`] or [`LogOutcome`] instead.
+    pub fn with_warnings(mut self, enabled: bool) -> Self {
+        self.warnings = enabled;
+        self
+    }
+
+    /// Enables or disables rate limiting altogether.
+    ///
+    /// Rate limiting is enabled by default. When disabled, every logged message is
+    /// written straight to the sink as it is, and logging calls return
+    /// [`LogOutcome::Emitted`] without reading the clock, normalizing, hashing or
+    /// copying keys, or updating any statistics. [`RateLog::check`] always passes.
+    /// Output formats, budgets and the other options do not apply. Meant for builds
+    /// that keep the call sites but want none of the bookkeeping cost, e.g. release
+    /// firmware.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{CaptureSink, Limit, RateLog};
+    ///
+    /// let mut logger =
+    ///     RateLog::with_sink(Limit::Rate(1), CaptureSink::new()).with_enabled(false);
+    ///
+    /// logger.log("Disk full");
+    /// logger.log("Disk full");
+    /// assert_eq!(logger.sink().lines(), ["Disk full", "Disk full"]);
+    /// assert!(logger.stats().messages().is_empty());
+    /// ```
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Caps the output at `max` lines per `per` across all messages.
+    ///
+    /// Per-message limits cannot protect against a flood of unique messages, since each
+    /// of them is a first occurrence. The global budget is layered on top: every line
+    /// written to the sink, including warnings and summaries, takes one unit of budget,
+    /// and lines beyond `max` within a window of `per` are dropped. Once lines can be
+    /// emitted again, or on [`RateLog::flush`], a single
+    /// `"global rate limit reached, suppressed N lines"` notice reports how many were
+    /// dropped; the notice itself does not count against the budget.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    /// use std::time::Duration;
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(10))
+    ///     .with_global_budget(200, Duration::from_secs(60));
+    ///
+    /// for request in 0..1000 {
+    ///     logger.log(&format!("unknown route /api/{request}"));
+    /// }
+    /// logger.flush();
+    /// // Prints the first 200 messages, then
+    /// // "global rate limit reached, suppressed 800 lines"
+    /// ```
+    pub fn with_global_budget(mut self, max: u32, per: Duration) -> Self {
+        self.budget = Some(GlobalBudget::new(max, per));
+        self
+    }
+
+    /// Tightens per-message limits automatically while more than `target` lines per
+    /// `per` are written across all messages, and relaxes them again when it is quiet.
+    ///
+    /// Static limits suit only one noise level; a workload whose noise varies by 100x
+    /// either floods the output or hides useful repeats. With an adaptive limit, the
+    /// lines written to the sink are counted in windows of `per`, and the limits are
+    /// scaled to steer that count towards `target`: repeat counts and intervals are
+    /// multiplied by the scale factor, budgets such as [`Limit::Window`] divided by it.
+    /// At twice the target the factor doubles, e.g. [`Limit::Rate`]`(10)` acts like
+    /// `Limit::Rate(20)`; once output drops below the target the factor shrinks again,
+    /// back to the configured limits at the latest after a window without output.
+    /// The factor is at most 1000. [`Limit::Exponential`], [`Limit::Never`] and
+    /// [`Limit::Always`] are not scaled.
+    ///
+    /// Unlike [`RateLog::with_global_budget`], nothing is dropped outright: first
+    /// occurrences are still emitted, only repeats are throttled harder.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    /// use std::time::Duration;
+    ///
+    /// // Aim for at most 100 lines per second in total
+    /// let logger = RateLog::new(Limit::Rate(10))
+    ///     .with_adaptive_limit(100, Duration::from_secs(1));
+    /// ```
+    pub fn with_adaptive_limit(mut self, target: u32, per: Duration) -> Self {
+        self.adaptive = Some(AdaptiveLimit::new(target, per));
+        self
+    }
+
+    /// Bounds the number of keys remembered for statistics and reports to `max`.
+    ///
+    /// Every distinct key is remembered for [`RateLog::stats`] and
+    /// [`RateLog::top_offenders`], which lets memory grow without bound in long-running
+    /// servers logging high-cardinality messages. Once `max` keys are tracked, logging a
+    /// new key evicts the least recently seen one. Evicting a key with suppressed
+    /// repeats emits a summary such as
+    /// `"evicted entry \"Disk full\" with 12 suppressed repeats"`. A `max` of zero is
+    /// treated as one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(10)).with_max_tracked_messages(1000);
+    ///
+    /// for request in 0..5000 {
+    ///     logger.log(&format!("request {request} failed"));
+    /// }
+    /// assert_eq!(logger.stats().messages().len(), 1000);
+    /// ```
+    pub fn with_max_tracked_messages(mut self, max: usize) -> Self {
+        self.max_tracked = Some(max.max(1));
+        self
+    }
+
+    /// Remembers keys for statistics and reports by a 64-bit hash instead of their text.
+    ///
+    /// Every distinct key is normally cloned into the statistics kept for
+    /// [`RateLog::stats`], [`RateLog::top_offenders`] and [`RateLog::report_suppressed`],
+    /// so multi-kilobyte messages are retained in full for as long as they are tracked.
+    /// With hashed keys only a fixed-size label such as `#a430d84680aabd0b` is kept per
+    /// key, and the text of a message is held only while it is the tracked one, for its
+    /// warnings and summaries. Statistics, reports and snapshots then identify keys by
+    /// their label; the labels are stable across runs.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(10)).with_hashed_keys(true);
+    ///
+    /// logger.log(&"stack trace ".repeat(1000));  // Prints the message
+    /// assert_eq!(logger.stats().messages()[0].key.len(), 17);
+    /// ```
+    pub fn with_hashed_keys(mut self, enabled: bool) -> Self {
+        self.hashed_keys = enabled;
+        self
+    }
+
+    /// Applies `limit` to the messages logged by [`RateLog::log_for_target`] for
+    /// targets matching `pattern`.
+    ///
+    /// Subsystems have very different acceptable noise levels. A pattern is either a
+    /// target such as `"db"`, matching only that target, or a prefix such as
+    /// `"net::*"`, matching `net` and every target below it, e.g. `net::tcp`. The
+    /// first matching pattern wins, in the order they were added; targets matching
+    /// none use the limit of this `RateLog`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    /// use std::time::Duration;
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(100))
+    ///     .with_target_limit("net::*", Limit::Rate(10))
+    ///     .with_target_limit("db", Limit::Duration(Duration::from_secs(5)));
+    ///
+    /// logger.log_for_target("net::tcp", "connection reset");  // Limited by Rate(10)
+    /// logger.log_for_target("cache", "eviction");             // Limited by Rate(100)
+    /// ```
+    pub fn with_target_limit(mut self, pattern: impl Into<String>, limit: Limit) -> Self {
+        self.target_limits.push((pattern.into(), limit));
+        self
+    }
+
+    /// Coalesces the warnings and summaries raised within each `tick` into one block.
+    ///
+    /// When many keys trip their limits at once, e.g. during an outage, one warning per
+    /// key would itself flood the output. With coalescing enabled, warnings are held back
+    /// until `tick` has passed since the first of them; a lone warning is then emitted
+    /// as usual, while several are replaced by a single line listing the keys with the
+    /// most repeats: `"12 keys exceeded limits: \"a\" (40 repeats), ... and 7 more"`.
+    /// Held back warnings are emitted by the next logging call after the tick ends or by
+    /// [`RateLog::flush`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    /// use std::time::Duration;
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(1)).with_warning_coalescing(Duration::from_secs(1));
+    ///
+    /// for key in ["db", "cache", "queue"] {
+    ///     logger.log(key);  // Prints the first occurrence of each key
+    ///     logger.log(key);  // Limit exceeded, the warning is held back
+    /// }
+    /// logger.flush();
+    /// // Prints: "3 keys exceeded limits: \"db\" (1 repeats), \"cache\" (1 repeats), \"queue\" (1 repeats)"
+    /// ```
+    pub fn with_warning_coalescing(mut self, tick: Duration) -> Self {
+        self.coalescer = Some(WarningCoalescer::new(tick));
+        self
+    }
+
+    /// Returns a snapshot of the statistics of every message tracked so far.
+    ///
+    /// Each [`MessageStats`] in the [`Stats`] snapshot holds the total number of occurrences, how many of them
+    /// were suppressed, when the message was first and last seen and the time
+    /// accumulated between its consecutive repeats. Useful for exposing numbers on a
+    /// health endpoint instead of parsing the log output. Snapshots of several limiters
+    /// can be combined with [`Stats::merge`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(10));
+    ///
+    /// logger.log("Disk full");
+    /// logger.log("Disk full");
+    /// logger.log("Connection lost");
+    ///
+    /// let stats = logger.stats();
+    /// let messages = stats.messages();
+    /// assert_eq!(messages[0].key, "Connection lost");
+    /// assert_eq!(messages[1].key, "Disk full");
+    /// assert_eq!((messages[1].occurrences, messages[1].suppressed), (2, 1));
+    /// ```
+    pub fn stats(&self) -> Stats {
+        let messages = self
+            .entries
+            .iter()
+            .map(|(key, entry)| MessageStats {
+                key: key.clone(),
+                occurrences: entry.occurrences,
+                suppressed: entry.suppressed,
+                first_seen: entry.first_seen,
+                last_seen: entry.last_seen,
+                duration: entry.duration,
+                intervals: entry.intervals.clone(),
+            })
+            .collect();
+        Stats::new(messages, self.poison_recoveries)
+    }
+
+    /// Captures the tracking state, to be restored after a restart.
+    ///
+    /// The snapshot holds the tracked message with its pending repeats and the
+    /// statistics of every key, but no configuration. See [`Snapshot`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(10));
+    /// logger.log("Disk full");  // Prints: "Disk full"
+    /// let snapshot = logger.snapshot();
+    ///
+    /// // After a restart
+    /// let mut logger = RateLog::new(Limit::Rate(10));
+    /// logger.restore(snapshot).unwrap();
+    /// logger.log("Disk full");  // Silent: already seen before the restart
+    /// ```
+    pub fn snapshot(&mut self) -> Snapshot {
+        let now = self.clock.now();
+        let mut messages: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| SnapshotEntry {
+                key: key.clone(),
+                occurrences: entry.occurrences,
+                suppressed: entry.suppressed,
+                severity: entry.severity,
+                first_seen_ago: now.saturating_duration_since(entry.first_seen),
+                last_seen_ago: now.saturating_duration_since(entry.last_seen),
+                duration: entry.duration,
+            })
+            .collect();
+        messages.sort_by(|a, b| a.key.cmp(&b.key));
+
+        Snapshot {
+            v: Snapshot::VERSION,
+            key: self.key.clone(),
+            keyed: self.keyed,
+            pending: self.current.count,
+            pending_duration: self.current.duration,
+            idle: self
+                .current
+                .last_timestamp
+                .map(|last| now.saturating_duration_since(last)),
+            messages,
+            poison_recoveries: self.poison_recoveries,
+        }
+    }
+
+    /// Replaces the tracking state with a [`Snapshot`] taken by [`RateLog::snapshot`].
+    ///
+    /// The tracked message continues where it left off: its next occurrence counts as
+    /// a repeat and its pending repeats are reported in the next warning or summary.
+    /// Meant to be called on a freshly created limiter; state already tracked is
+    /// discarded without a summary. Limits passed with individual calls, such as those
+    /// declared by a [`LogKey`], are not part of the snapshot and apply again from the
+    /// next such call. With [`RateLog::with_max_tracked_messages`], only the most
+    /// recently seen keys up to the bound are restored.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Persistence`] if the snapshot was written in a newer format.
+    pub fn restore(&mut self, snapshot: Snapshot) -> Result<()> {
+        if snapshot.v > Snapshot::VERSION {
+            return Err(Error::Persistence(format!(
+                "unsupported snapshot version {}",
+                snapshot.v
+            )));
+        }
+
+        let now = self.clock.now();
+        self.reset_state();
+        self.poison_recoveries = snapshot.poison_recoveries;
+        let mut messages = snapshot.messages;
+        if let Some(max) = self.max_tracked {
+            if messages.len() > max {
+                messages.sort_by_key(|saved| saved.last_seen_ago);
+                messages.truncate(max);
+            }
+        }
+        self.entries = messages
+            .into_iter()
+            .map(|saved| {
+                let mut entry = Entry::new(snapshot::before(now, saved.first_seen_ago));
+                entry.occurrences = saved.occurrences;
+                entry.suppressed = saved.suppressed;
+                entry.reported = saved.suppressed;
+                entry.severity = saved.severity;
+                entry.last_seen = snapshot::before(now, saved.last_seen_ago);
+                entry.duration = saved.duration;
+                (saved.key, entry)
+            })
+            .collect();
+
+        if !snapshot.key.is_empty() {
+            let last = snapshot
+                .idle
+                .map_or(now, |idle| snapshot::before(now, idle));
+            self.current.on_first(&self.limit, last, snapshot.key.len());
+            self.current.count = snapshot.pending;
+            self.current.duration = snapshot.pending_duration;
+            self.current.last_timestamp = Some(last);
+            self.key = snapshot.key;
+            self.key_hash = key_hash(&self.key);
+            self.keyed = snapshot.keyed;
+        }
+        Ok(())
+    }
+
+    /// Returns up to `n` keys with the highest weighted number of suppressed occurrences.
+    ///
+    /// Keys are ranked by [`Offender::score`], the number of occurrences that were not
+    /// emitted verbatim multiplied by the weight of the key's latest severity (see
+    /// [`RateLog::with_severity_weight`]). Keys without suppressed occurrences are
+    /// never listed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit, Severity};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(100))
+    ///     .with_severity_weight(Severity::Error, 10.0);
+    ///
+    /// for _ in 0..20 {
+    ///     logger.log_with_severity(Severity::Debug, "cache miss");
+    /// }
+    /// for _ in 0..3 {
+    ///     logger.log_with_severity(Severity::Error, "disk full");
+    /// }
+    ///
+    /// let top = logger.top_offenders(1);
+    /// assert_eq!(top[0].key, "disk full");
+    /// assert_eq!(top[0].score, 20.0);
+    /// ```
+    pub fn top_offenders(&self, n: usize) -> Vec<Offender> {
+        let mut offenders: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.suppressed > 0)
+            .map(|(key, entry)| Offender {
+                key: key.clone(),
+                severity: entry.severity,
+                suppressed: entry.suppressed,
+                score: entry.suppressed as f64 * self.severity_weight(entry.severity),
+                exemplar: entry.exemplar.clone(),
+            })
+            .collect();
+
+        offenders.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| b.suppressed.cmp(&a.suppressed))
+                .then_with(|| a.key.cmp(&b.key))
+        });
+        offenders.truncate(n);
+        offenders
+    }
+
+    /// Emits a one-line digest of the top `n` offenders.
+    ///
+    /// The digest lists the keys returned by [`RateLog::top_offenders`], e.g.
+    /// `"Top offenders: 1. \"disk full\" [error] 3 suppressed (score 30), 2. ..."`.
+    /// Nothing is emitted when no occurrences were suppressed.
+    pub fn report_top_offenders(&mut self, n: usize) {
+        let offenders = self.top_offenders(n);
+        if offenders.is_empty() {
+            return;
+        }
+
+        let ranking: Vec<_> = offenders
+            .iter()
+            .enumerate()
+            .map(|(rank, offender)| {
+                format!(
+                    "{}. \"{}\" [{}] {} suppressed (score {})",
+                    rank + 1,
+                    offender.key,
+                    offender.severity,
+                    offender.suppressed,
+                    offender.score
+                )
+            })
+            .collect();
+        self.emit_notice(
+            "top_offenders",
+            format!("Top offenders: {}", ranking.join(", ")),
+        );
+    }
+
+    /// Drains and returns the occurrences suppressed since the last report, one
+    /// [`Summary`] per key that has any.
+    ///
+    /// Lets a service dump a consolidated "what got throttled" report on demand, e.g. on
+    /// SIGUSR1 or at shutdown, in its own format. Keys are listed in order. Each call
+    /// starts a new report period, shared with [`RateLog::report_suppressed`], so
+    /// occurrences are reported once by either of them. Nothing is written to the sink
+    /// and the warnings and summaries of the rate limiter are not affected.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(1000));
+    ///
+    /// for _ in 0..10 {
+    ///     logger.log("disk full");
+    /// }
+    /// let report = logger.report();
+    /// assert_eq!(report[0].key, "disk full");
+    /// assert_eq!(report[0].suppressed, 9);
+    /// assert!(logger.report().is_empty());
+    /// ```
+    pub fn report(&mut self) -> Vec<Summary> {
+        let now = self.clock.now();
+        let since = self.last_report.replace(now);
+        let mut summaries: Vec<_> = self
+            .entries
+            .iter_mut()
+            .filter(|(_, entry)| entry.suppressed > entry.reported)
+            .map(|(key, entry)| Summary {
+                key: key.clone(),
+                severity: entry.severity,
+                suppressed: entry.suppressed
+                    - core::mem::replace(&mut entry.reported, entry.suppressed),
+                duration: now.saturating_duration_since(
+                    since.map_or(entry.first_seen, |since| since.max(entry.first_seen)),
+                ),
+                exemplar: entry.exemplar.clone(),
+            })
+            .collect();
+        summaries.sort_by(|a, b| a.key.cmp(&b.key));
+        summaries
+    }
+
+    /// Emits one line per key with occurrences suppressed since the last report.
+    ///
+    /// Each line reads e.g. `"\"disk full\" suppressed 4,231 times in the last 1m0s"`,
+    /// covering the time since the previous call, or since the earliest reported key was
+    /// first seen on the first call. Keys are listed in order. This is how syslog and
+    /// journald report suppression; calling it periodically, e.g. from a `Reporter`
+    /// thread (feature `std-thread`) or with [`RateLog::with_auto_report`], gives
+    /// operators a steady digest instead of threshold-triggered warnings. Use
+    /// [`RateLog::report`] to get the same information without writing it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(1000));
+    ///
+    /// for _ in 0..10 {
+    ///     logger.log("disk full");
+    /// }
+    /// logger.report_suppressed();
+    /// // Prints: "\"disk full\" suppressed 9 times in the last 0ms"
+    /// ```
+    pub fn report_suppressed(&mut self) {
+        let last_report = self.last_report;
+        let summaries = self.report();
+        let window = match last_report {
+            Some(since) => self.clock.last().saturating_duration_since(since),
+            None => summaries
+                .iter()
+                .map(|summary| summary.duration)
+                .max()
+                .unwrap_or_default(),
+        };
+        let window = self.duration_style.format(window);
+        for summary in summaries {
+            self.emit_notice(
+                "report",
+                format!(
+                    "\"{}\" suppressed {} times in the last {window}",
+                    summary.key,
+                    format_count(summary.suppressed)
+                ),
+            );
+        }
+    }
+
+    /// Returns the weight of `severity` used to rank top offenders.
+    fn severity_weight(&self, severity: Severity) -> f64 {
+        self.severity_weights.get(&severity).copied().unwrap_or(1.0)
+    }
+
+    /// Returns the most recently emitted lines, oldest first.
+    ///
+    /// The iterator is empty unless history was enabled with [`RateLog::with_history`].
+    pub fn recent(&self) -> impl Iterator<Item = &str> {
+        self.history.iter().map(String::as_str)
+    }
+
+    /// Logs a message with rate limiting applied.
+    ///
+    /// This method immediately prints any new or different message to the sink, then tracks
+    /// repeated messages and enforces the configured rate limit. Repeated messages are
+    /// counted silently until the limit is exceeded.
+    ///
+    /// # Output Behavior
+    ///
+    /// - **New/different message**: Immediately printed to the sink and resets all counters
+    /// - **Repeated message**: Counted silently (no immediate output)
+    /// - **Limit exceeded**: Prints rate limit warning to the sink
+    ///
+    /// # Rate Limiting Behavior
+    ///
+    /// - **Count-based**: Increments counter for each repeated message
+    /// - **Duration-based**: Accumulates elapsed time between repeated messages
+    /// - **Message change**: Flushes pending repeats, resets all tracking state and prints the new message
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The message to log and track for rate limiting, borrowed from any string
+    ///   type such as `&str`, `String`, `Cow<str>` or `Arc<str>`
+    ///
+    /// Repeats of the tracked message are compared in place and never copied, so
+    /// callers holding owned strings pay no extra allocation.
+    ///
+    /// # Returns
+    ///
+    /// A [`LogOutcome`] describing whether the message was emitted, suppressed or
+    /// triggered a rate limit warning.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(2));
+    ///
+    /// logger.log("Starting up");          // Prints: "Starting up"
+    /// logger.log("Error occurred");       // Prints: "Error occurred" (different message)
+    /// logger.log("Error occurred");       // Silent (1st repetition)
+    /// logger.log("Error occurred");       // Silent (2nd repetition)
+    /// logger.log("Error occurred");       // Prints: "Message: \"Error occurred\" repeat for 2 times in the past 15ms"
+    /// logger.log("Shutting down");        // Prints: "Shutting down" (different message)
+    /// ```
+    pub fn log(&mut self, msg: impl AsRef<str>) -> LogOutcome {
+        self.track_message(msg.as_ref(), Severity::default(), None)
+    }
+
+    /// Logs a message rate limited under a stable key instead of its text.
+    ///
+    /// Messages that differ only in their details ("failed to connect to 10.0.0.1",
+    /// "failed to connect to 10.0.0.2") can share one key and are then counted as
+    /// repeats of each other. Emitted lines always show the actual message, and rate
+    /// limit warnings report the key together with the last message seen.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The identity used for rate limiting
+    /// * `msg` - The message text to emit
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(2));
+    ///
+    /// logger.log_keyed("connect", "failed to connect to 10.0.0.1");  // Prints: "failed to connect to 10.0.0.1"
+    /// logger.log_keyed("connect", "failed to connect to 10.0.0.2");  // Silent (1st repetition)
+    /// logger.log_keyed("connect", "failed to connect to 10.0.0.3");
+    /// // Prints: "Key: \"connect\" repeat for 2 times in the past 0ms, last message: \"failed to connect to 10.0.0.3\""
+    /// ```
+    pub fn log_keyed(&mut self, key: &str, msg: &str) -> LogOutcome {
+        self.track(key, Detail::Text(msg), Severity::default(), None)
+    }
+
+    /// Logs a message rate limited under the location of the call site.
+    ///
+    /// Works like [`RateLog::log_keyed`] with `"file:line"` of the caller as the key,
+    /// so a call site formatting a different message every time is still throttled as
+    /// one source. See also [`rate_log_here!`], which derives the key at compile time.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(2));
+    ///
+    /// for port in [8080, 8081, 8082] {
+    ///     logger.log_here(&format!("port {port} unreachable"));
+    /// }
+    /// // Prints: "port 8080 unreachable"
+    /// // Prints: "Key: \"src/main.rs:8\" repeat for 2 times in the past 0ms, last message: \"port 8082 unreachable\""
+    /// ```
+    #[track_caller]
+    pub fn log_here(&mut self, msg: &str) -> LogOutcome {
+        let location = core::panic::Location::caller();
+        let key = LocationKey::new(location.file(), location.line());
+        self.log_keyed(key.as_str(), msg)
+    }
+
+    /// Logs a message together with the location it was logged from.
+    ///
+    /// Rate limiting works exactly like [`RateLog::log`]. The location is appended to
+    /// the emitted message as ` (at file:line in module)` and to the warnings and
+    /// summaries of its repeats as `, at file:line in module`, so they point at the
+    /// code producing them. The latest occurrence wins: repeats logged without a
+    /// location clear it. [`location!`] captures the location of its invocation; the
+    /// call-site macros such as [`rate_log!`] pass it automatically.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{Limit, Location, RateLog};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(2));
+    /// let location = Location::new("src/db.rs", 42, Some("app::db"));
+    ///
+    /// logger.log_at("Disk full", location);  // Prints: "Disk full (at src/db.rs:42 in app::db)"
+    /// logger.log_at("Disk full", location);  // Silent (1st repetition)
+    /// logger.log_at("Disk full", location);
+    /// // Prints: "Message: \"Disk full\" repeat for 2 times in the past 0ms, at src/db.rs:42 in app::db"
+    /// ```
+    pub fn log_at(&mut self, msg: &str, location: Location) -> LogOutcome {
+        self.call_location = Some(location);
+        let outcome = self.track_message(msg, Severity::default(), None);
+        self.call_location = None;
+        outcome
+    }
+
+    /// Logs a message rate limited under a statically known [`LogKey`].
+    ///
+    /// Works like [`RateLog::log_keyed`] with `key.key()` as the key, except that the
+    /// key's own limit, if it declares one, is enforced instead of the limit of this
+    /// `RateLog`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{log_keys, Limit, RateLog};
+    ///
+    /// log_keys! {
+    ///     #[derive(Clone, Copy)]
+    ///     enum NetKey {
+    ///         #[limit(Limit::Rate(1))]
+    ///         LinkDown,
+    ///     }
+    /// }
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(100));
+    ///
+    /// logger.log_key(NetKey::LinkDown, "eth0 down");  // Prints: "eth0 down"
+    /// logger.log_key(NetKey::LinkDown, "eth0 down");
+    /// // Prints: "Key: \"LinkDown\" repeat for 1 times in the past 0ms, last message: \"eth0 down\""
+    /// ```
+    pub fn log_key<K: LogKey>(&mut self, key: K, msg: &str) -> LogOutcome {
+        self.track(
+            key.key(),
+            Detail::Text(msg),
+            Severity::default(),
+            key.limit(),
+        )
+    }
+
+    /// Logs the [`Display`](core::fmt::Display) text of `item`, rate limited by its
+    /// [`RateKey`].
+    ///
+    /// Items whose key equals their text, such as strings, are logged like
+    /// [`RateLog::log`]; otherwise like [`RateLog::log_with`], so warnings name the key,
+    /// repeats with different text count towards the same limit, and the text is only
+    /// rendered when it is emitted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{Limit, RateLog};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(10));
+    /// logger.log_item("Disk full");  // Prints: "Disk full"
+    /// ```
+    pub fn log_item<T: RateKey + fmt::Display + ?Sized>(&mut self, item: &T) -> LogOutcome {
+        let key = item.rate_key();
+        if displays_as(item, &key) {
+            self.track_message(&key, Severity::default(), None)
+        } else {
+            // Like `log_with`: suppressed repeats never render the item
+            let mut build = || item.to_string();
+            self.track(&key, Detail::Lazy(&mut build), Severity::default(), None)
+        }
+    }
+
+    /// Logs a message given as raw bytes, e.g. read from a device or another process.
+    ///
+    /// Invalid UTF-8 and embedded NUL bytes are handled according to the sink's
+    /// [`TextPolicy`], see [`Sink::text_policy`]. Messages dropped by the policy are not
+    /// tracked and return [`LogOutcome::Dropped`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(2));
+    /// logger.log_bytes(b"bad byte \xff");  // Prints: "bad byte \u{FFFD}"
+    /// ```
+    pub fn log_bytes(&mut self, msg: &[u8]) -> LogOutcome {
+        match self.sink.text_policy().decode(msg) {
+            Some(msg) => self.track_message(&msg, Severity::default(), None),
+            None => LogOutcome::Dropped,
+        }
+    }
+
+    /// Logs a safety-critical message, which is always emitted immediately.
+    ///
+    /// Critical messages bypass every form of suppression: the limit, quiet keys,
+    /// alternation batching and the global budget. They are still tracked like any other
+    /// occurrence with [`Severity::Error`], so repeats suppressed before and after them
+    /// are counted together and later warnings and summaries stay accurate. Only a paused
+    /// limiter discards them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(2));
+    ///
+    /// logger.log("Brake pressure low");           // Prints: "Brake pressure low"
+    /// logger.log("Brake pressure low");           // Silent (1st repetition)
+    /// logger.log_critical("Brake pressure low");  // Prints: "Brake pressure low"
+    /// logger.log("Brake pressure low");
+    /// // Prints: "Message: \"Brake pressure low\" repeat for 2 times in the past 0ms"
+    /// ```
+    pub fn log_critical(&mut self, msg: &str) -> LogOutcome {
+        self.critical = true;
+        let outcome = self.track_message(msg, Severity::Error, None);
+        self.critical = false;
+        outcome
+    }
+
+    /// Logs a message with an explicit severity.
+    ///
+    /// Rate limiting works exactly like [`RateLog::log`]; the severity only affects how
+    /// the message is weighted in [`RateLog::top_offenders`]. Plain `log()` calls use
+    /// [`Severity::Info`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit, Severity};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(10));
+    /// logger.log_with_severity(Severity::Error, "Disk full");  // Prints: "Disk full"
+    /// ```
+    pub fn log_with_severity(&mut self, severity: Severity, msg: &str) -> LogOutcome {
+        self.track_message(msg, severity, None)
+    }
+
+    /// Emits `msg` verbatim without tracking it.
+    ///
+    /// Repeats pending for the tracked message are summarized first so that output
+    /// stays in order; the tracked message itself is kept.
+    fn pass_through(&mut self, msg: &str, severity: Severity) -> LogOutcome {
+        self.flush_pending();
+        self.emit_message(msg, severity, None, None);
+        #[cfg(feature = "metrics")]
+        telemetry::record_emitted();
+        LogOutcome::Emitted
+    }
+
+    /// Logs a message, enforcing `limit` instead of the limit of this `RateLog` for this
+    /// call only.
+    ///
+    /// Lets one logger apply a stricter limit to a hot path without a separate
+    /// `RateLog` per call site. The tracking state is still shared per message: calls
+    /// with and without an override count towards the same repeats, and each call is
+    /// judged by the limit it passes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(100));
+    /// let hot_path = Limit::Rate(2);
+    ///
+    /// logger.log("cache miss");                          // Prints: "cache miss"
+    /// logger.log("cache miss");                          // Silent (1st repetition)
+    /// logger.log_with_limit("cache miss", &hot_path);
+    /// // Prints: "Message: \"cache miss\" repeat for 2 times in the past 0ms"
+    /// ```
+    pub fn log_with_limit(&mut self, msg: &str, limit: &Limit) -> LogOutcome {
+        self.track_message(msg, Severity::default(), Some(limit.clone()))
+    }
+
+    /// Logs a message from `target`, e.g. a module path, enforcing the limit configured
+    /// for it with [`RateLog::with_target_limit`].
+    ///
+    /// Messages are still tracked by their text, as with [`RateLog::log_with_limit`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(100)).with_target_limit("net::*", Limit::Rate(1));
+    ///
+    /// logger.log_for_target("net::tcp", "connection reset");  // Prints: "connection reset"
+    /// logger.log_for_target("net::tcp", "connection reset");
+    /// // Prints: "Message: \"connection reset\" repeat for 1 times in the past 0ms"
+    /// ```
+    pub fn log_for_target(&mut self, target: &str, msg: &str) -> LogOutcome {
+        let limit = self.target_limit(target);
+        self.track_message(msg, Severity::default(), limit)
+    }
+
+    /// Returns the limit of the first pattern matching `target`, if any.
+    fn target_limit(&self, target: &str) -> Option<Limit> {
+        self.target_limits
+            .iter()
+            .find(|(pattern, _)| target_matches(pattern, target))
+            .map(|(_, limit)| limit.clone())
+    }
+
+    /// Logs a message whose text is built only if it is actually emitted.
+    ///
+    /// Works like [`RateLog::log_keyed`], but `f` is called only when the message is
+    /// emitted or reported in a rate limit warning. Suppressed repeats never pay for
+    /// formatting the message body.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(100));
+    ///
+    /// for attempt in 0..10 {
+    ///     // Formats the message once, for the first attempt only
+    ///     logger.log_with("connect", || format!("failed to connect, attempt {attempt}"));
+    /// }
+    /// ```
+    pub fn log_with(&mut self, key: &str, f: impl FnOnce() -> String) -> LogOutcome {
+        let mut f = Some(f);
+        let mut build = || f.take().map(|f| f()).unwrap_or_default();
+        self.track(key, Detail::Lazy(&mut build), Severity::default(), None)
+    }
+
+    /// Counts an occurrence of `key` and returns whether it would be emitted, without
+    /// emitting it.
+    ///
+    /// The occurrence is tracked exactly like a [`RateLog::log_keyed`] call, so `check`
+    /// lets expensive work, such as building a diagnostic dump, follow the same throttle
+    /// decision as a log line. Returns `true` only if the occurrence would be emitted
+    /// verbatim. Rate limit warnings and summaries for `key` are still written, so the
+    /// throttled work stays visible in the output.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(10));
+    ///
+    /// for _ in 0..100 {
+    ///     if logger.check("heap dump") {
+    ///         // Runs only for the first of the 100 occurrences
+    ///         println!("dumping heap");
+    ///     }
+    /// }
+    /// ```
+    pub fn check(&mut self, key: &str) -> bool {
+        self.track(key, Detail::Check, Severity::default(), None) == LogOutcome::Emitted
+    }
+
+    /// Applies rate limiting to a message logged without a key.
+    ///
+    /// The message is its own key unless a normalizer derives a different one.
+    fn track_message(&mut self, msg: &str, severity: Severity, limit: Option<Limit>) -> LogOutcome {
+        if let Some(normalizer) = self.normalizer.filter(|_| self.enabled) {
+            if let Cow::Owned(key) = normalizer.normalize(msg) {
+                return self.track(&key, Detail::Text(msg), severity, limit);
+            }
+        }
+        self.track(msg, Detail::Key, severity, limit)
+    }
+
+    /// Applies rate limiting to `key`, emitting `detail` (or the key itself) when allowed.
+    ///
+    /// `limit` overrides the limit of this `RateLog` while `key` is tracked.
+    fn track(
+        &mut self,
+        key: &str,
+        mut detail: Detail<'_>,
+        severity: Severity,
+        limit: Option<Limit>,
+    ) -> LogOutcome {
+        if !self.enabled {
+            return self.write_through(key, detail, severity);
+        }
+        let location = self.call_location.take();
+        let hash = key_hash(key);
+
+        if self.suppressed_at_any_time(key, hash, limit.as_ref()) {
+            return self.track_suppressed(key, detail, severity, location, limit);
+        }
+
+        let now = self.clock.now();
+        let bytes = match detail {
+            Detail::Text(text) => text.len(),
+            _ => key.len(),
+        };
+        let scaled = self
+            .adaptive
+            .as_mut()
+            .map(|adaptive| adaptive.factor(now))
+            .filter(|&factor| factor > 1.0)
+            .map(|factor| limit.as_ref().unwrap_or(&self.limit).scaled(factor));
+        let outcome;
+
+        if self.coalescer.as_ref().is_some_and(|c| c.due(now)) {
+            self.emit_coalesced();
+        }
+
+        if let Some(interval) = self.auto_report {
+            match self.last_report {
+                Some(last) if now.saturating_duration_since(last) >= interval => {
+                    self.report_suppressed();
+                }
+                Some(_) => {}
+                None => self.last_report = Some(now),
+            }
+        }
+
+        if let Some(expired) = self.dedup.as_mut().map(|dedup| dedup.expire(now)) {
+            self.summarize_tracked(expired);
+        }
+
+        self.settle_pending(now);
+
+        let idle = self
+            .current
+            .last_timestamp
+            .map(|last| now.duration_since(last));
+        if self
+            .idle_ttl
+            .is_some_and(|ttl| idle.is_some_and(|idle| idle >= ttl))
+        {
+            self.flush_pending();
+            self.reset_state();
+        }
+
+        let mut switched = !self.is_tracked(key, hash);
+        if switched && self.dedup.is_some() {
+            switched = !self.switch_deduplicated(key);
+        }
+
+        if !switched {
+            self.location = location;
+        }
+
+        if switched {
+            let rapid = match &mut self.alternation {
+                Some(alternation) => alternation.on_switch(&self.key, key, now),
+                None => false,
+            };
+            if !rapid {
+                self.flush_pending();
+            }
+
+            // Reuses the buffer of the previous key, so switching rarely allocates
+            self.key.clear();
+            self.key.push_str(key);
+            self.key_hash = hash;
+            self.keyed = detail.is_keyed();
+            self.current.reset();
+            self.key_limit = limit;
+            let limit = scaled
+                .as_ref()
+                .unwrap_or_else(|| self.key_limit.as_ref().unwrap_or(&self.limit));
+            self.current.on_first(limit, now, bytes);
+            self.exemplar = None;
+            self.location = location;
+            let refused = match self.limiter.as_mut() {
+                Some(limiter) if self.key_limit.is_none() => !limiter.on_event(now, key).emits(),
+                _ => false,
+            };
+
+            if (rapid || refused || !self.emit_first || self.quiet_keys.contains(key))
+                && !self.critical
+            {
+                outcome = LogOutcome::Suppressed;
+            } else {
+                self.emit_detail(key, &mut detail, severity, None);
+                outcome = LogOutcome::Emitted;
+            }
+        } else if self.critical || self.in_grace_period(key, now) {
+            self.emit_detail(key, &mut detail, severity, None);
+            outcome = LogOutcome::Emitted;
+        } else {
+            if let Some(exemplar) = self.exemplar_source.as_mut().and_then(|source| source()) {
+                self.exemplar = Some(exemplar);
+            }
+
+            if let Some(per) = self.decay {
+                self.current.decay(per, now);
+            }
+
+            self.key_limit = limit;
+            let decision = match self.limiter.as_mut() {
+                Some(limiter) if self.key_limit.is_none() => {
+                    let decision = limiter.on_event(now, key);
+                    self.current.on_decision(decision, self.duration_mode, now)
+                }
+                _ => {
+                    let limit = scaled
+                        .as_ref()
+                        .unwrap_or_else(|| self.key_limit.as_ref().unwrap_or(&self.limit));
+                    self.current
+                        .on_repeat(limit, self.count_mode, self.duration_mode, now, bytes)
+                }
+            };
+            match decision {
+                Decision::Emit => {
+                    self.emit_detail(key, &mut detail, severity, None);
+                    outcome = LogOutcome::Emitted;
+                }
+                Decision::EmitCounted(occurrences) => {
+                    self.emit_detail(
+                        key,
+                        &mut detail,
+                        severity,
+                        Some(Annotation::Seen(occurrences)),
+                    );
+                    outcome = LogOutcome::Emitted;
+                }
+                Decision::EmitSampled(rate) => {
+                    self.emit_detail(key, &mut detail, severity, Some(Annotation::Sampled(rate)));
+                    outcome = LogOutcome::Emitted;
+                }
+                Decision::EmitMore(more) => {
+                    self.emit_detail(key, &mut detail, severity, Some(Annotation::More(more)));
+                    outcome = LogOutcome::Emitted;
+                }
+                Decision::Suppress => outcome = LogOutcome::Suppressed,
+                Decision::Report(_) if self.in_cooldown(now) || self.summary_held(now) => {
+                    outcome = LogOutcome::Suppressed;
+                }
+                Decision::Report(trigger) => {
+                    self.current.last_warning = Some(now);
+                    let (count, duration) = self.release_held(now);
+                    if let Some(callback) = &mut self.limit_callback {
+                        callback(key, count, duration);
+                    }
+                    let message = detail.resolve();
+                    if self.message_on_limit && !detail.is_check() {
+                        let location = self.location;
+                        self.emit_message(
+                            message.as_deref().unwrap_or(key),
+                            severity,
+                            None,
+                            location.as_ref(),
+                        );
+                    }
+                    if self.warnings {
+                        let output = self.format_warning(&Warning {
+                            kind: WarningKind::LimitExceeded(trigger),
+                            key,
+                            keyed: detail.is_keyed(),
+                            message: message.as_deref(),
+                            count,
+                            duration,
+                            dropped_bytes: self.current.dropped_bytes,
+                            exemplar: self.exemplar.as_deref(),
+                            location: self.location,
+                        });
+                        let event = Event::Summary {
+                            key: Cow::Borrowed(key),
+                            count,
+                            duration,
+                            kind: WarningKind::LimitExceeded(trigger),
+                        };
+                        match &mut self.coalescer {
+                            Some(coalescer) => coalescer.push(output, event.into_owned(), now),
+                            None => self.emit_warning(output, &event),
+                        }
+                    }
+
+                    outcome = LogOutcome::LimitReported {
+                        count,
+                        duration,
+                        trigger,
+                    };
+
+                    self.current.clear_pending();
+                    self.last_message = None;
+                }
+            }
+
+            if self
+                .escalation
+                .is_some_and(|threshold| self.current.repeats == u64::from(threshold))
+                && !detail.is_check()
+            {
+                let text = detail.resolve();
+                self.escalate(key, text.as_deref().unwrap_or(key), now);
+            }
+        }
+
+        self.record_outcome(key, &mut detail, severity, switched, now, outcome)
+    }
+
+    /// Counts a repeat that [`RateLog::suppressed_at_any_time`] found to be suppressed,
+    /// skipping the checks of [`RateLog::track`] that cannot apply to it.
+    ///
+    /// The clock is not read: the repeat is stamped with the last reading.
+    fn track_suppressed(
+        &mut self,
+        key: &str,
+        mut detail: Detail<'_>,
+        severity: Severity,
+        location: Option<Location>,
+        limit: Option<Limit>,
+    ) -> LogOutcome {
+        let now = self.clock.last();
+        self.location = location;
+        if let Some(exemplar) = self.exemplar_source.as_mut().and_then(|source| source()) {
+            self.exemplar = Some(exemplar);
+        }
+        self.key_limit = limit;
+        self.current.on_suppressed(self.duration_mode, now);
+
+        // The key repeats, so its statistics are the hot entry unless they were
+        // evicted or reset since: `record_outcome` without lookups
+        let entry_key = self.entry_key(key);
+        let Some(entry) = self.entries.hot_mut(entry_key.as_ref()) else {
+            return self.record_outcome(
+                key,
+                &mut detail,
+                severity,
+                false,
+                now,
+                LogOutcome::Suppressed,
+            );
+        };
+        let elapsed = self
+            .current
+            .last_timestamp
+            .replace(now)
+            .map_or(Duration::ZERO, |last| now.duration_since(last));
+        entry.occurrences += 1;
+        entry.suppressed += 1;
+        entry.last_seen = now;
+        entry.duration += elapsed;
+        entry.intervals.record(elapsed);
+        entry.severity = severity;
+        if self.exemplar.is_some() {
+            entry.exemplar.clone_from(&self.exemplar);
+        }
+        #[cfg(feature = "metrics")]
+        self.labels.record(
+            entry_key.as_ref(),
+            &mut entry.counter,
+            &LogOutcome::Suppressed,
+        );
+
+        self.remember_last_message(&mut detail);
+        LogOutcome::Suppressed
+    }
+
+    /// Records the outcome of a call for `key` in the pending state and the statistics.
+    fn record_outcome(
+        &mut self,
+        key: &str,
+        detail: &mut Detail<'_>,
+        severity: Severity,
+        switched: bool,
+        now: Instant,
+        outcome: LogOutcome,
+    ) -> LogOutcome {
+        if outcome == LogOutcome::Suppressed {
+            self.remember_last_message(detail);
+        }
+
+        let elapsed = self
+            .current
+            .last_timestamp
+            .map(|last| now.duration_since(last));
+        self.current.last_timestamp = Some(now);
+
+        let entry_key = self.entry_key(key);
+        if self
+            .max_tracked
+            .is_some_and(|max| self.entries.len() >= max)
+            && !self.entries.contains_key(entry_key.as_ref())
+        {
+            self.evict_least_recent();
+        }
+        let entry = if switched {
+            self.entries.get_mut(entry_key.as_ref())
+        } else {
+            self.entries.promote(entry_key.as_ref())
+        };
+        let entry = match entry {
+            Some(entry) => entry,
+            None => self.entries.insert(entry_key.into_owned(), Entry::new(now)),
+        };
+        entry.occurrences += 1;
+        entry.last_seen = now;
+        entry.duration += elapsed.unwrap_or_default();
+        if let Some(elapsed) = elapsed {
+            entry.intervals.record(elapsed);
+        }
+        entry.severity = severity;
+        if outcome != LogOutcome::Emitted {
+            entry.suppressed += 1;
+        }
+        if outcome != LogOutcome::Emitted && self.exemplar.is_some() {
+            entry.exemplar.clone_from(&self.exemplar);
+        }
+        // Only suppressed repeats remain pending a warning or summary
+        if outcome != LogOutcome::Suppressed {
+            self.exemplar = None;
+        }
+
+        #[cfg(feature = "metrics")]
+        self.labels
+            .record(entry_key.as_ref(), &mut entry.counter, &outcome);
+
+        outcome
+    }
+
+    /// Keeps the text of a suppressed repeat for warnings, if they report it.
+    fn remember_last_message(&mut self, detail: &mut Detail<'_>) {
+        if !self.report_last_message {
+            return;
+        }
+        if let Some(text) = detail.resolve() {
+            // Reuses the buffer of the previous repeat, so repeats rarely allocate
+            match &mut self.last_message {
+                Some(last) => {
+                    last.clear();
+                    last.push_str(&text);
+                }
+                None => self.last_message = Some(text.into_owned()),
+            }
+        }
+    }
+
+    /// Emits a summary of repeats that were suppressed since the last warning.
+    ///
+    /// Repeats counted silently below the limit would otherwise be lost when the tracked
+    /// message changes or the logger goes away. `flush` reports them as
+    /// `"Message: \"text\" suppressed N repeats over X"` and clears the counters; the
+    /// tracked message is kept, so later repeats continue to be rate limited.
+    /// Nothing is emitted when there are no pending repeats. Finally the sink is asked to
+    /// write out any records it holds back (see [`Sink::flush`]).
+    ///
+    /// Pending repeats are also reported automatically whenever a different message is
+    /// logged, and the whole flush happens when the `RateLog` is dropped. It also ends an ongoing alternation batch (see
+    /// [`RateLog::with_alternation_batching`]), emitting its summary first.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(10));
+    ///
+    /// logger.log("Disk full");  // Prints: "Disk full"
+    /// logger.log("Disk full");  // Silent (1st repetition)
+    /// logger.log("Disk full");  // Silent (2nd repetition)
+    /// logger.flush();           // Prints: "Message: \"Disk full\" suppressed 2 repeats over 0ms"
+    /// logger.flush();           // Nothing pending, prints nothing
+    /// ```
+    pub fn flush(&mut self) {
+        if let Some(mut dedup) = self.dedup.take() {
+            for tracked in dedup.parked_mut().rev() {
+                let current = self.swap_tracked(core::mem::replace(tracked, Tracked::new()));
+                self.summarize_pending();
+                *tracked = self.swap_tracked(current);
+            }
+            self.dedup = Some(dedup);
+        }
+        self.flush_pending();
+        self.flush_held();
+        self.emit_coalesced();
+        if let Some(suppressed) = self.budget.as_mut().and_then(|b| b.take_suppressed()) {
+            self.emit_budget_notice(suppressed);
+        }
+        self.sink.flush();
+    }
+
+    /// Summarizes the pending repeats of messages that have been quiet for the settle
+    /// timeout, see [`RateLog::with_settle_timeout`].
+    ///
+    /// Does nothing without a settle timeout. Call it periodically when messages may
+    /// stop arriving altogether, e.g. from a timer of the application; a `Reporter`
+    /// (feature `std-thread`) calls it on every tick.
+    pub fn settle(&mut self) {
+        if self.settle_timeout.is_some() {
+            let now = self.clock.now();
+            self.settle_pending(now);
+        }
+    }
+
+    /// Clears all tracking state, as if the limiter had just been created.
+    ///
+    /// Forgets the tracked message with its pending repeats, the statistics of every
+    /// key and the history kept for deduplication, alternation batching, the global
+    /// budget and adaptive limiting. Pending repeats are discarded without a summary;
+    /// call [`RateLog::flush`] first to report them. The configuration, the sink and
+    /// the pause state are kept, e.g. to start counting afresh after recovering from
+    /// an incident without rewiring the output.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(10));
+    ///
+    /// logger.log("Disk full");  // Prints: "Disk full"
+    /// logger.log("Disk full");  // Silent (1st repetition)
+    /// logger.reset();
+    /// logger.log("Disk full");  // Prints: "Disk full"
+    /// assert_eq!(logger.stats().messages()[0].occurrences, 1);
+    /// ```
+    pub fn reset(&mut self) {
+        self.reset_state();
+        self.entries.clear();
+        self.last_report = None;
+        if let Some(dedup) = &mut self.dedup {
+            dedup.clear();
+        }
+        if let Some(alternation) = &mut self.alternation {
+            alternation.clear();
+        }
+        if let Some(budget) = &mut self.budget {
+            budget.reset();
+        }
+        if let Some(adaptive) = &mut self.adaptive {
+            adaptive.reset();
+        }
+    }
+
+    /// Clears the counters and statistics of a single key.
+    ///
+    /// `key` is the rate limiting key: the message text for plain `log()` calls, the
+    /// key passed to [`RateLog::log_keyed`], or the key derived by
+    /// [`RateLog::group_by`]. Its next occurrence is treated as a first occurrence.
+    /// Pending repeats of the key are discarded without a summary. Other keys are not
+    /// affected.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(10));
+    ///
+    /// logger.log("Disk full");  // Prints: "Disk full"
+    /// logger.log("Disk full");  // Silent (1st repetition)
+    /// logger.reset_key("Disk full");
+    /// logger.log("Disk full");  // Prints: "Disk full"
+    /// ```
+    pub fn reset_key(&mut self, key: &str) {
+        if self.is_tracked(key, key_hash(key)) {
+            self.reset_state();
+        }
+        if let Some(dedup) = &mut self.dedup {
+            dedup.take(key);
+        }
+        let entry_key = self.entry_key(key);
+        self.entries.remove(entry_key.as_ref());
+    }
+
+    /// Emits the summaries of pending repeats and alternation without flushing the sink.
+    fn flush_pending(&mut self) {
+        if let Some(summary) = self
+            .alternation
+            .as_mut()
+            .and_then(|a| a.finish(&self.duration_style))
+        {
+            self.emit_notice("alternation", summary);
+        }
+        self.summarize_pending();
+    }
+
+    /// Summarizes the pending repeats of the tracked and deduplicated messages that
+    /// have not been seen for the settle timeout at `now`.
+    fn settle_pending(&mut self, now: Instant) {
+        let Some(timeout) = self.settle_timeout else {
+            return;
+        };
+        let settled = |state: &State| {
+            state.count > 0
+                && state
+                    .last_timestamp
+                    .is_some_and(|last| now.saturating_duration_since(last) >= timeout)
+        };
+        if let Some(mut dedup) = self.dedup.take() {
+            for tracked in dedup.parked_mut().filter(|tracked| settled(&tracked.state)) {
+                let current = self.swap_tracked(core::mem::replace(tracked, Tracked::new()));
+                self.summarize_pending();
+                *tracked = self.swap_tracked(current);
+            }
+            self.dedup = Some(dedup);
+        }
+        if settled(&self.current) {
+            self.summarize_pending();
+        }
+    }
+
+    /// Emits the summary of the pending repeats of the tracked message, if any.
+    fn summarize_pending(&mut self) {
+        if self.current.count == 0 {
+            return;
+        }
+        let now = self.clock.last();
+        if self.summary_held(now) {
+            self.hold_pending();
+            return;
+        }
+
+        let (count, duration) = self.release_held(now);
+        if self.warnings {
+            let output = self.format_warning(&Warning {
+                kind: WarningKind::Summary,
+                key: &self.key,
+                keyed: self.keyed,
+                message: self.last_message.as_deref(),
+                count,
+                duration,
+                dropped_bytes: self.current.dropped_bytes,
+                exemplar: self.exemplar.as_deref(),
+                location: self.location,
+            });
+            let event = Event::Summary {
+                key: Cow::Owned(self.key.clone()),
+                count,
+                duration,
+                kind: WarningKind::Summary,
+            };
+            match &mut self.coalescer {
+                Some(coalescer) => {
+                    let seen = self
+                        .current
+                        .last_timestamp
+                        .unwrap_or_else(|| self.clock.last());
+                    coalescer.push(output, event, seen);
+                }
+                None => self.emit_warning(output, &event),
+            }
+        }
+
+        self.current.clear_pending();
+        self.exemplar = None;
+        self.last_message = None;
+    }
+
+    /// Returns `true` while warnings and summaries for the tracked key are held back,
+    /// see [`RateLog::with_summary_interval`].
+    fn summary_held(&self, now: Instant) -> bool {
+        let Some(interval) = self.summary_interval else {
+            return false;
+        };
+        self.entries
+            .get(self.entry_key(&self.key).as_ref())
+            .and_then(|entry| entry.last_summary)
+            .is_some_and(|last| now.duration_since(last) < interval)
+    }
+
+    /// Adds the pending repeats of the tracked key to those held back for it.
+    fn hold_pending(&mut self) {
+        let entry_key = self.entry_key(&self.key);
+        if let Some(entry) = self.entries.get_mut(entry_key.as_ref()) {
+            let held = entry.held.get_or_insert(Held {
+                count: 0,
+                duration: Duration::ZERO,
+                keyed: self.keyed,
+            });
+            held.count = held.count.saturating_add(self.current.count);
+            held.duration += self.current.duration;
+        }
+        self.current.clear_pending();
+        self.exemplar = None;
+        self.last_message = None;
+    }
+
+    /// Returns the pending repeats of the tracked key and their duration, including
+    /// those held back for it, and records a warning or summary for it at `now`.
+    fn release_held(&mut self, now: Instant) -> (u32, Duration) {
+        let mut totals = (self.current.count, self.current.duration);
+        if self.summary_interval.is_none() {
+            return totals;
+        }
+        let entry_key = self.entry_key(&self.key);
+        if let Some(entry) = self.entries.get_mut(entry_key.as_ref()) {
+            entry.last_summary = Some(now);
+            if let Some(held) = entry.held.take() {
+                totals.0 = totals.0.saturating_add(held.count);
+                totals.1 += held.duration;
+            }
+        }
+        totals
+    }
+
+    /// Emits the summaries of all repeats held back by the summary interval.
+    fn flush_held(&mut self) {
+        let Some(interval) = self.summary_interval.take() else {
+            return;
+        };
+        let mut held: Vec<Tracked> = self
+            .entries
+            .iter_mut()
+            .filter_map(|(key, entry)| {
+                let held = entry.held.take()?;
+                let mut tracked = Tracked::new();
+                tracked.key.push_str(key);
+                tracked.keyed = held.keyed;
+                tracked.state.count = held.count;
+                tracked.state.duration = held.duration;
+                Some(tracked)
+            })
+            .collect();
+        held.sort_by(|a, b| a.key.cmp(&b.key));
+        self.summarize_tracked(held);
+        self.summary_interval = Some(interval);
+    }
+
+    /// Parks the tracked message and switches to the parked state of `key`, if it is
+    /// within the dedup window.
+    ///
+    /// Returns `true` if `key` was switched to, so the call is counted as a repeat.
+    /// Otherwise nothing is tracked afterwards, like before the first call.
+    fn switch_deduplicated(&mut self, key: &str) -> bool {
+        let parked = self.dedup.as_mut().and_then(|dedup| dedup.take(key));
+        let returned = parked.is_some();
+        let previous = self.swap_tracked(parked.unwrap_or_else(Tracked::new));
+        if !previous.key.is_empty() {
+            let seen = previous
+                .state
+                .last_timestamp
+                .unwrap_or_else(|| self.clock.last());
+            if let Some(evicted) = self.dedup.as_mut().map(|dedup| dedup.park(previous, seen)) {
+                self.summarize_tracked(evicted);
+            }
+        }
+        returned
+    }
+
+    /// Emits the summaries of the pending repeats of messages leaving the dedup window.
+    fn summarize_tracked(&mut self, messages: Vec<Tracked>) {
+        for tracked in messages {
+            let current = self.swap_tracked(tracked);
+            self.summarize_pending();
+            self.swap_tracked(current);
+        }
+    }
+
+    /// Replaces the tracked message and its state, returning the previous ones.
+    fn swap_tracked(&mut self, tracked: Tracked) -> Tracked {
+        let previous = core::mem::replace(&mut self.key, tracked.key);
+        self.key_hash = key_hash(&self.key);
+        Tracked {
+            key: previous,
+            keyed: core::mem::replace(&mut self.keyed, tracked.keyed),
+            key_limit: core::mem::replace(&mut self.key_limit, tracked.key_limit),
+            state: core::mem::replace(&mut self.current, tracked.state),
+            exemplar: core::mem::replace(&mut self.exemplar, tracked.exemplar),
+            location: core::mem::replace(&mut self.location, tracked.location),
+            last_message: core::mem::replace(&mut self.last_message, tracked.last_message),
+        }
+    }
+
+    /// Forgets the least recently seen key, summarizing its suppressed repeats.
+    fn evict_least_recent(&mut self) {
+        let Some(key) = self
+            .entries
+            .iter()
+            .min_by(|(a_key, a), (b_key, b)| a.last_seen.cmp(&b.last_seen).then(a_key.cmp(b_key)))
+            .map(|(key, _)| key.clone())
+        else {
+            return;
+        };
+        let entry = self.entries.remove(&key).expect("evicted key is tracked");
+
+        if self.warnings && entry.suppressed > 0 {
+            let summary = format!(
+                "evicted entry \"{}\" with {} suppressed repeats",
+                key, entry.suppressed
+            );
+            let event = Event::Evicted {
+                key: Cow::Borrowed(&key),
+                suppressed: entry.suppressed,
+            };
+            self.emit_notice_event("evicted", &summary, &event);
+        }
+    }
+
+    /// Returns `true` while warnings for the tracked message are held back.
+    fn in_cooldown(&self, now: Instant) -> bool {
+        match (self.cooldown, self.current.last_warning) {
+            (Some(cooldown), Some(last_warning)) => now.duration_since(last_warning) < cooldown,
+            _ => false,
+        }
+    }
+
+    /// Returns the key of `entries` under which `key` is remembered.
+    fn entry_key<'k>(&self, key: &'k str) -> EntryKey<'k> {
+        if self.hashed_keys {
+            hashed_key(key)
+        } else {
+            EntryKey::Plain(key)
+        }
+    }
+
+    /// Returns `true` if `key`, whose [`key_hash`] is `hash`, is the tracked key.
+    fn is_tracked(&self, key: &str, hash: u64) -> bool {
+        self.key_hash == hash && self.key == key
+    }
+
+    /// Returns `true` if an occurrence of `key` is a repeat suppressed whatever the
+    /// time, so it needs no fresh clock reading.
+    ///
+    /// The first repeat after the first occurrence or a warning still reads the clock,
+    /// so the time pending repeats started at is exact.
+    fn suppressed_at_any_time(&self, key: &str, hash: u64, limit: Option<&Limit>) -> bool {
+        self.lazy_clock
+            && self.current.count > 0
+            && self.is_tracked(key, hash)
+            && !self.critical
+            && !self.show_rate
+            && !self.interval_percentiles
+            && self.limiter.is_none()
+            && self.adaptive.is_none()
+            && self.coalescer.is_none()
+            && self.auto_report.is_none()
+            && self.dedup.is_none()
+            && self.settle_timeout.is_none()
+            && self.idle_ttl.is_none()
+            && self.grace_period.is_none()
+            && self.decay.is_none()
+            && self.escalation.is_none()
+            && self
+                .current
+                .suppresses_next(limit.unwrap_or(&self.limit), self.count_mode)
+    }
+
+    /// Returns `true` while `key` is within the grace period following its first occurrence.
+    fn in_grace_period(&self, key: &str, now: Instant) -> bool {
+        let Some(grace_period) = self.grace_period else {
+            return false;
+        };
+        self.entries
+            .get(self.entry_key(key).as_ref())
+            .is_some_and(|entry| now.duration_since(entry.first_seen) < grace_period)
+    }
+
+    /// Returns the limit enforced for the tracked key.
+    fn active_limit(&self) -> &Limit {
+        self.key_limit.as_ref().unwrap_or(&self.limit)
+    }
+
+    /// Renders a warning or summary with the custom formatter, if any.
+    fn format_warning(&self, warning: &Warning<'_>) -> String {
+        if let Some(formatter) = &self.formatter {
+            return formatter(warning);
+        }
+        #[cfg(feature = "serde")]
+        if self.output_format == OutputFormat::Json {
+            return output::json_warning(warning, self.active_limit().is_composite());
+        }
+
+        let label = if warning.keyed { "Key" } else { "Message" };
+        let mut output = match warning.kind {
+            WarningKind::LimitExceeded(_) => format!(
+                "{}: \"{}\" repeat for {} times in the past {}",
+                label,
+                warning.key,
+                warning.count,
+                self.duration_style.format(warning.duration)
+            ),
+            WarningKind::Summary => format!(
+                "{}: \"{}\" suppressed {} repeats over {}",
+                label,
+                warning.key,
+                warning.count,
+                self.duration_style.format(warning.duration)
+            ),
+        };
+        if warning.dropped_bytes > 0 {
+            output.push_str(&format!(
+                ", dropped {} bytes",
+                format_count(warning.dropped_bytes)
+            ));
+        }
+        if let Some(rate) = warning.rate().filter(|_| self.show_rate) {
+            output.push_str(&format!(" ({})", format_rate(rate)));
+        }
+        if self.interval_percentiles {
+            let intervals = self
+                .entries
+                .get(self.entry_key(warning.key).as_ref())
+                .map(|entry| &entry.intervals);
+            if let Some((p50, p99)) =
+                intervals.and_then(|i| Some((i.percentile(50.0)?, i.percentile(99.0)?)))
+            {
+                output.push_str(&format!(
+                    ", p50 {}, p99 {} between repeats",
+                    self.duration_style.format(p50),
+                    self.duration_style.format(p99)
+                ));
+            }
+        }
+        if let Some(msg) = warning.message {
+            output.push_str(&format!(", last message: \"{msg}\""));
+        }
+        if let Some(location) = &warning.location {
+            output.push_str(&format!(", at {location}"));
+        }
+        if let WarningKind::LimitExceeded(trigger) = warning.kind {
+            if self.active_limit().is_composite() {
+                output.push_str(&format!(" ({})", trigger.describe()));
+            }
+        }
+        if let Some(exemplar) = warning.exemplar {
+            output.push_str(&format!(" # {{{exemplar}}}"));
+        }
+        output
+    }
+
+    /// Emits the message of a logging call, unless it only checks the limit.
+    fn emit_detail(
+        &mut self,
+        key: &str,
+        detail: &mut Detail<'_>,
+        severity: Severity,
+        annotation: Option<Annotation>,
+    ) {
+        if detail.is_check() {
+            return;
+        }
+        let text = detail.resolve();
+        let location = self.location;
+        self.emit_message(
+            text.as_deref().unwrap_or(key),
+            severity,
+            annotation,
+            location.as_ref(),
+        );
+    }
+
+    /// Writes a message straight to the sink without any bookkeeping, see
+    /// [`RateLog::with_enabled`].
+    fn write_through(&mut self, key: &str, detail: Detail<'_>, severity: Severity) -> LogOutcome {
+        let msg = match detail {
+            Detail::Key => Cow::Borrowed(key),
+            Detail::Text(text) => Cow::Borrowed(text),
+            Detail::Lazy(build) => Cow::Owned(build()),
+            Detail::Check => return LogOutcome::Emitted,
+        };
+        let event = Event::Emitted {
+            msg: Cow::Borrowed(&msg),
+            severity,
+        };
+        self.sink.emit_event(&event, &msg);
+        LogOutcome::Emitted
+    }
+
+    /// Re-emits a message that reached the escalation threshold and reports it.
+    fn escalate(&mut self, key: &str, text: &str, now: Instant) {
+        let elapsed = self
+            .current
+            .started
+            .map_or(Duration::ZERO, |started| now.duration_since(started));
+        if let Some(callback) = &mut self.limit_callback {
+            callback(key, self.current.repeats as u32, elapsed);
+        }
+        let event = Event::Escalated {
+            msg: Cow::Borrowed(text),
+        };
+        self.emit_notice_event("escalated", &format!("ESCALATED: {text}"), &event);
+    }
+
+    /// Forgets the tracked message and clears all counters.
+    fn reset_state(&mut self) {
+        self.key.clear();
+        self.key_hash = key_hash("");
+        self.key_limit = None;
+        self.current.reset();
+        self.exemplar = None;
+        self.location = None;
+        self.last_message = None;
+    }
+
+    /// Emits a logged message in the configured output format, with the location it
+    /// was logged from if known.
+    fn emit_message(
+        &mut self,
+        msg: &str,
+        severity: Severity,
+        annotation: Option<Annotation>,
+        location: Option<&Location>,
+    ) {
+        let line = self.output_format.message(msg, annotation, location);
+        let event = Event::Emitted {
+            msg: Cow::Borrowed(msg),
+            severity,
+        };
+        self.emit(line, &event);
+    }
+
+    /// Emits a notice of the given kind in the configured output format.
+    fn emit_notice(&mut self, kind: &'static str, text: String) {
+        let event = Event::Notice {
+            kind,
+            text: Cow::Borrowed(&text),
+        };
+        self.emit_notice_event(kind, &text, &event);
+    }
+
+    /// Emits a notice of the given kind in the configured output format, described
+    /// by `event`.
+    fn emit_notice_event(&mut self, kind: &str, text: &str, event: &Event<'_>) {
+        let line = self.output_format.notice(kind, text);
+        #[cfg(feature = "color")]
+        let line = match kind {
+            "escalated" => self.paint(Style::Escalation, line),
+            _ => self.paint(Style::Warning, line),
+        };
+        self.emit(line, event);
+    }
+
+    /// Emits a rendered rate limit warning or summary.
+    fn emit_warning(&mut self, line: String, event: &Event<'_>) {
+        #[cfg(feature = "color")]
+        let line = self.paint(Style::Warning, line);
+        self.emit(line, event);
+    }
+
+    /// Colors a line that is not a logged message, if colors are enabled.
+    #[cfg(feature = "color")]
+    fn paint(&self, style: Style, line: String) -> String {
+        if self.color && self.output_format == OutputFormat::Text {
+            style.paint(line)
+        } else {
+            line
+        }
+    }
+
+    /// Emits the warnings held back during the current coalescing tick, if any.
+    fn emit_coalesced(&mut self) {
+        match self.coalescer.as_mut().and_then(|c| c.finish()) {
+            Some(Coalesced::Single(line, event)) => self.emit_warning(line, &event),
+            Some(Coalesced::Group(block)) => self.emit_notice("coalesced", block),
+            None => {}
+        }
+    }
+
+    /// Writes a line to the sink unless output is paused or the global budget is exhausted.
+    fn emit(&mut self, line: String, event: &Event<'_>) {
+        if let Some(discarded) = &mut self.paused {
+            *discarded += 1;
+            return;
+        }
+        if let Some(budget) = self.budget.as_mut().filter(|_| !self.critical) {
+            match budget.admit(self.clock.last()) {
+                Admission::Emit => {}
+                Admission::EmitAfterSuppressed(suppressed) => self.emit_budget_notice(suppressed),
+                Admission::Suppress => return,
+            }
+        }
+        self.write(line, event);
+    }
+
+    /// Reports lines dropped by the global budget, bypassing the budget itself.
+    fn emit_budget_notice(&mut self, suppressed: u64) {
+        let notice = format!("global rate limit reached, suppressed {suppressed} lines");
+        let line = self.output_format.notice("global_limit", &notice);
+        #[cfg(feature = "color")]
+        let line = self.paint(Style::Warning, line);
+        let event = Event::Notice {
+            kind: "global_limit",
+            text: Cow::Borrowed(&notice),
+        };
+        self.write(line, &event);
+    }
+
+    /// Writes a line to the sink and records it in the history, if enabled.
+    fn write(&mut self, line: String, event: &Event<'_>) {
+        self.sink.emit_event(event, &line);
+        if let Some(adaptive) = &mut self.adaptive {
+            adaptive.record(self.clock.last());
+        }
+
+        if self.history_capacity > 0 {
+            if self.history.len() == self.history_capacity {
+                self.history.pop_front();
+            }
+            self.history.push_back(line);
+        }
+    }
+}
+
+impl<S: Sink> fmt::Display for RateLog<S> {
+    /// Renders the current state for debugging, one line per key.
+    ///
+    /// The first line names the limit, followed by the tracked message with its
+    /// pending repeats and how close they are to the limit, and one line per key seen
+    /// so far with its occurrences, in key order. Times are relative to the last logged
+    /// message. The exact wording may change between
+    /// releases; use [`RateLog::stats`] for values to compare against.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(10));
+    ///
+    /// logger.log("Disk full");
+    /// logger.log("Disk full");
+    /// println!("{logger}");
+    /// // Prints:
+    /// // limit: warn after 10 repeats
+    /// // tracking "Disk full": 1 pending over 0ms (1/10 repeats)
+    /// // "Disk full": 2 occurrences, 1 suppressed, last seen 0ms ago
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let now = self.clock.peek();
+        write!(f, "limit: {}", self.active_limit())?;
+        if self.is_paused() {
+            write!(f, " (paused)")?;
+        }
+        if !self.key.is_empty() {
+            write!(
+                f,
+                "\ntracking \"{}\": {} pending over {} ({})",
+                self.key,
+                self.current.count,
+                self.duration_style.format(self.current.duration),
+                self.current.progress(self.active_limit(), now)
+            )?;
+        }
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        for (key, entry) in entries {
+            write!(
+                f,
+                "\n\"{key}\": {} occurrences, {} suppressed, last seen {} ago",
+                entry.occurrences,
+                entry.suppressed,
+                self.duration_style
+                    .format(now.saturating_duration_since(entry.last_seen))
+            )?;
+            if let Some(held) = entry.held {
+                write!(f, ", {} repeats held back", held.count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S: Sink> fmt::Debug for RateLog<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLog")
+            .field("limit", &self.limit)
+            .field("key_limit", &self.key_limit)
+            .field("key", &self.key)
+            .field("pending", &self.current.count)
+            .field("duration", &self.current.duration)
+            .field("tracked_keys", &self.entries.len())
+            .field("paused", &self.is_paused())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: Sink> Drop for RateLog<S> {
+    /// Flushes pending repeats so the information that spam occurred is never lost.
+    fn drop(&mut self) {
+        // Avoid turning a panic inside a sink into an abort
+        #[cfg(feature = "std")]
+        if std::thread::panicking() {
+            return;
+        }
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_log_exceed_time() {
+        let mut rate_log = RateLog::with_sink(Limit::Rate(3), CaptureSink::new());
+
+        // First call - should not exceed
+        rate_log.log("message1");
+        assert_eq!(rate_log.sink().lines(), ["message1"]);
+        rate_log.sink_mut().clear();
+
+        // Second call - should not exceed (current becomes 1, limit is 3)
+        rate_log.log("message1");
+        assert!(rate_log.sink().lines().is_empty());
+
+        // Third call - should not exceed (current becomes 2, limit is 3)
+        rate_log.log("message1");
+        assert!(rate_log.sink().lines().is_empty());
+
+        // Fourth call - should exceed (current becomes 3, limit is 3)
+        rate_log.log("message1");
+        assert_eq!(
+            rate_log.sink().lines(),
+            ["Message: \"message1\" repeat for 3 times in the past 0ms"]
+        );
+        rate_log.sink_mut().clear();
+
+        // Fifth call - should not exceed (current becomes 1, limit is 3)
+        rate_log.log("message1");
+        assert!(rate_log.sink().lines().is_empty());
+
+        // Sixth call - should not exceed (current becomes 2, limit is 3)
+        rate_log.log("message1");
+        assert!(rate_log.sink().lines().is_empty());
+
+        // Seventh call - should exceed (current becomes 3, limit is 3)
+        rate_log.log("message1");
+        assert_eq!(
+            rate_log.sink().lines(),
+            ["Message: \"message1\" repeat for 3 times in the past 0ms"]
+        );
+        rate_log.sink_mut().clear();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_rate_log_exceed_duration() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(
+            Limit::Duration(Duration::from_millis(50)),
+            CaptureSink::new(),
+        )
+        .with_clock(clock.clone());
+
+        // First call
+        rate_log.log("message2");
+        assert_eq!(rate_log.sink().lines(), ["message2"]);
+        rate_log.sink_mut().clear();
+
+        // Second call after short delay - should not exceed
+        clock.advance(Duration::from_millis(20));
+        rate_log.log("message2");
+        assert!(rate_log.sink().lines().is_empty());
+
+        // Third call after longer delay - should exceed the 50ms limit
+        clock.advance(Duration::from_millis(40));
+        rate_log.log("message2");
+        assert_eq!(
+            rate_log.sink().lines(),
+            ["Message: \"message2\" repeat for 2 times in the past 60ms"]
+        );
+        rate_log.sink_mut().clear();
+
+        rate_log.log("message2");
+        assert!(rate_log.sink().lines().is_empty());
+
+        clock.advance(Duration::from_millis(50));
+        rate_log.log("message2");
+        assert_eq!(
+            rate_log.sink().lines(),
+            ["Message: \"message2\" repeat for 2 times in the past 50ms"]
+        );
+        rate_log.sink_mut().clear();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_rate_log_exceed_combined() {
+        let clock = MockClock::new();
+        // Any: the count threshold is reached long before the duration one
+        let mut rate_log =
+            RateLog::with_sink(Limit::Any(2, Duration::from_secs(60)), CaptureSink::new())
+                .with_clock(clock.clone());
+        rate_log.log("message3");
+        rate_log.log("message3");
+        rate_log.log("message3");
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message3",
+                "Message: \"message3\" repeat for 2 times in the past 0ms (count limit reached)"
+            ]
+        );
+
+        // Any: the duration threshold is reached before the count one
+        let mut rate_log = RateLog::with_sink(
+            Limit::Any(100, Duration::from_millis(30)),
+            CaptureSink::new(),
+        )
+        .with_clock(clock.clone());
+        rate_log.log("message3");
+        clock.advance(Duration::from_millis(30));
+        rate_log.log("message3");
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message3",
+                "Message: \"message3\" repeat for 1 times in the past 30ms (duration limit reached)"
+            ]
+        );
+
+        // All: reaching the count alone is not enough
+        let mut rate_log =
+            RateLog::with_sink(Limit::All(2, Duration::from_millis(30)), CaptureSink::new())
+                .with_clock(clock.clone());
+        rate_log.log("message3");
+        rate_log.log("message3");
+        rate_log.log("message3");
+        assert_eq!(rate_log.sink().lines(), ["message3"]);
+
+        clock.advance(Duration::from_millis(30));
+        assert!(matches!(
+            rate_log.log("message3"),
+            LogOutcome::LimitReported {
+                count: 3,
+                trigger: Trigger::CountAndDuration,
+                ..
+            }
+        ));
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message3",
+                "Message: \"message3\" repeat for 3 times in the past 30ms (count and duration limits reached)"
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_trigger_in_events() {
+        let clock = MockClock::new();
+        // Any: both thresholds reached by the same repeat
+        let mut rate_log =
+            RateLog::with_sink(Limit::Any(1, Duration::from_millis(10)), CaptureSink::new())
+                .with_clock(clock.clone());
+        rate_log.log("message1");
+        clock.advance(Duration::from_millis(10));
+        assert!(matches!(
+            rate_log.log("message1"),
+            LogOutcome::LimitReported {
+                trigger: Trigger::CountAndDuration,
+                ..
+            }
+        ));
+        // Consumers of typed events see the same trigger as the line
+        assert!(matches!(
+            rate_log.sink().records()[1],
+            Event::Summary {
+                kind: WarningKind::LimitExceeded(Trigger::CountAndDuration),
+                ..
+            }
+        ));
+
+        // Plain limits report their trigger without naming it in the line
+        let mut rate_log =
+            RateLog::with_sink(Limit::Rate(1), CaptureSink::new()).with_clock(clock.clone());
+        rate_log.log("message1");
+        assert!(matches!(
+            rate_log.log("message1"),
+            LogOutcome::LimitReported {
+                trigger: Trigger::Count,
+                ..
+            }
+        ));
+        assert_eq!(
+            rate_log.sink().lines()[1],
+            "Message: \"message1\" repeat for 1 times in the past 0ms"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_rate_log_exceed_window() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(
+            Limit::Window {
+                max: 2,
+                per: Duration::from_millis(50),
+            },
+            CaptureSink::new(),
+        )
+        .with_clock(clock.clone());
+
+        // The first two messages of the window are emitted verbatim
+        assert_eq!(rate_log.log("message6"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message6"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message6"), LogOutcome::Suppressed);
+        assert_eq!(rate_log.log("message6"), LogOutcome::Suppressed);
+
+        // Once the window has room again, one summary replaces the next message
+        clock.advance(Duration::from_millis(50));
+        assert!(matches!(
+            rate_log.log("message6"),
+            LogOutcome::LimitReported {
+                count: 3,
+                trigger: Trigger::Window,
+                ..
+            }
+        ));
+        assert_eq!(rate_log.log("message6"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message6"), LogOutcome::Suppressed);
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message6",
+                "message6",
+                "Message: \"message6\" repeat for 3 times in the past 50ms",
+                "message6"
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_rate_log_exceed_bytes() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(
+            Limit::Bytes {
+                max: 20,
+                per: Duration::from_millis(50),
+            },
+            CaptureSink::new(),
+        )
+        .with_clock(clock.clone());
+
+        // Two 8 byte messages fit into the budget, a third one does not
+        assert_eq!(rate_log.log("message6"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message6"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message6"), LogOutcome::Suppressed);
+        assert_eq!(rate_log.log("message6"), LogOutcome::Suppressed);
+
+        clock.advance(Duration::from_millis(50));
+        assert!(matches!(
+            rate_log.log("message6"),
+            LogOutcome::LimitReported {
+                count: 3,
+                trigger: Trigger::Bytes,
+                ..
+            }
+        ));
+        assert_eq!(rate_log.log("message6"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message6"), LogOutcome::Suppressed);
+        rate_log.flush();
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message6",
+                "message6",
+                "Message: \"message6\" repeat for 3 times in the past 50ms, dropped 24 bytes",
+                "message6",
+                "Message: \"message6\" suppressed 1 repeats over 0ms, dropped 8 bytes"
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_bytes_oversized_message() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(
+            Limit::Bytes {
+                max: 4,
+                per: Duration::from_millis(50),
+            },
+            CaptureSink::new(),
+        )
+        .with_clock(clock.clone());
+
+        // Longer than the whole budget: emitted once per window, not suppressed for good
+        assert_eq!(rate_log.log("message6"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message6"), LogOutcome::Suppressed);
+        clock.advance(Duration::from_millis(49));
+        assert_eq!(rate_log.log("message6"), LogOutcome::Suppressed);
+
+        clock.advance(Duration::from_millis(1));
+        assert!(matches!(
+            rate_log.log("message6"),
+            LogOutcome::LimitReported {
+                count: 3,
+                trigger: Trigger::Bytes,
+                ..
+            }
+        ));
+        assert_eq!(rate_log.log("message6"), LogOutcome::Suppressed);
+
+        // A zero budget behaves the same
+        let mut rate_log = RateLog::with_sink(
+            Limit::Bytes {
+                max: 0,
+                per: Duration::from_millis(50),
+            },
+            CaptureSink::new(),
+        )
+        .with_clock(clock.clone());
+        assert_eq!(rate_log.log("message6"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message6"), LogOutcome::Suppressed);
+        clock.advance(Duration::from_millis(50));
+        assert_ne!(rate_log.log("message6"), LogOutcome::Suppressed);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_rate_log_exceed_gcra() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(
+            Limit::Gcra {
+                period: Duration::from_millis(10),
+                burst: 2,
+            },
+            CaptureSink::new(),
+        )
+        .with_clock(clock.clone());
+
+        // A burst of two, then one message per period
+        assert_eq!(rate_log.log("message6"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message6"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message6"), LogOutcome::Suppressed);
+        clock.advance(Duration::from_millis(5));
+        assert_eq!(rate_log.log("message6"), LogOutcome::Suppressed);
+        clock.advance(Duration::from_millis(5));
+        assert!(matches!(
+            rate_log.log("message6"),
+            LogOutcome::LimitReported {
+                count: 3,
+                trigger: Trigger::Pacing,
+                ..
+            }
+        ));
+        assert_eq!(rate_log.log("message6"), LogOutcome::Suppressed);
+        clock.advance(Duration::from_millis(10));
+        assert_eq!(
+            rate_log.log("message6"),
+            LogOutcome::LimitReported {
+                count: 2,
+                duration: Duration::from_millis(10),
+                trigger: Trigger::Pacing,
+            }
+        );
+        clock.advance(Duration::from_millis(10));
+        assert_eq!(rate_log.log("message6"), LogOutcome::Emitted);
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message6",
+                "message6",
+                "Message: \"message6\" repeat for 3 times in the past 10ms",
+                "Message: \"message6\" repeat for 2 times in the past 10ms",
+                "message6"
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gcra_huge_period() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(
+            Limit::Gcra {
+                period: Duration::MAX,
+                burst: u32::MAX,
+            },
+            CaptureSink::new(),
+        )
+        .with_clock(clock.clone());
+
+        // The next arrival lies past the end of the time line: suppressed for good
+        // instead of overflowing
+        assert_eq!(rate_log.log("message6"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message6"), LogOutcome::Suppressed);
+        clock.advance(Duration::from_secs(3600));
+        assert_eq!(rate_log.log("message6"), LogOutcome::Suppressed);
+
+        // A new message starts over
+        assert_eq!(rate_log.log("message7"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message7"), LogOutcome::Suppressed);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_rate_log_exceed_token_bucket() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(
+            Limit::TokenBucket {
+                capacity: 2,
+                refill_per_sec: 20.0,
+            },
+            CaptureSink::new(),
+        )
+        .with_clock(clock.clone());
+
+        // A burst up to the capacity is emitted verbatim
+        assert_eq!(rate_log.log("message7"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message7"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message7"), LogOutcome::Suppressed);
+
+        // One token is refilled every 50ms
+        clock.advance(Duration::from_millis(50));
+        assert!(matches!(
+            rate_log.log("message7"),
+            LogOutcome::LimitReported {
+                count: 2,
+                trigger: Trigger::Tokens,
+                ..
+            }
+        ));
+        assert_eq!(rate_log.log("message7"), LogOutcome::Suppressed);
+
+        clock.advance(Duration::from_millis(50));
+        assert!(matches!(
+            rate_log.log("message7"),
+            LogOutcome::LimitReported { count: 2, .. }
+        ));
+        clock.advance(Duration::from_millis(50));
+        assert_eq!(rate_log.log("message7"), LogOutcome::Emitted);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_degenerate_token_buckets() {
+        let clock = MockClock::new();
+        let bucket = |capacity, refill_per_sec| {
+            let mut rate_log = RateLog::with_sink(
+                Limit::TokenBucket {
+                    capacity,
+                    refill_per_sec,
+                },
+                CaptureSink::new(),
+            )
+            .with_clock(clock.clone());
+            (0..3)
+                .map(|_| {
+                    let outcome = rate_log.log("message1");
+                    clock.advance(Duration::from_secs(1));
+                    outcome == LogOutcome::Emitted
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // A capacity of 0 behaves like 1
+        assert_eq!(bucket(0, 1.0), [true, true, true]);
+        // NaN and negative rates never refill, instead of emitting everything
+        assert_eq!(bucket(1, f64::NAN), [true, false, false]);
+        assert_eq!(bucket(1, -1.0), [true, false, false]);
+        // An infinite rate refills the whole bucket once time passes, but not before
+        assert_eq!(bucket(1, f64::INFINITY), [true, true, true]);
+        let mut rate_log = RateLog::with_sink(
+            Limit::TokenBucket {
+                capacity: 1,
+                refill_per_sec: f64::INFINITY,
+            },
+            CaptureSink::new(),
+        )
+        .with_clock(clock.clone());
+        rate_log.log("message1");
+        assert_eq!(rate_log.log("message1"), LogOutcome::Suppressed);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_rate_log_burst_then_steady() {
+        let clock = MockClock::new();
+        let limit = Limit::BurstThenSteady {
+            burst: 2,
+            interval: Duration::from_secs(10),
+        };
+        let mut rate_log = RateLog::with_sink(limit, CaptureSink::new()).with_clock(clock.clone());
+
+        for _ in 0..5 {
+            rate_log.log("message11");
+            clock.advance(Duration::from_secs(3));
+        }
+        // 15s after the first occurrence, 12s after the last emitted one
+        assert_eq!(rate_log.log("message11"), LogOutcome::Emitted);
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(rate_log.log("message11"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message11"), LogOutcome::Suppressed);
+        rate_log.flush();
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message11",
+                "message11",
+                "message11 (+3 more)",
+                "message11",
+                "Message: \"message11\" suppressed 1 repeats over 0ms"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rate_log_sample() {
+        let mut rate_log = RateLog::with_sink(Limit::Sample(3), CaptureSink::new());
+
+        let outcomes: Vec<_> = (0..8).map(|_| rate_log.log("message10")).collect();
+        let emitted: Vec<_> = outcomes
+            .iter()
+            .enumerate()
+            .filter(|(_, outcome)| **outcome == LogOutcome::Emitted)
+            .map(|(i, _)| i + 1)
+            .collect();
+        assert_eq!(emitted, [1, 4, 7]);
+
+        rate_log.flush();
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message10",
+                "message10 (sampled 1/3)",
+                "message10 (sampled 1/3)",
+                "Message: \"message10\" suppressed 1 repeats over 0ms"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rate_log_exponential() {
+        let mut rate_log = RateLog::with_sink(Limit::Exponential { base: 3 }, CaptureSink::new());
+
+        let outcomes: Vec<_> = (0..10).map(|_| rate_log.log("message8")).collect();
+        let emitted: Vec<_> = outcomes
+            .iter()
+            .enumerate()
+            .filter(|(_, outcome)| **outcome == LogOutcome::Emitted)
+            .map(|(i, _)| i + 1)
+            .collect();
+        assert_eq!(emitted, [1, 3, 9]);
+
+        rate_log.log("message9");
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message8",
+                "message8 (seen 3 times)",
+                "message8 (seen 9 times)",
+                "Message: \"message8\" suppressed 1 repeats over 0ms",
+                "message9"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_duration() {
+        // Test milliseconds (< 1 second)
+        let duration_ms = Duration::from_millis(500);
+        assert_eq!(format_duration(duration_ms), "500ms");
+
+        // Test seconds only (>= 1 second, < 1 minute)
+        let duration_s = Duration::from_secs(45);
+        assert_eq!(format_duration(duration_s), "45s");
+
+        // Test minutes and seconds (>= 1 minute, < 1 hour)
+        let duration_min = Duration::from_secs(3 * 60 + 25); // 3 minutes 25 seconds
+        assert_eq!(format_duration(duration_min), "3m25s");
+
+        // Test hours and minutes (>= 1 hour)
+        let duration_hour = Duration::from_secs(2 * 3600 + 45 * 60); // 2 hours 45 minutes
+        assert_eq!(format_duration(duration_hour), "2h45m");
+    }
+
+    #[test]
+    fn test_log_outcome() {
+        let mut rate_log = RateLog::with_sink(Limit::Rate(2), CaptureSink::new());
+
+        assert_eq!(rate_log.log("message4"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message4"), LogOutcome::Suppressed);
+        assert!(matches!(
+            rate_log.log("message4"),
+            LogOutcome::LimitReported { count: 2, .. }
+        ));
+        assert_eq!(rate_log.log("message4"), LogOutcome::Suppressed);
+        assert_eq!(rate_log.log("message5"), LogOutcome::Emitted);
+    }
+
+    #[test]
+    fn test_owned_messages() {
+        let mut rate_log = RateLog::with_sink(Limit::Rate(3), CaptureSink::new());
+        let shared: alloc::sync::Arc<str> = "message1".into();
+
+        rate_log.log("message1");
+        rate_log.log(String::from("message1"));
+        rate_log.log(Cow::Borrowed("message1"));
+        rate_log.log(&shared);
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" repeat for 3 times in the past 0ms"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_log_keyed() {
+        let mut rate_log = RateLog::with_sink(Limit::Rate(2), CaptureSink::new());
+
+        rate_log.log_keyed("connect", "failed to connect to 10.0.0.1");
+        rate_log.log_keyed("connect", "failed to connect to 10.0.0.2");
+        rate_log.log_keyed("connect", "failed to connect to 10.0.0.3");
+        rate_log.log_keyed("timeout", "request 7 timed out");
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "failed to connect to 10.0.0.1",
+                "Key: \"connect\" repeat for 2 times in the past 0ms, last message: \"failed to connect to 10.0.0.3\"",
+                "request 7 timed out"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_log_here() {
+        let mut rate_log = RateLog::with_sink(Limit::Rate(2), CaptureSink::new());
+
+        let line = line!() + 2;
+        for port in [8080, 8081, 8082] {
+            rate_log.log_here(&format!("port {port} unreachable"));
+        }
+        rate_log_here!(rate_log, "port {} unreachable", 8083);
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "port 8080 unreachable".to_string(),
+                format!("Key: \"{}:{line}\" repeat for 2 times in the past 0ms, last message: \"port 8082 unreachable\"", file!()),
+                "port 8083 unreachable".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_log_item_renders_lazily() {
+        struct Timeout<'a> {
+            url: &'a str,
+            rendered: &'a core::cell::Cell<u32>,
+        }
+
+        impl fmt::Display for Timeout<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.rendered.set(self.rendered.get() + 1);
+                write!(f, "timeout fetching {}", self.url)
+            }
+        }
+
+        impl RateKey for Timeout<'_> {
+            fn rate_key(&self) -> Cow<'_, str> {
+                Cow::Borrowed("Timeout")
+            }
+        }
+
+        let rendered = core::cell::Cell::new(0);
+        let mut rate_log = RateLog::with_sink(Limit::Rate(3), CaptureSink::new());
+        for url in ["a", "b", "c", "d", "e"] {
+            rate_log.log_item(&Timeout {
+                url,
+                rendered: &rendered,
+            });
+        }
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "timeout fetching a",
+                "Key: \"Timeout\" repeat for 3 times in the past 0ms, last message: \"timeout fetching d\""
+            ]
+        );
+        // Each call compares the text against the key once, stopping at the first
+        // mismatching piece; only the two emitted messages are rendered in full
+        assert_eq!(rendered.get(), 5 + 2);
+
+        // Items whose text is their key are tracked as plain messages
+        let mut rate_log = RateLog::with_sink(Limit::Rate(2), CaptureSink::new());
+        rate_log.log_item("Disk full");
+        rate_log.log_item(&String::from("Disk full"));
+        rate_log.log_item("Disk full");
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "Disk full",
+                "Message: \"Disk full\" repeat for 2 times in the past 0ms"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_displays_as() {
+        assert!(displays_as("Disk full", "Disk full"));
+        assert!(displays_as(
+            &format_args!("{} {}", "Disk", "full"),
+            "Disk full"
+        ));
+        assert!(displays_as("", ""));
+        assert!(!displays_as("Disk full", "Disk"));
+        assert!(!displays_as("Disk", "Disk full"));
+        assert!(!displays_as(
+            &format_args!("{} {}", "Disk", "empty"),
+            "Disk full"
+        ));
+    }
+
+    #[test]
+    fn test_location_key() {
+        let key = LocationKey::new("src/main.rs", 42);
+        assert_eq!(key.as_str(), "src/main.rs:42");
+        assert!(key.spilled.is_none());
+
+        // Exactly filling the buffer stays on the stack
+        let file = "a".repeat(LocationKey::CAPACITY - 3);
+        let key = LocationKey::new(&file, 42);
+        assert_eq!(key.as_str(), format!("{file}:42"));
+        assert!(key.spilled.is_none());
+
+        // Longer paths fall back to the heap instead of being truncated
+        let file = "a".repeat(LocationKey::CAPACITY);
+        let key = LocationKey::new(&file, 7);
+        assert_eq!(key.as_str(), format!("{file}:7"));
+        assert!(key.spilled.is_some());
+
+        let file = "é".repeat(LocationKey::CAPACITY / 2);
+        assert_eq!(LocationKey::new(&file, 1).as_str(), format!("{file}:1"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_log_at() {
+        let mut rate_log =
+            RateLog::with_sink(Limit::Rate(3), CaptureSink::new()).with_clock(MockClock::new());
+        let db = Location::new("src/db.rs", 42, Some("app::db"));
+
+        rate_log.log_at("message1", db);
+        for _ in 0..3 {
+            rate_log.log_at("message1", db);
+        }
+        rate_log.log_at("message1", Location::new("src/db.rs", 50, None));
+        rate_log.log("message2");
+        rate_log.log("message2");
+        rate_log.flush();
+
+        let here = location!();
+        assert_eq!(here.line(), line!() - 1);
+        assert_eq!(here.module_path(), Some(module_path!()));
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1 (at src/db.rs:42 in app::db)",
+                "Message: \"message1\" repeat for 3 times in the past 0ms, at src/db.rs:42 in app::db",
+                "Message: \"message1\" suppressed 1 repeats over 0ms, at src/db.rs:50",
+                "message2",
+                "Message: \"message2\" suppressed 1 repeats over 0ms",
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_limiter() {
+        // Emits every third occurrence of a key and reports the others every fifth
+        let mut seen = Map::<String, u32>::new();
+        let limiter = move |_now: Instant, key: &str| {
+            let seen = seen.entry(key.to_string()).or_default();
+            *seen += 1;
+            match *seen {
+                seen if seen % 3 == 1 => Decision::Emit,
+                seen if seen % 5 == 0 => Decision::Report(Trigger::Count),
+                _ => Decision::Suppress,
+            }
+        };
+        let mut rate_log = RateLogBuilder::new()
+            .limiter(limiter)
+            .sink(CaptureSink::new())
+            .clock(MockClock::new())
+            .build();
+
+        for _ in 0..7 {
+            rate_log.log("message1");
+        }
+        // Explicit limits take precedence over the limiter
+        rate_log.log_with_limit("message2", &Limit::Never);
+        rate_log.log_with_limit("message2", &Limit::Never);
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "message1",
+                "Message: \"message1\" repeat for 3 times in the past 0ms",
+                "message1",
+                "Message: \"message1\" suppressed 1 repeats over 0ms",
+                "message2",
+                "message2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_announce() {
+        let mut rate_log =
+            RateLog::with_sink(Limit::Any(100, Duration::from_secs(30)), CaptureSink::new());
+        rate_log.announce();
+        rate_log.log("message1");
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "rate-log: rate limiting active, warn after 100 repeats or 30s of repeats, whichever comes first",
+                "message1"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_flush_pending_repeats() {
+        let mut capture = CaptureSink::new();
+        {
+            let mut rate_log = RateLog::with_sink(Limit::Rate(3), &mut capture);
+
+            rate_log.log("message1");
+            rate_log.log("message1");
+            rate_log.log("message1");
+            // Switching messages reports the two pending repeats
+            rate_log.log("message2");
+            rate_log.log("message2");
+            rate_log.flush();
+            // Nothing pending anymore
+            rate_log.flush();
+            rate_log.log("message2");
+            rate_log.log_keyed("key", "message3");
+            rate_log.log_keyed("key", "message4");
+            // Dropping reports the pending repeat of "key"
+        }
+
+        assert_eq!(
+            capture.lines(),
+            [
+                "message1",
+                "Message: \"message1\" suppressed 2 repeats over 0ms",
+                "message2",
+                "Message: \"message2\" suppressed 1 repeats over 0ms",
+                "Message: \"message2\" suppressed 1 repeats over 0ms",
+                "message3",
+                "Key: \"key\" suppressed 1 repeats over 0ms"
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_quiet_key() {
+        let mut rate_log = RateLog::builder()
+            .limit(Limit::Rate(2))
+            .sink(CaptureSink::new())
+            .quiet_key("noise")
+            .build();
+
+        assert_eq!(rate_log.log("noise"), LogOutcome::Suppressed);
+        assert_eq!(rate_log.log("noise"), LogOutcome::Suppressed);
+        rate_log.log("noise");
+        rate_log.log("signal");
+        rate_log.log_keyed("noise", "noise from keyed call");
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "Message: \"noise\" repeat for 2 times in the past 0ms",
+                "signal"
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_emit_first() {
+        let mut rate_log = RateLog::builder()
+            .limit(Limit::Rate(2))
+            .sink(CaptureSink::new())
+            .clock(MockClock::new())
+            .emit_first(false)
+            .build();
+
+        assert_eq!(rate_log.log("message1"), LogOutcome::Suppressed);
+        rate_log.log("message1");
+        rate_log.log("message1");
+        rate_log.log("message2");
+        rate_log.log("message2");
+        assert_eq!(rate_log.log_critical("message3"), LogOutcome::Emitted);
+        rate_log.flush();
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "Message: \"message1\" repeat for 2 times in the past 0ms",
+                "Message: \"message2\" suppressed 1 repeats over 0ms",
+                "message3",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_alternation_batching() {
+        let mut rate_log = RateLog::with_sink(Limit::Rate(10), CaptureSink::new())
+            .with_alternation_batching(2, Duration::from_secs(60));
+
+        for _ in 0..3 {
+            rate_log.log("message1");
+            rate_log.log("message2");
+        }
+        rate_log.log("message3");
+        rate_log.log("message1");
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "message2",
+                "2 messages alternated 4 times in the past 0ms",
+                "message3"
+            ]
+        );
+        // Returning to a recent message starts a new batch
+        assert_eq!(rate_log.log("message3"), LogOutcome::Suppressed);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_dedup_window() {
+        let mut rate_log = RateLog::with_sink(Limit::Rate(2), CaptureSink::new())
+            .with_dedup_window(DedupWindow::Messages(1));
+
+        for _ in 0..3 {
+            rate_log.log("message1");
+            rate_log.log("message2");
+        }
+        rate_log.log("message3");
+        rate_log.log("message1");
+        rate_log.flush();
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "message2",
+                "Message: \"message1\" repeat for 2 times in the past 0ms",
+                "Message: \"message2\" repeat for 2 times in the past 0ms",
+                "message3",
+                "message1",
+            ]
+        );
+
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(Limit::Rate(10), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_dedup_window(DedupWindow::Time(Duration::from_secs(5)));
+
+        for key in ["message1", "message2", "message3", "message1"] {
+            rate_log.log(key);
+            clock.advance(Duration::from_secs(1));
+        }
+        clock.advance(Duration::from_secs(5));
+        rate_log.log("message2");
+        clock.advance(Duration::from_secs(6));
+        rate_log.log("message3");
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "message2",
+                "message3",
+                "message2",
+                "Message: \"message1\" suppressed 1 repeats over 3s",
+                "message3",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_top_offenders() {
+        let mut rate_log = RateLog::with_sink(Limit::Rate(100), CaptureSink::new())
+            .with_severity_weight(Severity::Error, 10.0);
+
+        for _ in 0..21 {
+            rate_log.log_with_severity(Severity::Debug, "message1");
+        }
+        for _ in 0..3 {
+            rate_log.log_with_severity(Severity::Error, "message2");
+        }
+        for _ in 0..6 {
+            rate_log.log("message3");
+        }
+        rate_log.log("message4");
+
+        let keys: Vec<_> = rate_log
+            .top_offenders(5)
+            .into_iter()
+            .map(|offender| (offender.key, offender.suppressed, offender.score))
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                ("message1".to_string(), 20, 20.0),
+                ("message2".to_string(), 2, 20.0),
+                ("message3".to_string(), 5, 5.0)
+            ]
+        );
+
+        rate_log.sink_mut().clear();
+        rate_log.report_top_offenders(2);
+        assert_eq!(
+            rate_log.sink().lines(),
+            ["Top offenders: 1. \"message1\" [debug] 20 suppressed (score 20), 2. \"message2\" [error] 2 suppressed (score 20)"]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_grace_period() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(Limit::Rate(2), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_grace_period(Duration::from_millis(50));
+
+        assert_eq!(rate_log.log("message1"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message1"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message1"), LogOutcome::Emitted);
+
+        clock.advance(Duration::from_millis(50));
+        assert_eq!(rate_log.log("message1"), LogOutcome::Suppressed);
+        assert!(matches!(
+            rate_log.log("message1"),
+            LogOutcome::LimitReported { count: 2, .. }
+        ));
+        assert_eq!(rate_log.sink().lines().len(), 4);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_clock_read_interval() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(
+            Limit::Duration(Duration::from_millis(20)),
+            CaptureSink::new(),
+        )
+        .with_clock(clock.clone())
+        .with_clock_read_interval(3);
+
+        // Calls 2 and 3 reuse the reading of call 1, so no time passes for them
+        rate_log.log("message1");
+        clock.advance(Duration::from_millis(25));
+        assert_eq!(rate_log.log("message1"), LogOutcome::Suppressed);
+        assert_eq!(rate_log.log("message1"), LogOutcome::Suppressed);
+
+        // Call 4 reads the clock again
+        assert!(matches!(
+            rate_log.log("message1"),
+            LogOutcome::LimitReported { count: 3, .. }
+        ));
+    }
+
+    /// Counts the readings of a [`MockClock`].
+    #[derive(Clone)]
+    #[cfg(feature = "std")]
+    struct CountingClock {
+        clock: MockClock,
+        reads: alloc::sync::Arc<core::sync::atomic::AtomicU32>,
+    }
+
+    #[cfg(feature = "std")]
+    impl CountingClock {
+        fn new(clock: MockClock) -> Self {
+            Self {
+                clock,
+                reads: Default::default(),
+            }
+        }
+
+        /// Returns the readings since the last call.
+        fn take_reads(&self) -> u32 {
+            self.reads.swap(0, core::sync::atomic::Ordering::Relaxed)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl Clock for CountingClock {
+        fn now(&self) -> Instant {
+            self.reads
+                .fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+            self.clock.now()
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_lazy_clock() {
+        let clock = MockClock::new();
+        let counting = CountingClock::new(clock.clone());
+        let mut rate_log = RateLog::with_sink(Limit::Rate(4), CaptureSink::new())
+            .with_clock(counting.clone())
+            .with_lazy_clock(true);
+
+        // The first occurrence and the first repeat read the clock
+        rate_log.log("message1");
+        clock.advance(Duration::from_millis(10));
+        assert_eq!(rate_log.log("message1"), LogOutcome::Suppressed);
+        assert_eq!(counting.take_reads(), 2);
+
+        // Repeats 2 and 3 are suppressed whatever the time, so they skip the clock
+        for _ in 0..2 {
+            clock.advance(Duration::from_millis(10));
+            assert_eq!(rate_log.log("message1"), LogOutcome::Suppressed);
+        }
+        assert_eq!(counting.take_reads(), 0);
+
+        // Repeat 4 reports, reading the clock for the exact duration
+        clock.advance(Duration::from_millis(10));
+        assert!(matches!(
+            rate_log.log("message1"),
+            LogOutcome::LimitReported { count: 4, duration, .. }
+                if duration == Duration::from_millis(40)
+        ));
+        assert_eq!(counting.take_reads(), 1);
+
+        // A summary only covers the time up to the last reading
+        for _ in 0..2 {
+            clock.advance(Duration::from_millis(10));
+            rate_log.log("message1");
+        }
+        rate_log.flush();
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" repeat for 4 times in the past 40ms",
+                "Message: \"message1\" suppressed 2 repeats over 10ms",
+            ]
+        );
+        let stats = rate_log.stats();
+        let message = stats.get("message1").unwrap();
+        assert_eq!((message.occurrences, message.suppressed), (7, 6));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_lazy_clock_disabled() {
+        let clock = MockClock::new();
+        let counting = CountingClock::new(clock.clone());
+        // The default stamps every repeat with its own time
+        let default =
+            RateLog::with_sink(Limit::Rate(100), CaptureSink::new()).with_clock(counting.clone());
+        let built = RateLogBuilder::new()
+            .limit(Limit::Rate(100))
+            .sink(CaptureSink::new())
+            .build()
+            .with_clock(counting.clone());
+        // Limits other than `Rate` and features measuring the time of repeats always
+        // read the clock
+        let duration = RateLog::with_sink(Limit::Any(100, Duration::MAX), CaptureSink::new())
+            .with_clock(counting.clone())
+            .with_lazy_clock(true);
+        let rate = RateLog::with_sink(Limit::Rate(100), CaptureSink::new())
+            .with_clock(counting.clone())
+            .with_lazy_clock(true)
+            .with_rate(true);
+        let ttl = RateLog::with_sink(Limit::Rate(100), CaptureSink::new())
+            .with_clock(counting.clone())
+            .with_lazy_clock(true)
+            .with_idle_ttl(Duration::from_secs(60));
+
+        for mut rate_log in [default, built, duration, rate, ttl] {
+            for _ in 0..10 {
+                clock.advance(Duration::from_millis(10));
+                rate_log.log("message1");
+            }
+            assert_eq!(counting.take_reads(), 10);
+            rate_log.flush();
+            assert!(rate_log.sink().lines()[1]
+                .starts_with("Message: \"message1\" suppressed 9 repeats over 90ms"));
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_lazy_clock_restored() {
+        let clock = MockClock::new();
+        let mut before = RateLog::with_sink(Limit::Rate(10), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_lazy_clock(true);
+        for _ in 0..3 {
+            before.log("message1");
+        }
+
+        // Restored statistics are not the hot entry yet: the first suppressed repeat
+        // looks them up instead
+        let mut after = RateLog::with_sink(Limit::Rate(10), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_lazy_clock(true);
+        after.restore(before.snapshot()).unwrap();
+        for _ in 0..3 {
+            assert_eq!(after.log("message1"), LogOutcome::Suppressed);
+        }
+        let stats = after.stats();
+        let message = stats.get("message1").unwrap();
+        assert_eq!((message.occurrences, message.suppressed), (6, 5));
+        assert_eq!(stats.messages().len(), 1);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_lazy_clock_keys() {
+        // Same length and same first and last 8 bytes: only the full comparison
+        // tells them apart
+        let (key1, key2) = ("message-1-payload", "message-2-payload");
+        assert_eq!(key_hash(key1), key_hash(key2));
+        assert_ne!(key_hash(key1), key_hash("message-payload"));
+        assert_ne!(key_hash("ab"), key_hash("ba"));
+        assert_ne!(key_hash(""), key_hash("\0"));
+
+        let clock = MockClock::new();
+        let counting = CountingClock::new(clock.clone());
+        let mut rate_log = RateLog::with_sink(Limit::Rate(100), CaptureSink::new())
+            .with_clock(counting.clone())
+            .with_lazy_clock(true);
+        for key in [key1, key1, key1, key2, key2, key1] {
+            rate_log.log(key);
+        }
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                key1,
+                "Message: \"message-1-payload\" suppressed 2 repeats over 0ms",
+                key2,
+                "Message: \"message-2-payload\" suppressed 1 repeats over 0ms",
+                key1
+            ]
+        );
+        // Only the 2nd repeat of each run skips the clock
+        assert_eq!(counting.take_reads(), 5);
+
+        // Resetting the key keeps the cached hash in sync
+        rate_log.reset_key(key1);
+        assert_eq!(rate_log.log(key1), LogOutcome::Emitted);
+        assert_eq!(rate_log.log(key1), LogOutcome::Suppressed);
+        assert_eq!(rate_log.log(key1), LogOutcome::Suppressed);
+        assert_eq!(rate_log.log(key2), LogOutcome::Emitted);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_output() {
+        let mut rate_log = RateLog::with_sink(Limit::Exponential { base: 2 }, CaptureSink::new())
+            .with_output_format(OutputFormat::Json);
+
+        for _ in 0..3 {
+            rate_log.log("message1");
+        }
+        rate_log.log_keyed("disk", "disk \"sda\" full");
+        rate_log.flush();
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                r#"{"v":1,"msg":"message1","kind":"message"}"#,
+                r#"{"v":1,"msg":"message1","seen":2,"kind":"message"}"#,
+                r#"{"v":1,"msg":"message1","repeats":1,"window_ms":0,"kind":"summary"}"#,
+                r#"{"v":1,"msg":"disk \"sda\" full","kind":"message"}"#
+            ]
+        );
+
+        // Records parse back into the versioned schema
+        let event: EventRecord = serde_json::from_str(&rate_log.sink().lines()[3]).unwrap();
+        assert_eq!(event.v, EventRecord::SCHEMA_VERSION);
+        assert_eq!(event.msg, "disk \"sda\" full");
+        assert_eq!(event.repeats, None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_event_record_compatibility() {
+        // Records of a later release with new fields and kinds still parse
+        let line = r#"{"v":1,"msg":"disk full","kind":"quarantined","host":"db1","repeats":3}"#;
+        let event: EventRecord = serde_json::from_str(line).unwrap();
+        assert_eq!(
+            (event.kind.as_ref(), event.repeats),
+            ("quarantined", Some(3))
+        );
+
+        // Mandatory fields are required, optional ones default to absent
+        assert!(serde_json::from_str::<EventRecord>(r#"{"v":1,"msg":"disk full"}"#).is_err());
+        let event: EventRecord =
+            serde_json::from_str(r#"{"v":1,"msg":"x","kind":"message"}"#).unwrap();
+        assert_eq!(event.to_json(), r#"{"v":1,"msg":"x","kind":"message"}"#);
+
+        // Keyed warnings of composite limits carry the key and the trigger
+        let mut rate_log =
+            RateLog::with_sink(Limit::Any(1, Duration::from_secs(60)), CaptureSink::new())
+                .with_clock(MockClock::new())
+                .with_output_format(OutputFormat::Json);
+        rate_log.log_keyed("disk", "disk sda full");
+        rate_log.log_keyed("disk", "disk sdb full");
+        let event: EventRecord = serde_json::from_str(&rate_log.sink().lines()[1]).unwrap();
+        assert_eq!(event.key.as_deref(), Some("disk"));
+        assert_eq!(event.msg, "disk sdb full");
+        assert_eq!(event.trigger.as_deref(), Some("count limit reached"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_limit_callback() {
+        use std::sync::{Arc, Mutex};
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let captured = Arc::clone(&reports);
+        let mut rate_log = RateLog::with_sink(Limit::Rate(2), CaptureSink::new())
+            .on_limit_exceeded(move |key, count, _| {
+                captured.lock().unwrap().push((key.to_string(), count));
+            })
+            .with_warnings(false);
+
+        for _ in 0..6 {
+            rate_log.log("message1");
+        }
+        rate_log.log("message2");
+
+        assert_eq!(
+            *reports.lock().unwrap(),
+            [("message1".to_string(), 2), ("message1".to_string(), 2)]
+        );
+        assert_eq!(rate_log.sink().lines(), ["message1", "message2"]);
+    }
+
+    #[test]
+    fn test_custom_formatter() {
+        let mut rate_log =
+            RateLog::with_sink(Limit::Rate(2), CaptureSink::new()).with_formatter(|warning| {
+                let kind = match warning.kind {
+                    WarningKind::LimitExceeded(_) => "exceeded",
+                    WarningKind::Summary => "summary",
+                };
+                format!(
+                    "RATELIMIT {} key={} count={}",
+                    kind, warning.key, warning.count
+                )
+            });
+
+        rate_log.log_keyed("disk", "disk full");
+        rate_log.log_keyed("disk", "disk full");
+        rate_log.log("message1");
+        rate_log.log("message1");
+        rate_log.log("message1");
+        rate_log.flush();
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "disk full",
+                "RATELIMIT summary key=disk count=1",
+                "message1",
+                "RATELIMIT exceeded key=message1 count=2"
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_stats() {
+        let clock = MockClock::new();
+        let mut rate_log =
+            RateLog::with_sink(Limit::Rate(2), CaptureSink::new()).with_clock(clock.clone());
+
+        for _ in 0..3 {
+            rate_log.log("message1");
+            clock.advance(Duration::from_millis(10));
+        }
+        rate_log.log("message2");
+        clock.advance(Duration::from_millis(5));
+        rate_log.log("message1");
+
+        let snapshot = rate_log.stats();
+        let stats = snapshot.messages();
+        assert_eq!(stats.len(), 2);
+
+        let message1 = &stats[0];
+        assert_eq!(message1.key, "message1");
+        assert_eq!((message1.occurrences, message1.suppressed), (4, 2));
+        assert_eq!(message1.duration, Duration::from_millis(20));
+        assert!(message1.last_seen > stats[1].first_seen);
+        assert_eq!(stats[1].first_seen, stats[1].last_seen);
+
+        let mut other =
+            RateLog::with_sink(Limit::Rate(2), CaptureSink::new()).with_clock(clock.clone());
+        other.log("message1");
+        other.log("message3");
+
+        let mut merged = rate_log.stats();
+        merged.merge(&other.stats());
+        let keys: Vec<_> = merged
+            .messages()
+            .iter()
+            .map(|stats| (stats.key.as_str(), stats.occurrences))
+            .collect();
+        assert_eq!(keys, [("message1", 5), ("message2", 1), ("message3", 1)]);
+        assert_eq!(
+            merged.get("message1").unwrap().first_seen,
+            message1.first_seen
+        );
+        assert_eq!(merged.get("message1").unwrap().suppressed, 2);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_stats_merge_edge_cases() {
+        let clock = MockClock::new();
+        let mut early =
+            RateLog::with_sink(Limit::Rate(5), CaptureSink::new()).with_clock(clock.clone());
+        early.log("b");
+        clock.advance(Duration::from_millis(10));
+        let mut late =
+            RateLog::with_sink(Limit::Rate(5), CaptureSink::new()).with_clock(clock.clone());
+        for key in ["c", "a", "b"] {
+            late.log(key);
+        }
+        clock.advance(Duration::from_millis(10));
+        late.log("b");
+
+        // Merging into an empty snapshot copies it
+        let mut merged = Stats::default();
+        merged.merge(&late.stats());
+        assert_eq!(merged, late.stats());
+
+        // Keys only seen by the other snapshot keep the order, shared keys span both
+        let mut merged = early.stats();
+        merged.merge(&late.stats());
+        let keys: Vec<_> = merged.messages().iter().map(|m| m.key.as_str()).collect();
+        assert_eq!(keys, ["a", "b", "c"]);
+        let b = merged.get("b").unwrap();
+        assert_eq!((b.occurrences, b.suppressed), (3, 1));
+        assert_eq!(b.first_seen, early.stats().messages()[0].first_seen);
+        assert_eq!(b.last_seen, late.stats().get("b").unwrap().last_seen);
+        assert_eq!(b.intervals.count(), 1);
+        assert!(merged.get("d").is_none());
+
+        // Merging with itself doubles the counts
+        let mut doubled = late.stats();
+        doubled.merge(&late.stats());
+        assert_eq!(doubled.get("b").unwrap().occurrences, 4);
+        assert_eq!(doubled.messages().len(), 3);
+    }
+
+    #[test]
+    fn test_exemplars() {
+        let mut request = 0;
+        let mut rate_log =
+            RateLog::with_sink(Limit::Rate(2), CaptureSink::new()).with_exemplars(move || {
+                request += 1;
+                (request != 3).then(|| format!("trace_id=\"req{request}\""))
+            });
+
+        for _ in 0..4 {
+            rate_log.log("message1");
+        }
+        rate_log.log("message2");
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" repeat for 2 times in the past 0ms # {trace_id=\"req2\"}",
+                "Message: \"message1\" suppressed 1 repeats over 0ms",
+                "message2"
+            ]
+        );
+        assert_eq!(
+            rate_log.top_offenders(1)[0].exemplar.as_deref(),
+            Some("trace_id=\"req2\"")
+        );
+    }
+
+    #[test]
+    fn test_log_bytes() {
+        let mut rate_log = RateLog::with_sink(Limit::Rate(1), CaptureSink::new());
+        assert_eq!(rate_log.log_bytes(b"message1 \xff"), LogOutcome::Emitted);
+        assert!(matches!(
+            rate_log.log_bytes(b"message1 \xff"),
+            LogOutcome::LimitReported { count: 1, .. }
+        ));
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1 \u{FFFD}",
+                "Message: \"message1 \u{FFFD}\" repeat for 1 times in the past 0ms"
+            ]
+        );
+
+        let sink = Sanitized::new(CaptureSink::new(), TextPolicy::Drop);
+        let mut rate_log = RateLog::with_sink(Limit::Rate(1), sink);
+        assert_eq!(rate_log.log_bytes(b"message2\0"), LogOutcome::Dropped);
+        assert!(rate_log.sink().inner().lines().is_empty());
+        assert!(rate_log.stats().messages().is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_warning_coalescing() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(Limit::Rate(1), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_warning_coalescing(Duration::from_millis(100));
+
+        for i in 1..=7 {
+            let key = format!("message{i}");
+            for _ in 0..=i {
+                rate_log.log(&key);
+            }
+        }
+        assert_eq!(rate_log.sink().lines().len(), 7);
+
+        // The next call after the tick emits the grouped block
+        clock.advance(Duration::from_millis(100));
+        rate_log.log("message8");
+        assert_eq!(
+            rate_log.sink().lines()[7],
+            "7 keys exceeded limits: \"message7\" (7 repeats), \"message6\" (6 repeats), \
+             \"message5\" (5 repeats), \"message4\" (4 repeats), \"message3\" (3 repeats) and 2 more"
+        );
+        assert_eq!(rate_log.sink().lines()[8], "message8");
+
+        // A lone warning is emitted unchanged
+        rate_log.log("message8");
+        rate_log.flush();
+        assert_eq!(
+            rate_log.sink().lines()[9],
+            "Message: \"message8\" repeat for 1 times in the past 0ms"
+        );
+    }
+
+    #[test]
+    fn test_log_with() {
+        let mut rate_log = RateLog::with_sink(Limit::Rate(3), CaptureSink::new());
+        let mut built = 0;
+
+        for i in 0..4 {
+            rate_log.log_with("key1", || {
+                built += 1;
+                format!("message{i}")
+            });
+        }
+
+        // Built for the first occurrence and the warning only
+        assert_eq!(built, 2);
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message0",
+                "Key: \"key1\" repeat for 3 times in the past 0ms, last message: \"message3\""
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_max_tracked_messages() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(Limit::Rate(5), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_max_tracked_messages(2);
+
+        rate_log.log("message1");
+        rate_log.log("message1");
+        clock.advance(Duration::from_millis(10));
+        rate_log.log("message2");
+        clock.advance(Duration::from_millis(10));
+        rate_log.log("message1");
+        clock.advance(Duration::from_millis(10));
+        // message2 is the least recently seen, but has nothing suppressed
+        rate_log.log("message3");
+        clock.advance(Duration::from_millis(10));
+        rate_log.log("message4");
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" suppressed 1 repeats over 0ms",
+                "message2",
+                "message1",
+                "message3",
+                "message4",
+                "evicted entry \"message1\" with 1 suppressed repeats"
+            ]
+        );
+        let stats = rate_log.stats();
+        let keys: Vec<_> = stats.messages().iter().map(|m| m.key.as_str()).collect();
+        assert_eq!(keys, ["message3", "message4"]);
+    }
+
+    #[test]
+    fn test_target_limits() {
+        let mut rate_log = RateLog::with_sink(Limit::Rate(100), CaptureSink::new())
+            .with_target_limit("net::*", Limit::Rate(1))
+            .with_target_limit("db", Limit::Never);
+
+        for target in ["net", "net::tcp", "network", "db", "db::pool"] {
+            rate_log.log_for_target(target, target);
+            rate_log.log_for_target(target, target);
+        }
+        rate_log.log_for_target("db", "db");
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "net",
+                "Message: \"net\" repeat for 1 times in the past 0ms",
+                "net::tcp",
+                "Message: \"net::tcp\" repeat for 1 times in the past 0ms",
+                "network",
+                "Message: \"network\" suppressed 1 repeats over 0ms",
+                "db",
+                "db",
+                "db::pool",
+                "Message: \"db::pool\" suppressed 1 repeats over 0ms",
+                "db",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hashed_keys() {
+        let mut rate_log =
+            RateLog::with_sink(Limit::Rate(2), CaptureSink::new()).with_hashed_keys(true);
+        let long = "x".repeat(4096);
+
+        for _ in 0..3 {
+            rate_log.log(&long);
+        }
+        rate_log.log("message2");
+        rate_log.log(&long);
+
+        assert_eq!(rate_log.sink().lines().len(), 4);
+        assert!(rate_log.sink().lines()[1].contains(" repeat for 2 times"));
+        let stats = rate_log.stats();
+        let mut keys: Vec<_> = stats.messages().iter().map(|m| m.key.clone()).collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            [
+                hashed_key("message2").into_owned(),
+                hashed_key(&long).into_owned()
+            ]
+        );
+        assert_eq!(hashed_key("a").as_ref(), "#af63dc4c8601ec8c");
+        assert_eq!(
+            stats.messages().iter().map(|m| m.occurrences).sum::<u64>(),
+            5
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_settle_timeout() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(Limit::Rate(5), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_settle_timeout(Duration::from_secs(10));
+
+        for _ in 0..5 {
+            rate_log.log("message1");
+            clock.advance(Duration::from_secs(1));
+        }
+        rate_log.settle();
+        assert_eq!(rate_log.sink().lines(), ["message1"]);
+
+        clock.advance(Duration::from_secs(9));
+        rate_log.settle();
+        rate_log.settle();
+        rate_log.log("message1");
+        clock.advance(Duration::from_secs(10));
+        rate_log.log("message2");
+        rate_log.flush();
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" suppressed 4 repeats over 4s",
+                "Message: \"message1\" suppressed 1 repeats over 10s",
+                "message2"
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_idle_ttl() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(Limit::Rate(2), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_idle_ttl(Duration::from_secs(600));
+
+        rate_log.log("message1");
+        clock.advance(Duration::from_secs(599));
+        rate_log.log("message1");
+        clock.advance(Duration::from_secs(600));
+        rate_log.log("message1");
+        rate_log.log("message1");
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" suppressed 1 repeats over 9m59s",
+                "message1"
+            ]
+        );
+        assert_eq!(
+            rate_log.stats().messages()[0].duration,
+            Duration::from_secs(599)
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_cooldown() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(Limit::Rate(2), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_cooldown(Duration::from_secs(60));
+
+        for _ in 0..10 {
+            rate_log.log("message1");
+        }
+        clock.advance(Duration::from_secs(60));
+        assert!(matches!(
+            rate_log.log("message1"),
+            LogOutcome::LimitReported { count: 8, .. }
+        ));
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" repeat for 2 times in the past 0ms",
+                "Message: \"message1\" repeat for 8 times in the past 1m0s"
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_summary_interval() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(Limit::Rate(1), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_summary_interval(Duration::from_secs(60));
+
+        for _ in 0..3 {
+            for _ in 0..3 {
+                rate_log.log("message1");
+            }
+            rate_log.log("message2");
+            clock.advance(Duration::from_secs(10));
+        }
+        clock.advance(Duration::from_secs(30));
+        rate_log.log("message1");
+        rate_log.log("message1");
+        rate_log.log("message1");
+        rate_log.log("message2");
+        rate_log.flush();
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" repeat for 1 times in the past 0ms",
+                "message2",
+                "message1",
+                "message2",
+                "message1",
+                "message2",
+                "message1",
+                "Message: \"message1\" repeat for 6 times in the past 0ms",
+                "message2",
+                "Message: \"message1\" suppressed 1 repeats over 0ms",
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_display() {
+        let clock = MockClock::new();
+        let mut rate_log =
+            RateLog::with_sink(Limit::Rate(10), CaptureSink::new()).with_clock(clock.clone());
+
+        rate_log.log("message2");
+        clock.advance(Duration::from_secs(2));
+        for _ in 0..4 {
+            rate_log.log("message1");
+            clock.advance(Duration::from_secs(1));
+        }
+
+        assert_eq!(
+            rate_log.to_string(),
+            "limit: warn after 10 repeats\n\
+             tracking \"message1\": 3 pending over 3s (3/10 repeats)\n\
+             \"message1\": 4 occurrences, 3 suppressed, last seen 0ms ago\n\
+             \"message2\": 1 occurrences, 0 suppressed, last seen 5s ago"
+        );
+        assert!(format!("{rate_log:?}").starts_with("RateLog { limit: Rate(10),"));
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut rate_log = RateLog::with_sink(Limit::Always, CaptureSink::new())
+            .group_by(Normalizer::StripNumbers)
+            .with_enabled(false);
+
+        assert_eq!(rate_log.log("message 1"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message 2"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log_keyed("key", "message3"), LogOutcome::Emitted);
+        assert_eq!(
+            rate_log.log_with("key", || "message4".to_string()),
+            LogOutcome::Emitted
+        );
+        assert!(rate_log.check("key"));
+        rate_log.flush();
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            ["message 1", "message 2", "message3", "message4"]
+        );
+        assert!(rate_log.stats().messages().is_empty());
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut rate_log = RateLog::with_sink(Limit::Rate(10), CaptureSink::new());
+
+        rate_log.log("message1");
+        rate_log.log("message2");
+        rate_log.log("message2");
+        rate_log.reset_key("message2");
+        rate_log.log("message2");
+        rate_log.log("message2");
+        assert_eq!(
+            rate_log
+                .stats()
+                .messages()
+                .iter()
+                .map(|stats| (stats.key.as_str(), stats.occurrences))
+                .collect::<Vec<_>>(),
+            [("message1", 1), ("message2", 2)]
+        );
+
+        rate_log.reset_key("message1");
+        assert_eq!(rate_log.stats().messages().len(), 1);
+        rate_log.reset();
+        assert!(rate_log.stats().messages().is_empty());
+        rate_log.log("message2");
+        rate_log.flush();
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            ["message1", "message2", "message2", "message2",]
+        );
+    }
+
+    #[test]
+    fn test_report_last_message() {
+        let mut rate_log =
+            RateLog::with_sink(Limit::Rate(2), CaptureSink::new()).with_report_last_message(true);
+
+        rate_log.log_keyed("key1", "message1");
+        rate_log.log_keyed("key1", "message2");
+        rate_log.log_keyed("key1", "message3");
+        rate_log.log_keyed("key1", "message4");
+        rate_log.log_keyed("key2", "message5");
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Key: \"key1\" repeat for 2 times in the past 0ms, last message: \"message3\"",
+                "Key: \"key1\" suppressed 1 repeats over 0ms, last message: \"message4\"",
+                "message5"
+            ]
+        );
+    }
 
-            println!("{msg}");
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_duration_style() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(Limit::Rate(2), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_duration_style(DurationStyle::Exact);
 
-            #[cfg(test)]
-            {
-                self.output.push_str(msg);
-            }
-        } else {
-            self.current.count += 1;
+        for _ in 0..3 {
+            rate_log.log("message1");
+            clock.advance(Duration::from_millis(750));
+        }
+        rate_log.log("message1");
+        rate_log.flush();
 
-            if let Some(last_call) = self.current.last_timestamp {
-                let elapsed = now.duration_since(last_call);
-                self.current.duration += elapsed;
-            }
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" repeat for 2 times in the past 1.5s",
+                "Message: \"message1\" suppressed 1 repeats over 0.75s"
+            ]
+        );
+    }
 
-            if self.current.exceeds_limit(&self.limit) {
-                let output = format!(
-                    "Message: \"{}\" repeat for {} times in the past {}",
-                    msg,
-                    self.current.count,
-                    format_duration(self.current.duration)
-                );
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_rate_in_warnings() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(Limit::Rate(500), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_rate(true);
+
+        for _ in 0..501 {
+            rate_log.log("message1");
+            clock.advance(Duration::from_millis(4));
+        }
+        for _ in 0..3 {
+            rate_log.log("message1");
+            clock.advance(Duration::from_millis(400));
+        }
+        rate_log.log("message2");
+        rate_log.log("message2");
+        rate_log.flush();
 
-                self.current.reset();
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" repeat for 500 times in the past 2s (250/s)",
+                "Message: \"message1\" suppressed 3 repeats over 804ms (3.7/s)",
+                "message2",
+                "Message: \"message2\" suppressed 1 repeats over 0ms"
+            ]
+        );
+    }
 
-                println!("{output}");
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_interval_percentiles() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(Limit::Rate(100), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_interval_percentiles(true);
 
-                #[cfg(test)]
-                {
-                    self.output.push_str(&output);
-                }
-            }
+        rate_log.log("message1");
+        for i in 0..100 {
+            let gap = if i % 10 == 0 { 40 } else { 2 };
+            clock.advance(Duration::from_micros(gap * 1000 - 500));
+            rate_log.log("message1");
         }
+        rate_log.log("message2");
 
-        self.current.last_timestamp = Some(now);
+        let lines = rate_log.sink().lines();
+        assert_eq!(
+            lines[1],
+            "Message: \"message1\" repeat for 100 times in the past 530ms, p50 2ms, p99 39ms between repeats"
+        );
+        assert_eq!(lines[2], "message2");
+
+        let stats = rate_log.stats();
+        let intervals = &stats.get("message1").unwrap().intervals;
+        assert_eq!(intervals.count(), 100);
+        assert_eq!(
+            intervals.percentile(99.0),
+            Some(Duration::from_micros(39_500))
+        );
+        let buckets: Vec<_> = intervals
+            .buckets()
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        assert_eq!(
+            buckets,
+            [
+                (Duration::from_millis(2), 90),
+                (Duration::from_millis(50), 10)
+            ]
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_set_limit() {
+        let mut rate_log = RateLog::with_sink(Limit::Rate(5), CaptureSink::new());
+
+        rate_log.log("message1");
+        rate_log.log("message1");
+        rate_log.set_limit(Limit::Window {
+            max: 1,
+            per: Duration::from_secs(60),
+        });
+        // The window restarts with the tracked message, leaving no budget
+        rate_log.log("message1");
+        assert_eq!(rate_log.stats().messages()[0].suppressed, 2);
+
+        rate_log.set_limit(Limit::Rate(3));
+        assert_eq!(rate_log.limit(), &Limit::Rate(3));
+        assert!(matches!(
+            rate_log.log("message1"),
+            LogOutcome::LimitReported { count: 3, .. }
+        ));
+    }
 
     #[test]
-    fn test_rate_log_exceed_time() {
-        let mut rate_log = RateLog::new(Limit::Rate(3));
+    fn test_passthrough_and_suppress_all() {
+        let mut rate_log = RateLog::with_sink(Limit::Never, CaptureSink::new());
+        for _ in 0..3 {
+            assert_eq!(rate_log.log("message1"), LogOutcome::Emitted);
+        }
+        assert_eq!(rate_log.sink().lines(), ["message1"; 3]);
 
-        // First call - should not exceed
+        let mut rate_log = RateLog::with_sink(Limit::Always, CaptureSink::new());
+        for _ in 0..100 {
+            rate_log.log("message2");
+        }
+        rate_log.log("message3");
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message2",
+                "Message: \"message2\" suppressed 99 repeats over 0ms",
+                "message3"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_suppress_all_saturates() {
+        // Repeats suppressed by `Always` are only reset by another message
+        let mut rate_log = RateLog::with_sink(Limit::Always, CaptureSink::new());
         rate_log.log("message1");
-        assert_eq!(rate_log.output, "message1");
-        rate_log.output.clear();
+        rate_log.current.count = u32::MAX - 1;
+        for _ in 0..3 {
+            assert_eq!(rate_log.log("message1"), LogOutcome::Suppressed);
+        }
+        assert_eq!(rate_log.current.count, u32::MAX);
+        rate_log.log("message2");
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" suppressed 4294967295 repeats over 0ms",
+                "message2"
+            ]
+        );
 
-        // Second call - should not exceed (current becomes 1, limit is 3)
+        // Counting the first occurrence as well cannot overflow either
+        let mut rate_log = RateLog::with_sink(Limit::Rate(u32::MAX), CaptureSink::new())
+            .with_count_mode(CountMode::IncludeFirst);
         rate_log.log("message1");
-        assert_eq!(rate_log.output, "");
+        rate_log.current.count = u32::MAX - 1;
+        assert!(matches!(
+            rate_log.log("message1"),
+            LogOutcome::LimitReported {
+                count: u32::MAX,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_pause_resume() {
+        let mut rate_log = RateLog::with_sink(Limit::Rate(1), CaptureSink::new());
 
-        // Third call - should not exceed (current becomes 2, limit is 3)
         rate_log.log("message1");
-        assert_eq!(rate_log.output, "");
+        rate_log.pause();
+        assert!(rate_log.is_paused());
+        rate_log.log("message1");
+        rate_log.log("message2");
+        rate_log.resume();
+        rate_log.resume();
+        rate_log.log("message2");
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "rate-log: resumed, 2 lines discarded while paused",
+                "Message: \"message2\" repeat for 1 times in the past 0ms"
+            ]
+        );
+        assert_eq!(rate_log.stats().get("message1").unwrap().occurrences, 2);
+    }
+
+    #[test]
+    fn test_group_by() {
+        let mut rate_log = RateLog::with_sink(Limit::Rate(2), CaptureSink::new())
+            .group_by(Normalizer::StripNumbers);
 
-        // Fourth call - should exceed (current becomes 3, limit is 3)
         rate_log.log("message1");
+        rate_log.log("message2");
+        rate_log.log("message3");
+        rate_log.log("no details");
+
         assert_eq!(
-            rate_log.output,
-            "Message: \"message1\" repeat for 3 times in the past 0ms"
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Key: \"message<num>\" repeat for 2 times in the past 0ms, last message: \"message3\"",
+                "no details"
+            ]
         );
-        rate_log.output.clear();
+    }
 
-        // Fifth call - should not exceed (current becomes 1, limit is 3)
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_global_budget() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(Limit::Rate(10), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_global_budget(2, Duration::from_secs(60));
+
+        for i in 1..=5 {
+            rate_log.log(format!("message{i}"));
+        }
+        clock.advance(Duration::from_secs(60));
+        rate_log.log("message6");
+        rate_log.log("message7");
+        rate_log.log("message8");
+        rate_log.flush();
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "message2",
+                "global rate limit reached, suppressed 3 lines",
+                "message6",
+                "message7",
+                "global rate limit reached, suppressed 1 lines"
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_adaptive_limit() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(Limit::Rate(2), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_adaptive_limit(2, Duration::from_secs(1));
+        let warnings = |rate_log: &mut RateLog<CaptureSink>, calls| {
+            (0..calls)
+                .filter(|_| matches!(rate_log.log("message1"), LogOutcome::LimitReported { .. }))
+                .count()
+        };
+
+        // 4 lines in the first window double the limit to Rate(4)
+        assert_eq!(warnings(&mut rate_log, 7), 3);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(warnings(&mut rate_log, 8), 2);
+
+        // After a quiet window the configured limit applies again
+        clock.advance(Duration::from_secs(3));
+        assert_eq!(warnings(&mut rate_log, 4), 2);
+    }
+
+    #[test]
+    fn test_scaled_limits_saturate() {
+        let max = Duration::MAX;
+        assert_eq!(Limit::Duration(max).scaled(2.0), Limit::Duration(max));
+        assert_eq!(
+            Limit::Any(u32::MAX, max).scaled(1000.0),
+            Limit::Any(u32::MAX, max)
+        );
+        assert_eq!(
+            Limit::Gcra {
+                period: max / 2,
+                burst: 3
+            }
+            .scaled(3.0),
+            Limit::Gcra {
+                period: max,
+                burst: 3
+            }
+        );
+        assert_eq!(
+            Limit::BurstThenSteady {
+                burst: 5,
+                interval: Duration::from_secs(u64::MAX / 10)
+            }
+            .scaled(1000.0),
+            Limit::BurstThenSteady {
+                burst: 5,
+                interval: max
+            }
+        );
+        // Representable results are still scaled
+        assert_eq!(
+            Limit::Duration(Duration::from_secs(2)).scaled(1.5),
+            Limit::Duration(Duration::from_secs(3))
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_adaptive_limit_huge_duration() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(Limit::Duration(Duration::MAX), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_adaptive_limit(1, Duration::from_secs(1));
+
+        // Different messages exceed the target, raising the factor above 1
+        for window in 0..3 {
+            for i in 0..4 {
+                rate_log.log(format!("message{window}{i}"));
+            }
+            clock.advance(Duration::from_secs(1));
+        }
+        assert_eq!(rate_log.log("message1"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log("message1"), LogOutcome::Suppressed);
+    }
+
+    #[test]
+    fn test_count_modes() {
+        for (mode, warned_at) in [(CountMode::RepeatsOnly, 4), (CountMode::IncludeFirst, 3)] {
+            let mut rate_log =
+                RateLog::with_sink(Limit::Rate(3), CaptureSink::new()).with_count_mode(mode);
+
+            let mut outcomes = Vec::new();
+            for _ in 0..7 {
+                outcomes.push(rate_log.log("message1"));
+            }
+
+            let warnings: Vec<_> = outcomes
+                .iter()
+                .enumerate()
+                .filter(|(_, outcome)| matches!(outcome, LogOutcome::LimitReported { .. }))
+                .map(|(call, _)| call + 1)
+                .collect();
+            // Later warnings always cover 3 repeats
+            assert_eq!(warnings, [warned_at, warned_at + 3], "{mode:?}");
+        }
+    }
+
+    #[cfg(feature = "color")]
+    #[test]
+    fn test_color() {
+        let mut rate_log = RateLog::with_sink(Limit::Rate(1), CaptureSink::new())
+            .with_escalation(2)
+            .with_color(ColorChoice::Always);
+
+        for _ in 0..3 {
+            rate_log.log("message1");
+        }
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "\x1b[33mMessage: \"message1\" repeat for 1 times in the past 0ms\x1b[0m",
+                "\x1b[33mMessage: \"message1\" repeat for 1 times in the past 0ms\x1b[0m",
+                "\x1b[31mESCALATED: message1\x1b[0m"
+            ]
+        );
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_wasm_clock() {
+        // Time comes from `web_time`, which falls back to `std::time` off the web
+        let start: web_time::Instant = clock::Instant::now();
+        let mut rate_log = RateLog::with_sink(
+            Limit::Duration(Duration::from_millis(20)),
+            CaptureSink::new(),
+        );
         rate_log.log("message1");
-        assert_eq!(rate_log.output, "");
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(matches!(
+            rate_log.log("message1"),
+            LogOutcome::LimitReported { duration, .. } if duration >= Duration::from_millis(20)
+        ));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert!(clock::SystemTime::now() > clock::UNIX_EPOCH);
+    }
 
-        // Sixth call - should not exceed (current becomes 2, limit is 3)
+    #[test]
+    fn test_monotonic() {
+        use alloc::sync::Arc;
+        use core::sync::atomic::{AtomicU64, Ordering};
+
+        /// A millisecond counter standing in for a hardware timer.
+        struct Timer(Arc<AtomicU64>);
+
+        impl Monotonic for Timer {
+            fn elapsed(&self) -> Duration {
+                Duration::from_millis(self.0.load(Ordering::Relaxed))
+            }
+        }
+
+        let ticks = Arc::new(AtomicU64::new(1_000));
+        let limit = Limit::Duration(Duration::from_secs(1));
+        let mut rate_log =
+            RateLog::with_sink(limit, CaptureSink::new()).with_monotonic(Timer(ticks.clone()));
+
+        rate_log.log("message1");
+        ticks.fetch_add(600, Ordering::Relaxed);
+        rate_log.log("message1");
+        ticks.fetch_add(600, Ordering::Relaxed);
         rate_log.log("message1");
-        assert_eq!(rate_log.output, "");
 
-        // Seventh call - should exceed (current becomes 3, limit is 3)
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" repeat for 2 times in the past 1s"
+            ]
+        );
+    }
+
+    #[cfg(not(feature = "std"))]
+    #[test]
+    fn test_monotonic_without_std() {
+        use core::sync::atomic::{AtomicU64, Ordering};
+
+        /// A millisecond counter advancing on every reading.
+        struct Timer(AtomicU64);
+
+        impl Monotonic for Timer {
+            fn elapsed(&self) -> Duration {
+                Duration::from_millis(self.0.fetch_add(400, Ordering::Relaxed) + 400)
+            }
+        }
+
+        // Without a source time stands still, so duration limits never trigger
+        let limit = Limit::Duration(Duration::from_secs(1));
+        let mut rate_log = RateLog::with_sink(limit.clone(), CaptureSink::new());
+        for _ in 0..5 {
+            rate_log.log("message1");
+        }
+        assert_eq!(rate_log.sink().lines(), ["message1"]);
+
+        let mut rate_log =
+            RateLog::with_sink(limit, CaptureSink::new()).with_monotonic(Timer(AtomicU64::new(0)));
+        for _ in 0..4 {
+            rate_log.log("message1");
+        }
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" repeat for 3 times in the past 1s"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_log_critical() {
+        let mut rate_log = RateLog::with_sink(Limit::Rate(2), CaptureSink::new())
+            .with_quiet_key("message1")
+            .with_global_budget(1, Duration::from_secs(60));
+
         rate_log.log("message1");
+        rate_log.log("message1");
+        assert_eq!(rate_log.log_critical("message1"), LogOutcome::Emitted);
+        assert_eq!(rate_log.log_critical("message1"), LogOutcome::Emitted);
+        rate_log.log("message1");
+
         assert_eq!(
-            rate_log.output,
-            "Message: \"message1\" repeat for 3 times in the past 0ms"
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "message1",
+                "Message: \"message1\" repeat for 2 times in the past 0ms"
+            ]
         );
-        rate_log.output.clear();
+        let stats = rate_log.stats();
+        let stats = stats.get("message1").unwrap();
+        assert_eq!((stats.occurrences, stats.suppressed), (5, 3));
     }
 
+    #[cfg(feature = "std")]
     #[test]
-    fn test_rate_log_exceed_duration() {
-        use std::thread;
+    fn test_duration_modes() {
+        for (mode, reported) in [
+            (DurationMode::SumOfGaps, "7s"),
+            (DurationMode::SinceFirstSeen, "2s"),
+        ] {
+            let clock = MockClock::new();
+            let mut rate_log = RateLog::with_sink(Limit::Rate(3), CaptureSink::new())
+                .with_clock(clock.clone())
+                .with_duration_mode(mode);
 
-        let mut rate_log = RateLog::new(Limit::Duration(Duration::from_millis(50)));
+            rate_log.log("message1");
+            clock.advance(Duration::from_secs(5));
+            for _ in 0..3 {
+                rate_log.log("message1");
+                clock.advance(Duration::from_secs(1));
+            }
 
-        // First call
-        rate_log.log("message2");
-        assert_eq!(rate_log.output, "message2");
-        rate_log.output.clear();
+            assert_eq!(
+                rate_log.sink().lines()[1],
+                format!("Message: \"message1\" repeat for 3 times in the past {reported}")
+            );
+        }
+    }
 
-        // Second call after short delay - should not exceed
-        thread::sleep(Duration::from_millis(20));
-        rate_log.log("message2");
-        assert_eq!(rate_log.output, "");
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_decay() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(Limit::Rate(3), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_decay(Duration::from_secs(1));
 
-        // Third call after longer delay - should exceed the 50ms limit
-        thread::sleep(Duration::from_millis(40));
-        rate_log.log("message2");
+        // Slow repetition leaks away as fast as it accumulates
+        rate_log.log("message1");
+        for _ in 0..10 {
+            clock.advance(Duration::from_millis(900));
+            rate_log.log("message1");
+        }
+        assert_eq!(rate_log.sink().lines(), ["message1"]);
+
+        // A burst still reaches the limit
+        for _ in 0..3 {
+            clock.advance(Duration::from_millis(100));
+            rate_log.log("message1");
+        }
         assert_eq!(
-            rate_log.output,
-            "Message: \"message2\" repeat for 2 times in the past 60ms"
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" repeat for 3 times in the past 9s"
+            ]
         );
-        rate_log.output.clear();
+    }
 
-        rate_log.log("message2");
-        assert_eq!(rate_log.output, "");
+    #[test]
+    fn test_log_with_limit() {
+        let strict = Limit::Rate(2);
+        let mut rate_log = RateLog::with_sink(Limit::Rate(5), CaptureSink::new());
+
+        rate_log.log_with_limit("message1", &strict);
+        rate_log.log("message1");
+        rate_log.log("message1");
+        rate_log.log_with_limit("message1", &strict);
+        rate_log.flush();
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" repeat for 3 times in the past 0ms"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_message_on_limit() {
+        let mut rate_log =
+            RateLog::with_sink(Limit::Rate(2), CaptureSink::new()).with_message_on_limit(true);
+
+        for i in 1..=3 {
+            rate_log.log_keyed("disk", &format!("disk full {i}"));
+        }
 
-        thread::sleep(Duration::from_millis(50));
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "disk full 1",
+                "disk full 3",
+                "Key: \"disk\" repeat for 2 times in the past 0ms, last message: \"disk full 3\""
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let mut rate_log = RateLog::with_sink(
+            Limit::Window {
+                max: 2,
+                per: Duration::from_secs(60),
+            },
+            CaptureSink::new(),
+        );
+
+        assert!(rate_log.check("dump"));
+        assert!(rate_log.check("dump"));
+        assert!(!rate_log.check("dump"));
+        rate_log.flush();
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            ["Key: \"dump\" suppressed 1 repeats over 0ms"]
+        );
+        assert_eq!(rate_log.stats().get("dump").unwrap().occurrences, 3);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_escalation() {
+        use std::sync::{Arc, Mutex};
+
+        let clock = MockClock::new();
+        let escalated = Arc::new(Mutex::new(Vec::new()));
+        let calls = Arc::clone(&escalated);
+        let mut rate_log = RateLog::with_sink(Limit::Rate(2), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_escalation(5)
+            .on_limit_exceeded(move |key, count, duration| {
+                calls
+                    .lock()
+                    .unwrap()
+                    .push((key.to_string(), count, duration));
+            });
+
+        for _ in 0..8 {
+            clock.advance(Duration::from_secs(1));
+            rate_log.log("message1");
+        }
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" repeat for 2 times in the past 2s",
+                "Message: \"message1\" repeat for 2 times in the past 2s",
+                "ESCALATED: message1",
+                "Message: \"message1\" repeat for 2 times in the past 2s"
+            ]
+        );
+        assert_eq!(
+            escalated.lock().unwrap()[2],
+            ("message1".to_string(), 5, Duration::from_secs(5))
+        );
+    }
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(4231), "4,231");
+        assert_eq!(format_count(1234567), "1,234,567");
+        // Group boundaries and the largest count
+        assert_eq!(format_count(1000), "1,000");
+        assert_eq!(format_count(100_000), "100,000");
+        assert_eq!(format_count(u64::MAX), "18,446,744,073,709,551,615");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_report_suppressed() {
+        let clock = MockClock::new();
+        let mut rate_log =
+            RateLog::with_sink(Limit::Rate(1000), CaptureSink::new()).with_clock(clock.clone());
+
+        for _ in 0..3 {
+            rate_log.log("message1");
+        }
+        clock.advance(Duration::from_secs(30));
+        for _ in 0..2 {
+            rate_log.log("message2");
+        }
+        rate_log.report_suppressed();
+        clock.advance(Duration::from_secs(60));
         rate_log.log("message2");
+        rate_log.report_suppressed();
+        clock.advance(Duration::from_secs(60));
+        rate_log.report_suppressed();
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" suppressed 2 repeats over 0ms",
+                "message2",
+                "\"message1\" suppressed 2 times in the last 30s",
+                "\"message2\" suppressed 1 times in the last 30s",
+                "\"message2\" suppressed 1 times in the last 1m0s"
+            ]
+        );
+
+        for _ in 0..2 {
+            rate_log.log("message3");
+        }
+        clock.advance(Duration::from_secs(5));
+        rate_log.log("message3");
         assert_eq!(
-            rate_log.output,
-            "Message: \"message2\" repeat for 2 times in the past 50ms"
+            rate_log.report(),
+            [Summary {
+                key: "message3".to_string(),
+                severity: Severity::Info,
+                suppressed: 2,
+                duration: Duration::from_secs(5),
+                exemplar: None,
+            }]
         );
-        rate_log.output.clear();
+        assert_eq!(rate_log.report(), []);
     }
 
+    #[cfg(feature = "std")]
     #[test]
-    fn test_format_duration() {
-        // Test milliseconds (< 1 second)
-        let duration_ms = Duration::from_millis(500);
-        assert_eq!(format_duration(duration_ms), "500ms");
+    fn test_auto_report() {
+        let clock = MockClock::new();
+        let mut rate_log = RateLog::with_sink(Limit::Rate(1000), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_auto_report(Duration::from_secs(60));
 
-        // Test seconds only (>= 1 second, < 1 minute)
-        let duration_s = Duration::from_secs(45);
-        assert_eq!(format_duration(duration_s), "45s");
+        for _ in 0..3 {
+            rate_log.log("message1");
+            clock.advance(Duration::from_secs(30));
+        }
+        rate_log.log("message1");
 
-        // Test minutes and seconds (>= 1 minute, < 1 hour)
-        let duration_min = Duration::from_secs(3 * 60 + 25); // 3 minutes 25 seconds
-        assert_eq!(format_duration(duration_min), "3m25s");
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "message1",
+                "\"message1\" suppressed 1 times in the last 1m0s"
+            ]
+        );
+    }
 
-        // Test hours and minutes (>= 1 hour)
-        let duration_hour = Duration::from_secs(2 * 3600 + 45 * 60); // 2 hours 45 minutes
-        assert_eq!(format_duration(duration_hour), "2h45m");
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_recent_history() {
+        let mut rate_log = RateLog::new(Limit::Rate(2)).with_history(3);
+
+        rate_log.log("message1");
+        rate_log.log("message1");
+        rate_log.log("message1");
+        rate_log.log("message2");
+        rate_log.log("message3");
+
+        // Oldest line is evicted once capacity is reached
+        assert_eq!(
+            rate_log.recent().collect::<Vec<_>>(),
+            vec![
+                "Message: \"message1\" repeat for 2 times in the past 0ms",
+                "message2",
+                "message3"
+            ]
+        );
+
+        // History is disabled by default
+        let mut rate_log = RateLog::new(Limit::Rate(2));
+        rate_log.log("message1");
+        assert_eq!(rate_log.recent().count(), 0);
     }
 }
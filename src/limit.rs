@@ -0,0 +1,1022 @@
+//! Rate limiting policies and the per-message tracking state enforcing them.
+
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::String;
+use core::fmt;
+use core::time::Duration;
+
+use crate::clock::Instant;
+
+use crate::{format_duration, Decision};
+
+/// Defines the type and threshold for rate limiting.
+///
+/// `Limit` specifies how rate limiting should be applied - either by counting
+/// message occurrences or by measuring time duration between repeated messages.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::Limit;
+/// use std::time::Duration;
+///
+/// // Allow up to 10 repeated messages before triggering rate limit
+/// let count_limit = Limit::Rate(10);
+///
+/// // Allow up to 5 seconds of accumulated time between repeated messages
+/// let time_limit = Limit::Duration(Duration::from_secs(5));
+/// ```
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum Limit {
+    /// Count-based rate limiting.
+    ///
+    /// Triggers when the same message is repeated more than the specified number of times.
+    /// The counter resets when a different message is logged.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Rate(3));
+    /// // Will trigger rate limit warning after 4th identical message
+    /// ```
+    Rate(u32),
+
+    /// Duration-based rate limiting.
+    ///
+    /// Triggers when the accumulated time between consecutive identical messages
+    /// exceeds the specified duration. Time is measured between actual calls,
+    /// providing real-world timing behavior.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    /// use std::time::Duration;
+    ///
+    /// let mut logger = RateLog::new(Limit::Duration(Duration::from_millis(500)));
+    /// // Will trigger if total elapsed time between identical messages > 500ms
+    /// ```
+    Duration(Duration),
+
+    /// Combined rate limiting that triggers on whichever threshold is reached first.
+    ///
+    /// Triggers when the same message is repeated the specified number of times
+    /// **or** the accumulated time between identical messages reaches the specified
+    /// duration, whichever happens first.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    /// use std::time::Duration;
+    ///
+    /// let mut logger = RateLog::new(Limit::Any(100, Duration::from_secs(30)));
+    /// // Will trigger after 100 repeats or 30 seconds, whichever happens first
+    /// ```
+    Any(u32, Duration),
+
+    /// Combined rate limiting that requires both thresholds to be reached.
+    ///
+    /// Triggers only once the same message has been repeated the specified number
+    /// of times **and** the accumulated time between identical messages has reached
+    /// the specified duration.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    /// use std::time::Duration;
+    ///
+    /// let mut logger = RateLog::new(Limit::All(100, Duration::from_secs(30)));
+    /// // Will trigger once there were at least 100 repeats spanning at least 30 seconds
+    /// ```
+    All(u32, Duration),
+
+    /// Sliding time-window rate limiting.
+    ///
+    /// Allows at most `max` identical messages - including the first occurrence - to be
+    /// emitted verbatim within any rolling window of length `per`. Further repeats are
+    /// suppressed, and once the window has room again a single summary of the
+    /// suppressed repeats is emitted in place of the next message. This matches the
+    /// classic "at most 5 logs per second" semantics.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    /// use std::time::Duration;
+    ///
+    /// let mut logger = RateLog::new(Limit::Window {
+    ///     max: 5,
+    ///     per: Duration::from_secs(1),
+    /// });
+    /// // Prints the message at most 5 times per second, then one summary per window
+    /// ```
+    Window {
+        /// The maximum number of messages emitted per window.
+        max: u32,
+        /// The length of the rolling window.
+        per: Duration,
+    },
+
+    /// Sliding time-window byte budget.
+    ///
+    /// Emits identical messages verbatim - including the first occurrence - as long as
+    /// the bytes emitted within any rolling window of length `per` stay within `max`.
+    /// Further repeats are suppressed, and once the budget has room again a single
+    /// summary reporting the suppressed repeats and their dropped bytes is emitted in
+    /// place of the next message. Bounds the write volume of storage with a strict
+    /// budget, which counts alone cannot do for messages of varying length.
+    ///
+    /// Messages are measured by the length of their text. Messages of
+    /// [`RateLog::log_with`](crate::RateLog::log_with), and items of
+    /// [`RateLog::log_item`](crate::RateLog::log_item) keyed apart from their text,
+    /// are only built when emitted and measured by the length of their key. A message longer than `max`
+    /// is emitted whenever nothing else was emitted within the window, so it appears
+    /// at most once per `per` rather than being suppressed for good.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    /// use std::time::Duration;
+    ///
+    /// let mut logger = RateLog::new(Limit::Bytes {
+    ///     max: 4096,
+    ///     per: Duration::from_secs(60),
+    /// });
+    /// // Writes at most 4 KiB of the same message per minute
+    /// ```
+    Bytes {
+        /// The maximum number of bytes emitted per window.
+        max: usize,
+        /// The length of the rolling window.
+        per: Duration,
+    },
+
+    /// Token bucket rate limiting.
+    ///
+    /// Every emitted message - including the first occurrence - takes a token from a
+    /// bucket holding at most `capacity` tokens, which refills continuously at
+    /// `refill_per_sec` tokens per second. Bursts are emitted verbatim up to the
+    /// capacity, after which messages are emitted at the steady refill rate. Repeats
+    /// arriving while the bucket is empty are suppressed and summarized once a token
+    /// becomes available again.
+    ///
+    /// A `capacity` below 1 behaves like 1. A negative or NaN `refill_per_sec` never
+    /// refills the bucket, and an infinite one refills it completely as soon as any
+    /// time passed. Configuration strings reject such values instead.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::TokenBucket {
+    ///     capacity: 10,
+    ///     refill_per_sec: 0.5,
+    /// });
+    /// // Allows a burst of 10 messages, then one message every 2 seconds
+    /// ```
+    TokenBucket {
+        /// The maximum number of tokens, i.e. the largest burst emitted verbatim.
+        capacity: u32,
+        /// The number of tokens added to the bucket per second.
+        refill_per_sec: f64,
+    },
+
+    /// Generic cell rate algorithm (GCRA) pacing.
+    ///
+    /// Emits identical messages - including the first occurrence - at a steady pace of
+    /// one per `period`, allowing up to `burst` of them at once after a quiet time.
+    /// Each message moves a theoretical arrival time forward by `period`; a message
+    /// arriving more than `burst - 1` periods ahead of it is suppressed, and once
+    /// messages are allowed again a single summary of the suppressed repeats is
+    /// emitted in place of the next one. Equivalent to a token bucket holding `burst`
+    /// tokens refilled every `period`, but kept in a single timestamp and without
+    /// floating point accumulation, so steady streams are paced predictably. A `burst`
+    /// below 1 behaves like 1. A `period` too large to add to the current time, e.g.
+    /// [`Duration::MAX`], suppresses every repeat after the first occurrence.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    /// use std::time::Duration;
+    ///
+    /// let mut logger = RateLog::new(Limit::Gcra {
+    ///     period: Duration::from_secs(1),
+    ///     burst: 5,
+    /// });
+    /// // Allows a burst of 5 messages, then one message per second
+    /// ```
+    Gcra {
+        /// The time between messages at the steady pace.
+        period: Duration,
+        /// The maximum number of messages emitted at once.
+        burst: u32,
+    },
+
+    /// Exponential backoff rate limiting.
+    ///
+    /// Emits a repeated message on its 1st, `base`th, `base²`th, `base³`th, ...
+    /// occurrence, similar to kernel printk ratelimiting, so the interval between
+    /// emissions grows exponentially. Every emitted repeat includes the number of
+    /// occurrences so far. A `base` below 2 behaves like 2.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Exponential { base: 2 });
+    ///
+    /// for _ in 0..8 {
+    ///     logger.log("Retrying");
+    /// }
+    /// // Prints: "Retrying"
+    /// // Prints: "Retrying (seen 2 times)"
+    /// // Prints: "Retrying (seen 4 times)"
+    /// // Prints: "Retrying (seen 8 times)"
+    /// ```
+    Exponential {
+        /// The growth factor between the occurrences that get emitted.
+        base: u32,
+    },
+
+    /// Sampling rate limiting.
+    ///
+    /// Emits 1 out of every N identical messages: the 1st, (N+1)th, (2N+1)th, ...
+    /// occurrence. Sampled repeats are annotated with `(sampled 1/N)`, giving a
+    /// representative trickle of very high frequency messages rather than only a
+    /// summary. A value below 1 behaves like 1.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Sample(100));
+    ///
+    /// for _ in 0..250 {
+    ///     logger.log("Packet received");
+    /// }
+    /// // Prints: "Packet received"
+    /// // Prints: "Packet received (sampled 1/100)"
+    /// // Prints: "Packet received (sampled 1/100)"
+    /// ```
+    Sample(u32),
+
+    /// Burst-then-steady rate limiting, as nginx and HAProxy do for repeated error lines.
+    ///
+    /// Lets the first `burst` occurrences of a message through unthrottled, preserving
+    /// the initial context, then emits at most one occurrence per `interval`. Each of
+    /// those is annotated with the number of occurrences suppressed since the previous
+    /// one, as `(+K more)`. A `burst` below 1 behaves like 1.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    /// use std::time::Duration;
+    ///
+    /// let mut logger = RateLog::new(Limit::BurstThenSteady {
+    ///     burst: 3,
+    ///     interval: Duration::from_secs(10),
+    /// });
+    ///
+    /// for _ in 0..100 {
+    ///     logger.log("upstream timed out");
+    /// }
+    /// // Prints "upstream timed out" 3 times, then once every 10 seconds at most as
+    /// // "upstream timed out (+K more)"
+    /// ```
+    BurstThenSteady {
+        /// The number of occurrences emitted before throttling starts.
+        burst: u32,
+        /// The minimum time between emissions once the burst is used up.
+        interval: Duration,
+    },
+
+    /// Passthrough mode: every repeat is emitted and only statistics are collected.
+    ///
+    /// Useful while debugging, when every occurrence matters.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Never);
+    ///
+    /// logger.log("Cache miss");  // Prints: "Cache miss"
+    /// logger.log("Cache miss");  // Prints: "Cache miss"
+    /// assert_eq!(logger.stats().messages()[0].occurrences, 2);
+    /// ```
+    Never,
+
+    /// Suppress-everything mode: every repeat is counted silently and only reported in
+    /// the summary emitted when the message changes or the logger is flushed.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rate_log::{RateLog, Limit};
+    ///
+    /// let mut logger = RateLog::new(Limit::Always);
+    ///
+    /// for _ in 0..1000 {
+    ///     logger.log("Cache miss");
+    /// }
+    /// logger.flush();
+    /// // Prints: "Cache miss"
+    /// // Prints: "Message: \"Cache miss\" suppressed 999 repeats over 0ms"
+    /// ```
+    Always,
+}
+
+impl fmt::Display for Limit {
+    /// Describes the policy in plain words, as used in the startup announcement.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Rate(count) => write!(f, "warn after {count} repeats"),
+            Limit::Duration(duration) => {
+                write!(f, "warn after {} of repeats", format_duration(*duration))
+            }
+            Limit::Any(count, duration) => write!(
+                f,
+                "warn after {count} repeats or {} of repeats, whichever comes first",
+                format_duration(*duration)
+            ),
+            Limit::All(count, duration) => write!(
+                f,
+                "warn after {count} repeats spanning at least {}",
+                format_duration(*duration)
+            ),
+            Limit::Window { max, per } => write!(
+                f,
+                "allow at most {max} messages per {}",
+                format_duration(*per)
+            ),
+            Limit::Bytes { max, per } => {
+                write!(f, "allow at most {max} bytes per {}", format_duration(*per))
+            }
+            Limit::TokenBucket {
+                capacity,
+                refill_per_sec,
+            } => write!(
+                f,
+                "allow bursts of {capacity} messages refilled at {refill_per_sec}/s"
+            ),
+            Limit::Gcra { period, burst } => write!(
+                f,
+                "allow one message per {} with bursts of {burst}",
+                format_duration(*period)
+            ),
+            Limit::Exponential { base } => {
+                write!(f, "emit repeats with exponential backoff (base {base})")
+            }
+            Limit::Sample(rate) => write!(f, "emit 1 out of every {rate} repeats"),
+            Limit::BurstThenSteady { burst, interval } => write!(
+                f,
+                "allow the first {burst} messages, then one every {}",
+                format_duration(*interval)
+            ),
+            Limit::Never => write!(f, "emit every repeat"),
+            Limit::Always => write!(
+                f,
+                "suppress every repeat, summarize when the message changes"
+            ),
+        }
+    }
+}
+
+impl Limit {
+    /// Returns `true` for limits combining several components, whose warnings
+    /// state which component was reached.
+    pub(crate) fn is_composite(&self) -> bool {
+        matches!(self, Limit::Any(..) | Limit::All(..))
+    }
+
+    /// Returns the limit emitting about `factor` times fewer lines for the same
+    /// repeats: counts and intervals grow by `factor`, budgets shrink by it.
+    ///
+    /// [`Limit::Exponential`], [`Limit::Never`] and [`Limit::Always`] are returned
+    /// unchanged. `factor` must be at least 1; scaled values are rounded down and
+    /// saturate.
+    pub(crate) fn scaled(&self, factor: f64) -> Limit {
+        let more = |count: u32| (f64::from(count) * factor) as u32;
+        let fewer = |count: u32| (f64::from(count) / factor).max(1.0) as u32;
+        let longer = |duration: Duration| {
+            Duration::try_from_secs_f64(duration.as_secs_f64() * factor).unwrap_or(Duration::MAX)
+        };
+        match *self {
+            Limit::Rate(count) => Limit::Rate(more(count)),
+            Limit::Duration(duration) => Limit::Duration(longer(duration)),
+            Limit::Any(count, duration) => Limit::Any(more(count), longer(duration)),
+            Limit::All(count, duration) => Limit::All(more(count), longer(duration)),
+            Limit::Window { max, per } => Limit::Window {
+                max: fewer(max),
+                per,
+            },
+            Limit::Bytes { max, per } => Limit::Bytes {
+                max: ((max as f64) / factor).max(1.0) as usize,
+                per,
+            },
+            Limit::TokenBucket {
+                capacity,
+                refill_per_sec,
+            } => Limit::TokenBucket {
+                capacity: fewer(capacity),
+                refill_per_sec: refill_per_sec / factor,
+            },
+            Limit::Gcra { period, burst } => Limit::Gcra {
+                period: longer(period),
+                burst,
+            },
+            Limit::Sample(rate) => Limit::Sample(more(rate)),
+            Limit::BurstThenSteady { burst, interval } => Limit::BurstThenSteady {
+                burst,
+                interval: longer(interval),
+            },
+            Limit::Exponential { .. } | Limit::Never | Limit::Always => self.clone(),
+        }
+    }
+}
+
+/// The component of a [`Limit`] that caused a rate limit warning.
+///
+/// Plain limits always report their single component. Composite limits
+/// ([`Limit::Any`], [`Limit::All`]) report whichever components were reached, so
+/// consumers can tell a short burst (count) from slow, chronic repetition (duration).
+/// Budget based limits report [`Trigger::Window`] ([`Limit::Window`]),
+/// [`Trigger::Bytes`] ([`Limit::Bytes`]), [`Trigger::Tokens`] ([`Limit::TokenBucket`])
+/// or [`Trigger::Pacing`] ([`Limit::Gcra`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// The repeat count threshold was reached.
+    Count,
+
+    /// The accumulated duration threshold was reached.
+    Duration,
+
+    /// Both the count and the duration thresholds were reached.
+    CountAndDuration,
+
+    /// The per-window message budget was used up.
+    Window,
+
+    /// The per-window byte budget was used up.
+    Bytes,
+
+    /// The token bucket ran out of tokens.
+    Tokens,
+
+    /// A message arrived ahead of the pace allowed by [`Limit::Gcra`].
+    Pacing,
+}
+
+impl Trigger {
+    /// Returns a short human-readable description, as used in warnings.
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Trigger::Count => "count limit reached",
+            Trigger::Duration => "duration limit reached",
+            Trigger::CountAndDuration => "count and duration limits reached",
+            Trigger::Window => "window limit reached",
+            Trigger::Bytes => "byte budget used up",
+            Trigger::Tokens => "token bucket empty",
+            Trigger::Pacing => "pacing limit reached",
+        }
+    }
+}
+
+/// Whether the first occurrence of a message counts towards count based limits.
+///
+/// By default [`Limit::Rate`]`(3)` allows 3 repeats after the first occurrence, so the
+/// warning fires on the 4th call. With [`CountMode::IncludeFirst`] the first occurrence
+/// is counted too and the warning fires on the 3rd call. Only the first warning of a
+/// message is affected; later warnings always cover the given number of repeats.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{CountMode, Limit, RateLog};
+///
+/// let mut logger = RateLog::new(Limit::Rate(3)).with_count_mode(CountMode::IncludeFirst);
+///
+/// logger.log("Disk full");  // Prints: "Disk full"
+/// logger.log("Disk full");  // Silent
+/// logger.log("Disk full");
+/// // Prints: "Message: \"Disk full\" repeat for 2 times in the past 0ms"
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CountMode {
+    /// The count threshold applies to repeats after the first occurrence.
+    #[default]
+    RepeatsOnly,
+
+    /// The first occurrence counts towards the count threshold.
+    IncludeFirst,
+}
+
+/// How the duration reported in warnings and checked by duration based limits is
+/// measured.
+///
+/// By default the duration is the sum of the gaps between consecutive calls, starting
+/// with the gap before the first repeat. [`DurationMode::SinceFirstSeen`] measures the
+/// wall-clock time elapsed since the first suppressed repeat instead, so
+/// `"in the past 5s"` means 5 real seconds since the burst started.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{DurationMode, Limit, RateLog};
+///
+/// let logger = RateLog::new(Limit::Rate(10)).with_duration_mode(DurationMode::SinceFirstSeen);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DurationMode {
+    /// The sum of the gaps between consecutive calls.
+    #[default]
+    SumOfGaps,
+
+    /// The time elapsed since the first repeat not yet covered by a warning or summary.
+    SinceFirstSeen,
+}
+
+/// The tracking state of the current message.
+#[derive(Debug)]
+pub(crate) struct State {
+    /// Repeats counted since the last warning or summary.
+    pub(crate) count: u32,
+    /// Time accumulated between the repeats counted in `count`.
+    pub(crate) duration: Duration,
+    pub(crate) last_timestamp: Option<Instant>,
+    /// The time the last rate limit warning was emitted.
+    pub(crate) last_warning: Option<Instant>,
+    /// The time of the first occurrence of the message.
+    pub(crate) started: Option<Instant>,
+    /// Repeats since the first occurrence, not reset by warnings or summaries.
+    pub(crate) repeats: u64,
+    /// Quiet time not yet converted into forgotten repeats, only used with decay.
+    leak: Duration,
+    /// The time of the first pending repeat, only used by [`DurationMode::SinceFirstSeen`].
+    pending_since: Option<Instant>,
+    /// Emission times within the current window, only used by [`Limit::Window`].
+    window: VecDeque<Instant>,
+    /// Emission times and sizes within the current window, only used by [`Limit::Bytes`].
+    sent: VecDeque<(Instant, usize)>,
+    /// The total size of the emissions in `sent`.
+    sent_bytes: usize,
+    /// The size of the repeats counted in `count`, only used by [`Limit::Bytes`].
+    pub(crate) dropped_bytes: u64,
+    /// Tokens left in the bucket, only used by [`Limit::TokenBucket`].
+    tokens: f64,
+    /// The last time the bucket was refilled, only used by [`Limit::TokenBucket`].
+    refilled: Option<Instant>,
+    /// The theoretical arrival time of the next message, only used by [`Limit::Gcra`].
+    tat: Option<Instant>,
+    /// Whether the theoretical arrival time is past the end of the time line, so
+    /// [`Limit::Gcra`] admits no further messages.
+    paced_out: bool,
+    /// Occurrences of the message so far, only used by [`Limit::Exponential`],
+    /// [`Limit::Sample`] and [`Limit::BurstThenSteady`].
+    occurrences: u64,
+    /// The occurrence emitted next, only used by [`Limit::Exponential`].
+    next_emission: u64,
+    /// The time the message was last emitted, only used by [`Limit::BurstThenSteady`].
+    last_emission: Option<Instant>,
+}
+
+impl State {
+    pub(crate) fn new() -> Self {
+        State {
+            count: 0,
+            duration: Duration::from_secs(0),
+            last_timestamp: None,
+            last_warning: None,
+            started: None,
+            repeats: 0,
+            leak: Duration::from_secs(0),
+            pending_since: None,
+            window: VecDeque::new(),
+            sent: VecDeque::new(),
+            sent_bytes: 0,
+            dropped_bytes: 0,
+            tokens: 0.0,
+            refilled: None,
+            tat: None,
+            paced_out: false,
+            occurrences: 0,
+            next_emission: 0,
+            last_emission: None,
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.clear_pending();
+        self.last_timestamp = None;
+        self.last_warning = None;
+        self.started = None;
+        self.repeats = 0;
+        self.window.clear();
+        self.sent.clear();
+        self.sent_bytes = 0;
+        self.tokens = 0.0;
+        self.refilled = None;
+        self.tat = None;
+        self.paced_out = false;
+        self.occurrences = 0;
+        self.next_emission = 0;
+        self.last_emission = None;
+    }
+
+    /// Returns `true` if `limit` suppresses the next repeat whatever the time, which
+    /// only pure count limits below their threshold do.
+    pub(crate) fn suppresses_next(&self, limit: &Limit, count_mode: CountMode) -> bool {
+        let Limit::Rate(max) = limit else {
+            return false;
+        };
+        // Mirrors `exceeds_limit` after the repeat is counted
+        let count = match count_mode {
+            CountMode::IncludeFirst if self.last_warning.is_none() => self.count.saturating_add(2),
+            _ => self.count.saturating_add(1),
+        };
+        count < *max
+    }
+
+    /// Clears the repeats pending a warning or summary.
+    pub(crate) fn clear_pending(&mut self) {
+        self.count = 0;
+        self.duration = Duration::from_secs(0);
+        self.leak = Duration::from_secs(0);
+        self.pending_since = None;
+        self.dropped_bytes = 0;
+    }
+
+    /// Forgets one pending repeat for every `per` of quiet time since the last call.
+    ///
+    /// Like a leaky bucket, the remainder of the quiet time carries over to the next
+    /// call, so slow but steady repetition drains at the same rate as a long pause.
+    pub(crate) fn decay(&mut self, per: Duration, now: Instant) {
+        let Some(last) = self.last_timestamp else {
+            return;
+        };
+        self.leak += now.duration_since(last);
+        let per_nanos = per.as_nanos().max(1);
+        let leaked = self.leak.as_nanos() / per_nanos;
+        self.count = self
+            .count
+            .saturating_sub(u32::try_from(leaked).unwrap_or(u32::MAX));
+        self.leak = if self.count == 0 {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_nanos((self.leak.as_nanos() % per_nanos) as u64)
+        };
+    }
+
+    /// Records the first occurrence of a message of `bytes` bytes, which is always
+    /// emitted.
+    pub(crate) fn on_first(&mut self, limit: &Limit, now: Instant, bytes: usize) {
+        self.started.get_or_insert(now);
+        match limit {
+            Limit::Window { .. } => self.window.push_back(now),
+            Limit::Bytes { .. } => {
+                self.sent.push_back((now, bytes));
+                self.sent_bytes += bytes;
+            }
+            Limit::TokenBucket { capacity, .. } => {
+                self.tokens = (f64::from(*capacity) - 1.0).max(0.0);
+                self.refilled = Some(now);
+            }
+            Limit::Gcra { period, .. } => self.pace(now, *period),
+            Limit::Exponential { base } => {
+                self.occurrences = 1;
+                self.next_emission = u64::from((*base).max(2));
+            }
+            Limit::Sample(_) => self.occurrences = 1,
+            Limit::BurstThenSteady { .. } => {
+                self.occurrences = 1;
+                self.last_emission = Some(now);
+            }
+            _ => {}
+        }
+    }
+
+    /// Records a repeat of `bytes` bytes of the current message and decides how to
+    /// handle it.
+    pub(crate) fn on_repeat(
+        &mut self,
+        limit: &Limit,
+        count_mode: CountMode,
+        duration_mode: DurationMode,
+        now: Instant,
+        bytes: usize,
+    ) -> Decision {
+        self.record_repeat(duration_mode, now);
+
+        if let Limit::Exponential { base } = limit {
+            self.occurrences += 1;
+            if self.occurrences < self.next_emission {
+                self.count = self.count.saturating_add(1);
+                return Decision::Suppress;
+            }
+            self.next_emission = self.next_emission.saturating_mul(u64::from((*base).max(2)));
+            self.clear_pending();
+            return Decision::EmitCounted(self.occurrences);
+        }
+
+        match limit {
+            Limit::Never => {
+                self.clear_pending();
+                return Decision::Emit;
+            }
+            Limit::Always => {
+                self.count = self.count.saturating_add(1);
+                return Decision::Suppress;
+            }
+            _ => {}
+        }
+
+        if let Limit::Sample(rate) = limit {
+            let rate = (*rate).max(1);
+            self.occurrences += 1;
+            if (self.occurrences - 1) % u64::from(rate) != 0 {
+                self.count = self.count.saturating_add(1);
+                return Decision::Suppress;
+            }
+            self.clear_pending();
+            return Decision::EmitSampled(rate);
+        }
+
+        if let Limit::BurstThenSteady { burst, interval } = limit {
+            self.occurrences += 1;
+            let throttled = self.occurrences > u64::from((*burst).max(1))
+                && self
+                    .last_emission
+                    .is_some_and(|last| now.duration_since(last) < *interval);
+            if throttled {
+                self.count = self.count.saturating_add(1);
+                return Decision::Suppress;
+            }
+            let more = u64::from(self.count);
+            self.clear_pending();
+            self.last_emission = Some(now);
+            return if more == 0 {
+                Decision::Emit
+            } else {
+                Decision::EmitMore(more)
+            };
+        }
+
+        // Budget based limits emit messages verbatim while budget is left, and
+        // summarize suppressed repeats once budget becomes available again.
+        let budget = match limit {
+            Limit::Window { max, per } => {
+                Some((self.take_window_slot(*max, *per, now), Trigger::Window))
+            }
+            Limit::Bytes { max, per } => {
+                Some((self.take_bytes(*max, *per, bytes, now), Trigger::Bytes))
+            }
+            Limit::TokenBucket {
+                capacity,
+                refill_per_sec,
+            } => Some((
+                self.take_token(*capacity, *refill_per_sec, now),
+                Trigger::Tokens,
+            )),
+            Limit::Gcra { period, burst } => {
+                Some((self.take_arrival(*period, *burst, now), Trigger::Pacing))
+            }
+            _ => None,
+        };
+        if let Some((allowed, trigger)) = budget {
+            if trigger == Trigger::Bytes && (!allowed || self.count > 0) {
+                self.dropped_bytes += bytes as u64;
+            }
+            if !allowed {
+                self.count = self.count.saturating_add(1);
+                return Decision::Suppress;
+            }
+            if self.count > 0 {
+                self.count = self.count.saturating_add(1);
+                return Decision::Report(trigger);
+            }
+            self.duration = Duration::from_secs(0);
+            return Decision::Emit;
+        }
+
+        self.count = self.count.saturating_add(1);
+        match self.exceeds_limit(limit, count_mode) {
+            Some(trigger) => Decision::Report(trigger),
+            None => Decision::Suppress,
+        }
+    }
+
+    /// Records a repeat of the current message known to be suppressed, see
+    /// [`State::suppresses_next`].
+    pub(crate) fn on_suppressed(&mut self, duration_mode: DurationMode, now: Instant) {
+        self.record_repeat(duration_mode, now);
+        self.count = self.count.saturating_add(1);
+    }
+
+    /// Records a repeat of the current message handled as `decision`, which was made
+    /// by a custom [`Limiter`](crate::Limiter).
+    ///
+    /// Emitted repeats leave the pending ones untouched, so no suppressed repeat goes
+    /// unreported.
+    pub(crate) fn on_decision(
+        &mut self,
+        decision: Decision,
+        duration_mode: DurationMode,
+        now: Instant,
+    ) -> Decision {
+        self.record_repeat(duration_mode, now);
+        if !decision.emits() {
+            self.count = self.count.saturating_add(1);
+        }
+        decision
+    }
+
+    /// Counts a repeat and accumulates the time since the previous call.
+    fn record_repeat(&mut self, duration_mode: DurationMode, now: Instant) {
+        self.repeats += 1;
+        match duration_mode {
+            DurationMode::SumOfGaps => {
+                if let Some(last_call) = self.last_timestamp {
+                    let elapsed = now.duration_since(last_call);
+                    self.duration += elapsed;
+                }
+            }
+            DurationMode::SinceFirstSeen => {
+                let since = *self.pending_since.get_or_insert(now);
+                self.duration = now.duration_since(since);
+            }
+        }
+    }
+
+    /// Describes how close the current message is to `limit` at `now`, e.g.
+    /// `3/10 repeats`.
+    pub(crate) fn progress(&self, limit: &Limit, now: Instant) -> String {
+        let tracked = |duration: Duration, limit: Duration| {
+            format!("{}/{}", format_duration(duration), format_duration(limit))
+        };
+        match limit {
+            Limit::Rate(count) => format!("{}/{count} repeats", self.count),
+            Limit::Duration(duration) => tracked(self.duration, *duration),
+            Limit::Any(count, duration) | Limit::All(count, duration) => format!(
+                "{}/{count} repeats, {}",
+                self.count,
+                tracked(self.duration, *duration)
+            ),
+            Limit::Window { max, per } => {
+                let used = self
+                    .window
+                    .iter()
+                    .filter(|sent| now.duration_since(**sent) < *per)
+                    .count();
+                format!("{used}/{max} messages in window")
+            }
+            Limit::Bytes { max, per } => {
+                let used: usize = self
+                    .sent
+                    .iter()
+                    .filter(|(sent, _)| now.duration_since(*sent) < *per)
+                    .map(|(_, bytes)| bytes)
+                    .sum();
+                format!("{used}/{max} bytes in window")
+            }
+            Limit::TokenBucket { capacity, .. } => {
+                format!("{:.1}/{capacity} tokens left", self.tokens)
+            }
+            Limit::Gcra { .. } if self.paced_out => String::from("paced out"),
+            Limit::Gcra { .. } => match self.tat.filter(|tat| *tat > now) {
+                Some(tat) => format!("paced for {}", format_duration(tat.duration_since(now))),
+                None => String::from("not paced"),
+            },
+            Limit::Exponential { .. } => format!(
+                "{} occurrences, next emitted at {}",
+                self.occurrences, self.next_emission
+            ),
+            Limit::Sample(rate) => format!("{} occurrences, 1/{rate} emitted", self.occurrences),
+            Limit::BurstThenSteady { burst, .. } => {
+                format!("{}/{burst} burst occurrences", self.occurrences)
+            }
+            Limit::Never => String::from("every repeat emitted"),
+            Limit::Always => String::from("every repeat suppressed"),
+        }
+    }
+
+    /// Takes a slot in the rolling window of emissions, if one is free.
+    fn take_window_slot(&mut self, max: u32, per: Duration, now: Instant) -> bool {
+        while let Some(&oldest) = self.window.front() {
+            if now.duration_since(oldest) < per {
+                break;
+            }
+            self.window.pop_front();
+        }
+
+        if self.window.len() >= max as usize {
+            return false;
+        }
+        self.window.push_back(now);
+        true
+    }
+
+    /// Takes `bytes` from the rolling window byte budget, if that many are left.
+    fn take_bytes(&mut self, max: usize, per: Duration, bytes: usize, now: Instant) -> bool {
+        while let Some(&(oldest, size)) = self.sent.front() {
+            if now.duration_since(oldest) < per {
+                break;
+            }
+            self.sent.pop_front();
+            self.sent_bytes -= size;
+        }
+
+        // An empty window admits anything, so messages longer than `max` still get
+        // through once per window instead of never again
+        if !self.sent.is_empty() && self.sent_bytes.saturating_add(bytes) > max {
+            return false;
+        }
+        self.sent.push_back((now, bytes));
+        self.sent_bytes += bytes;
+        true
+    }
+
+    /// Refills the token bucket for the time passed and takes a token, if one is left.
+    fn take_token(&mut self, capacity: u32, refill_per_sec: f64, now: Instant) -> bool {
+        if let Some(refilled) = self.refilled {
+            let elapsed = now.duration_since(refilled).as_secs_f64();
+            // Written so that NaN rates, and infinite ones without elapsed time, add nothing
+            let refill = if refill_per_sec > 0.0 {
+                refill_per_sec
+            } else {
+                0.0
+            };
+            let added = elapsed * refill;
+            if added > 0.0 {
+                self.tokens = (self.tokens + added).min(f64::from(capacity.max(1)));
+            }
+        }
+        self.refilled = Some(now);
+
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+
+    /// Moves the theoretical arrival time forward by `period`, unless `now` is more
+    /// than `burst - 1` periods ahead of it.
+    fn take_arrival(&mut self, period: Duration, burst: u32, now: Instant) -> bool {
+        if self.paced_out {
+            return false;
+        }
+        let tolerance = period.saturating_mul(burst.max(1) - 1);
+        let tat = self.tat.map_or(now, |tat| tat.max(now));
+        if tat.duration_since(now) > tolerance {
+            return false;
+        }
+        self.pace(tat, period);
+        true
+    }
+
+    /// Moves the theoretical arrival time to `period` after `tat`, saturating at the
+    /// end of the time line, after which nothing is admitted anymore.
+    fn pace(&mut self, tat: Instant, period: Duration) {
+        self.tat = tat.checked_add(period);
+        self.paced_out = self.tat.is_none();
+    }
+
+    /// Returns which component of `limit` was reached, if any.
+    fn exceeds_limit(&self, limit: &Limit, mode: CountMode) -> Option<Trigger> {
+        // Until the first warning, the first occurrence may count as well
+        let count = match mode {
+            CountMode::IncludeFirst if self.last_warning.is_none() => self.count.saturating_add(1),
+            _ => self.count,
+        };
+        let (count_reached, duration_reached) = match limit {
+            Limit::Rate(limit_count) => (count >= *limit_count, false),
+            Limit::Duration(limit_duration) => (false, self.duration >= *limit_duration),
+            Limit::Any(limit_count, limit_duration) => {
+                (count >= *limit_count, self.duration >= *limit_duration)
+            }
+            Limit::All(limit_count, limit_duration) => {
+                let count_reached = count >= *limit_count;
+                let duration_reached = self.duration >= *limit_duration;
+                if !(count_reached && duration_reached) {
+                    return None;
+                }
+                (count_reached, duration_reached)
+            }
+            Limit::Window { .. }
+            | Limit::Bytes { .. }
+            | Limit::TokenBucket { .. }
+            | Limit::Gcra { .. }
+            | Limit::Exponential { .. }
+            | Limit::Sample(_)
+            | Limit::BurstThenSteady { .. }
+            | Limit::Never
+            | Limit::Always => return None,
+        };
+
+        match (count_reached, duration_reached) {
+            (true, true) => Some(Trigger::CountAndDuration),
+            (true, false) => Some(Trigger::Count),
+            (false, true) => Some(Trigger::Duration),
+            (false, false) => None,
+        }
+    }
+}
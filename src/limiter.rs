@@ -0,0 +1,100 @@
+//! Custom rate limiting algorithms.
+
+use alloc::boxed::Box;
+
+use crate::clock::Instant;
+use crate::Trigger;
+
+/// What to do with an occurrence of a message, as decided by a [`Limiter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Decision {
+    /// Emit the message verbatim.
+    Emit,
+    /// Emit the message annotated with its number of occurrences so far, as
+    /// `(seen N times)`.
+    EmitCounted(u64),
+    /// Emit the message annotated with the sampling rate, as `(sampled 1/N)`.
+    EmitSampled(u32),
+    /// Emit the message annotated with the number of occurrences suppressed before
+    /// it, as `(+K more)`.
+    EmitMore(u64),
+    /// Count the message silently.
+    Suppress,
+    /// Emit a rate limit warning covering the pending repeats, including this one.
+    Report(Trigger),
+}
+
+impl Decision {
+    /// Returns `true` if the message itself is emitted.
+    pub(crate) fn emits(&self) -> bool {
+        !matches!(self, Decision::Suppress | Decision::Report(_))
+    }
+}
+
+/// A rate limiting algorithm deciding the fate of every occurrence of a message.
+///
+/// The built-in algorithms are the variants of [`Limit`](crate::Limit). A custom
+/// limiter installed with [`RateLog::with_limiter`](crate::RateLog::with_limiter)
+/// replaces them, e.g. to enforce business-specific quotas, while the `RateLog` keeps
+/// doing the bookkeeping: it counts suppressed repeats and the time between them,
+/// reports them in warnings and summaries, and keeps the statistics.
+///
+/// The limiter keeps whatever state it needs, per key or across keys. It is asked
+/// about every occurrence, including the first one of a key, which is withheld like
+/// a quiet key (see [`RateLog::with_quiet_key`](crate::RateLog::with_quiet_key)) if
+/// the decision does not emit it. Suppressed repeats stay pending until a
+/// [`Decision::Report`] or a summary covers them, even if repeats in between are
+/// emitted. Closures `FnMut(Instant, &str) -> Decision`
+/// implement the trait.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{Decision, Limit, Limiter, RateLog, Trigger};
+/// use std::collections::HashMap;
+/// use std::time::Instant;
+///
+/// /// Emits each key at most `quota` times, ever.
+/// struct Quota {
+///     quota: u32,
+///     used: HashMap<String, u32>,
+/// }
+///
+/// impl Limiter for Quota {
+///     fn on_event(&mut self, _now: Instant, key: &str) -> Decision {
+///         let used = self.used.entry(key.to_string()).or_default();
+///         *used += 1;
+///         match *used {
+///             used if used <= self.quota => Decision::Emit,
+///             used if used % 100 == 0 => Decision::Report(Trigger::Count),
+///             _ => Decision::Suppress,
+///         }
+///     }
+/// }
+///
+/// let mut logger = RateLog::new(Limit::Rate(10)).with_limiter(Quota {
+///     quota: 2,
+///     used: HashMap::new(),
+/// });
+///
+/// logger.log("Disk full");  // Prints: "Disk full"
+/// logger.log("Disk full");  // Prints: "Disk full"
+/// logger.log("Disk full");  // Silent, the quota is used up
+/// ```
+pub trait Limiter: Send {
+    /// Decides how to handle an occurrence of `key` at `now`.
+    fn on_event(&mut self, now: Instant, key: &str) -> Decision;
+}
+
+impl Limiter for Box<dyn Limiter> {
+    fn on_event(&mut self, now: Instant, key: &str) -> Decision {
+        (**self).on_event(now, key)
+    }
+}
+
+impl<F: FnMut(Instant, &str) -> Decision + Send> Limiter for F {
+    fn on_event(&mut self, now: Instant, key: &str) -> Decision {
+        self(now, key)
+    }
+}
@@ -0,0 +1,94 @@
+//! One [`RateLog`] per thread, without locking.
+
+use std::cell::RefCell;
+use std::thread::LocalKey;
+
+use crate::{Limit, LogOutcome, RateLog};
+
+/// The per-thread storage behind a [`ThreadRateLog`], declared by
+/// [`thread_rate_log!`](crate::thread_rate_log!).
+#[doc(hidden)]
+pub type ThreadSlot = RefCell<Option<RateLog>>;
+
+/// A handle to a [`RateLog`] owned by the current thread, returned by
+/// [`thread_rate_log!`](crate::thread_rate_log!).
+///
+/// Every thread logging through the same handle gets its own limiter, created on its
+/// first call, so threads never contend for a lock. Repeats are counted per thread:
+/// a message logged once by each of four threads is printed four times. This suits
+/// thread-per-connection designs, where each thread should be throttled on its own;
+/// use [`SharedRateLog`](crate::SharedRateLog) to rate limit across threads instead.
+///
+/// A thread's limiter is dropped when the thread exits, which reports its pending
+/// repeats. Logging through the handle from within its own sink panics.
+#[derive(Clone)]
+pub struct ThreadRateLog {
+    /// The per-thread storage of the limiter.
+    slot: &'static LocalKey<ThreadSlot>,
+    /// The limit of limiters created for new threads.
+    limit: Limit,
+}
+
+impl ThreadRateLog {
+    #[doc(hidden)]
+    pub fn new(slot: &'static LocalKey<ThreadSlot>, limit: Limit) -> Self {
+        ThreadRateLog { slot, limit }
+    }
+
+    /// Logs a message through the limiter of the current thread.
+    ///
+    /// See [`RateLog::log`] for the rate limiting behavior.
+    pub fn log(&self, msg: &str) -> LogOutcome {
+        self.with(|rate_log| rate_log.log(msg))
+    }
+
+    /// Logs a message rate limited under `key` through the limiter of the current
+    /// thread.
+    ///
+    /// See [`RateLog::log_keyed`] for the rate limiting behavior.
+    pub fn log_keyed(&self, key: &str, msg: &str) -> LogOutcome {
+        self.with(|rate_log| rate_log.log_keyed(key, msg))
+    }
+
+    /// Reports the pending repeats of the current thread, see [`RateLog::flush`].
+    pub fn flush(&self) {
+        self.with(RateLog::flush);
+    }
+
+    /// Calls `f` with the limiter of the current thread, creating it if needed.
+    pub fn with<R>(&self, f: impl FnOnce(&mut RateLog) -> R) -> R {
+        self.slot.with(|slot| {
+            let mut slot = slot.borrow_mut();
+            f(slot.get_or_insert_with(|| RateLog::new(self.limit.clone())))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_thread_rate_log() {
+        let handle = || crate::thread_rate_log!(Limit::Rate(2));
+        let logger = handle();
+
+        assert_eq!(logger.log("message1"), LogOutcome::Emitted);
+        assert_eq!(logger.log("message1"), LogOutcome::Suppressed);
+
+        // Another thread starts with a limiter of its own
+        let other = logger.clone();
+        let outcomes = thread::spawn(move || [other.log("message1"), other.log("message1")])
+            .join()
+            .unwrap();
+        assert_eq!(outcomes, [LogOutcome::Emitted, LogOutcome::Suppressed]);
+
+        // The call site is the same, so is the limiter
+        assert!(matches!(
+            handle().log("message1"),
+            LogOutcome::LimitReported { count: 2, .. }
+        ));
+        assert_eq!(logger.with(|rate_log| rate_log.stats().messages().len()), 1);
+    }
+}
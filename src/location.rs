@@ -0,0 +1,77 @@
+//! The source code location a message was logged from.
+
+use core::fmt;
+
+/// The place in the source code a message was logged from.
+///
+/// Passed to [`RateLog::log_at`](crate::RateLog::log_at), usually created with
+/// [`location!`](crate::location!), which also records the module. The location of
+/// the latest occurrence is appended to emitted messages and to the warnings and
+/// summaries of their repeats, so a summary still points at the code producing it.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{location, Location};
+///
+/// let here = location!();
+/// assert_eq!(here.file(), file!());
+/// assert_eq!(here.module_path(), Some(module_path!()));
+///
+/// let location = Location::new("src/db.rs", 42, Some("app::db"));
+/// assert_eq!(location.to_string(), "src/db.rs:42 in app::db");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Location {
+    /// The source file.
+    file: &'static str,
+    /// The line within the file.
+    line: u32,
+    /// The module path, if known.
+    module_path: Option<&'static str>,
+}
+
+impl Location {
+    /// Creates a location from its parts, e.g. `file!()`, `line!()` and `module_path!()`.
+    pub const fn new(file: &'static str, line: u32, module_path: Option<&'static str>) -> Self {
+        Location {
+            file,
+            line,
+            module_path,
+        }
+    }
+
+    /// Returns the location of the caller of the function this is called in, without
+    /// the module path, see [`core::panic::Location::caller`].
+    #[track_caller]
+    pub fn caller() -> Self {
+        let location = core::panic::Location::caller();
+        Location::new(location.file(), location.line(), None)
+    }
+
+    /// Returns the source file.
+    pub fn file(&self) -> &'static str {
+        self.file
+    }
+
+    /// Returns the line within the file.
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Returns the module path, if known.
+    pub fn module_path(&self) -> Option<&'static str> {
+        self.module_path
+    }
+}
+
+impl fmt::Display for Location {
+    /// Formats the location as `file:line`, followed by ` in module` if known.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)?;
+        if let Some(module_path) = self.module_path {
+            write!(f, " in {module_path}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,286 @@
+//! Throttling in front of an existing `log` backend.
+
+use std::sync::Mutex;
+
+use log::{Level, Log, Metadata, Record};
+
+use crate::{Limit, LineKind, RateLog, Severity, Sink};
+
+/// The target of summaries emitted on [`Log::flush`], which belong to no record, and of
+/// the records written by [`LogSink`].
+const TARGET: &str = "rate_log";
+
+/// A `log::Log` implementation rate limiting the records passed to another one.
+///
+/// Lets `env_logger`, `fern` or `simplelog` users insert throttling in front of their
+/// current backend. Records are rate limited by their message, with the limit of their
+/// target if one is set with [`RateLimited::with_target_limit`]: first occurrences
+/// are forwarded unchanged, suppressed records are dropped, and rate limit warnings
+/// and summaries are forwarded as synthetic `Warn` records with the target and
+/// location of the record that triggered them; summaries written by `log::logger().flush()`
+/// use the `rate_log` target. Records the inner logger is not enabled for are neither
+/// forwarded nor counted.
+///
+/// # Examples
+///
+/// ```rust
+/// use log::{Log, Metadata, Record};
+/// use rate_log::{Limit, RateLimited};
+///
+/// struct Backend;
+///
+/// impl Log for Backend {
+///     fn enabled(&self, _: &Metadata) -> bool {
+///         true
+///     }
+///
+///     fn log(&self, record: &Record) {
+///         println!("{} {}", record.level(), record.args());
+///     }
+///
+///     fn flush(&self) {}
+/// }
+///
+/// // E.g. Box::new(env_logger::Builder::from_default_env().build())
+/// let logger = RateLimited::wrap(Box::new(Backend), Limit::Rate(100));
+/// log::set_boxed_logger(Box::new(logger)).unwrap();
+/// log::set_max_level(log::LevelFilter::Info);
+///
+/// log::warn!("disk full");  // Prints: "WARN disk full"
+/// log::warn!("disk full");  // Dropped
+/// ```
+pub struct RateLimited {
+    /// The backend records are forwarded to.
+    inner: Box<dyn Log>,
+
+    /// Decides which records are forwarded, collecting the lines to forward.
+    rate_log: Mutex<RateLog<Lines>>,
+}
+
+/// A sink collecting the lines of one logging call.
+#[derive(Default)]
+struct Lines(Vec<(String, LineKind)>);
+
+impl Sink for Lines {
+    fn emit(&mut self, record: &str) {
+        self.emit_line(record, LineKind::Summary);
+    }
+
+    fn emit_line(&mut self, record: &str, kind: LineKind) {
+        self.0.push((record.to_string(), kind));
+    }
+}
+
+impl RateLimited {
+    /// Wraps `inner`, forwarding records to it rate limited by `limit`.
+    pub fn wrap(inner: Box<dyn Log>, limit: Limit) -> Self {
+        RateLimited {
+            inner,
+            rate_log: Mutex::new(RateLog::with_sink(limit, Lines::default())),
+        }
+    }
+
+    /// Applies `limit` to the records of targets matching `pattern`, such as `"db"` or
+    /// `"net::*"`.
+    ///
+    /// See [`RateLog::with_target_limit`] for how patterns match.
+    pub fn with_target_limit(self, pattern: impl Into<String>, limit: Limit) -> Self {
+        let rate_log = self
+            .rate_log
+            .into_inner()
+            .unwrap_or_else(|err| err.into_inner());
+        RateLimited {
+            inner: self.inner,
+            rate_log: Mutex::new(rate_log.with_target_limit(pattern, limit)),
+        }
+    }
+
+    /// Runs `f` on the limiter and returns the lines it emitted.
+    fn lines(&self, f: impl FnOnce(&mut RateLog<Lines>)) -> Vec<(String, LineKind)> {
+        // A panicking backend never runs while the lock is held
+        let mut rate_log = self.rate_log.lock().unwrap_or_else(|err| err.into_inner());
+        f(&mut rate_log);
+        std::mem::take(&mut rate_log.sink_mut().0)
+    }
+
+    /// Forwards a line written by the limiter itself as a `Warn` record.
+    fn forward_summary(&self, line: &str, origin: Option<&Record>) {
+        let (target, module_path, file, line_number) = match origin {
+            Some(origin) => (
+                origin.target(),
+                origin.module_path(),
+                origin.file(),
+                origin.line(),
+            ),
+            None => (TARGET, None, None, None),
+        };
+        self.inner.log(
+            &Record::builder()
+                .args(format_args!("{line}"))
+                .level(Level::Warn)
+                .target(target)
+                .module_path(module_path)
+                .file(file)
+                .line(line_number)
+                .build(),
+        );
+    }
+}
+
+impl Log for RateLimited {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.inner.enabled(record.metadata()) {
+            return;
+        }
+        let msg = record.args().to_string();
+        let lines = self.lines(|rate_log| {
+            let limit = rate_log.target_limit(record.target());
+            rate_log.track_message(&msg, severity(record.level()), limit);
+        });
+        for (line, kind) in lines {
+            match kind {
+                LineKind::Message(_) => self.inner.log(record),
+                LineKind::Summary => self.forward_summary(&line, Some(record)),
+            }
+        }
+    }
+
+    fn flush(&self) {
+        for (line, _) in self.lines(RateLog::flush) {
+            self.forward_summary(&line, None);
+        }
+        self.inner.flush();
+    }
+}
+
+/// A sink writing every line as a record to the installed `log` backend.
+///
+/// Bridges a limiter into an application logging through `log`, e.g. to route
+/// rate limited panics to `log::error!` with
+/// [`install_panic_hook_with`](crate::install_panic_hook_with). Messages are logged
+/// with the level of their [`Severity`], warnings, summaries and notices as `Warn`,
+/// all with the `rate_log` target.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{install_panic_hook_with, Limit, LogSink, RateLog};
+///
+/// install_panic_hook_with(RateLog::with_sink(Limit::Rate(10), LogSink));
+/// # let _ = std::panic::take_hook();
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LogSink;
+
+impl Sink for LogSink {
+    fn emit(&mut self, record: &str) {
+        self.emit_line(record, LineKind::Summary);
+    }
+
+    fn emit_line(&mut self, record: &str, kind: LineKind) {
+        let level = match kind {
+            LineKind::Message(severity) => level(severity),
+            LineKind::Summary => Level::Warn,
+        };
+        log::log!(target: TARGET, level, "{record}");
+    }
+
+    fn flush(&mut self) {
+        log::logger().flush();
+    }
+}
+
+/// Returns the `log` level of a severity.
+fn level(severity: Severity) -> Level {
+    match severity {
+        Severity::Error => Level::Error,
+        Severity::Warn => Level::Warn,
+        Severity::Info => Level::Info,
+        Severity::Debug => Level::Debug,
+        Severity::Trace => Level::Trace,
+    }
+}
+
+/// Returns the severity of a `log` level.
+fn severity(level: Level) -> Severity {
+    match level {
+        Level::Error => Severity::Error,
+        Level::Warn => Severity::Warn,
+        Level::Info => Severity::Info,
+        Level::Debug => Severity::Debug,
+        Level::Trace => Severity::Trace,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// A backend recording the level, target and message of every record.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl Log for Recorder {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.level() <= Level::Info
+        }
+
+        fn log(&self, record: &Record) {
+            let line = format!("{} {} {}", record.level(), record.target(), record.args());
+            self.0.lock().unwrap().push(line);
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn test_rate_limited() {
+        let recorder = Recorder::default();
+        let logger = RateLimited::wrap(Box::new(recorder.clone()), Limit::Rate(2))
+            .with_target_limit("db::*", Limit::Never);
+        let log = |level, msg: &str| {
+            logger.log(
+                &Record::builder()
+                    .args(format_args!("{msg}"))
+                    .level(level)
+                    .target("app::net")
+                    .build(),
+            );
+        };
+
+        for _ in 0..4 {
+            log(Level::Error, "connection reset");
+        }
+        log(Level::Debug, "connection reset");
+        log(Level::Info, "retrying");
+        log(Level::Info, "retrying");
+        logger.flush();
+        for _ in 0..2 {
+            logger.log(
+                &Record::builder()
+                    .args(format_args!("slow query"))
+                    .level(Level::Info)
+                    .target("db::pool")
+                    .build(),
+            );
+        }
+
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            [
+                "ERROR app::net connection reset",
+                "WARN app::net Message: \"connection reset\" repeat for 2 times in the past 0ms",
+                "WARN app::net Message: \"connection reset\" suppressed 1 repeats over 0ms",
+                "INFO app::net retrying",
+                "WARN rate_log Message: \"retrying\" suppressed 1 repeats over 0ms",
+                "INFO db::pool slow query",
+                "INFO db::pool slow query",
+            ]
+        );
+    }
+}
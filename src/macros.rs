@@ -0,0 +1,249 @@
+//! Convenience macros with a per-call-site rate limiter.
+
+/// Logs a `format!`-style message through a rate limiter private to the call site.
+///
+/// The first argument is the [`Limit`](crate::Limit) to apply; it is evaluated once,
+/// when the call site is reached for the first time. The limiter is stored in a
+/// static owned by the macro invocation, so no `RateLog` needs to be threaded
+/// through the program and different call sites never influence each other. The
+/// limiter is shared between threads. Messages are logged with the
+/// [`location!`](crate::location!) of the invocation, see
+/// [`RateLog::log_at`](crate::RateLog::log_at).
+///
+/// Evaluates to the [`LogOutcome`](crate::LogOutcome) of the call.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{rate_log, Limit};
+///
+/// for attempt in 0..100 {
+///     // Prints "polling queue (at src/main.rs:5 in app)" once, then a warning every 10 repeats
+///     rate_log!(Limit::Rate(10), "polling {}", "queue");
+/// }
+/// ```
+#[macro_export]
+macro_rules! rate_log {
+    ($limit:expr, $($arg:tt)+) => {
+        $crate::__rate_log_at_call_site!($limit, "", $($arg)+)
+    };
+}
+
+/// Logs a `format!`-style warning through a rate limiter private to the call site.
+///
+/// Behaves like [`rate_log!`](crate::rate_log!) but prefixes the message with `[WARN] `.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{rate_warn, Limit};
+///
+/// let host = "10.0.0.1";
+/// let err = "connection refused";
+/// rate_warn!(Limit::Rate(10), "retrying {} ({})", host, err);
+/// // Prints: "[WARN] retrying 10.0.0.1 (connection refused) (at src/main.rs:5 in app)"
+/// ```
+#[macro_export]
+macro_rules! rate_warn {
+    ($limit:expr, $($arg:tt)+) => {
+        $crate::__rate_log_at_call_site!($limit, "[WARN] ", $($arg)+)
+    };
+}
+
+/// Logs a `format!`-style error through a rate limiter private to the call site.
+///
+/// Behaves like [`rate_log!`](crate::rate_log!) but prefixes the message with `[ERROR] `.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{rate_error, Limit};
+/// use std::time::Duration;
+///
+/// let path = "/var/data";
+/// rate_error!(Limit::Duration(Duration::from_secs(60)), "disk full: {}", path);
+/// // Prints: "[ERROR] disk full: /var/data (at src/main.rs:5 in app)"
+/// ```
+#[macro_export]
+macro_rules! rate_error {
+    ($limit:expr, $($arg:tt)+) => {
+        $crate::__rate_log_at_call_site!($limit, "[ERROR] ", $($arg)+)
+    };
+}
+
+/// Logs a `format!`-style message through the process-wide rate limiter.
+///
+/// Unlike [`rate_log!`](crate::rate_log!), all `glog!` call sites share one limiter,
+/// configured once with [`init_global`](crate::init_global). Messages are logged with
+/// the [`location!`](crate::location!) of the invocation. Evaluates to the
+/// [`LogOutcome`](crate::LogOutcome) of the call.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{glog, init_global, Limit};
+///
+/// init_global(Limit::Rate(2)).unwrap();
+///
+/// for _ in 0..3 {
+///     glog!("disk {} full", "sda");
+/// }
+/// // Prints: "disk sda full (at src/main.rs:6 in app)"
+/// // Prints: "Message: \"disk sda full\" repeat for 2 times in the past 0ms, at src/main.rs:6 in app"
+/// ```
+#[macro_export]
+macro_rules! glog {
+    ($($arg:tt)+) => {
+        $crate::global().log_at(&::std::format!($($arg)+), $crate::location!())
+    };
+}
+
+/// Returns the [`Location`](crate::Location) of the invocation, including the module.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{location, Limit, RateLog};
+///
+/// let mut logger = RateLog::new(Limit::Rate(10));
+/// logger.log_at("Disk full", location!());  // Prints: "Disk full (at src/main.rs:4 in app)"
+/// ```
+#[macro_export]
+macro_rules! location {
+    () => {
+        $crate::Location::new(
+            ::core::file!(),
+            ::core::line!(),
+            ::core::option::Option::Some(::core::module_path!()),
+        )
+    };
+}
+
+/// Logs a `format!`-style message through `logger`, rate limited per call site.
+///
+/// The key is the `file:line` of the invocation rather than the message text, so a
+/// call site that formats a different message every time, e.g. with a request id, is
+/// still throttled as one logical source. Works with any logger providing
+/// `log_keyed(key, msg)`, such as [`RateLog`](crate::RateLog),
+/// [`SharedRateLog`](crate::SharedRateLog) or `AsyncRateLog`, and evaluates to the
+/// [`LogOutcome`](crate::LogOutcome) of the call.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{rate_log_here, Limit, RateLog};
+///
+/// let mut logger = RateLog::new(Limit::Rate(2));
+///
+/// for id in 0..3 {
+///     rate_log_here!(logger, "request {} failed", id);
+/// }
+/// // Prints: "request 0 failed"
+/// // Prints: "Key: \"src/main.rs:7\" repeat for 2 times in the past 0ms, last message: \"request 2 failed\""
+/// ```
+#[macro_export]
+macro_rules! rate_log_here {
+    ($logger:expr, $($arg:tt)+) => {
+        $logger.log_keyed(
+            ::core::concat!(::core::file!(), ":", ::core::line!()),
+            &$crate::__format!($($arg)+),
+        )
+    };
+}
+
+/// Returns a handle to a rate limiter private to the call site and the current thread.
+///
+/// The argument is the [`Limit`](crate::Limit) to apply. Each thread calling through
+/// the handle lazily gets its own [`RateLog`](crate::RateLog) writing to stdout, so
+/// logging never takes a lock, and repeats are counted per thread. Call sites never
+/// share limiters. See [`ThreadRateLog`](crate::ThreadRateLog).
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{thread_rate_log, Limit};
+/// use std::thread;
+///
+/// let workers: Vec<_> = (0..4)
+///     .map(|id| {
+///         thread::spawn(move || {
+///             let logger = thread_rate_log!(Limit::Rate(10));
+///             for _ in 0..100 {
+///                 // Prints once per thread, then a warning every 10 repeats
+///                 logger.log(&format!("connection {id} stalled"));
+///             }
+///         })
+///     })
+///     .collect();
+/// for worker in workers {
+///     worker.join().unwrap();
+/// }
+/// ```
+#[macro_export]
+macro_rules! thread_rate_log {
+    ($limit:expr) => {{
+        ::std::thread_local! {
+            static LIMITER: $crate::__ThreadSlot = const { ::core::cell::RefCell::new(::core::option::Option::None) };
+        }
+        $crate::ThreadRateLog::new(&LIMITER, $limit)
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __rate_log_at_call_site {
+    ($limit:expr, $prefix:expr, $($arg:tt)+) => {{
+        static LIMITER: ::std::sync::OnceLock<$crate::SharedRateLog> =
+            ::std::sync::OnceLock::new();
+        LIMITER
+            .get_or_init(|| $crate::SharedRateLog::new($limit))
+            .log_at(
+                &::std::format!("{}{}", $prefix, ::std::format_args!($($arg)+)),
+                $crate::location!(),
+            )
+    }};
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{Limit, LogOutcome};
+
+    #[test]
+    fn test_call_site_macros() {
+        let outcomes: Vec<_> = (0..4)
+            .map(|_| rate_warn!(Limit::Rate(2), "retrying {} ({})", "host", "refused"))
+            .collect();
+        assert_eq!(outcomes[0], LogOutcome::Emitted);
+        assert_eq!(outcomes[1], LogOutcome::Suppressed);
+        assert!(matches!(
+            outcomes[2],
+            LogOutcome::LimitReported { count: 2, .. }
+        ));
+        assert_eq!(outcomes[3], LogOutcome::Suppressed);
+
+        // Each call site owns its limiter, even for identical messages
+        for _ in 0..2 {
+            assert_eq!(
+                rate_error!(Limit::Rate(2), "fatal"),
+                rate_error!(Limit::Rate(2), "fatal")
+            );
+        }
+        assert_eq!(rate_log!(Limit::Rate(2), "fatal"), LogOutcome::Emitted);
+    }
+
+    #[test]
+    fn test_global_macro() {
+        // The global limiter is shared by the whole test binary, so only this test uses it
+        assert!(crate::init_global(Limit::Rate(1)).is_ok());
+        assert!(matches!(
+            crate::init_global(Limit::Rate(5)),
+            Err(crate::Error::AlreadyInitialized)
+        ));
+
+        assert_eq!(glog!("global {}", 1), LogOutcome::Emitted);
+        assert!(matches!(
+            glog!("global {}", 1),
+            LogOutcome::LimitReported { count: 1, .. }
+        ));
+    }
+}
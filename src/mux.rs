@@ -0,0 +1,116 @@
+//! Routing of messages to one of several rate limiters.
+
+use crate::sink::DefaultSink;
+use crate::{LogOutcome, RateLog, Sink};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// A predicate deciding whether a message belongs to a route.
+type Predicate = Box<dyn Fn(&str) -> bool + Send>;
+
+/// A multiplexer that routes each message to one of several rate limiters.
+///
+/// Routes are checked in the order they were added and the first route whose
+/// predicate matches the message wins. Messages matching no route fall through to
+/// the default limiter. This lets wildly different policies (audit vs debug vs
+/// network) sit behind a single `log()` entry point.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{Limit, Mux, RateLog};
+///
+/// let mut mux = Mux::new(RateLog::new(Limit::Rate(10)))
+///     .route(|msg| msg.starts_with("audit:"), RateLog::new(Limit::Rate(1000)))
+///     .route(|msg| msg.contains("timeout"), RateLog::new(Limit::Rate(3)));
+///
+/// mux.log("audit: user logged in");  // Routed to the audit limiter
+/// mux.log("request timeout");        // Routed to the network limiter
+/// mux.log("cache miss");             // Falls through to the default limiter
+/// ```
+pub struct Mux<S: Sink = DefaultSink> {
+    /// The configured routes, checked in insertion order.
+    routes: Vec<(Predicate, RateLog<S>)>,
+
+    /// The limiter receiving messages that match no route.
+    default: RateLog<S>,
+}
+
+impl<S: Sink> Mux<S> {
+    /// Creates a multiplexer that sends every message to `default` until routes are added.
+    pub fn new(default: RateLog<S>) -> Self {
+        Mux {
+            routes: Vec::new(),
+            default,
+        }
+    }
+
+    /// Adds a route sending messages matching `predicate` to `limiter`.
+    ///
+    /// Routes added earlier take precedence over routes added later.
+    pub fn route<P>(mut self, predicate: P, limiter: RateLog<S>) -> Self
+    where
+        P: Fn(&str) -> bool + Send + 'static,
+    {
+        self.routes.push((Box::new(predicate), limiter));
+        self
+    }
+
+    /// Logs a message through the first limiter whose route matches it.
+    ///
+    /// Returns the [`LogOutcome`] reported by the selected limiter.
+    pub fn log(&mut self, msg: &str) -> LogOutcome {
+        self.limiter_for_mut(msg).log(msg)
+    }
+
+    /// Returns the limiter that `msg` would be routed to.
+    pub fn limiter_for(&self, msg: &str) -> &RateLog<S> {
+        self.routes
+            .iter()
+            .find(|(predicate, _)| predicate(msg))
+            .map_or(&self.default, |(_, limiter)| limiter)
+    }
+
+    /// Returns a mutable reference to the limiter that `msg` would be routed to.
+    pub fn limiter_for_mut(&mut self, msg: &str) -> &mut RateLog<S> {
+        self.routes
+            .iter_mut()
+            .find(|(predicate, _)| predicate(msg))
+            .map_or(&mut self.default, |(_, limiter)| limiter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CaptureSink, Limit};
+
+    #[test]
+    fn test_mux_routing() {
+        let mut mux = Mux::new(RateLog::with_sink(Limit::Rate(1), CaptureSink::new()))
+            .route(
+                |msg| msg.starts_with("audit:"),
+                RateLog::with_sink(Limit::Rate(100), CaptureSink::new()),
+            )
+            .route(
+                |msg| msg.starts_with("audit: login"),
+                RateLog::with_sink(Limit::Rate(1), CaptureSink::new()),
+            );
+
+        for _ in 0..3 {
+            mux.log("audit: login");
+            mux.log("audit: login");
+            mux.log("debug");
+            mux.log("debug");
+        }
+
+        // First matching route wins, so the stricter second route never sees messages
+        let audit = mux.limiter_for("audit: login").sink().lines();
+        assert_eq!(audit, ["audit: login"]);
+
+        // Every repeat trips the default Rate(1) limit
+        let default = mux.limiter_for("debug").sink().lines();
+        assert_eq!(default.len(), 6);
+        assert_eq!(default[0], "debug");
+    }
+}
@@ -0,0 +1,141 @@
+//! Grouping of messages that differ only in variable details.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+/// Derives the rate limiting key of a message by stripping variable details.
+///
+/// Messages with the same normalized form are counted as repeats of each other, while
+/// the emitted text keeps the original details. Warnings then report the normalized
+/// form as the key together with the last message seen.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::Normalizer;
+///
+/// assert_eq!(
+///     Normalizer::StripNumbers.normalize("request 123 timed out after 30s"),
+///     "request <num> timed out after <num>s"
+/// );
+/// assert_eq!(
+///     Normalizer::StripUuids.normalize("job 67e55044-10b1-426f-9247-bb680e5fe0c8 failed"),
+///     "job <uuid> failed"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub enum Normalizer {
+    /// Replaces every run of ASCII digits with `<num>`.
+    StripNumbers,
+
+    /// Replaces every UUID in the canonical 8-4-4-4-12 hexadecimal form with `<uuid>`.
+    StripUuids,
+
+    /// Applies both [`Normalizer::StripUuids`] and [`Normalizer::StripNumbers`].
+    StripAll,
+
+    /// A user-supplied normalization.
+    Custom(fn(&str) -> Cow<'_, str>),
+}
+
+impl Normalizer {
+    /// Returns the normalized form of `msg`, borrowing it unchanged if nothing was stripped.
+    pub fn normalize<'a>(&self, msg: &'a str) -> Cow<'a, str> {
+        match self {
+            Normalizer::StripNumbers => strip_numbers(msg),
+            Normalizer::StripUuids => strip_uuids(msg),
+            Normalizer::StripAll => match strip_uuids(msg) {
+                Cow::Borrowed(msg) => strip_numbers(msg),
+                Cow::Owned(msg) => Cow::Owned(strip_numbers(&msg).into_owned()),
+            },
+            Normalizer::Custom(normalize) => normalize(msg),
+        }
+    }
+}
+
+/// Replaces runs of ASCII digits with `<num>`.
+fn strip_numbers(msg: &str) -> Cow<'_, str> {
+    if !msg.bytes().any(|b| b.is_ascii_digit()) {
+        return Cow::Borrowed(msg);
+    }
+
+    let mut out = String::with_capacity(msg.len());
+    let mut in_number = false;
+    for c in msg.chars() {
+        if c.is_ascii_digit() {
+            if !in_number {
+                out.push_str("<num>");
+            }
+            in_number = true;
+        } else {
+            out.push(c);
+            in_number = false;
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// The length of a canonical UUID such as `67e55044-10b1-426f-9247-bb680e5fe0c8`.
+const UUID_LEN: usize = 36;
+
+/// Returns `true` if `bytes` starts with a UUID in canonical form.
+fn is_uuid(bytes: &[u8]) -> bool {
+    bytes.len() >= UUID_LEN
+        && bytes[..UUID_LEN].iter().enumerate().all(|(i, b)| match i {
+            8 | 13 | 18 | 23 => *b == b'-',
+            _ => b.is_ascii_hexdigit(),
+        })
+}
+
+/// Replaces UUIDs in canonical form with `<uuid>`.
+fn strip_uuids(msg: &str) -> Cow<'_, str> {
+    let bytes = msg.as_bytes();
+    let mut out = String::new();
+    let mut copied = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        // Only match UUIDs that do not continue a longer hexadecimal word
+        let boundary = i == 0 || !bytes[i - 1].is_ascii_hexdigit();
+        if boundary && is_uuid(&bytes[i..]) {
+            out.push_str(&msg[copied..i]);
+            out.push_str("<uuid>");
+            i += UUID_LEN;
+            copied = i;
+        } else {
+            i += 1;
+        }
+    }
+
+    if copied == 0 {
+        return Cow::Borrowed(msg);
+    }
+    out.push_str(&msg[copied..]);
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalizers() {
+        assert!(matches!(
+            Normalizer::StripAll.normalize("no details"),
+            Cow::Borrowed("no details")
+        ));
+        assert_eq!(
+            Normalizer::StripAll.normalize("1 of 67E55044-10B1-426F-9247-BB680E5FE0C8: 42"),
+            "<num> of <uuid>: <num>"
+        );
+        // Too short and embedded in a longer word
+        assert_eq!(
+            Normalizer::StripUuids
+                .normalize("67e55044-10b1-426f-9247 a67e55044-10b1-426f-9247-bb680e5fe0c8"),
+            "67e55044-10b1-426f-9247 a67e55044-10b1-426f-9247-bb680e5fe0c8"
+        );
+        assert_eq!(
+            Normalizer::Custom(|msg| Cow::Owned(msg.to_lowercase())).normalize("Disk FULL"),
+            "disk full"
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! The textual representation of emitted lines.
+
+#[cfg(feature = "serde")]
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::{String, ToString};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::Location;
+#[cfg(feature = "serde")]
+use crate::{Warning, WarningKind};
+
+/// How emitted lines are rendered.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use rate_log::{RateLog, Limit, OutputFormat};
+///
+/// let mut logger = RateLog::new(Limit::Rate(2)).with_output_format(OutputFormat::Json);
+///
+/// logger.log("Disk full");  // Prints: {"v":1,"msg":"Disk full","kind":"message"}
+/// logger.log("Disk full");  // Silent (1st repetition)
+/// logger.log("Disk full");
+/// // Prints: {"v":1,"msg":"Disk full","repeats":2,"window_ms":0,"kind":"rate_limited"}
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Plain text, e.g. `Message: "Disk full" repeat for 5 times in the past 103ms`.
+    #[default]
+    Text,
+
+    /// One [`EventRecord`] serialized as a JSON object per line, e.g.
+    /// `{"v":1,"msg":"Disk full","repeats":5,"window_ms":103,"kind":"rate_limited"}`.
+    ///
+    /// Every record has a `msg` and a `kind`: `message` for logged messages,
+    /// `rate_limited` and `summary` for warnings (with `repeats` and `window_ms`), and
+    /// `alternation`, `announce` or `top_offenders` for the other notices. Keyed
+    /// warnings add the `key`; optional fields such as `seen`, `sample_rate`,
+    /// `trigger`, `exemplar` and the `file`, `line` and `module` of a located
+    /// message appear only when they apply.
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+/// An annotation on an emitted message.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Annotation {
+    /// The number of occurrences so far, see [`Limit::Exponential`](crate::Limit::Exponential).
+    Seen(u64),
+    /// The sampling rate, see [`Limit::Sample`](crate::Limit::Sample).
+    Sampled(u32),
+    /// The occurrences suppressed since the previous emission, see
+    /// [`Limit::BurstThenSteady`](crate::Limit::BurstThenSteady).
+    More(u64),
+}
+
+/// A machine-readable event, the wire format of [`OutputFormat::Json`].
+///
+/// Every record carries the schema version `v`, currently
+/// [`EventRecord::SCHEMA_VERSION`]. Within one schema version the format is stable:
+///
+/// - existing fields keep their name, type and meaning,
+/// - new optional fields and new `kind` values may be added,
+/// - optional fields are omitted when they do not apply.
+///
+/// Any other change bumps `v`. Parsers should therefore ignore unknown fields and
+/// unknown kinds, which deserializing into `EventRecord` does.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::EventRecord;
+///
+/// let line = r#"{"v":1,"msg":"Disk full","repeats":5,"window_ms":103,"kind":"rate_limited"}"#;
+/// let event: EventRecord = serde_json::from_str(line).unwrap();
+///
+/// assert_eq!(event.v, EventRecord::SCHEMA_VERSION);
+/// assert_eq!(event.kind, "rate_limited");
+/// assert_eq!(event.repeats, Some(5));
+/// ```
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct EventRecord<'a> {
+    /// The schema version of the record.
+    pub v: u32,
+    /// The logged message, or the key of a warning about an unkeyed message.
+    pub msg: Cow<'a, str>,
+    /// The key of a keyed warning.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<Cow<'a, str>>,
+    /// The number of repeats reported by a warning.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeats: Option<u32>,
+    /// The time in milliseconds covered by a warning.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_ms: Option<u64>,
+    /// The number of occurrences so far of a message emitted by `Limit::Exponential`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seen: Option<u64>,
+    /// The sampling rate of a message emitted by `Limit::Sample`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<u32>,
+    /// The occurrences suppressed before a message emitted by `Limit::BurstThenSteady`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub more: Option<u64>,
+    /// The size in bytes of the repeats a warning reports as not emitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dropped_bytes: Option<u64>,
+    /// The kind of record: `message`, `rate_limited`, `summary` or a notice kind.
+    pub kind: Cow<'a, str>,
+    /// The limit component that caused a warning, for composite limits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<Cow<'a, str>>,
+    /// The exemplar labels attached to a warning.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exemplar: Option<Cow<'a, str>>,
+    /// The source file the message was logged from, see `RateLog::log_at`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<Cow<'a, str>>,
+    /// The line within `file` the message was logged from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    /// The module path the message was logged from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub module: Option<Cow<'a, str>>,
+}
+
+#[cfg(feature = "serde")]
+impl<'a> EventRecord<'a> {
+    /// The current schema version.
+    pub const SCHEMA_VERSION: u32 = 1;
+
+    /// Creates a record of the given kind with only the mandatory fields set.
+    fn new(kind: &'a str, msg: &'a str) -> Self {
+        EventRecord {
+            v: Self::SCHEMA_VERSION,
+            msg: Cow::Borrowed(msg),
+            kind: Cow::Borrowed(kind),
+            ..EventRecord::default()
+        }
+    }
+
+    /// Returns the record with the fields of `location` set, if any.
+    fn located(self, location: Option<&Location>) -> Self {
+        match location {
+            Some(location) => EventRecord {
+                file: Some(Cow::Borrowed(location.file())),
+                line: Some(location.line()),
+                module: location.module_path().map(Cow::Borrowed),
+                ..self
+            },
+            None => self,
+        }
+    }
+
+    /// Serializes the record as a single line of JSON.
+    pub fn to_json(&self) -> String {
+        // Serializing plain strings and integers cannot fail
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+impl OutputFormat {
+    /// Renders a logged message, with its annotation and location if any.
+    pub(crate) fn message(
+        &self,
+        msg: &str,
+        annotation: Option<Annotation>,
+        location: Option<&Location>,
+    ) -> String {
+        match self {
+            OutputFormat::Text => {
+                let mut line = match annotation {
+                    None => msg.to_string(),
+                    Some(Annotation::Seen(occurrences)) => {
+                        format!("{msg} (seen {occurrences} times)")
+                    }
+                    Some(Annotation::Sampled(rate)) => format!("{msg} (sampled 1/{rate})"),
+                    Some(Annotation::More(more)) => format!("{msg} (+{more} more)"),
+                };
+                if let Some(location) = location {
+                    line.push_str(&format!(" (at {location})"));
+                }
+                line
+            }
+            #[cfg(feature = "serde")]
+            OutputFormat::Json => EventRecord {
+                seen: match annotation {
+                    Some(Annotation::Seen(occurrences)) => Some(occurrences),
+                    _ => None,
+                },
+                sample_rate: match annotation {
+                    Some(Annotation::Sampled(rate)) => Some(rate),
+                    _ => None,
+                },
+                more: match annotation {
+                    Some(Annotation::More(more)) => Some(more),
+                    _ => None,
+                },
+                ..EventRecord::new("message", msg)
+            }
+            .located(location)
+            .to_json(),
+        }
+    }
+
+    /// Renders a notice that is not tied to a single message, such as the announcement.
+    #[cfg_attr(not(feature = "serde"), allow(unused_variables))]
+    pub(crate) fn notice(&self, kind: &str, text: &str) -> String {
+        match self {
+            OutputFormat::Text => text.to_string(),
+            #[cfg(feature = "serde")]
+            OutputFormat::Json => EventRecord::new(kind, text).to_json(),
+        }
+    }
+}
+
+/// Renders a warning as a JSON record.
+///
+/// The trigger is only included for composite limits, like in the plain text format.
+#[cfg(feature = "serde")]
+pub(crate) fn json_warning(warning: &Warning<'_>, show_trigger: bool) -> String {
+    let (kind, trigger) = match warning.kind {
+        WarningKind::LimitExceeded(trigger) => ("rate_limited", Some(trigger)),
+        WarningKind::Summary => ("summary", None),
+    };
+    EventRecord {
+        key: warning.keyed.then_some(Cow::Borrowed(warning.key)),
+        repeats: Some(warning.count),
+        window_ms: Some(u64::try_from(warning.duration.as_millis()).unwrap_or(u64::MAX)),
+        dropped_bytes: Some(warning.dropped_bytes).filter(|&bytes| bytes > 0),
+        trigger: trigger
+            .filter(|_| show_trigger)
+            .map(|trigger| Cow::Borrowed(trigger.describe())),
+        exemplar: warning.exemplar.map(Cow::Borrowed),
+        ..EventRecord::new(kind, warning.message.unwrap_or(warning.key))
+    }
+    .located(warning.location.as_ref())
+    .to_json()
+}
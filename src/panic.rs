@@ -0,0 +1,193 @@
+//! Rate limiting the output of panics, e.g. of tasks respawned in a crash loop.
+
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::cell::Cell;
+use std::panic;
+use std::thread;
+
+use crate::{
+    Detail, Limit, LineKind, LogOutcome, RateLog, Severity, SharedRateLog, Sink, StderrSink,
+};
+
+thread_local! {
+    /// Set while the current thread reports a panic, to detect panics in the sink.
+    static REPORTING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Replaces the panic hook with one printing panics to stderr through a limiter.
+///
+/// A task that is respawned after every panic otherwise floods the output with the
+/// same panic message during a crash loop. Panics are rate limited by their location
+/// and message, regardless of the panicking thread, so repeats are summarized like
+/// any other message: `Key: "panicked at src/worker.rs:12:5: connection lost" repeat
+/// for 10 times in the past 3s, last message: "..."`. Emitted panics look like those
+/// of the default hook, including the backtrace if enabled with `RUST_BACKTRACE`.
+///
+/// Returns a handle to the limiter, e.g. to flush pending repeats before exiting. See
+/// [`install_panic_hook_with`] to write panics to another sink, such as
+/// [`LogSink`](crate::LogSink) to route them through `log::error!`.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{install_panic_hook, Limit};
+/// use std::thread;
+///
+/// let panics = install_panic_hook(Limit::Rate(10));
+///
+/// for _ in 0..20 {
+///     // Prints the panic once, then a warning every 10 repeats
+///     let _ = thread::spawn(|| panic!("connection lost")).join();
+/// }
+/// panics.lock().flush();
+/// # let _ = std::panic::take_hook();
+/// ```
+pub fn install_panic_hook(limit: Limit) -> SharedRateLog<StderrSink> {
+    install_panic_hook_with(RateLog::with_sink(limit, StderrSink))
+}
+
+/// Replaces the panic hook with one reporting panics through `rate_log`.
+///
+/// Works like [`install_panic_hook`] with a configured limiter, e.g. one writing to
+/// a file or to the `log` backend. Panics are logged with [`Severity::Error`]. A panic
+/// raised by the sink while it reports another panic, and a panic raised while the
+/// limiter is locked, e.g. by its sink during [`RateLog::flush`] or by another
+/// thread, is printed to stderr directly.
+pub fn install_panic_hook_with<S: Sink + Send + 'static>(rate_log: RateLog<S>) -> SharedRateLog<S> {
+    let shared = SharedRateLog::from_rate_log(rate_log);
+    let limiter = shared.clone();
+    panic::set_hook(Box::new(move |info| {
+        let payload = info
+            .payload()
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("Box<dyn Any>");
+        let location = info
+            .location()
+            .map(|location| location.to_string())
+            .unwrap_or_default();
+        let current = thread::current();
+        let name = current.name().unwrap_or("<unnamed>");
+
+        if REPORTING.with(|reporting| reporting.replace(true)) {
+            eprintln!("{}", describe(name, &location, payload));
+            return;
+        }
+        let _reporting = Reporting;
+        // The panicking thread may hold the lock already, e.g. when the sink of the
+        // limiter panics: waiting for it would deadlock
+        match limiter.try_lock() {
+            Some(mut rate_log) => {
+                report(&mut rate_log, name, &location, payload, Backtrace::capture);
+            }
+            None => eprintln!("{}", describe(name, &location, payload)),
+        }
+    }));
+    shared
+}
+
+/// Clears [`REPORTING`] when dropped, also if the sink panics.
+struct Reporting;
+
+impl Drop for Reporting {
+    fn drop(&mut self) {
+        REPORTING.with(|reporting| reporting.set(false));
+    }
+}
+
+/// Logs a panic of thread `name` at `location` with `payload` through `rate_log`.
+///
+/// The backtrace is only captured if the panic is emitted, and written as a line of
+/// its own so it does not end up in warnings and summaries.
+fn report<S: Sink>(
+    rate_log: &mut RateLog<S>,
+    name: &str,
+    location: &str,
+    payload: &str,
+    backtrace: impl FnOnce() -> Backtrace,
+) {
+    let key = format!("panicked at {location}: {payload}");
+    let msg = describe(name, location, payload);
+    let outcome = rate_log.track(&key, Detail::Text(&msg), Severity::Error, None);
+    if outcome == LogOutcome::Emitted {
+        let backtrace = backtrace();
+        if backtrace.status() == BacktraceStatus::Captured {
+            rate_log.sink.emit_line(
+                &format!("stack backtrace:\n{backtrace}"),
+                LineKind::Message(Severity::Error),
+            );
+        }
+    }
+}
+
+/// Returns the panic message in the format of the default panic hook.
+fn describe(name: &str, location: &str, payload: &str) -> String {
+    format!("thread '{name}' panicked at {location}:\n{payload}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CaptureSink, MockClock};
+
+    #[test]
+    fn test_report_panic() {
+        let mut rate_log =
+            RateLog::with_sink(Limit::Rate(2), CaptureSink::new()).with_clock(MockClock::new());
+
+        for name in ["worker-1", "worker-2", "worker-3"] {
+            report(
+                &mut rate_log,
+                name,
+                "src/worker.rs:12:5",
+                "connection lost",
+                Backtrace::disabled,
+            );
+        }
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "thread 'worker-1' panicked at src/worker.rs:12:5:\nconnection lost",
+                "Key: \"panicked at src/worker.rs:12:5: connection lost\" repeat for 2 times in the past 0ms, last message: \"thread 'worker-3' panicked at src/worker.rs:12:5:\nconnection lost\"",
+            ]
+        );
+    }
+
+    /// Panics when asked to write a line containing `sink failure`.
+    struct PanickingSink;
+
+    impl Sink for PanickingSink {
+        fn emit(&mut self, record: &str) {
+            assert!(!record.contains("sink failure"), "sink failed");
+        }
+    }
+
+    #[test]
+    fn test_sink_panics_while_locked() {
+        use std::sync::mpsc;
+        use std::time::Duration;
+
+        let panics = install_panic_hook_with(RateLog::with_sink(Limit::Rate(10), PanickingSink));
+        let (done, finished) = mpsc::channel();
+        let logger = panics.clone();
+        thread::spawn(move || {
+            // The sink panics while the guard is held, so the hook cannot lock it
+            let result = thread::spawn(move || logger.lock().log("sink failure")).join();
+            done.send(result.is_err()).unwrap();
+        });
+        // A deadlocked hook also blocks failing assertions and `take_hook`
+        let panicked = finished
+            .recv_timeout(Duration::from_secs(10))
+            .unwrap_or_else(|_| {
+                eprintln!("the panic hook deadlocked");
+                std::process::abort()
+            });
+        let _ = panic::take_hook();
+
+        assert!(panicked);
+        // The poisoned lock is recovered afterwards
+        assert_eq!(panics.poison_recoveries(), 1);
+    }
+}
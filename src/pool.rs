@@ -0,0 +1,297 @@
+//! Per-thread limiters with periodic central aggregation.
+
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::clock::Instant;
+use crate::{format_duration, LogOutcome, RateLog, Sink, Stats};
+
+/// A pool of per-thread limiters whose statistics are merged by a central aggregator.
+///
+/// Sharing one limiter between threads ([`SharedRateLog`](crate::SharedRateLog)) puts a
+/// lock on every logging call. With a pool, every thread logs through its own
+/// [`PooledRateLog`] instead, without any cross-thread synchronization on the hot
+/// path. Each local limiter publishes a [`Stats`] snapshot at most once per `interval`;
+/// a background aggregator thread merges the snapshots every `interval` and emits a
+/// global summary for each message with newly suppressed repeats:
+/// `Global: "text" suppressed N repeats in the past X`.
+///
+/// Dropping the pool stops the aggregator after a final summary. Local limiters also
+/// publish when dropped, so drop them before the pool to have their last repeats
+/// included.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{Limit, RateLog, RateLogPool, StderrSink};
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// let pool = RateLogPool::new(Duration::from_secs(10), StderrSink);
+///
+/// let handles: Vec<_> = (0..4)
+///     .map(|_| {
+///         let mut logger = pool.local(RateLog::new(Limit::Rate(100)));
+///         thread::spawn(move || {
+///             for _ in 0..1000 {
+///                 logger.log("Worker heartbeat");
+///             }
+///         })
+///     })
+///     .collect();
+///
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+///
+/// assert_eq!(pool.stats().get("Worker heartbeat").unwrap().occurrences, 4000);
+/// ```
+pub struct RateLogPool {
+    /// State shared with the local limiters and the aggregator.
+    shared: Arc<PoolShared>,
+
+    /// The aggregator thread, joined on drop.
+    aggregator: Option<JoinHandle<()>>,
+}
+
+/// The part of a pool shared between threads.
+struct PoolShared {
+    /// How often local limiters publish and the aggregator reports.
+    interval: Duration,
+
+    /// The latest snapshot published by every local limiter, indexed by its slot.
+    snapshots: Mutex<Vec<Stats>>,
+
+    /// Set when the pool is dropped, to stop the aggregator.
+    stopped: Mutex<bool>,
+
+    /// Wakes up the aggregator when the pool is dropped.
+    wakeup: Condvar,
+}
+
+impl PoolShared {
+    /// Locks the snapshots, ignoring poisoning: snapshots are replaced as a whole.
+    fn snapshots(&self) -> MutexGuard<'_, Vec<Stats>> {
+        self.snapshots
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Merges the latest snapshots of all local limiters.
+    fn merged(&self) -> Stats {
+        let mut merged = Stats::default();
+        for snapshot in self.snapshots().iter() {
+            merged.merge(snapshot);
+        }
+        merged
+    }
+}
+
+impl RateLogPool {
+    /// Creates a pool whose aggregator reports to `sink` every `interval`.
+    pub fn new<S: Sink + Send + 'static>(interval: Duration, sink: S) -> Self {
+        let shared = Arc::new(PoolShared {
+            interval,
+            snapshots: Mutex::new(Vec::new()),
+            stopped: Mutex::new(false),
+            wakeup: Condvar::new(),
+        });
+
+        let aggregator = {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || aggregate(&shared, sink))
+        };
+
+        RateLogPool {
+            shared,
+            aggregator: Some(aggregator),
+        }
+    }
+
+    /// Adds a local limiter to the pool.
+    ///
+    /// The returned [`PooledRateLog`] is meant to be moved into, and used by, a single
+    /// thread. `rate_log` keeps its own configuration and sink for local output.
+    pub fn local<S: Sink>(&self, rate_log: RateLog<S>) -> PooledRateLog<S> {
+        let mut snapshots = self.shared.snapshots();
+        snapshots.push(Stats::default());
+
+        PooledRateLog {
+            rate_log,
+            shared: Arc::clone(&self.shared),
+            slot: snapshots.len() - 1,
+            published: Instant::now(),
+        }
+    }
+
+    /// Returns the merged statistics of all local limiters as last published.
+    pub fn stats(&self) -> Stats {
+        self.shared.merged()
+    }
+}
+
+impl Drop for RateLogPool {
+    /// Stops the aggregator after a final summary.
+    fn drop(&mut self) {
+        *self
+            .shared
+            .stopped
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = true;
+        self.shared.wakeup.notify_all();
+
+        if let Some(aggregator) = self.aggregator.take() {
+            let _ = aggregator.join();
+        }
+    }
+}
+
+/// Runs the aggregator until the pool is dropped.
+fn aggregate<S: Sink>(shared: &PoolShared, mut sink: S) {
+    let mut reported = Stats::default();
+    let mut since = Instant::now();
+
+    loop {
+        let stopped = {
+            let guard = shared
+                .stopped
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let (guard, _) = shared
+                .wakeup
+                .wait_timeout_while(guard, shared.interval, |stopped| !*stopped)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            *guard
+        };
+
+        let merged = shared.merged();
+        for stats in merged.messages() {
+            let previous = reported.get(&stats.key).map_or(0, |stats| stats.suppressed);
+            if stats.suppressed > previous {
+                sink.emit(&format!(
+                    "Global: \"{}\" suppressed {} repeats in the past {}",
+                    stats.key,
+                    stats.suppressed - previous,
+                    format_duration(since.elapsed())
+                ));
+            }
+        }
+        sink.flush();
+        reported = merged;
+        since = Instant::now();
+
+        if stopped {
+            return;
+        }
+    }
+}
+
+/// A thread-local limiter belonging to a [`RateLogPool`].
+///
+/// Logs through its own [`RateLog`] without any synchronization, and publishes its
+/// statistics to the pool at most once per pool interval and when dropped.
+pub struct PooledRateLog<S: Sink> {
+    /// The local limiter.
+    rate_log: RateLog<S>,
+
+    /// The pool this limiter publishes to.
+    shared: Arc<PoolShared>,
+
+    /// The index of this limiter's snapshot in the pool.
+    slot: usize,
+
+    /// The time of the last published snapshot.
+    published: Instant,
+}
+
+impl<S: Sink> PooledRateLog<S> {
+    /// Logs a message through the local limiter.
+    ///
+    /// See [`RateLog::log`] for the rate limiting behavior.
+    pub fn log(&mut self, msg: &str) -> LogOutcome {
+        let outcome = self.rate_log.log(msg);
+        self.maybe_publish();
+        outcome
+    }
+
+    /// Logs a message rate limited under `key` through the local limiter.
+    ///
+    /// See [`RateLog::log_keyed`] for the rate limiting behavior.
+    pub fn log_keyed(&mut self, key: &str, msg: &str) -> LogOutcome {
+        let outcome = self.rate_log.log_keyed(key, msg);
+        self.maybe_publish();
+        outcome
+    }
+
+    /// Returns a reference to the local limiter.
+    pub fn rate_log(&self) -> &RateLog<S> {
+        &self.rate_log
+    }
+
+    /// Publishes the local statistics to the pool right away.
+    pub fn publish(&mut self) {
+        let stats = self.rate_log.stats();
+        self.shared.snapshots()[self.slot] = stats;
+        self.published = Instant::now();
+    }
+
+    /// Publishes the local statistics if the pool interval has passed.
+    fn maybe_publish(&mut self) {
+        if self.published.elapsed() >= self.shared.interval {
+            self.publish();
+        }
+    }
+}
+
+impl<S: Sink> Drop for PooledRateLog<S> {
+    fn drop(&mut self) {
+        self.publish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CaptureSink, Limit};
+
+    /// A sink sending every record over a channel.
+    struct ChannelSink(std::sync::mpsc::Sender<String>);
+
+    impl Sink for ChannelSink {
+        fn emit(&mut self, record: &str) {
+            let _ = self.0.send(record.to_string());
+        }
+    }
+
+    #[test]
+    fn test_pool_aggregation() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let pool = RateLogPool::new(Duration::from_secs(60), ChannelSink(sender));
+
+        let handles: Vec<_> = (0..3)
+            .map(|_| {
+                let mut logger =
+                    pool.local(RateLog::with_sink(Limit::Rate(100), CaptureSink::new()));
+                thread::spawn(move || {
+                    for _ in 0..10 {
+                        logger.log("message1");
+                    }
+                    assert_eq!(logger.rate_log().sink().lines(), ["message1"]);
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let stats = pool.stats();
+        let message1 = stats.get("message1").unwrap();
+        assert_eq!((message1.occurrences, message1.suppressed), (30, 27));
+
+        drop(pool);
+        let reports: Vec<_> = receiver.iter().collect();
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].starts_with("Global: \"message1\" suppressed 27 repeats in the past"));
+    }
+}
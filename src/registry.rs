@@ -0,0 +1,241 @@
+//! Named limiters managed in one place.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::{Limit, RateLog, SharedRateLog, Sink, StdoutSink};
+
+/// Creates the limiter of a name from the limit configured for it.
+type Factory<S> = Box<dyn Fn(&str, Limit) -> RateLog<S> + Send + Sync>;
+
+/// A registry of named limiters, one per subsystem, created on first use.
+///
+/// Large applications need dozens of independently tuned throttles. A registry hands
+/// out a [`SharedRateLog`] per name, e.g. `"network"` or `"storage"`, created lazily
+/// with the limit configured for that name or the registry's default limit. Every
+/// [`Registry::get`] for the same name returns a handle to the same limiter.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{Limit, Registry};
+/// use std::collections::HashMap;
+///
+/// let limits = HashMap::from([
+///     ("network".to_string(), Limit::Rate(5)),
+///     ("storage".to_string(), Limit::Rate(50)),
+/// ]);
+/// let registry = Registry::new(Limit::Rate(10)).with_limits(limits);
+///
+/// registry.get("network").log("connection reset");  // Uses Limit::Rate(5)
+/// registry.get("cache").log("eviction storm");      // Uses the default Limit::Rate(10)
+///
+/// assert_eq!(*registry.get("network").lock().limit(), Limit::Rate(5));
+/// ```
+pub struct Registry<S: Sink = StdoutSink> {
+    /// The configured limits, locked after `loggers` when both are needed.
+    limits: Mutex<Limits>,
+
+    /// Creates the limiter of a name on first use.
+    factory: Factory<S>,
+
+    /// The limiters created so far.
+    loggers: Mutex<HashMap<String, SharedRateLog<S>>>,
+}
+
+/// The limits a [`Registry`] creates its limiters with.
+struct Limits {
+    /// The limit of names without a configured limit.
+    default: Limit,
+
+    /// The limits configured per name.
+    names: HashMap<String, Limit>,
+}
+
+impl Limits {
+    /// Returns the limit of `name`.
+    fn of(&self, name: &str) -> &Limit {
+        self.names.get(name).unwrap_or(&self.default)
+    }
+}
+
+impl Registry {
+    /// Creates a registry of limiters writing to stdout, using `default` for every name
+    /// without a configured limit.
+    pub fn new(default: Limit) -> Self {
+        Registry::with_factory(default, |_, limit| RateLog::new(limit))
+    }
+}
+
+impl<S: Sink> Registry<S> {
+    /// Creates a registry building its limiters with `factory`.
+    ///
+    /// The factory receives the name and its limit, and can configure the limiter
+    /// further, e.g. with a sink that prefixes every line with the name.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{Decorated, Limit, RateLog, Registry, StdoutSink};
+    ///
+    /// let registry = Registry::with_factory(Limit::Rate(10), |name, limit| {
+    ///     let prefix = format!("[{name}] ");
+    ///     RateLog::with_sink(limit, Decorated::new(StdoutSink).prefix(prefix))
+    /// });
+    ///
+    /// registry.get("network").log("connection reset");  // Prints: "[network] connection reset"
+    /// ```
+    pub fn with_factory(
+        default: Limit,
+        factory: impl Fn(&str, Limit) -> RateLog<S> + Send + Sync + 'static,
+    ) -> Self {
+        Registry {
+            limits: Mutex::new(Limits {
+                default,
+                names: HashMap::new(),
+            }),
+            factory: Box::new(factory),
+            loggers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Configures the limit of the limiter named `name`.
+    ///
+    /// Only affects limiters created afterwards.
+    pub fn with_limit(mut self, name: impl Into<String>, limit: Limit) -> Self {
+        self.limits_mut().names.insert(name.into(), limit);
+        self
+    }
+
+    /// Configures the limits of several names at once, e.g. from a config map.
+    pub fn with_limits(mut self, limits: impl IntoIterator<Item = (String, Limit)>) -> Self {
+        self.limits_mut().names.extend(limits);
+        self
+    }
+
+    /// Replaces the default limit and the limits of all names, e.g. after reloading a
+    /// configuration file.
+    ///
+    /// Limiters created before are switched over with [`SharedRateLog::set_limit`] if
+    /// their limit changed, keeping the repeats and statistics accumulated so far.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{Limit, Registry};
+    ///
+    /// let registry = Registry::new(Limit::Rate(10)).with_limit("network", Limit::Rate(5));
+    /// let network = registry.get("network");
+    ///
+    /// registry.set_limits(Limit::Rate(10), [("network".to_string(), Limit::Rate(50))]);
+    /// assert_eq!(*network.lock().limit(), Limit::Rate(50));
+    /// ```
+    pub fn set_limits(&self, default: Limit, limits: impl IntoIterator<Item = (String, Limit)>) {
+        let loggers = self.loggers();
+        let mut configured = self.limits.lock().unwrap_or_else(|err| err.into_inner());
+        *configured = Limits {
+            default,
+            names: limits.into_iter().collect(),
+        };
+
+        for (name, logger) in loggers.iter() {
+            let limit = configured.of(name);
+            let mut rate_log = logger.lock();
+            if rate_log.limit() != limit {
+                rate_log.set_limit(limit.clone());
+            }
+        }
+    }
+
+    /// Returns the limiter named `name`, creating it on first use.
+    pub fn get(&self, name: &str) -> SharedRateLog<S> {
+        let mut loggers = self.loggers();
+        if let Some(logger) = loggers.get(name) {
+            return logger.clone();
+        }
+
+        let limit = self
+            .limits
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .of(name)
+            .clone();
+        let logger = SharedRateLog::from_rate_log((self.factory)(name, limit));
+        loggers.insert(name.to_string(), logger.clone());
+        logger
+    }
+
+    /// Returns the names of the limiters created so far, in order.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<_> = self.loggers().keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Returns the configured limits of a registry that is still being set up.
+    fn limits_mut(&mut self) -> &mut Limits {
+        self.limits.get_mut().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Locks the created limiters, ignoring poisoning: the map is only ever extended.
+    fn loggers(&self) -> MutexGuard<'_, HashMap<String, SharedRateLog<S>>> {
+        self.loggers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CaptureSink;
+
+    #[test]
+    fn test_registry() {
+        let registry = Registry::with_factory(Limit::Rate(10), |_, limit| {
+            RateLog::with_sink(limit, CaptureSink::new())
+        })
+        .with_limit("network", Limit::Rate(1));
+
+        registry.get("network").log("message1");
+        registry.get("network").log("message1");
+        registry.get("storage").log("message1");
+        registry.get("storage").log("message1");
+
+        assert_eq!(registry.names(), ["network", "storage"]);
+        assert_eq!(
+            registry.get("network").lock().sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" repeat for 1 times in the past 0ms"
+            ]
+        );
+        assert_eq!(registry.get("storage").lock().sink().lines(), ["message1"]);
+    }
+
+    #[test]
+    fn test_set_limits() {
+        let registry = Registry::with_factory(Limit::Rate(10), |_, limit| {
+            RateLog::with_sink(limit, CaptureSink::new())
+        });
+        let network = registry.get("network");
+        let storage = registry.get("storage");
+        for _ in 0..3 {
+            network.log("message1");
+        }
+
+        registry.set_limits(Limit::Rate(10), [("network".to_string(), Limit::Rate(3))]);
+        network.log("message1");
+
+        assert_eq!(*storage.lock().limit(), Limit::Rate(10));
+        assert_eq!(*registry.get("cache").lock().limit(), Limit::Rate(10));
+        // The repeats counted before the reload still count towards the new limit
+        assert_eq!(
+            network.lock().sink().lines(),
+            [
+                "message1",
+                "Message: \"message1\" repeat for 3 times in the past 0ms"
+            ]
+        );
+    }
+}
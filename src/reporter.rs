@@ -0,0 +1,101 @@
+//! Periodic suppression reports from a background thread.
+
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::{SharedRateLog, Sink};
+
+/// A background thread emitting [`RateLog::report_suppressed`](crate::RateLog::report_suppressed)
+/// lines through a [`SharedRateLog`] at a fixed interval.
+///
+/// Every tick also calls [`RateLog::settle`](crate::RateLog::settle), so with a settle
+/// timeout the repeats of messages that went quiet are summarized within an interval.
+///
+/// The thread stops when [`Reporter::stop`] is called or the reporter is dropped,
+/// without waiting for the current interval to end.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{Limit, Reporter, SharedRateLog};
+/// use std::time::Duration;
+///
+/// let logger = SharedRateLog::new(Limit::Rate(1000));
+/// let reporter = Reporter::spawn(logger.clone(), Duration::from_secs(60));
+///
+/// logger.log("disk full");
+/// logger.log("disk full");
+/// // Every minute prints e.g. "\"disk full\" suppressed 1 times in the last 1m0s"
+///
+/// reporter.stop();
+/// ```
+pub struct Reporter {
+    /// Wakes the thread up to stop; dropping it has the same effect.
+    stop: Option<Sender<()>>,
+
+    /// The reporting thread.
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Reporter {
+    /// Starts reporting suppressed occurrences logged through `logger` every `interval`.
+    pub fn spawn<S: Sink + Send + 'static>(logger: SharedRateLog<S>, interval: Duration) -> Self {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let handle = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                let mut rate_log = logger.lock();
+                rate_log.settle();
+                rate_log.report_suppressed();
+                rate_log.sink_mut().flush();
+            }
+        });
+        Reporter {
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+
+    /// Stops the reporting thread and waits for it to finish.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    /// Signals the thread to stop and joins it.
+    fn shutdown(&mut self) {
+        drop(self.stop.take());
+        if let Some(handle) = self.handle.take() {
+            // A panicking sink already poisoned the lock, which SharedRateLog recovers from
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Reporter {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CaptureSink, Limit, RateLog};
+
+    #[test]
+    fn test_reporter() {
+        let rate_log = RateLog::with_sink(Limit::Rate(1000), CaptureSink::new());
+        let logger = SharedRateLog::from_rate_log(rate_log);
+        let reporter = Reporter::spawn(logger.clone(), Duration::from_millis(20));
+
+        logger.log("message1");
+        logger.log("message1");
+        thread::sleep(Duration::from_millis(100));
+        reporter.stop();
+
+        let lines = logger.lock().sink().lines().to_vec();
+        assert_eq!(lines[0], "message1");
+        assert!(lines[1].starts_with("\"message1\" suppressed 1 times in the last "));
+        assert_eq!(lines.len(), 2);
+    }
+}
@@ -0,0 +1,104 @@
+//! Logging errors of a [`Result`] on the way through.
+
+use alloc::string::ToString;
+use core::fmt::Display;
+
+use crate::{Detail, RateLog, Severity, Sink};
+
+/// Logs the error of a [`Result`] through a [`RateLog`] and passes the `Result` on
+/// unchanged.
+///
+/// Replaces the `if let Err(err) = &result { logger.log(...) }` boilerplate at call
+/// sites that handle or propagate the error afterwards. Errors are logged with
+/// [`Severity::Error`]; `Ok` values are not logged.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{Limit, RateLog, RateLogResultExt};
+///
+/// fn connect(port: u16) -> Result<(), String> {
+///     Err(format!("connection refused on port {port}"))
+/// }
+///
+/// let mut logger = RateLog::new(Limit::Rate(10));
+/// for _ in 0..3 {
+///     // Prints "connection refused on port 80" once, the other errors are repeats
+///     let _ = connect(80).log_err_rate_limited(&mut logger);
+/// }
+/// ```
+pub trait RateLogResultExt: Sized {
+    /// Logs the error, rate limited by its [`Display`] text.
+    fn log_err_rate_limited<S: Sink>(self, rate_log: &mut RateLog<S>) -> Self;
+
+    /// Logs the [`Display`] text of the error, rate limited by the type of the error.
+    ///
+    /// All errors of one type count as repeats of each other, even if their text
+    /// differs, e.g. because it contains a path or an address.
+    fn log_err_rate_limited_by_type<S: Sink>(self, rate_log: &mut RateLog<S>) -> Self;
+}
+
+impl<T, E: Display> RateLogResultExt for Result<T, E> {
+    fn log_err_rate_limited<S: Sink>(self, rate_log: &mut RateLog<S>) -> Self {
+        if let Err(err) = &self {
+            rate_log.log_with_severity(Severity::Error, &err.to_string());
+        }
+        self
+    }
+
+    fn log_err_rate_limited_by_type<S: Sink>(self, rate_log: &mut RateLog<S>) -> Self {
+        if let Err(err) = &self {
+            let msg = err.to_string();
+            rate_log.track(
+                core::any::type_name::<E>(),
+                Detail::Text(&msg),
+                Severity::Error,
+                None,
+            );
+        }
+        self
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{CaptureSink, Limit, MockClock};
+    use std::num::ParseIntError;
+
+    #[test]
+    fn test_log_err_rate_limited() {
+        let mut rate_log =
+            RateLog::with_sink(Limit::Rate(2), CaptureSink::new()).with_clock(MockClock::new());
+
+        assert_eq!(
+            "42".parse::<u8>().log_err_rate_limited(&mut rate_log),
+            Ok(42)
+        );
+        for input in ["a", "a", "b"] {
+            assert!(input
+                .parse::<u8>()
+                .log_err_rate_limited(&mut rate_log)
+                .is_err());
+        }
+        for input in ["300", "-1", "x"] {
+            let _ = input
+                .parse::<u8>()
+                .log_err_rate_limited_by_type(&mut rate_log);
+        }
+
+        assert_eq!(
+            rate_log.sink().lines(),
+            [
+                "invalid digit found in string",
+                "Message: \"invalid digit found in string\" repeat for 2 times in the past 0ms",
+                "number too large to fit in target type",
+                format!(
+                    "Key: \"{}\" repeat for 2 times in the past 0ms, last message: \"invalid digit found in string\"",
+                    core::any::type_name::<ParseIntError>()
+                )
+                .as_str(),
+            ]
+        );
+    }
+}
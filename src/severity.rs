@@ -0,0 +1,54 @@
+//! Message severities.
+
+use core::fmt;
+
+/// The importance of a logged message.
+///
+/// Severities order from least to most important, so `Severity::Error > Severity::Debug`.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::Severity;
+///
+/// assert!(Severity::Error > Severity::Warn);
+/// assert_eq!(Severity::Warn.to_string(), "warn");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Severity {
+    /// Very detailed diagnostics.
+    Trace,
+    /// Diagnostics useful while debugging.
+    Debug,
+    /// Regular informational messages. Used by calls that do not specify a severity.
+    #[default]
+    Info,
+    /// Unexpected conditions that do not stop the application.
+    Warn,
+    /// Failures that need attention.
+    Error,
+}
+
+impl Severity {
+    /// Returns the lowercase name of the severity.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Trace => "trace",
+            Severity::Debug => "debug",
+            Severity::Info => "info",
+            Severity::Warn => "warn",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
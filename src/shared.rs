@@ -0,0 +1,226 @@
+//! Thread-safe sharing of a single [`RateLog`].
+
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, TryLockError};
+
+use crate::{Limit, Location, LogOutcome, RateLog, Sink, StdoutSink};
+
+/// A cloneable, thread-safe handle to a [`RateLog`].
+///
+/// All clones share the same limiter state, so identical messages logged from
+/// different threads are rate limited together.
+///
+/// # Poisoning recovery
+///
+/// If a thread panics while holding the internal lock (for example inside a custom
+/// [`Sink`]), the lock becomes poisoned. Instead of propagating that panic into every
+/// subsequent logging call, `SharedRateLog` recovers: the tracking state is reset,
+/// logging continues normally and the event is counted in
+/// [`Stats::poison_recoveries`](crate::Stats::poison_recoveries).
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{Limit, SharedRateLog};
+/// use std::thread;
+///
+/// let logger = SharedRateLog::new(Limit::Rate(10));
+///
+/// let handles: Vec<_> = (0..4)
+///     .map(|_| {
+///         let logger = logger.clone();
+///         thread::spawn(move || logger.log("Worker heartbeat"))
+///     })
+///     .collect();
+///
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+/// // Prints "Worker heartbeat" once, the other three calls are counted as repeats
+/// ```
+pub struct SharedRateLog<S: Sink = StdoutSink> {
+    /// The shared limiter.
+    inner: Arc<Mutex<RateLog<S>>>,
+}
+
+impl SharedRateLog {
+    /// Creates a new shared limiter writing to stdout.
+    pub fn new(limit: Limit) -> Self {
+        SharedRateLog::from_rate_log(RateLog::new(limit))
+    }
+}
+
+impl<S: Sink> SharedRateLog<S> {
+    /// Wraps an already configured [`RateLog`] for sharing across threads.
+    pub fn from_rate_log(rate_log: RateLog<S>) -> Self {
+        SharedRateLog {
+            inner: Arc::new(Mutex::new(rate_log)),
+        }
+    }
+
+    /// Logs a message through the shared limiter.
+    ///
+    /// See [`RateLog::log`] for the rate limiting behavior.
+    pub fn log(&self, msg: &str) -> LogOutcome {
+        self.lock().log(msg)
+    }
+
+    /// Logs a message rate limited under `key` through the shared limiter.
+    ///
+    /// See [`RateLog::log_keyed`] for the rate limiting behavior.
+    pub fn log_keyed(&self, key: &str, msg: &str) -> LogOutcome {
+        self.lock().log_keyed(key, msg)
+    }
+
+    /// Logs a message together with its location through the shared limiter.
+    ///
+    /// See [`RateLog::log_at`] for how the location is reported.
+    pub fn log_at(&self, msg: &str, location: Location) -> LogOutcome {
+        self.lock().log_at(msg, location)
+    }
+
+    /// Replaces the limit of the shared limiter for all clones at once.
+    ///
+    /// See [`RateLog::set_limit`] for how accumulated state is kept.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{Limit, SharedRateLog};
+    ///
+    /// let logger = SharedRateLog::new(Limit::Rate(10));
+    /// let admin = logger.clone();
+    ///
+    /// // E.g. from an admin endpoint: relax throttling for every thread
+    /// admin.set_limit(Limit::Rate(1000));
+    /// ```
+    pub fn set_limit(&self, limit: Limit) {
+        self.lock().set_limit(limit);
+    }
+
+    /// Locks the shared limiter for direct access, e.g. to inspect its sink.
+    ///
+    /// A poisoned lock is recovered transparently as described in the
+    /// [type-level documentation](SharedRateLog#poisoning-recovery).
+    pub fn lock(&self) -> MutexGuard<'_, RateLog<S>> {
+        match self.inner.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => self.recover(poisoned),
+        }
+    }
+
+    /// Locks the shared limiter unless it is locked already, e.g. by the current
+    /// thread, recovering a poisoned lock like [`SharedRateLog::lock`].
+    pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, RateLog<S>>> {
+        match self.inner.try_lock() {
+            Ok(guard) => Some(guard),
+            Err(TryLockError::Poisoned(poisoned)) => Some(self.recover(poisoned)),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+
+    /// Resets the tracking state of a poisoned lock and counts the recovery.
+    fn recover<'a>(
+        &'a self,
+        poisoned: PoisonError<MutexGuard<'a, RateLog<S>>>,
+    ) -> MutexGuard<'a, RateLog<S>> {
+        let mut guard = poisoned.into_inner();
+        guard.reset_state();
+        guard.poison_recoveries += 1;
+        self.inner.clear_poison();
+        guard
+    }
+
+    /// Returns how many times a poisoned lock has been recovered.
+    ///
+    /// Also reported by [`RateLog::stats`] and kept in snapshots.
+    pub fn poison_recoveries(&self) -> u64 {
+        self.lock().poison_recoveries
+    }
+}
+
+impl<S: Sink> Clone for SharedRateLog<S> {
+    fn clone(&self) -> Self {
+        SharedRateLog {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CaptureSink;
+    use std::thread;
+
+    /// A sink that panics when asked to emit a specific record.
+    struct PanickingSink {
+        capture: CaptureSink,
+    }
+
+    impl Sink for PanickingSink {
+        fn emit(&mut self, record: &str) {
+            if record == "boom" {
+                panic!("sink failure");
+            }
+            self.capture.emit(record);
+        }
+    }
+
+    #[test]
+    fn test_shared_poison_recovery() {
+        let shared = SharedRateLog::from_rate_log(RateLog::with_sink(
+            Limit::Rate(10),
+            PanickingSink {
+                capture: CaptureSink::new(),
+            },
+        ));
+
+        shared.log("message1");
+        shared.log("message1");
+
+        let clone = shared.clone();
+        let result = thread::spawn(move || clone.log("boom")).join();
+        assert!(result.is_err());
+
+        // The poisoned lock is recovered with fresh state instead of panicking
+        assert_eq!(shared.log("message1"), LogOutcome::Emitted);
+        assert_eq!(shared.poison_recoveries(), 1);
+        assert_eq!(
+            shared.lock().sink().capture.lines(),
+            [
+                "message1",
+                "Message: \"message1\" suppressed 1 repeats over 0ms",
+                "message1"
+            ]
+        );
+        assert_eq!(shared.poison_recoveries(), 1);
+        assert_eq!(shared.lock().stats().poison_recoveries(), 1);
+    }
+
+    #[test]
+    fn test_poison_recoveries_in_stats_and_snapshots() {
+        let shared = SharedRateLog::from_rate_log(RateLog::with_sink(
+            Limit::Rate(10),
+            PanickingSink {
+                capture: CaptureSink::new(),
+            },
+        ));
+        for _ in 0..2 {
+            let clone = shared.clone();
+            assert!(thread::spawn(move || clone.log("boom")).join().is_err());
+            // Each recovery is counted once, also by the clone that recovers it
+            shared.log("message1");
+        }
+        assert_eq!(shared.lock().stats().poison_recoveries(), 2);
+
+        // Recoveries survive a restart and add up across limiters
+        let snapshot = shared.lock().snapshot();
+        assert_eq!(snapshot.poison_recoveries(), 2);
+        let mut restored = RateLog::with_sink(Limit::Rate(10), CaptureSink::new());
+        restored.restore(snapshot.clone()).unwrap();
+        let mut stats = restored.stats();
+        stats.merge(&shared.lock().stats());
+        assert_eq!(stats.poison_recoveries(), 4);
+        assert_eq!(snapshot.diff(&snapshot).poison_recoveries(), 0);
+    }
+}
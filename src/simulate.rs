@@ -0,0 +1,93 @@
+//! Replaying timed log calls against a virtual clock.
+
+use std::time::Duration;
+
+use crate::{CaptureSink, Event, MockClock, RateLog};
+
+impl RateLog<CaptureSink> {
+    /// Replays a timed sequence of log calls against a virtual clock and returns the
+    /// events emitted.
+    ///
+    /// Each item is logged with [`RateLog::log`] after advancing the clock by its
+    /// duration, i.e. durations are the time since the previous call. Once all calls
+    /// are replayed the limiter is flushed, so repeats still pending are part of the
+    /// result. Nothing sleeps, which makes it suitable for property based tests and
+    /// fuzzing of limit semantics.
+    ///
+    /// The clock is replaced by a [`MockClock`] and events captured before the call
+    /// are discarded. The statistics remain available afterwards, e.g. to check that
+    /// every call was counted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{CaptureSink, Event, Limit, RateLog};
+    /// use std::time::Duration;
+    ///
+    /// let mut logger = RateLog::with_sink(Limit::Rate(2), CaptureSink::new());
+    /// let events = logger.simulate([
+    ///     (Duration::ZERO, "Disk full"),
+    ///     (Duration::from_secs(1), "Disk full"),
+    ///     (Duration::from_secs(1), "Disk full"),
+    /// ]);
+    ///
+    /// assert!(matches!(
+    ///     events[..],
+    ///     [Event::Emitted { .. }, Event::Summary { count: 2, .. }]
+    /// ));
+    /// ```
+    pub fn simulate<'a>(
+        &mut self,
+        events: impl IntoIterator<Item = (Duration, &'a str)>,
+    ) -> Vec<Event<'static>> {
+        let clock = MockClock::new();
+        self.clock.set_source(clock.clone());
+        self.sink.clear();
+
+        for (delay, msg) in events {
+            clock.advance(delay);
+            self.log(msg);
+        }
+        self.flush();
+        self.sink.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Limit, WarningKind};
+
+    #[test]
+    fn test_simulate() {
+        let mut rate_log =
+            RateLog::with_sink(Limit::Duration(Duration::from_secs(10)), CaptureSink::new());
+        let calls = (0..30).map(|i| {
+            let msg = if i % 10 == 9 { "other" } else { "message1" };
+            (Duration::from_secs(1), msg)
+        });
+
+        let events = rate_log.simulate(calls);
+        let summaries: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Summary {
+                    count,
+                    kind: WarningKind::Summary,
+                    ..
+                } => Some(*count),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(summaries, [8, 8, 8]);
+
+        // Every call is accounted for in the statistics
+        let occurrences: u64 = rate_log
+            .stats()
+            .messages()
+            .iter()
+            .map(|stats| stats.occurrences)
+            .sum();
+        assert_eq!(occurrences, 30);
+    }
+}
@@ -0,0 +1,740 @@
+//! Output destinations for rate limited messages.
+//!
+//! Every line produced by a [`RateLog`](crate::RateLog) - first occurrences as well as
+//! rate limit warnings - is handed to a [`Sink`]. The default [`StdoutSink`] keeps the
+//! original `println!` behavior, while custom sinks can route output to stderr, files,
+//! channels or in-memory buffers.
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::fs::{File, OpenOptions};
+#[cfg(feature = "std")]
+use std::io::{self, IsTerminal, Write};
+#[cfg(feature = "std")]
+use std::path::Path;
+
+#[cfg(feature = "std")]
+use crate::{Error, Result};
+use crate::{Event, Severity, TextPolicy};
+
+/// A destination for lines emitted by a [`RateLog`](crate::RateLog).
+///
+/// Implement this trait to redirect output anywhere: a file, a socket, a channel
+/// or your own logging pipeline.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{RateLog, Limit, Sink};
+///
+/// struct Prefixed;
+///
+/// impl Sink for Prefixed {
+///     fn emit(&mut self, record: &str) {
+///         println!("[app] {record}");
+///     }
+/// }
+///
+/// let mut logger = RateLog::with_sink(Limit::Rate(3), Prefixed);
+/// logger.log("Starting up"); // Prints: "[app] Starting up"
+/// ```
+pub trait Sink {
+    /// Writes a single emitted line.
+    ///
+    /// `record` never contains a trailing newline; sinks writing to line based
+    /// outputs are responsible for adding one.
+    fn emit(&mut self, record: &str);
+
+    /// Writes a single emitted line together with what it reports.
+    ///
+    /// Called by the default [`Sink::emit_event`]. The default implementation ignores
+    /// `kind` and calls [`Sink::emit`]; sinks that treat messages and summaries
+    /// differently, such as a syslog sink assigning priorities, override it.
+    fn emit_line(&mut self, record: &str, kind: LineKind) {
+        let _ = kind;
+        self.emit(record);
+    }
+
+    /// Receives a typed [`Event`] together with the line rendered for it.
+    ///
+    /// [`RateLog`](crate::RateLog) writes every line through this method. The default
+    /// implementation passes `line` on to [`Sink::emit_line`], so text output stays
+    /// exactly as configured; structured consumers override it to use `event` instead.
+    fn emit_event(&mut self, event: &Event<'_>, line: &str) {
+        self.emit_line(line, event.line_kind());
+    }
+
+    /// Writes several emitted lines at once.
+    ///
+    /// Called by buffering sinks such as [`Buffered`](crate::Buffered). The default
+    /// implementation emits the records one by one; sinks backed by files or sockets
+    /// can override it to issue a single write.
+    fn emit_batch(&mut self, records: &[String]) {
+        for record in records {
+            self.emit(record);
+        }
+    }
+
+    /// Writes out any records held back by the sink.
+    ///
+    /// Called by [`RateLog::flush`](crate::RateLog::flush). The default implementation
+    /// does nothing.
+    fn flush(&mut self) {}
+
+    /// Returns how messages that are not clean UTF-8 text are handled for this sink.
+    ///
+    /// Consulted by [`RateLog::log_bytes`](crate::RateLog::log_bytes). The default is
+    /// [`TextPolicy::Lossy`]; wrap a sink in [`Sanitized`](crate::Sanitized) to choose
+    /// another policy.
+    fn text_policy(&self) -> TextPolicy {
+        TextPolicy::default()
+    }
+
+    /// Returns `true` if lines are written to an interactive terminal.
+    ///
+    /// Used to decide whether output is colored by default (with the `color` feature).
+    /// Sinks writing to stdout or stderr check the stream; the default is `false`.
+    fn is_terminal(&self) -> bool {
+        false
+    }
+}
+
+/// What an emitted line reports, passed to [`Sink::emit_line`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineKind {
+    /// A logged message of the given severity.
+    Message(Severity),
+    /// A line written by the rate limiter itself: rate limit warnings, suppression
+    /// summaries and notices.
+    Summary,
+}
+
+impl<S: Sink + ?Sized> Sink for &mut S {
+    fn emit(&mut self, record: &str) {
+        (**self).emit(record);
+    }
+
+    fn emit_line(&mut self, record: &str, kind: LineKind) {
+        (**self).emit_line(record, kind);
+    }
+
+    fn emit_event(&mut self, event: &Event<'_>, line: &str) {
+        (**self).emit_event(event, line);
+    }
+
+    fn emit_batch(&mut self, records: &[String]) {
+        (**self).emit_batch(records);
+    }
+
+    fn flush(&mut self) {
+        (**self).flush();
+    }
+
+    fn text_policy(&self) -> TextPolicy {
+        (**self).text_policy()
+    }
+
+    fn is_terminal(&self) -> bool {
+        (**self).is_terminal()
+    }
+}
+
+impl<S: Sink + ?Sized> Sink for Box<S> {
+    fn emit(&mut self, record: &str) {
+        (**self).emit(record);
+    }
+
+    fn emit_line(&mut self, record: &str, kind: LineKind) {
+        (**self).emit_line(record, kind);
+    }
+
+    fn emit_event(&mut self, event: &Event<'_>, line: &str) {
+        (**self).emit_event(event, line);
+    }
+
+    fn emit_batch(&mut self, records: &[String]) {
+        (**self).emit_batch(records);
+    }
+
+    fn flush(&mut self) {
+        (**self).flush();
+    }
+
+    fn text_policy(&self) -> TextPolicy {
+        (**self).text_policy()
+    }
+
+    fn is_terminal(&self) -> bool {
+        (**self).is_terminal()
+    }
+}
+
+/// The sink of a [`RateLog`](crate::RateLog) created without one.
+#[cfg(feature = "std")]
+pub(crate) type DefaultSink = StdoutSink;
+
+/// The sink of a [`RateLog`](crate::RateLog) created without one. There is no standard
+/// output without `std`, so any sink can be boxed instead.
+#[cfg(not(feature = "std"))]
+pub(crate) type DefaultSink = Box<dyn Sink + Send>;
+
+/// Writes `records` as newline terminated lines with a single write call.
+#[cfg(feature = "std")]
+fn write_lines(out: &mut impl Write, records: &[String]) -> io::Result<()> {
+    let len = records.iter().map(|record| record.len() + 1).sum();
+    let mut batch = String::with_capacity(len);
+    for record in records {
+        batch.push_str(record);
+        batch.push('\n');
+    }
+    out.write_all(batch.as_bytes())
+}
+
+/// A sink that prints every line to stdout.
+///
+/// This is the default sink used by [`RateLog::new`](crate::RateLog::new).
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StdoutSink;
+
+#[cfg(feature = "std")]
+impl Sink for StdoutSink {
+    fn emit(&mut self, record: &str) {
+        // Like println!, but a closed stdout (e.g. piped into `head`) must not panic
+        let _ = writeln!(io::stdout().lock(), "{record}");
+    }
+
+    fn is_terminal(&self) -> bool {
+        io::stdout().is_terminal()
+    }
+
+    fn emit_batch(&mut self, records: &[String]) {
+        // Like println!, but failing to write a log line must not take the application down
+        let _ = write_lines(&mut io::stdout().lock(), records);
+    }
+
+    fn flush(&mut self) {
+        let _ = io::stdout().flush();
+    }
+}
+
+/// A sink that prints every line to stderr.
+///
+/// Useful for daemons and command line tools where stdout is reserved for data.
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StderrSink;
+
+#[cfg(feature = "std")]
+impl Sink for StderrSink {
+    fn emit(&mut self, record: &str) {
+        let _ = writeln!(io::stderr().lock(), "{record}");
+    }
+
+    fn emit_batch(&mut self, records: &[String]) {
+        let _ = write_lines(&mut io::stderr().lock(), records);
+    }
+
+    fn is_terminal(&self) -> bool {
+        io::stderr().is_terminal()
+    }
+}
+
+/// A sink that writes every line to any [`std::io::Write`] target.
+///
+/// Covers files, sockets, `Vec<u8>` buffers and the standard streams alike. Each
+/// record is written followed by a newline. A failing log target never takes the
+/// application down: the first write error is kept and can be inspected with
+/// [`WriterSink::take_error`], later records are still attempted.
+///
+/// Usually created through [`RateLog::with_writer`](crate::RateLog::with_writer).
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{RateLog, Limit, WriterSink};
+///
+/// let mut logger = RateLog::with_sink(Limit::Rate(5), WriterSink::new(Vec::new()));
+/// logger.log("Connection lost");
+///
+/// assert_eq!(logger.sink().get_ref(), b"Connection lost\n");
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct WriterSink<W: Write> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+#[cfg(feature = "std")]
+impl WriterSink<File> {
+    /// Opens the file at `path` for appending, creating it if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if the file cannot be opened.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use rate_log::{RateLog, Limit, WriterSink};
+    ///
+    /// let sink = WriterSink::create("/var/log/app.log")?;
+    /// let mut logger = RateLog::with_sink(Limit::Rate(5), sink);
+    /// # Ok::<(), rate_log::Error>(())
+    /// ```
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(WriterSink::new(file))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> WriterSink<W> {
+    /// Wraps `writer`.
+    pub fn new(writer: W) -> Self {
+        WriterSink {
+            writer,
+            error: None,
+        }
+    }
+
+    /// Returns and clears the first write error since the last call, if any.
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take().map(Error::Io)
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Unwraps the sink, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> WriterSink<W> {
+    /// Keeps the first error of a write operation.
+    fn record(&mut self, result: io::Result<()>) {
+        if let Err(err) = result {
+            self.error.get_or_insert(err);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Sink for WriterSink<W> {
+    fn emit(&mut self, record: &str) {
+        let result = writeln!(self.writer, "{record}");
+        self.record(result);
+    }
+
+    fn emit_batch(&mut self, records: &[String]) {
+        let result = write_lines(&mut self.writer, records);
+        self.record(result);
+    }
+
+    fn flush(&mut self) {
+        let result = self.writer.flush();
+        self.record(result);
+    }
+}
+
+/// A sink that captures every line and [`Event`] in memory.
+///
+/// Intended for tests: capture what the rate limiter emitted and assert on it
+/// without relying on stdout capture, either as rendered text with
+/// [`CaptureSink::lines`] or as typed events with [`CaptureSink::records`]. Lines
+/// reaching the sink without an event, e.g. through [`Decorated`], are recorded the
+/// same way [`ChannelSink`](crate::ChannelSink) reports them.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{RateLog, Limit, CaptureSink, Event};
+///
+/// let mut logger = RateLog::with_sink(Limit::Rate(1), CaptureSink::new());
+///
+/// logger.log("Error occurred");
+/// logger.log("Error occurred");
+///
+/// assert_eq!(
+///     logger.sink().lines(),
+///     [
+///         "Error occurred",
+///         "Message: \"Error occurred\" repeat for 1 times in the past 0ms",
+///     ]
+/// );
+/// assert!(matches!(
+///     logger.sink_mut().take()[..],
+///     [Event::Emitted { .. }, Event::Summary { count: 1, .. }]
+/// ));
+/// assert!(logger.sink().lines().is_empty());
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct CaptureSink {
+    lines: Vec<String>,
+    records: Vec<Event<'static>>,
+}
+
+impl CaptureSink {
+    /// Creates an empty capture sink.
+    pub fn new() -> Self {
+        CaptureSink::default()
+    }
+
+    /// Returns all captured lines, oldest first.
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Returns all captured events, oldest first, one per line.
+    pub fn records(&self) -> &[Event<'static>] {
+        &self.records
+    }
+
+    /// Returns all captured events and discards them together with their lines.
+    pub fn take(&mut self) -> Vec<Event<'static>> {
+        self.lines.clear();
+        core::mem::take(&mut self.records)
+    }
+
+    /// Discards all captured lines and events.
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.records.clear();
+    }
+}
+
+impl Sink for CaptureSink {
+    fn emit(&mut self, record: &str) {
+        self.emit_line(record, LineKind::Message(Severity::default()));
+    }
+
+    fn emit_line(&mut self, record: &str, kind: LineKind) {
+        self.emit_event(&Event::from_line(record, kind), record);
+    }
+
+    fn emit_event(&mut self, event: &Event<'_>, line: &str) {
+        self.lines.push(line.to_string());
+        self.records.push(event.clone().into_owned());
+    }
+}
+
+/// A piece of text added around every record by a [`Decorated`] sink.
+enum Decoration {
+    /// Text that never changes, such as a subsystem tag.
+    Static(String),
+    /// Text generated for every record, such as a correlation id.
+    Dynamic(Box<dyn FnMut() -> String + Send>),
+}
+
+impl Decoration {
+    fn render(&mut self, out: &mut String) {
+        match self {
+            Decoration::Static(text) => out.push_str(text),
+            Decoration::Dynamic(generate) => out.push_str(&generate()),
+        }
+    }
+}
+
+/// A sink wrapper that adds a prefix and/or suffix to every record.
+///
+/// Decorations are either static strings or callbacks evaluated each time a record
+/// is emitted, so embedding applications can tag output (subsystem names, correlation
+/// ids, ...) without wrapping every call site.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{RateLog, Limit, CaptureSink, Decorated};
+///
+/// let sink = Decorated::new(CaptureSink::new())
+///     .prefix("[net] ")
+///     .suffix_with(|| " (id=42)".to_string());
+/// let mut logger = RateLog::with_sink(Limit::Rate(5), sink);
+///
+/// logger.log("Connection lost");
+///
+/// assert_eq!(logger.sink().inner().lines(), ["[net] Connection lost (id=42)"]);
+/// ```
+pub struct Decorated<S> {
+    inner: S,
+    prefix: Option<Decoration>,
+    suffix: Option<Decoration>,
+}
+
+impl<S: Sink> Decorated<S> {
+    /// Wraps `inner` without any decoration.
+    pub fn new(inner: S) -> Self {
+        Decorated {
+            inner,
+            prefix: None,
+            suffix: None,
+        }
+    }
+
+    /// Sets a static prefix written before every record.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(Decoration::Static(prefix.into()));
+        self
+    }
+
+    /// Sets a prefix generated by `prefix` for every record.
+    pub fn prefix_with<F>(mut self, prefix: F) -> Self
+    where
+        F: FnMut() -> String + Send + 'static,
+    {
+        self.prefix = Some(Decoration::Dynamic(Box::new(prefix)));
+        self
+    }
+
+    /// Sets a static suffix written after every record.
+    pub fn suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.suffix = Some(Decoration::Static(suffix.into()));
+        self
+    }
+
+    /// Sets a suffix generated by `suffix` for every record.
+    pub fn suffix_with<F>(mut self, suffix: F) -> Self
+    where
+        F: FnMut() -> String + Send + 'static,
+    {
+        self.suffix = Some(Decoration::Dynamic(Box::new(suffix)));
+        self
+    }
+
+    /// Returns a reference to the wrapped sink.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped sink.
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Unwraps the decorator, returning the wrapped sink.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Sink> Decorated<S> {
+    /// Returns `record` with the prefix and suffix, or `None` if there are none.
+    fn decorate(&mut self, record: &str) -> Option<String> {
+        if self.prefix.is_none() && self.suffix.is_none() {
+            return None;
+        }
+
+        let mut line = String::with_capacity(record.len());
+        if let Some(prefix) = &mut self.prefix {
+            prefix.render(&mut line);
+        }
+        line.push_str(record);
+        if let Some(suffix) = &mut self.suffix {
+            suffix.render(&mut line);
+        }
+        Some(line)
+    }
+}
+
+impl<S: Sink> Sink for Decorated<S> {
+    fn emit(&mut self, record: &str) {
+        match self.decorate(record) {
+            Some(line) => self.inner.emit(&line),
+            None => self.inner.emit(record),
+        }
+    }
+
+    fn emit_line(&mut self, record: &str, kind: LineKind) {
+        match self.decorate(record) {
+            Some(line) => self.inner.emit_line(&line, kind),
+            None => self.inner.emit_line(record, kind),
+        }
+    }
+
+    fn flush(&mut self) {
+        self.inner.flush();
+    }
+
+    fn text_policy(&self) -> TextPolicy {
+        self.inner.text_policy()
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.inner.is_terminal()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+
+    /// Forwards every line to a channel, like a sink feeding a logging thread.
+    #[cfg(feature = "std")]
+    struct ChannelSink(std::sync::mpsc::Sender<String>);
+
+    #[cfg(feature = "std")]
+    impl Sink for ChannelSink {
+        fn emit(&mut self, record: &str) {
+            let _ = self.0.send(record.to_string());
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_custom_sink() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut rate_log = crate::RateLog::with_sink(crate::Limit::Rate(2), ChannelSink(sender))
+            .with_clock(crate::MockClock::new());
+
+        for _ in 0..3 {
+            rate_log.log("disk full");
+        }
+        // Batches fall back to one `emit` per record
+        rate_log
+            .sink_mut()
+            .emit_batch(&["first".to_string(), "second".to_string()]);
+        rate_log.flush();
+
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            [
+                "disk full",
+                "Message: \"disk full\" repeat for 2 times in the past 0ms",
+                "first",
+                "second",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_capture_sink_lines() {
+        let mut sink = CaptureSink::new();
+        assert!(sink.lines().is_empty());
+
+        sink.emit("first");
+        sink.emit_line("second", LineKind::Summary);
+        assert_eq!(sink.lines(), ["first", "second"]);
+        assert_eq!(sink.records().len(), 2);
+
+        // Cleared sinks start over
+        sink.clear();
+        assert!(sink.lines().is_empty() && sink.records().is_empty());
+        sink.emit("third");
+        assert_eq!(sink.lines(), ["third"]);
+    }
+
+    #[test]
+    fn test_decorated_sink() {
+        let mut counter = 0;
+        let mut sink = Decorated::new(CaptureSink::new())
+            .prefix("[db] ")
+            .suffix_with(move || {
+                counter += 1;
+                format!(" #{counter}")
+            });
+
+        sink.emit("query failed");
+        sink.emit("query failed");
+
+        assert_eq!(
+            sink.inner().lines(),
+            ["[db] query failed #1", "[db] query failed #2"]
+        );
+
+        // Without decorations records pass through untouched
+        let mut sink = Decorated::new(CaptureSink::new());
+        sink.emit("query failed");
+        assert_eq!(sink.into_inner().lines(), ["query failed"]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_capture_sink() {
+        let mut rate_log = crate::RateLog::with_sink(crate::Limit::Rate(1), CaptureSink::new())
+            .with_clock(crate::MockClock::new());
+        rate_log.log_with_severity(Severity::Warn, "disk full");
+        rate_log.log("disk full");
+        rate_log.sink_mut().emit("raw");
+
+        let sink = rate_log.sink_mut();
+        assert_eq!(sink.records().len(), 3);
+        assert_eq!(
+            sink.take()[..],
+            [
+                Event::Emitted {
+                    msg: "disk full".into(),
+                    severity: Severity::Warn
+                },
+                Event::Summary {
+                    key: "disk full".into(),
+                    count: 1,
+                    duration: core::time::Duration::ZERO,
+                    kind: crate::WarningKind::LimitExceeded(crate::Trigger::Count)
+                },
+                Event::Emitted {
+                    msg: "raw".into(),
+                    severity: Severity::Info
+                },
+            ]
+        );
+        assert!(sink.lines().is_empty() && sink.records().is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_writer_sink() {
+        let mut sink = WriterSink::new(Vec::new());
+        sink.emit("first");
+        sink.emit_batch(&["second".to_string(), "third".to_string()]);
+        sink.flush();
+
+        assert!(sink.take_error().is_none());
+        assert_eq!(sink.into_inner(), b"first\nsecond\nthird\n");
+
+        // Errors are kept for the caller instead of being printed or panicking
+        let mut buf = [0u8; 4];
+        let mut sink = WriterSink::new(&mut buf[..]);
+        sink.emit("too long");
+        sink.emit("again");
+        assert!(matches!(sink.take_error(), Some(Error::Io(_))));
+        assert!(sink.take_error().is_none());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_writer_sink_create() {
+        let dir = std::env::temp_dir().join(format!("rate-log-sink-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.log");
+
+        // Reopening appends instead of truncating
+        for record in ["first", "second"] {
+            let mut sink = WriterSink::create(&path).unwrap();
+            sink.emit(record);
+            assert!(sink.take_error().is_none());
+        }
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+
+        // A missing directory is reported as an I/O error with its cause
+        let err = WriterSink::create(dir.join("missing").join("app.log")).unwrap_err();
+        assert!(matches!(&err, Error::Io(io) if io.kind() == io::ErrorKind::NotFound));
+        assert!(err.to_string().starts_with("I/O error: "));
+        assert!(std::error::Error::source(&err).is_some());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,447 @@
+//! Tracking state persisted across restarts.
+
+use alloc::string::String;
+#[cfg(feature = "serde")]
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::clock::Instant;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::Severity;
+#[cfg(feature = "serde")]
+use crate::{Error, Result};
+
+/// The tracking state of a [`RateLog`](crate::RateLog) at one point in time.
+///
+/// Taken with [`RateLog::snapshot`](crate::RateLog::snapshot) and loaded into a new
+/// limiter with [`RateLog::restore`](crate::RateLog::restore), e.g. by a daemon that
+/// restarts every few minutes and should not re-print the first occurrence of a message
+/// that already repeated 10,000 times. Times are stored relative to the moment the
+/// snapshot was taken, since [`Instant`]s do not survive a restart.
+///
+/// With the `serde` feature, snapshots implement `Serialize` and `Deserialize` and can
+/// be converted to and from JSON with [`Snapshot::to_json`] and [`Snapshot::from_json`].
+///
+/// Snapshots of several processes can be combined with [`Snapshot::merge`], e.g. by a
+/// supervisor aggregating the suppression statistics of its workers. Workers
+/// reporting periodically send [`Snapshot::diff`]s, so nothing is counted twice.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{Limit, RateLog, Snapshot};
+///
+/// let mut worker = RateLog::new(Limit::Rate(10));
+/// worker.log("Disk full");
+/// let first = worker.snapshot();
+/// worker.log("Disk full");
+/// let second = worker.snapshot();
+///
+/// // In the supervisor, e.g. after receiving the snapshots as JSON
+/// let mut total = Snapshot::default();
+/// total.merge(&first);
+/// total.merge(&second.diff(&first));
+/// assert_eq!(total.get("Disk full").unwrap().occurrences(), 2);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Snapshot {
+    /// The format version of the snapshot.
+    pub(crate) v: u32,
+    /// The key of the tracked message, empty if none.
+    pub(crate) key: String,
+    /// Whether the tracked message was logged with an explicit key.
+    pub(crate) keyed: bool,
+    /// Repeats of the tracked message not yet covered by a warning or summary.
+    pub(crate) pending: u32,
+    /// Time accumulated between the pending repeats.
+    pub(crate) pending_duration: Duration,
+    /// Time between the last occurrence of the tracked message and the snapshot.
+    pub(crate) idle: Option<Duration>,
+    /// The per-key statistics, ordered by key. Deserialized statistics are sorted and
+    /// duplicate keys combined, since lookups and merges binary search them.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "sorted_messages"))]
+    pub(crate) messages: Vec<SnapshotEntry>,
+    /// How many times a poisoned lock around the limiter was recovered. Missing in
+    /// snapshots written before it was added.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) poison_recoveries: u64,
+}
+
+/// The statistics of one key within a [`Snapshot`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SnapshotEntry {
+    pub(crate) key: String,
+    pub(crate) occurrences: u64,
+    pub(crate) suppressed: u64,
+    pub(crate) severity: Severity,
+    /// Time between the first occurrence and the snapshot.
+    pub(crate) first_seen_ago: Duration,
+    /// Time between the latest occurrence and the snapshot.
+    pub(crate) last_seen_ago: Duration,
+    pub(crate) duration: Duration,
+}
+
+impl SnapshotEntry {
+    /// Returns the rate limiting key.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the total number of occurrences.
+    pub fn occurrences(&self) -> u64 {
+        self.occurrences
+    }
+
+    /// Returns the number of occurrences that were not emitted verbatim.
+    pub fn suppressed(&self) -> u64 {
+        self.suppressed
+    }
+
+    /// Returns the severity of the latest occurrence.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Returns the time between the first occurrence and the snapshot.
+    pub fn first_seen_ago(&self) -> Duration {
+        self.first_seen_ago
+    }
+
+    /// Returns the time between the latest occurrence and the snapshot.
+    pub fn last_seen_ago(&self) -> Duration {
+        self.last_seen_ago
+    }
+
+    /// Returns the time accumulated between consecutive occurrences.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Combines the statistics of the same key from another snapshot.
+    fn merge(&mut self, other: &SnapshotEntry) {
+        self.occurrences += other.occurrences;
+        self.suppressed += other.suppressed;
+        if other.last_seen_ago < self.last_seen_ago {
+            self.severity = other.severity;
+        }
+        self.first_seen_ago = self.first_seen_ago.max(other.first_seen_ago);
+        self.last_seen_ago = self.last_seen_ago.min(other.last_seen_ago);
+        self.duration += other.duration;
+    }
+}
+
+impl Default for Snapshot {
+    /// Returns an empty snapshot, e.g. to merge the snapshots of several processes into.
+    fn default() -> Self {
+        Snapshot {
+            v: Snapshot::VERSION,
+            key: String::new(),
+            keyed: false,
+            pending: 0,
+            pending_duration: Duration::ZERO,
+            idle: None,
+            messages: Vec::new(),
+            poison_recoveries: 0,
+        }
+    }
+}
+
+impl Snapshot {
+    /// The current format version.
+    pub const VERSION: u32 = 1;
+
+    /// Returns the key of the message tracked when the snapshot was taken, if any.
+    pub fn key(&self) -> Option<&str> {
+        Some(self.key.as_str()).filter(|key| !key.is_empty())
+    }
+
+    /// Returns the number of keys with statistics in the snapshot.
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Returns `true` if the snapshot holds no statistics.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Returns the statistics of every key, ordered by key.
+    pub fn messages(&self) -> &[SnapshotEntry] {
+        &self.messages
+    }
+
+    /// Returns the statistics of the given key, if it was seen.
+    pub fn get(&self, key: &str) -> Option<&SnapshotEntry> {
+        self.messages
+            .binary_search_by(|entry| entry.key.as_str().cmp(key))
+            .ok()
+            .map(|index| &self.messages[index])
+    }
+
+    /// Returns how many times a poisoned lock around the limiter was recovered, see
+    /// [`Stats::poison_recoveries`](crate::Stats::poison_recoveries).
+    pub fn poison_recoveries(&self) -> u64 {
+        self.poison_recoveries
+    }
+
+    /// Adds the statistics of another snapshot to this one, e.g. of another process.
+    ///
+    /// Counts and durations of keys known to both are added up; the first-seen and
+    /// last-seen times become the earliest and latest of both, and the severity is
+    /// that of the latest occurrence. Keys only seen by `other` are added. Times are
+    /// combined as if both snapshots were taken at the same moment.
+    ///
+    /// The pending repeats of the tracked message are added up if both snapshots
+    /// track the same key. Otherwise the tracked message of this snapshot is kept, or
+    /// that of `other` if this snapshot tracks none; the statistics count the repeats
+    /// pending for the other key either way. Poisoning recoveries are added up.
+    pub fn merge(&mut self, other: &Snapshot) {
+        self.poison_recoveries += other.poison_recoveries;
+        for entry in &other.messages {
+            match self
+                .messages
+                .binary_search_by(|own| own.key.as_str().cmp(&entry.key))
+            {
+                Ok(index) => self.messages[index].merge(entry),
+                Err(index) => self.messages.insert(index, entry.clone()),
+            }
+        }
+
+        if self.key.is_empty() {
+            self.key.clone_from(&other.key);
+            self.keyed = other.keyed;
+            self.pending = other.pending;
+            self.pending_duration = other.pending_duration;
+            self.idle = other.idle;
+        } else if self.key == other.key {
+            self.pending = self.pending.saturating_add(other.pending);
+            self.pending_duration += other.pending_duration;
+            self.idle = match (self.idle, other.idle) {
+                (Some(own), Some(other)) => Some(own.min(other)),
+                (own, other) => own.or(other),
+            };
+        }
+        self.v = Snapshot::VERSION;
+    }
+
+    /// Returns what changed since `earlier`, a snapshot of the same limiter.
+    ///
+    /// Counts and durations are those accumulated since `earlier`; keys without new
+    /// occurrences are left out. Times and the tracked message are those of this
+    /// snapshot. Merging the diffs of periodic snapshots counts every occurrence
+    /// exactly once.
+    pub fn diff(&self, earlier: &Snapshot) -> Snapshot {
+        let messages = self
+            .messages
+            .iter()
+            .filter_map(|entry| {
+                let mut entry = entry.clone();
+                if let Some(before) = earlier.get(&entry.key) {
+                    entry.occurrences = entry.occurrences.saturating_sub(before.occurrences);
+                    entry.suppressed = entry.suppressed.saturating_sub(before.suppressed);
+                    entry.duration = entry.duration.saturating_sub(before.duration);
+                }
+                (entry.occurrences > 0).then_some(entry)
+            })
+            .collect();
+        Snapshot {
+            messages,
+            poison_recoveries: self
+                .poison_recoveries
+                .saturating_sub(earlier.poison_recoveries),
+            ..self.clone()
+        }
+    }
+
+    /// Serializes the snapshot as JSON.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        // Serializing plain strings, integers and durations cannot fail
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Parses a snapshot serialized with [`Snapshot::to_json`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Persistence`] if `json` is not a valid snapshot.
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|err| Error::Persistence(err.to_string()))
+    }
+}
+
+/// Deserializes per-key statistics, ordering them by key and combining the statistics
+/// of duplicate keys.
+#[cfg(feature = "serde")]
+fn sorted_messages<'de, D>(deserializer: D) -> core::result::Result<Vec<SnapshotEntry>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let mut messages = Vec::<SnapshotEntry>::deserialize(deserializer)?;
+    messages.sort_by(|a, b| a.key.cmp(&b.key));
+    messages.dedup_by(|entry, kept| {
+        let duplicate = entry.key == kept.key;
+        if duplicate {
+            kept.merge(entry);
+        }
+        duplicate
+    });
+    Ok(messages)
+}
+
+/// Returns the instant `ago` before `now`, or `now` if that precedes the clock's origin.
+pub(crate) fn before(now: Instant, ago: Duration) -> Instant {
+    now.checked_sub(ago).unwrap_or(now)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{CaptureSink, Limit, MockClock, RateLog};
+    use std::time::Duration;
+
+    #[test]
+    fn test_snapshot_restore() {
+        let clock = MockClock::new();
+        let mut before =
+            RateLog::with_sink(Limit::Rate(10), CaptureSink::new()).with_clock(clock.clone());
+        before.log("message1");
+        clock.advance(Duration::from_secs(1));
+        before.log("message2");
+        before.log("message2");
+        let snapshot = before.snapshot();
+        assert_eq!(snapshot.key(), Some("message2"));
+        assert_eq!(snapshot.len(), 2);
+
+        let mut after =
+            RateLog::with_sink(Limit::Rate(10), CaptureSink::new()).with_clock(clock.clone());
+        after.restore(snapshot).unwrap();
+        after.log("message2");
+        after.flush();
+
+        assert_eq!(
+            after.sink().lines(),
+            ["Message: \"message2\" suppressed 2 repeats over 0ms"]
+        );
+        let stats = after.stats();
+        assert_eq!(stats.get("message1").unwrap().occurrences, 1);
+        assert_eq!(stats.get("message2").unwrap().occurrences, 3);
+        assert_eq!(stats.get("message2").unwrap().suppressed, 2);
+    }
+
+    #[test]
+    fn test_snapshot_merge() {
+        let clock = MockClock::new();
+        let mut worker1 = RateLog::new(Limit::Rate(10)).with_clock(clock.clone());
+        let mut worker2 = RateLog::new(Limit::Rate(10)).with_clock(clock.clone());
+        worker1.log("message1");
+        worker2.log("message2");
+        clock.advance(Duration::from_secs(1));
+        worker1.log("message1");
+        worker2.log("message1");
+        let first = worker1.snapshot();
+
+        let mut total = crate::Snapshot::default();
+        total.merge(&first);
+        total.merge(&worker2.snapshot());
+        assert_eq!(total.key(), Some("message1"));
+        assert_eq!(total.len(), 2);
+        let message1 = total.get("message1").unwrap();
+        assert_eq!(message1.occurrences(), 3);
+        assert_eq!(message1.suppressed(), 1);
+        assert_eq!(message1.first_seen_ago(), Duration::from_secs(1));
+        assert_eq!(message1.last_seen_ago(), Duration::ZERO);
+        assert_eq!(message1.duration(), Duration::from_secs(1));
+
+        // Only the new occurrence is merged the second time
+        clock.advance(Duration::from_secs(1));
+        worker1.log("message1");
+        let diff = worker1.snapshot().diff(&first);
+        assert_eq!(diff.len(), 1);
+        total.merge(&diff);
+        let message1 = total.get("message1").unwrap();
+        assert_eq!(message1.occurrences(), 4);
+        assert_eq!(message1.suppressed(), 2);
+        assert_eq!(total.get("message2").unwrap().occurrences(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_snapshot_json() {
+        let mut rate_log = RateLog::with_sink(Limit::Rate(10), CaptureSink::new());
+        rate_log.log("message1");
+        let snapshot = rate_log.snapshot();
+
+        let json = snapshot.to_json();
+        assert_eq!(crate::Snapshot::from_json(&json).unwrap(), snapshot);
+        assert!(crate::Snapshot::from_json("{}").is_err());
+
+        let mut newer = snapshot;
+        newer.v = crate::Snapshot::VERSION + 1;
+        assert!(RateLog::new(Limit::Rate(10)).restore(newer).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_snapshot_json_unsorted() {
+        let clock = MockClock::new();
+        let mut rate_log =
+            RateLog::with_sink(Limit::Rate(10), CaptureSink::new()).with_clock(clock.clone());
+        for msg in ["message1", "message2", "message3", "message2"] {
+            rate_log.log(msg);
+        }
+        let snapshot = rate_log.snapshot();
+
+        // Written by hand or by another tool, out of order and with a duplicate
+        let mut unsorted = snapshot.clone();
+        unsorted.messages.reverse();
+        unsorted.messages.push(snapshot.messages[0].clone());
+        let restored = crate::Snapshot::from_json(&unsorted.to_json()).unwrap();
+
+        let keys: Vec<_> = restored
+            .messages()
+            .iter()
+            .map(|entry| entry.key())
+            .collect();
+        assert_eq!(keys, ["message1", "message2", "message3"]);
+        assert_eq!(restored.get("message1").unwrap().occurrences(), 2);
+        assert_eq!(restored.get("message2").unwrap().occurrences(), 2);
+        assert_eq!(restored.get("message3").unwrap().occurrences(), 1);
+
+        let mut total = crate::Snapshot::default();
+        total.merge(&restored);
+        total.merge(&snapshot);
+        assert_eq!(total.len(), 3);
+        assert_eq!(total.get("message3").unwrap().occurrences(), 2);
+    }
+
+    #[test]
+    fn test_restore_max_tracked() {
+        let clock = MockClock::new();
+        let mut before =
+            RateLog::with_sink(Limit::Rate(10), CaptureSink::new()).with_clock(clock.clone());
+        for msg in ["message1", "message2", "message3", "message1"] {
+            before.log(msg);
+            clock.advance(Duration::from_secs(1));
+        }
+
+        // Only the most recently seen keys fit
+        let mut after = RateLog::with_sink(Limit::Rate(10), CaptureSink::new())
+            .with_clock(clock.clone())
+            .with_max_tracked_messages(2);
+        after.restore(before.snapshot()).unwrap();
+        let keys: Vec<_> = after
+            .stats()
+            .messages()
+            .iter()
+            .map(|entry| entry.key.clone())
+            .collect();
+        assert_eq!(keys, ["message1", "message3"]);
+    }
+}
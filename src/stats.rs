@@ -0,0 +1,458 @@
+//! Per-key bookkeeping kept across message changes.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::clock::Instant;
+
+use crate::{Map, Severity};
+
+/// The upper bounds of the [`IntervalHistogram`] buckets in milliseconds; one more
+/// bucket holds longer intervals.
+const BUCKET_BOUNDS_MS: [u64; 15] = [
+    1, 2, 5, 10, 20, 50, 100, 200, 500, 1_000, 2_000, 5_000, 10_000, 30_000, 60_000,
+];
+
+/// A fixed-bucket histogram of the times between consecutive repeats of a message.
+///
+/// Buckets grow from 1ms to 60s in 1-2-5 steps, so the histogram stays small while
+/// still telling a tight loop (sub-millisecond repeats) from periodic retries
+/// (repeats every few seconds). Part of [`MessageStats`]. With
+/// [`RateLog::with_lazy_clock`](crate::RateLog::with_lazy_clock), repeats skipping the
+/// clock are recorded at the time of the last reading.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{RateLog, Limit, MockClock};
+/// use std::time::Duration;
+///
+/// let clock = MockClock::new();
+/// let mut logger = RateLog::new(Limit::Rate(100)).with_clock(clock.clone());
+///
+/// for _ in 0..10 {
+///     logger.log("Retrying");
+///     clock.advance(Duration::from_secs(3));
+/// }
+/// let stats = logger.stats();
+/// let intervals = &stats.get("Retrying").unwrap().intervals;
+/// assert_eq!(intervals.count(), 9);
+/// assert_eq!(intervals.percentile(50.0), Some(Duration::from_secs(3)));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntervalHistogram {
+    /// The number of intervals per bucket.
+    counts: [u64; BUCKET_BOUNDS_MS.len() + 1],
+    /// The longest interval recorded.
+    max: Duration,
+}
+
+impl IntervalHistogram {
+    /// Records the time between two consecutive repeats.
+    pub(crate) fn record(&mut self, interval: Duration) {
+        let millis = interval.as_millis();
+        let bucket = BUCKET_BOUNDS_MS
+            .iter()
+            .position(|&bound| millis < u128::from(bound))
+            .unwrap_or(BUCKET_BOUNDS_MS.len());
+        self.counts[bucket] += 1;
+        self.max = self.max.max(interval);
+    }
+
+    /// Returns the number of intervals recorded.
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Returns the upper bound of the bucket holding the `p`th percentile (0 to 100)
+    /// of the intervals, capped at the longest interval recorded, or `None` if no
+    /// interval was recorded.
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        // Rounds up without `f64::ceil`, which needs `std`
+        let exact = p.clamp(0.0, 100.0) / 100.0 * count as f64;
+        let rank = (exact as u64 + u64::from((exact as u64 as f64) < exact)).max(1);
+        let mut seen = 0;
+        for (bucket, &bucket_count) in self.counts.iter().enumerate() {
+            seen += bucket_count;
+            if seen >= rank {
+                let bound = BUCKET_BOUNDS_MS
+                    .get(bucket)
+                    .map_or(self.max, |&bound| Duration::from_millis(bound));
+                return Some(bound.min(self.max));
+            }
+        }
+        Some(self.max)
+    }
+
+    /// Returns the buckets as pairs of their exclusive upper bound and the number of
+    /// intervals in them. The last bucket is unbounded and reported as [`Duration::MAX`].
+    pub fn buckets(&self) -> impl Iterator<Item = (Duration, u64)> + '_ {
+        BUCKET_BOUNDS_MS
+            .iter()
+            .map(|&bound| Duration::from_millis(bound))
+            .chain([Duration::MAX])
+            .zip(self.counts.iter().copied())
+    }
+
+    /// Adds the intervals recorded by another histogram.
+    pub fn merge(&mut self, other: &IntervalHistogram) {
+        for (count, other) in self.counts.iter_mut().zip(other.counts) {
+            *count += other;
+        }
+        self.max = self.max.max(other.max);
+    }
+}
+
+/// What a [`RateLog`](crate::RateLog) remembers about a key beyond the current message.
+#[derive(Debug)]
+pub(crate) struct Entry {
+    /// The total number of occurrences.
+    pub(crate) occurrences: u64,
+    /// The number of occurrences that were not emitted verbatim.
+    pub(crate) suppressed: u64,
+    /// The severity of the latest occurrence.
+    pub(crate) severity: Severity,
+    /// The exemplar of the latest suppressed occurrence that had one.
+    pub(crate) exemplar: Option<String>,
+    /// The time of the first occurrence.
+    pub(crate) first_seen: Instant,
+    /// The time of the latest occurrence.
+    pub(crate) last_seen: Instant,
+    /// Time accumulated between consecutive occurrences.
+    pub(crate) duration: Duration,
+    /// The times between consecutive occurrences.
+    pub(crate) intervals: IntervalHistogram,
+    /// The value of `suppressed` at the last periodic report.
+    pub(crate) reported: u64,
+    /// The time of the latest warning or summary, only kept with a summary interval.
+    pub(crate) last_summary: Option<Instant>,
+    /// Repeats whose summary was held back by the summary interval.
+    pub(crate) held: Option<Held>,
+    /// The suppression counter of the key, registered on its first suppression.
+    #[cfg(feature = "metrics")]
+    pub(crate) counter: Option<metrics::Counter>,
+}
+
+/// Repeats of a key whose summary was held back, see
+/// [`RateLog::with_summary_interval`](crate::RateLog::with_summary_interval).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Held {
+    /// The number of repeats held back.
+    pub(crate) count: u32,
+    /// The time accumulated between them.
+    pub(crate) duration: Duration,
+    /// Whether the key was logged through an explicit key.
+    pub(crate) keyed: bool,
+}
+
+impl Entry {
+    pub(crate) fn new(now: Instant) -> Self {
+        Entry {
+            occurrences: 0,
+            suppressed: 0,
+            severity: Severity::default(),
+            exemplar: None,
+            first_seen: now,
+            last_seen: now,
+            duration: Duration::from_secs(0),
+            intervals: IntervalHistogram::default(),
+            reported: 0,
+            last_summary: None,
+            held: None,
+            #[cfg(feature = "metrics")]
+            counter: None,
+        }
+    }
+}
+
+/// The statistics of every key, with those of the key currently repeating kept aside
+/// so its repeats skip hashing the key.
+#[derive(Debug, Default)]
+pub(crate) struct Entries {
+    /// The statistics of every key but the hot one.
+    map: Map<String, Entry>,
+    /// The hot key and its statistics, not in `map`.
+    hot: Option<(String, Entry)>,
+}
+
+impl Entries {
+    pub(crate) fn new() -> Self {
+        Entries::default()
+    }
+
+    /// Makes `key` the hot key, if it has statistics, and returns them.
+    ///
+    /// Costs two lookups unless `key` already is the hot key, so it pays off for keys
+    /// that repeat, not for every key logged.
+    pub(crate) fn promote(&mut self, key: &str) -> Option<&mut Entry> {
+        if !matches!(&self.hot, Some((hot, _)) if hot == key) {
+            let promoted = self.map.remove_entry(key)?;
+            if let Some((hot, entry)) = self.hot.replace(promoted) {
+                self.map.insert(hot, entry);
+            }
+        }
+        self.hot.as_mut().map(|(_, entry)| entry)
+    }
+
+    /// Returns the statistics of `key` if it is the hot key, without any lookup.
+    pub(crate) fn hot_mut(&mut self, key: &str) -> Option<&mut Entry> {
+        match &mut self.hot {
+            Some((hot, entry)) if hot == key => Some(entry),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<&Entry> {
+        match &self.hot {
+            Some((hot, entry)) if hot == key => Some(entry),
+            _ => self.map.get(key),
+        }
+    }
+
+    pub(crate) fn get_mut(&mut self, key: &str) -> Option<&mut Entry> {
+        match &mut self.hot {
+            Some((hot, entry)) if hot == key => Some(entry),
+            _ => self.map.get_mut(key),
+        }
+    }
+
+    /// Adds the statistics of a key without any, returning them.
+    pub(crate) fn insert(&mut self, key: String, entry: Entry) -> &mut Entry {
+        self.map.entry(key).or_insert(entry)
+    }
+
+    pub(crate) fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub(crate) fn remove(&mut self, key: &str) -> Option<Entry> {
+        match self.hot.take() {
+            Some((hot, entry)) if hot == key => Some(entry),
+            hot => {
+                self.hot = hot;
+                self.map.remove(key)
+            }
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.map.len() + usize::from(self.hot.is_some())
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.map.clear();
+        self.hot = None;
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&String, &Entry)> {
+        self.map
+            .iter()
+            .chain(self.hot.as_ref().map(|(key, entry)| (key, entry)))
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = (&String, &mut Entry)> {
+        self.map
+            .iter_mut()
+            .chain(self.hot.as_mut().map(|(key, entry)| (&*key, entry)))
+    }
+}
+
+impl FromIterator<(String, Entry)> for Entries {
+    fn from_iter<I: IntoIterator<Item = (String, Entry)>>(iter: I) -> Self {
+        Entries {
+            map: iter.into_iter().collect(),
+            hot: None,
+        }
+    }
+}
+
+/// Statistics about a tracked message.
+///
+/// Part of the [`Stats`] snapshot returned by [`RateLog::stats`](crate::RateLog::stats).
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageStats {
+    /// The rate limiting key, i.e. the message text for plain `log()` calls.
+    pub key: String,
+    /// The total number of occurrences, emitted or not.
+    pub occurrences: u64,
+    /// The number of occurrences that were not emitted verbatim.
+    pub suppressed: u64,
+    /// The time of the first occurrence.
+    pub first_seen: Instant,
+    /// The time of the latest occurrence.
+    pub last_seen: Instant,
+    /// Time accumulated between consecutive repeats of the message, the same measure
+    /// used by [`Limit::Duration`](crate::Limit::Duration).
+    pub duration: Duration,
+    /// The distribution of the times between consecutive repeats.
+    pub intervals: IntervalHistogram,
+}
+
+/// A key ranked in a top-offenders report.
+///
+/// Returned by [`RateLog::top_offenders`](crate::RateLog::top_offenders).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Offender {
+    /// The rate limiting key, i.e. the message text for plain `log()` calls.
+    pub key: String,
+    /// The severity of the latest occurrence.
+    pub severity: Severity,
+    /// The number of occurrences that were not emitted verbatim.
+    pub suppressed: u64,
+    /// The weighted impact: `suppressed` multiplied by the weight of `severity`.
+    pub score: f64,
+    /// The exemplar labels of the latest suppressed occurrence that had one, see
+    /// [`RateLog::with_exemplars`](crate::RateLog::with_exemplars).
+    pub exemplar: Option<String>,
+}
+
+/// The occurrences of a key suppressed since the previous report.
+///
+/// Returned by [`RateLog::report`](crate::RateLog::report).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    /// The rate limiting key, i.e. the message text for plain `log()` calls.
+    pub key: String,
+    /// The severity of the latest occurrence.
+    pub severity: Severity,
+    /// The number of occurrences not emitted verbatim since the previous report.
+    pub suppressed: u64,
+    /// The time covered: since the previous report, or since the key was first seen
+    /// if that was later.
+    pub duration: Duration,
+    /// The exemplar labels of the latest suppressed occurrence that had one, see
+    /// [`RateLog::with_exemplars`](crate::RateLog::with_exemplars).
+    pub exemplar: Option<String>,
+}
+
+impl MessageStats {
+    /// Returns the occurrences per second between the first and the latest one, or
+    /// `None` if the message was seen only once or at a single instant.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rate_log::{RateLog, Limit, MockClock};
+    /// use std::time::Duration;
+    ///
+    /// let clock = MockClock::new();
+    /// let mut logger = RateLog::new(Limit::Rate(100)).with_clock(clock.clone());
+    ///
+    /// for _ in 0..=100 {
+    ///     logger.log("Disk full");
+    ///     clock.advance(Duration::from_millis(10));
+    /// }
+    /// assert_eq!(logger.stats().get("Disk full").unwrap().rate(), Some(101.0));
+    /// ```
+    pub fn rate(&self) -> Option<f64> {
+        let secs = self.last_seen.duration_since(self.first_seen).as_secs_f64();
+        (secs > 0.0).then(|| self.occurrences as f64 / secs)
+    }
+
+    /// Combines the statistics of the same message gathered by another limiter.
+    ///
+    /// Counts and durations are added up; the first-seen and last-seen times become
+    /// the earliest and latest of both.
+    pub fn merge(&mut self, other: &MessageStats) {
+        self.occurrences += other.occurrences;
+        self.suppressed += other.suppressed;
+        self.first_seen = self.first_seen.min(other.first_seen);
+        self.last_seen = self.last_seen.max(other.last_seen);
+        self.duration += other.duration;
+        self.intervals.merge(&other.intervals);
+    }
+}
+
+/// A snapshot of per-message statistics, ordered by key.
+///
+/// Returned by [`RateLog::stats`](crate::RateLog::stats). Snapshots taken from several
+/// limiters, e.g. one per thread for contention-free logging, can be combined into a
+/// single report with [`Stats::merge`].
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{RateLog, Limit};
+///
+/// let mut worker1 = RateLog::new(Limit::Rate(10));
+/// let mut worker2 = RateLog::new(Limit::Rate(10));
+///
+/// worker1.log("Disk full");
+/// worker2.log("Disk full");
+/// worker2.log("Connection lost");
+///
+/// let mut stats = worker1.stats();
+/// stats.merge(&worker2.stats());
+///
+/// assert_eq!(stats.get("Disk full").unwrap().occurrences, 2);
+/// assert_eq!(stats.messages().len(), 2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Stats {
+    /// The statistics of every message, ordered by key.
+    messages: Vec<MessageStats>,
+
+    /// How many times a poisoned lock around the limiter was recovered.
+    poison_recoveries: u64,
+}
+
+impl Stats {
+    /// Creates a snapshot from unordered per-message statistics.
+    pub(crate) fn new(mut messages: Vec<MessageStats>, poison_recoveries: u64) -> Self {
+        messages.sort_by(|a, b| a.key.cmp(&b.key));
+        Stats {
+            messages,
+            poison_recoveries,
+        }
+    }
+
+    /// Returns the statistics of every message, ordered by key.
+    pub fn messages(&self) -> &[MessageStats] {
+        &self.messages
+    }
+
+    /// Returns the statistics of the message with the given key, if it was seen.
+    pub fn get(&self, key: &str) -> Option<&MessageStats> {
+        self.messages
+            .binary_search_by(|stats| stats.key.as_str().cmp(key))
+            .ok()
+            .map(|index| &self.messages[index])
+    }
+
+    /// Returns how many times a [`SharedRateLog`](crate::SharedRateLog) recovered the
+    /// limiter from a poisoned lock, each time resetting the tracked message.
+    pub fn poison_recoveries(&self) -> u64 {
+        self.poison_recoveries
+    }
+
+    /// Adds the statistics of another snapshot to this one.
+    ///
+    /// Messages known to both snapshots are combined with [`MessageStats::merge`];
+    /// messages only seen by `other` are added. Poisoning recoveries are added up.
+    pub fn merge(&mut self, other: &Stats) {
+        self.poison_recoveries += other.poison_recoveries;
+        for stats in &other.messages {
+            match self
+                .messages
+                .binary_search_by(|own| own.key.as_str().cmp(&stats.key))
+            {
+                Ok(index) => self.messages[index].merge(stats),
+                Err(index) => self.messages.insert(index, stats.clone()),
+            }
+        }
+    }
+}
+
+impl IntoIterator for Stats {
+    type Item = MessageStats;
+    type IntoIter = alloc::vec::IntoIter<MessageStats>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.messages.into_iter()
+    }
+}
@@ -0,0 +1,157 @@
+//! Rate limiting the items of an asynchronous `Stream`.
+
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use crate::{Limit, RateKey, RateLog, Sink};
+
+/// Adds [`RateLimitExt::rate_limit_logs`] to every [`Stream`].
+pub trait RateLimitExt: Stream + Sized {
+    /// Rate limits the items of the stream, yielding the lines a [`RateLog`] with
+    /// `limit` would emit.
+    ///
+    /// Drops a limiter into an existing async log forwarding pipeline as a stream
+    /// adapter: items are logged with [`RateLog::log_item`], so strings are rate limited
+    /// by their text and other items by their [`RateKey`]. The adapter yields the text
+    /// of emitted items as well as the rate limit warnings and summaries, in the order
+    /// they were written. When the stream ends, pending repeats are summarized like on
+    /// [`RateLog::flush`] before the adapter ends too.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures::executor::block_on;
+    /// use futures::stream::{self, StreamExt};
+    /// use rate_log::{Limit, RateLimitExt};
+    ///
+    /// let lines = stream::iter(["disk full", "disk full", "disk full", "link down"])
+    ///     .rate_limit_logs(Limit::Rate(2));
+    ///
+    /// assert_eq!(
+    ///     block_on(lines.collect::<Vec<_>>()),
+    ///     [
+    ///         "disk full",
+    ///         "Message: \"disk full\" repeat for 2 times in the past 0ms",
+    ///         "link down",
+    ///     ]
+    /// );
+    /// ```
+    fn rate_limit_logs(self, limit: Limit) -> RateLimitedLogs<Self>
+    where
+        Self::Item: RateKey + Display,
+    {
+        RateLimitedLogs::new(self, RateLog::with_sink(limit, Queue::default()))
+    }
+}
+
+impl<St: Stream> RateLimitExt for St {}
+
+/// A stream yielding the lines a limiter emits for the items of another stream.
+///
+/// Created with [`RateLimitExt::rate_limit_logs`].
+pub struct RateLimitedLogs<St> {
+    /// The rate limited stream.
+    stream: Pin<Box<St>>,
+
+    /// Decides which items are yielded, queueing the lines to yield.
+    rate_log: RateLog<Queue>,
+
+    /// Whether the rate limited stream has ended.
+    done: bool,
+}
+
+/// A sink queueing lines until the stream yields them.
+#[derive(Default)]
+struct Queue(VecDeque<String>);
+
+impl Sink for Queue {
+    fn emit(&mut self, record: &str) {
+        self.0.push_back(record.to_string());
+    }
+}
+
+impl<St> RateLimitedLogs<St> {
+    /// Wraps `stream`, logging its items through `rate_log`.
+    fn new(stream: St, rate_log: RateLog<Queue>) -> Self {
+        RateLimitedLogs {
+            stream: Box::pin(stream),
+            rate_log,
+            done: false,
+        }
+    }
+}
+
+impl<St> Stream for RateLimitedLogs<St>
+where
+    St: Stream,
+    St::Item: RateKey + Display,
+{
+    type Item = String;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<String>> {
+        let this = &mut *self;
+        loop {
+            if let Some(line) = this.rate_log.sink_mut().0.pop_front() {
+                return Poll::Ready(Some(line));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    this.rate_log.log_item(&item);
+                }
+                Poll::Ready(None) => {
+                    this.done = true;
+                    this.rate_log.flush();
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockClock;
+    use futures::executor::block_on;
+    use futures::stream::{self, StreamExt};
+    use std::borrow::Cow;
+    use std::fmt;
+
+    struct Timeout(u16);
+
+    impl fmt::Display for Timeout {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "timeout on port {}", self.0)
+        }
+    }
+
+    impl RateKey for Timeout {
+        fn rate_key(&self) -> Cow<'_, str> {
+            Cow::Borrowed("Timeout")
+        }
+    }
+
+    #[test]
+    fn test_rate_limit_logs() {
+        let rate_log =
+            RateLog::with_sink(Limit::Rate(2), Queue::default()).with_clock(MockClock::new());
+        let items = stream::iter([80, 81, 82, 83].map(Timeout));
+        let lines = RateLimitedLogs::new(items, rate_log);
+
+        assert_eq!(
+            block_on(lines.collect::<Vec<_>>()),
+            [
+                "timeout on port 80",
+                "Key: \"Timeout\" repeat for 2 times in the past 0ms, last message: \"timeout on port 82\"",
+                "Key: \"Timeout\" suppressed 1 repeats over 0ms",
+            ]
+        );
+    }
+}
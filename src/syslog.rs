@@ -0,0 +1,279 @@
+//! Forwarding of emitted lines to the local syslog daemon.
+
+use std::collections::HashMap;
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+
+use crate::{Error, LineKind, Result, Severity, Sink};
+
+/// The sockets a local syslog daemon listens on, in the order they are tried.
+const SOCKET_PATHS: [&str; 3] = ["/dev/log", "/var/run/syslog", "/var/run/log"];
+
+/// The syslog facility, identifying the kind of program that logged a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Facility {
+    /// Kernel messages.
+    Kern = 0,
+    /// User-level messages.
+    User = 1,
+    /// The mail system.
+    Mail = 2,
+    /// System daemons.
+    Daemon = 3,
+    /// Security and authorization messages.
+    Auth = 4,
+    /// Messages generated internally by the syslog daemon.
+    Syslog = 5,
+    /// The line printer subsystem.
+    Lpr = 6,
+    /// The network news subsystem.
+    News = 7,
+    /// The UUCP subsystem.
+    Uucp = 8,
+    /// The clock daemon.
+    Cron = 9,
+    /// Private security and authorization messages.
+    AuthPriv = 10,
+    /// The FTP daemon.
+    Ftp = 11,
+    /// Reserved for local use (0).
+    Local0 = 16,
+    /// Reserved for local use (1).
+    Local1 = 17,
+    /// Reserved for local use (2).
+    Local2 = 18,
+    /// Reserved for local use (3).
+    Local3 = 19,
+    /// Reserved for local use (4).
+    Local4 = 20,
+    /// Reserved for local use (5).
+    Local5 = 21,
+    /// Reserved for local use (6).
+    Local6 = 22,
+    /// Reserved for local use (7).
+    Local7 = 23,
+}
+
+/// The syslog severity level of a line, from most to least severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyslogLevel {
+    /// The system is unusable.
+    Emergency = 0,
+    /// Action must be taken immediately.
+    Alert = 1,
+    /// Critical conditions.
+    Critical = 2,
+    /// Error conditions.
+    Error = 3,
+    /// Warning conditions.
+    Warning = 4,
+    /// Normal but significant conditions.
+    Notice = 5,
+    /// Informational messages.
+    Info = 6,
+    /// Debug-level messages.
+    Debug = 7,
+}
+
+impl From<Severity> for SyslogLevel {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Trace | Severity::Debug => SyslogLevel::Debug,
+            Severity::Info => SyslogLevel::Info,
+            Severity::Warn => SyslogLevel::Warning,
+            Severity::Error => SyslogLevel::Error,
+        }
+    }
+}
+
+/// A sink that forwards every line to the local syslog daemon.
+///
+/// Available with the `syslog` feature on Unix. Lines are sent as datagrams in the
+/// traditional `<PRI>ident[pid]: line` format to the daemon's Unix socket. The
+/// priority combines the configured [`Facility`] with a [`SyslogLevel`]: logged
+/// messages map their [`Severity`] (configurable with [`SyslogSink::level`]), while
+/// rate limit warnings and suppression summaries use [`SyslogSink::summary_level`].
+///
+/// As with [`WriterSink`](crate::WriterSink), a failing daemon never takes the
+/// application down: the first send error is kept for [`SyslogSink::take_error`] and
+/// the sink reconnects on the next line, e.g. after the daemon restarted.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use rate_log::{Facility, Limit, RateLog, Severity, SyslogLevel, SyslogSink};
+///
+/// let sink = SyslogSink::new("appliance")?
+///     .facility(Facility::Local3)
+///     .level(Severity::Warn, SyslogLevel::Notice)
+///     .summary_level(SyslogLevel::Warning);
+/// let mut logger = RateLog::with_sink(Limit::Rate(5), sink);
+///
+/// logger.log_with_severity(Severity::Error, "Fan failure");  // Sent as <155>appliance[..]: Fan failure
+/// # Ok::<(), rate_log::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct SyslogSink {
+    socket: UnixDatagram,
+    path: PathBuf,
+    ident: String,
+    pid: u32,
+    facility: Facility,
+    levels: HashMap<Severity, SyslogLevel>,
+    summary_level: SyslogLevel,
+    connected: bool,
+    error: Option<io::Error>,
+}
+
+impl SyslogSink {
+    /// Connects to the local syslog daemon, tagging lines with `ident`.
+    ///
+    /// Tries `/dev/log`, `/var/run/syslog` and `/var/run/log` in this order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if no syslog socket accepts the connection.
+    pub fn new(ident: impl Into<String>) -> Result<Self> {
+        let ident = ident.into();
+        let mut last_error = None;
+        for path in SOCKET_PATHS {
+            match SyslogSink::connect(path, ident.clone()) {
+                Ok(sink) => return Ok(sink),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| Error::Io(io::ErrorKind::NotFound.into())))
+    }
+
+    /// Connects to a syslog daemon listening on the Unix datagram socket at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if the socket cannot be connected.
+    pub fn connect(path: impl AsRef<Path>, ident: impl Into<String>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(&path)?;
+        Ok(SyslogSink {
+            socket,
+            path,
+            ident: ident.into(),
+            pid: std::process::id(),
+            facility: Facility::User,
+            levels: HashMap::new(),
+            summary_level: SyslogLevel::Notice,
+            connected: true,
+            error: None,
+        })
+    }
+
+    /// Sets the facility of every line. Defaults to [`Facility::User`].
+    pub fn facility(mut self, facility: Facility) -> Self {
+        self.facility = facility;
+        self
+    }
+
+    /// Sends messages of `severity` with `level` instead of the default mapping.
+    ///
+    /// By default, trace and debug messages are sent as [`SyslogLevel::Debug`], info
+    /// as [`SyslogLevel::Info`], warn as [`SyslogLevel::Warning`] and error as
+    /// [`SyslogLevel::Error`].
+    pub fn level(mut self, severity: Severity, level: SyslogLevel) -> Self {
+        self.levels.insert(severity, level);
+        self
+    }
+
+    /// Sets the level of rate limit warnings, suppression summaries and notices.
+    /// Defaults to [`SyslogLevel::Notice`].
+    pub fn summary_level(mut self, level: SyslogLevel) -> Self {
+        self.summary_level = level;
+        self
+    }
+
+    /// Returns and clears the first send error since the last call, if any.
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take().map(Error::Io)
+    }
+
+    /// Returns the syslog level of a line of `kind`.
+    fn level_of(&self, kind: LineKind) -> SyslogLevel {
+        match kind {
+            LineKind::Message(severity) => self
+                .levels
+                .get(&severity)
+                .copied()
+                .unwrap_or_else(|| severity.into()),
+            LineKind::Summary => self.summary_level,
+        }
+    }
+
+    /// Sends `record` with `level`, reconnecting first if the last send failed.
+    fn send(&mut self, record: &str, level: SyslogLevel) {
+        let priority = self.facility as u8 * 8 + level as u8;
+        let datagram = format!("<{priority}>{}[{}]: {record}", self.ident, self.pid);
+
+        if !self.connected {
+            self.connected = self.socket.connect(&self.path).is_ok();
+        }
+        if let Err(err) = self.socket.send(datagram.as_bytes()) {
+            self.connected = false;
+            self.error.get_or_insert(err);
+        }
+    }
+}
+
+impl Sink for SyslogSink {
+    fn emit(&mut self, record: &str) {
+        self.send(record, SyslogLevel::Info);
+    }
+
+    fn emit_line(&mut self, record: &str, kind: LineKind) {
+        let level = self.level_of(kind);
+        self.send(record, level);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Limit, RateLog};
+
+    #[test]
+    fn test_syslog_priorities() {
+        let dir = std::env::temp_dir().join(format!("rate-log-syslog-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("log");
+        let _ = std::fs::remove_file(&path);
+        let daemon = UnixDatagram::bind(&path).unwrap();
+
+        let sink = SyslogSink::connect(&path, "test")
+            .unwrap()
+            .facility(Facility::Local3)
+            .level(Severity::Warn, SyslogLevel::Critical);
+        let mut rate_log = RateLog::with_sink(Limit::Rate(1), sink);
+        rate_log.log_with_severity(Severity::Error, "message1");
+        rate_log.log_with_severity(Severity::Error, "message1");
+        rate_log.log_with_severity(Severity::Warn, "message2");
+        drop(rate_log);
+
+        let pid = std::process::id();
+        let mut buf = [0; 256];
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            let len = daemon.recv(&mut buf).unwrap();
+            received.push(String::from_utf8_lossy(&buf[..len]).into_owned());
+        }
+        assert_eq!(
+            received,
+            [
+                format!("<155>test[{pid}]: message1"),
+                format!(
+                    "<157>test[{pid}]: Message: \"message1\" repeat for 1 times in the past 0ms"
+                ),
+                format!("<154>test[{pid}]: message2"),
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+//! Suppression counters exported through the `metrics` facade.
+
+use std::collections::HashSet;
+
+use metrics::Counter;
+
+use crate::LogOutcome;
+
+/// The counter of lines emitted verbatim.
+pub(crate) const EMITTED_TOTAL: &str = "rate_log_emitted_total";
+
+/// The counter of occurrences not emitted verbatim, labelled with their key.
+pub(crate) const SUPPRESSED_TOTAL: &str = "rate_log_suppressed_total";
+
+/// The most keys of one limiter with a label value of their own.
+pub(crate) const MAX_LABELLED_KEYS: usize = 100;
+
+/// The label value shared by the keys beyond [`MAX_LABELLED_KEYS`].
+pub(crate) const OTHER_KEY: &str = "_other";
+
+/// Counts a line emitted verbatim.
+pub(crate) fn record_emitted() {
+    metrics::counter!(EMITTED_TOTAL).increment(1);
+}
+
+/// Hands out the label values of `rate_log_suppressed_total` for one limiter.
+///
+/// The first [`MAX_LABELLED_KEYS`] keys suppressed get their own label value, later
+/// ones share [`OTHER_KEY`], so a flood of distinct messages cannot blow up the
+/// cardinality of the metric. A key keeps its label value once given, even after it
+/// is evicted or the limiter is reset.
+#[derive(Debug, Default)]
+pub(crate) struct Labels {
+    /// The keys given a label value of their own.
+    labelled: HashSet<String>,
+}
+
+impl Labels {
+    /// Counts the outcome of a logging call for the key labelled `label`.
+    ///
+    /// The suppression counter of a key is registered on its first suppression and
+    /// kept in `counter`, so only that call allocates the label value.
+    pub(crate) fn record(
+        &mut self,
+        label: &str,
+        counter: &mut Option<Counter>,
+        outcome: &LogOutcome,
+    ) {
+        match outcome {
+            LogOutcome::Emitted => record_emitted(),
+            LogOutcome::Suppressed | LogOutcome::LimitReported { .. } => counter
+                .get_or_insert_with(|| self.register(label))
+                .increment(1),
+            LogOutcome::Dropped => {}
+        }
+    }
+
+    /// Registers the suppression counter of the key labelled `label`.
+    fn register(&mut self, label: &str) -> Counter {
+        if !self.labelled.contains(label) {
+            if self.labelled.len() >= MAX_LABELLED_KEYS {
+                return metrics::counter!(SUPPRESSED_TOTAL, "key" => OTHER_KEY);
+            }
+            self.labelled.insert(label.to_string());
+        }
+        metrics::counter!(SUPPRESSED_TOTAL, "key" => label.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CaptureSink, Limit, RateLog};
+    use metrics::{
+        Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+    };
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    /// A recorder keeping counters in memory, keyed by their rendered name and labels.
+    #[derive(Default)]
+    struct TestRecorder {
+        counters: Mutex<BTreeMap<String, Arc<AtomicU64>>>,
+    }
+
+    impl TestRecorder {
+        fn values(&self) -> Vec<(String, u64)> {
+            let counters = self.counters.lock().unwrap();
+            counters
+                .iter()
+                .map(|(key, value)| (key.clone(), value.load(Ordering::Relaxed)))
+                .collect()
+        }
+    }
+
+    impl Recorder for TestRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            let labels: Vec<_> = key
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect();
+            let name = format!("{}{{{}}}", key.name(), labels.join(","));
+            let counter = self
+                .counters
+                .lock()
+                .unwrap()
+                .entry(name)
+                .or_default()
+                .clone();
+            Counter::from_arc(counter)
+        }
+
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    #[test]
+    fn test_bounded_labels() {
+        let recorder = TestRecorder::default();
+        metrics::with_local_recorder(&recorder, || {
+            let mut rate_log = RateLog::with_sink(Limit::Always, CaptureSink::new());
+            for i in 0..=super::MAX_LABELLED_KEYS {
+                rate_log.log(format!("message{i}"));
+                rate_log.log(format!("message{i}"));
+            }
+            // Keys keep the label value they were given first
+            rate_log.log("message0");
+            rate_log.log("message0");
+        });
+
+        let values = recorder.values();
+        assert_eq!(values.len(), super::MAX_LABELLED_KEYS + 2);
+        assert!(values.contains(&("rate_log_suppressed_total{key=message0}".to_string(), 2)));
+        assert!(values.contains(&("rate_log_suppressed_total{key=_other}".to_string(), 1)));
+    }
+
+    #[test]
+    fn test_evicted_key_labels() {
+        let recorder = TestRecorder::default();
+        metrics::with_local_recorder(&recorder, || {
+            let mut rate_log =
+                RateLog::with_sink(Limit::Always, CaptureSink::new()).with_max_tracked_messages(1);
+            // Every key is evicted and added again, without taking another slot
+            for _ in 0..3 {
+                for i in 0..super::MAX_LABELLED_KEYS {
+                    rate_log.log(format!("message{i}"));
+                    rate_log.log(format!("message{i}"));
+                }
+            }
+            rate_log.reset();
+            rate_log.log("message0");
+            rate_log.log("message0");
+        });
+
+        let values = recorder.values();
+        assert_eq!(values.len(), super::MAX_LABELLED_KEYS + 1);
+        assert!(values.iter().all(|(name, _)| !name.contains("_other")));
+        assert!(values.contains(&("rate_log_suppressed_total{key=message0}".to_string(), 4)));
+        assert!(values.contains(&("rate_log_suppressed_total{key=message1}".to_string(), 3)));
+    }
+
+    #[test]
+    fn test_hashed_key_labels() {
+        let recorder = TestRecorder::default();
+        metrics::with_local_recorder(&recorder, || {
+            let mut rate_log =
+                RateLog::with_sink(Limit::Rate(5), CaptureSink::new()).with_hashed_keys(true);
+            rate_log.log("token=secret");
+            rate_log.log("token=secret");
+        });
+
+        // Messages hidden from the statistics stay hidden from the metrics
+        assert!(recorder
+            .values()
+            .iter()
+            .all(|(name, _)| !name.contains("secret")));
+    }
+
+    #[test]
+    fn test_counters() {
+        let recorder = TestRecorder::default();
+        metrics::with_local_recorder(&recorder, || {
+            let mut rate_log = RateLog::with_sink(Limit::Rate(2), CaptureSink::new());
+            for _ in 0..4 {
+                rate_log.log("message1");
+            }
+            rate_log.log("message2");
+        });
+
+        assert_eq!(
+            recorder.values(),
+            [
+                ("rate_log_emitted_total{}".to_string(), 2),
+                ("rate_log_suppressed_total{key=message1}".to_string(), 3)
+            ]
+        );
+    }
+}
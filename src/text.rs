@@ -0,0 +1,175 @@
+//! Handling of messages that are not clean UTF-8 text.
+//!
+//! Messages logged through [`RateLog::log_bytes`](crate::RateLog::log_bytes) may contain
+//! invalid UTF-8, and any message may contain embedded NUL bytes. Plain files cope with
+//! both, but syslog and journald treat NUL as a terminator and reject invalid UTF-8, so
+//! every [`Sink`] declares the [`TextPolicy`] it needs.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::{LineKind, Sink};
+
+/// How invalid UTF-8 and embedded NUL bytes are handled before reaching a sink.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::TextPolicy;
+///
+/// let bytes = b"bad \xff byte\0";
+///
+/// assert_eq!(TextPolicy::Lossy.decode(bytes).unwrap(), "bad \u{FFFD} byte\u{FFFD}");
+/// assert_eq!(TextPolicy::Escape.decode(bytes).unwrap(), "bad \\xff byte\\x00");
+/// assert_eq!(TextPolicy::Drop.decode(bytes), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextPolicy {
+    /// Replaces invalid sequences and NUL bytes with `U+FFFD REPLACEMENT CHARACTER`.
+    #[default]
+    Lossy,
+
+    /// Writes invalid bytes and NUL bytes as `\xNN` escapes.
+    Escape,
+
+    /// Discards messages containing invalid UTF-8 or NUL bytes.
+    Drop,
+}
+
+impl TextPolicy {
+    /// Converts `bytes` into text according to this policy.
+    ///
+    /// Returns `None` if the message must be dropped. Clean UTF-8 without NUL bytes is
+    /// borrowed unchanged.
+    pub fn decode(self, bytes: &[u8]) -> Option<Cow<'_, str>> {
+        if let Ok(text) = core::str::from_utf8(bytes) {
+            if !text.contains('\0') {
+                return Some(Cow::Borrowed(text));
+            }
+        }
+
+        match self {
+            TextPolicy::Lossy => Some(Cow::Owned(
+                String::from_utf8_lossy(bytes).replace('\0', "\u{FFFD}"),
+            )),
+            TextPolicy::Escape => {
+                let mut text = String::with_capacity(bytes.len());
+                for chunk in bytes.utf8_chunks() {
+                    for c in chunk.valid().chars() {
+                        match c {
+                            '\0' => text.push_str("\\x00"),
+                            c => text.push(c),
+                        }
+                    }
+                    for byte in chunk.invalid() {
+                        let _ = write!(text, "\\x{byte:02x}");
+                    }
+                }
+                Some(Cow::Owned(text))
+            }
+            TextPolicy::Drop => None,
+        }
+    }
+}
+
+/// A sink wrapper that enforces a [`TextPolicy`] on every record.
+///
+/// Use it in front of sinks that cannot store NUL bytes. It also tells
+/// [`RateLog::log_bytes`](crate::RateLog::log_bytes) how to decode invalid UTF-8 for
+/// the wrapped sink.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{CaptureSink, Limit, RateLog, Sanitized, TextPolicy};
+///
+/// let sink = Sanitized::new(CaptureSink::new(), TextPolicy::Escape);
+/// let mut logger = RateLog::with_sink(Limit::Rate(5), sink);
+///
+/// logger.log_bytes(b"read \xff\xfe");
+/// logger.log("nul\0byte");
+///
+/// assert_eq!(logger.sink().inner().lines(), ["read \\xff\\xfe", "nul\\x00byte"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Sanitized<S> {
+    inner: S,
+    policy: TextPolicy,
+}
+
+impl<S: Sink> Sanitized<S> {
+    /// Wraps `inner`, applying `policy` to its records.
+    pub fn new(inner: S, policy: TextPolicy) -> Self {
+        Sanitized { inner, policy }
+    }
+
+    /// Returns a reference to the wrapped sink.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped sink.
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Unwraps the decorator, returning the wrapped sink.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Sink> Sink for Sanitized<S> {
+    fn emit(&mut self, record: &str) {
+        if let Some(record) = self.policy.decode(record.as_bytes()) {
+            self.inner.emit(&record);
+        }
+    }
+
+    fn emit_line(&mut self, record: &str, kind: LineKind) {
+        if let Some(record) = self.policy.decode(record.as_bytes()) {
+            self.inner.emit_line(&record, kind);
+        }
+    }
+
+    fn flush(&mut self) {
+        self.inner.flush();
+    }
+
+    fn text_policy(&self) -> TextPolicy {
+        self.policy
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.inner.is_terminal()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CaptureSink;
+
+    #[test]
+    fn test_text_policy() {
+        for policy in [TextPolicy::Lossy, TextPolicy::Escape, TextPolicy::Drop] {
+            assert!(matches!(
+                policy.decode(b"clean"),
+                Some(Cow::Borrowed("clean"))
+            ));
+        }
+        assert_eq!(
+            TextPolicy::Escape.decode("ü\0\u{1}".as_bytes()).unwrap(),
+            "ü\\x00\u{1}"
+        );
+        // Truncated multi-byte sequence
+        assert_eq!(TextPolicy::Escape.decode(b"\xc3").unwrap(), "\\xc3");
+        assert_eq!(TextPolicy::Lossy.decode(b"\xc3").unwrap(), "\u{FFFD}");
+
+        let mut sink = Sanitized::new(CaptureSink::new(), TextPolicy::Drop);
+        sink.emit("message1\0");
+        sink.emit("message2");
+        assert_eq!(sink.inner().lines(), ["message2"]);
+    }
+}
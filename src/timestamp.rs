@@ -0,0 +1,227 @@
+//! Wall-clock timestamps implemented purely with [`std::time::SystemTime`].
+//!
+//! Available with the `timestamp` feature. No date/time crate is required: timestamps
+//! are rendered either as seconds and milliseconds since the Unix epoch or as a
+//! minimal RFC 3339 string in UTC. The `chrono` feature adds local time and custom
+//! format strings.
+
+#[cfg(feature = "chrono")]
+use std::fmt::Write;
+
+use crate::clock::{SystemTime, UNIX_EPOCH};
+use crate::{LineKind, Sink, TextPolicy};
+
+/// The textual representation used for wall-clock timestamps.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::TimestampFormat;
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+///
+/// assert_eq!(TimestampFormat::EpochMillis.format(time), "1700000000.123");
+/// assert_eq!(TimestampFormat::Rfc3339.format(time), "2023-11-14T22:13:20.123Z");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimestampFormat {
+    /// Seconds and milliseconds since the Unix epoch, e.g. `1700000000.123`.
+    EpochMillis,
+
+    /// RFC 3339 in UTC with millisecond precision, e.g. `2023-11-14T22:13:20.123Z`.
+    #[default]
+    Rfc3339,
+
+    /// RFC 3339 in local time with millisecond precision and the UTC offset,
+    /// e.g. `2023-11-14T23:13:20.123+01:00`.
+    #[cfg(feature = "chrono")]
+    Rfc3339Local,
+
+    /// A [`chrono` format string](https://docs.rs/chrono/latest/chrono/format/strftime/)
+    /// rendered in local time, e.g. `"%Y-%m-%d %H:%M:%S%.3f"`.
+    ///
+    /// Invalid format strings fall back to [`TimestampFormat::Rfc3339`].
+    #[cfg(feature = "chrono")]
+    Custom(&'static str),
+}
+
+impl TimestampFormat {
+    /// Formats `time` using this format.
+    ///
+    /// Times before the Unix epoch are clamped to the epoch.
+    pub fn format(&self, time: SystemTime) -> String {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let secs = since_epoch.as_secs();
+        let millis = since_epoch.subsec_millis();
+
+        match self {
+            TimestampFormat::EpochMillis => format!("{secs}.{millis:03}"),
+            TimestampFormat::Rfc3339 => {
+                let (year, month, day) = civil_from_days(secs / 86_400);
+                let secs_of_day = secs % 86_400;
+                format!(
+                    "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+                    year,
+                    month,
+                    day,
+                    secs_of_day / 3600,
+                    (secs_of_day % 3600) / 60,
+                    secs_of_day % 60,
+                    millis
+                )
+            }
+            #[cfg(feature = "chrono")]
+            TimestampFormat::Rfc3339Local => {
+                TimestampFormat::Custom("%Y-%m-%dT%H:%M:%S%.3f%:z").format(time)
+            }
+            #[cfg(feature = "chrono")]
+            TimestampFormat::Custom(format) => {
+                let local: chrono::DateTime<chrono::Local> =
+                    (std::time::UNIX_EPOCH + since_epoch).into();
+                let mut out = String::new();
+                match write!(out, "{}", local.format(format)) {
+                    Ok(()) => out,
+                    Err(_) => TimestampFormat::Rfc3339.format(time),
+                }
+            }
+        }
+    }
+
+    /// Formats the current wall-clock time using this format.
+    pub fn now(&self) -> String {
+        self.format(SystemTime::now())
+    }
+}
+
+/// Converts a number of days since 1970-01-01 into a `(year, month, day)` triple.
+///
+/// Based on Howard Hinnant's `civil_from_days` algorithm for the proleptic
+/// Gregorian calendar.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// A sink wrapper that prefixes every record with the current wall-clock time.
+///
+/// # Examples
+///
+/// ```rust
+/// use rate_log::{RateLog, Limit, StdoutSink, Timestamped, TimestampFormat};
+///
+/// let sink = Timestamped::new(StdoutSink, TimestampFormat::Rfc3339);
+/// let mut logger = RateLog::with_sink(Limit::Rate(5), sink);
+///
+/// logger.log("Connection lost");  // Prints: "2023-11-14T22:13:20.123Z Connection lost"
+/// ```
+#[derive(Debug, Clone)]
+pub struct Timestamped<S> {
+    inner: S,
+    format: TimestampFormat,
+}
+
+impl<S: Sink> Timestamped<S> {
+    /// Wraps `inner`, prefixing its records with timestamps in `format`.
+    pub fn new(inner: S, format: TimestampFormat) -> Self {
+        Timestamped { inner, format }
+    }
+
+    /// Returns a reference to the wrapped sink.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped sink.
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Unwraps the decorator, returning the wrapped sink.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Sink> Sink for Timestamped<S> {
+    fn emit(&mut self, record: &str) {
+        let line = format!("{} {}", self.format.now(), record);
+        self.inner.emit(&line);
+    }
+
+    fn emit_line(&mut self, record: &str, kind: LineKind) {
+        let line = format!("{} {}", self.format.now(), record);
+        self.inner.emit_line(&line, kind);
+    }
+
+    fn flush(&mut self) {
+        self.inner.flush();
+    }
+
+    fn text_policy(&self) -> TextPolicy {
+        self.inner.text_policy()
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.inner.is_terminal()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CaptureSink;
+    use std::time::Duration;
+
+    #[test]
+    fn test_timestamp_format() {
+        assert_eq!(
+            TimestampFormat::Rfc3339.format(UNIX_EPOCH),
+            "1970-01-01T00:00:00.000Z"
+        );
+        // Leap day
+        let time = UNIX_EPOCH + Duration::from_secs(951_782_400) + Duration::from_millis(5);
+        assert_eq!(
+            TimestampFormat::Rfc3339.format(time),
+            "2000-02-29T00:00:00.005Z"
+        );
+        assert_eq!(TimestampFormat::EpochMillis.format(time), "951782400.005");
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_custom_format() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+
+        // Seconds and milliseconds do not depend on the local time zone
+        assert_eq!(TimestampFormat::Custom("%S%.3f").format(time), "20.123");
+        assert_eq!(
+            TimestampFormat::Custom("%Q").format(time),
+            "2023-11-14T22:13:20.123Z"
+        );
+        let local = TimestampFormat::Rfc3339Local.format(time);
+        assert_eq!(local.len(), "2023-11-14T22:13:20.123+00:00".len());
+        assert!(local.contains("20.123"));
+    }
+
+    #[test]
+    fn test_timestamped_sink() {
+        let mut sink = Timestamped::new(CaptureSink::new(), TimestampFormat::Rfc3339);
+        sink.emit("message1");
+
+        let line = &sink.inner().lines()[0];
+        let (timestamp, record) = line.split_once(' ').unwrap();
+        assert_eq!(record, "message1");
+        assert_eq!(timestamp.len(), "1970-01-01T00:00:00.000Z".len());
+        assert!(timestamp.ends_with('Z'));
+    }
+}
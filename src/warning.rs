@@ -0,0 +1,57 @@
+//! Customizable formatting of rate limit warnings and summaries.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::time::Duration;
+
+use crate::{Location, Trigger};
+
+/// Why a [`Warning`] is emitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// The limit was exceeded; carries the component that was reached.
+    LimitExceeded(Trigger),
+
+    /// Repeats still pending are summarized, e.g. because the message changed or the
+    /// logger is flushed.
+    Summary,
+}
+
+/// The details of a rate limit warning or summary, passed to custom formatters.
+///
+/// See [`RateLog::with_formatter`](crate::RateLog::with_formatter).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning<'a> {
+    /// Why the warning is emitted.
+    pub kind: WarningKind,
+    /// The rate limiting key, i.e. the message text for plain `log()` calls.
+    pub key: &'a str,
+    /// Whether `key` is an explicit key from [`RateLog::log_keyed`](crate::RateLog::log_keyed).
+    pub keyed: bool,
+    /// The latest message logged under an explicit key, if known.
+    pub message: Option<&'a str>,
+    /// The number of repeats covered by the warning.
+    pub count: u32,
+    /// The time accumulated between the covered repeats.
+    pub duration: Duration,
+    /// The size of the covered repeats that were not emitted, see
+    /// [`Limit::Bytes`](crate::Limit::Bytes); 0 for other limits.
+    pub dropped_bytes: u64,
+    /// The exemplar labels captured for the latest covered repeat, if any.
+    pub exemplar: Option<&'a str>,
+    /// The location the latest covered repeat was logged from, if known, see
+    /// [`RateLog::log_at`](crate::RateLog::log_at).
+    pub location: Option<Location>,
+}
+
+impl Warning<'_> {
+    /// Returns the number of covered repeats per second, or `None` if no time passed
+    /// between them.
+    pub fn rate(&self) -> Option<f64> {
+        let secs = self.duration.as_secs_f64();
+        (secs > 0.0).then(|| f64::from(self.count) / secs)
+    }
+}
+
+/// A function turning a [`Warning`] into the emitted line.
+pub(crate) type Formatter = Box<dyn Fn(&Warning<'_>) -> String + Send>;
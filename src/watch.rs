@@ -0,0 +1,173 @@
+//! Reloading the limits of a registry when its configuration file changes.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use crate::{Config, Error, Registry, Sink};
+
+/// A background thread polling a configuration file and applying the limits it
+/// describes to a [`Registry`] whenever the file changes.
+///
+/// Lets noisy services be tuned in production without a restart. Limiters already
+/// handed out switch to their new limit with [`Registry::set_limits`], keeping the
+/// repeats and statistics accumulated so far. Only limits are reloaded: a changed
+/// sink, format, grouping rule or option needs a new registry.
+///
+/// The file is polled every interval by comparing its modification time and size,
+/// which works on every platform and file system without extra dependencies. A file
+/// that fails to load leaves the current limits in place; the first such error is
+/// kept for [`ConfigWatcher::take_error`].
+///
+/// The thread stops when [`ConfigWatcher::stop`] is called or the watcher is dropped.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use rate_log::{Config, ConfigWatcher};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let registry = Arc::new(Config::from_toml("/etc/myapp/rate-log.toml")?.into_registry()?);
+/// let watcher = ConfigWatcher::spawn(
+///     "/etc/myapp/rate-log.toml",
+///     Arc::clone(&registry),
+///     Duration::from_secs(5),
+/// );
+///
+/// registry.get("network").log("connection reset");
+/// # Ok::<(), rate_log::Error>(())
+/// ```
+pub struct ConfigWatcher {
+    /// Wakes the thread up to stop; dropping it has the same effect.
+    stop: Option<Sender<()>>,
+
+    /// The polling thread.
+    handle: Option<JoinHandle<()>>,
+
+    /// The first error loading the file.
+    error: Arc<Mutex<Option<Error>>>,
+}
+
+impl ConfigWatcher {
+    /// Starts checking the file at `path` for changes every `interval`, applying them
+    /// to `registry`.
+    ///
+    /// The file is read as YAML if its name ends in `.yaml` or `.yml`, and as TOML
+    /// otherwise. Its current contents are assumed to be applied already.
+    pub fn spawn<S: Sink + Send + 'static>(
+        path: impl Into<PathBuf>,
+        registry: Arc<Registry<S>>,
+        interval: Duration,
+    ) -> Self {
+        let path = path.into();
+        let error = Arc::new(Mutex::new(None));
+        let errors = Arc::clone(&error);
+        let (stop, stopped) = mpsc::channel::<()>();
+        // Taken before spawning, so changes made right after this call are noticed
+        let mut seen = version(&path);
+        let handle = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                let current = version(&path);
+                if current == seen {
+                    continue;
+                }
+                seen = current;
+                match Config::load(&path) {
+                    Ok(config) => {
+                        let (default, limits) = config.limits();
+                        registry.set_limits(default, limits);
+                    }
+                    Err(err) => {
+                        let mut error = errors.lock().unwrap_or_else(|err| err.into_inner());
+                        error.get_or_insert(err);
+                    }
+                }
+            }
+        });
+        ConfigWatcher {
+            stop: Some(stop),
+            handle: Some(handle),
+            error,
+        }
+    }
+
+    /// Returns and clears the first error loading the file since the last call.
+    pub fn take_error(&self) -> Option<Error> {
+        self.error
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .take()
+    }
+
+    /// Stops the polling thread and waits for it to finish.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    /// Signals the thread to stop and joins it.
+    fn shutdown(&mut self) {
+        drop(self.stop.take());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for ConfigWatcher {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Returns the modification time and size of the file at `path`, if it exists.
+fn version(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Limit;
+
+    /// Replaces the file at `path` at once, so the watcher never reads it half written.
+    fn replace(path: &Path, contents: &str) {
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, contents).unwrap();
+        fs::rename(&tmp, path).unwrap();
+    }
+
+    #[test]
+    fn test_config_watcher() {
+        let dir = std::env::temp_dir().join(format!("rate-log-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("rate-log.toml");
+        fs::write(&path, "[keys.network]\nlimit = \"rate:5\"\n").unwrap();
+
+        let registry = Arc::new(Config::from_toml(&path).unwrap().into_registry().unwrap());
+        let network = registry.get("network");
+        let watcher = ConfigWatcher::spawn(&path, Arc::clone(&registry), Duration::from_millis(10));
+
+        // Wait for the reload, with a generous bound for slow machines
+        replace(&path, "[keys.network]\nlimit = \"rate:500\"\n");
+        assert!((0..500).any(|_| {
+            thread::sleep(Duration::from_millis(10));
+            *network.lock().limit() == Limit::Rate(500)
+        }));
+
+        replace(&path, "[keys.network]\nlimit = \"rate\"\n");
+        let error = (0..500).find_map(|_| {
+            thread::sleep(Duration::from_millis(10));
+            watcher.take_error()
+        });
+        assert!(matches!(error, Some(Error::Config(_))));
+        assert_eq!(*network.lock().limit(), Limit::Rate(500));
+
+        watcher.stop();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}